@@ -0,0 +1,133 @@
+//! Mined transactions from and to an address, found by polling GraphQL. See
+//! [`crate::Arweave::watch_address`].
+
+use std::collections::{BTreeSet, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::graphql::TxNode;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// Sent to the address by another wallet.
+    Incoming,
+    /// Signed by the address.
+    Outgoing,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AddressActivity {
+    pub direction: Direction,
+    pub node: TxNode,
+    /// Cursor to resume watching right after this transaction.
+    pub cursor: ActivityCursor,
+}
+
+/// Position of a watch: the height up to which transactions were reported, and those
+/// reported at that height, since the block may still be indexed further.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct ActivityCursor {
+    pub height: u64,
+    pub seen: BTreeSet<String>,
+}
+
+impl ActivityCursor {
+    /// Cursor reporting the transactions mined from block `height` on.
+    pub fn at(height: u64) -> Self {
+        Self {
+            height,
+            seen: BTreeSet::new(),
+        }
+    }
+
+    /// Mined transactions of `nodes` not reported yet, in block order, each with the
+    /// cursor following it. Advances the cursor past them.
+    pub fn advance(&mut self, address: &str, nodes: Vec<TxNode>) -> Vec<AddressActivity> {
+        let mut ids = HashSet::new();
+        let mut nodes: Vec<(u64, TxNode)> = nodes
+            .into_iter()
+            .filter_map(|node| Some((node.block.as_ref()?.height, node)))
+            .filter(|(height, node)| {
+                *height >= self.height
+                    && !(*height == self.height && self.seen.contains(&node.id))
+                    && ids.insert(node.id.clone())
+            })
+            .collect();
+        nodes.sort_by(|(a, x), (b, y)| a.cmp(b).then_with(|| x.id.cmp(&y.id)));
+
+        nodes
+            .into_iter()
+            .map(|(height, node)| {
+                if height > self.height {
+                    self.height = height;
+                    self.seen.clear();
+                }
+                self.seen.insert(node.id.clone());
+                let direction = if node.owner.address == address {
+                    Direction::Outgoing
+                } else {
+                    Direction::Incoming
+                };
+                AddressActivity {
+                    direction,
+                    node,
+                    cursor: self.clone(),
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{ActivityCursor, Direction};
+    use crate::graphql::TxNode;
+
+    fn node(id: &str, owner: &str, height: Option<u64>) -> TxNode {
+        serde_json::from_value(json!({
+            "id": id,
+            "anchor": "",
+            "signature": "",
+            "recipient": "",
+            "owner": { "address": owner, "key": "" },
+            "fee": { "winston": "0", "ar": "0" },
+            "quantity": { "winston": "0", "ar": "0" },
+            "data": { "size": "0", "type": null },
+            "tags": [],
+            "block": height.map(|h| json!({ "id": "", "timestamp": 0, "height": h, "previous": "" })),
+            "bundledIn": null
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_advance() {
+        let mut cursor = ActivityCursor::at(10);
+        let activity = cursor.advance(
+            "me",
+            vec![
+                node("c", "other", Some(12)),
+                node("a", "me", Some(11)),
+                node("pending", "other", None),
+                node("old", "other", Some(9)),
+                node("c", "other", Some(12)),
+            ],
+        );
+        let ids: Vec<&str> = activity.iter().map(|a| a.node.id.as_str()).collect();
+        assert_eq!(ids, vec!["a", "c"]);
+        assert_eq!(activity[0].direction, Direction::Outgoing);
+        assert_eq!(activity[1].direction, Direction::Incoming);
+        assert_eq!(activity[0].cursor.height, 11);
+        assert_eq!(cursor, activity[1].cursor);
+
+        let activity = cursor.advance(
+            "me",
+            vec![node("c", "other", Some(12)), node("d", "other", Some(12))],
+        );
+        assert_eq!(activity.len(), 1);
+        assert_eq!(activity[0].node.id, "d");
+        assert_eq!(cursor.seen.len(), 2);
+    }
+}
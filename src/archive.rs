@@ -0,0 +1,205 @@
+//! Reading of tar and zip archives, whose files are uploaded one by one by
+//! [`crate::Arweave::upload_archive`].
+
+use std::{
+    fs::File,
+    io::Read,
+    path::{Component, Path, PathBuf},
+};
+
+use flate2::read::GzDecoder;
+use tokio::sync::mpsc;
+
+use crate::error::Error;
+
+/// Bytes reserved ahead for an entry, whatever size its header claims.
+const MAX_PREALLOCATION: u64 = 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Tar,
+    TarGz,
+    Zip,
+}
+
+impl ArchiveFormat {
+    /// Format of the archive at `path`, from its extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_str()?.to_ascii_lowercase();
+        if name.ends_with(".tar") {
+            Some(ArchiveFormat::Tar)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(ArchiveFormat::TarGz)
+        } else if name.ends_with(".zip") {
+            Some(ArchiveFormat::Zip)
+        } else {
+            None
+        }
+    }
+}
+
+/// A file of an archive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveEntry {
+    /// Path of the file inside the archive, `/` separated.
+    pub path: String,
+    pub data: Vec<u8>,
+}
+
+/// Reads the files of the archive at `path` on a blocking thread. Files are sent one at a
+/// time, so only the one being consumed is held in memory; directories and links are
+/// skipped. Reading stops at the first error, which is sent last.
+pub fn read_entries(
+    path: PathBuf,
+    format: ArchiveFormat,
+) -> mpsc::Receiver<Result<ArchiveEntry, Error>> {
+    let (sender, receiver) = mpsc::channel(1);
+    tokio::task::spawn_blocking(move || {
+        let res = File::open(&path)
+            .map_err(|e| Error::IoError(e.to_string()))
+            .and_then(|file| match format {
+                ArchiveFormat::Tar => read_tar(file, &sender),
+                ArchiveFormat::TarGz => read_tar(GzDecoder::new(file), &sender),
+                ArchiveFormat::Zip => read_zip(file, &sender),
+            });
+        if let Err(err) = res {
+            let _ = sender.blocking_send(Err(err));
+        }
+    });
+    receiver
+}
+
+type Sender = mpsc::Sender<Result<ArchiveEntry, Error>>;
+
+fn read_tar<R: Read>(reader: R, sender: &Sender) -> Result<(), Error> {
+    let mut archive = tar::Archive::new(reader);
+    for entry in archive.entries().map_err(archive_error)? {
+        let mut entry = entry.map_err(archive_error)?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let path = entry_path(&entry.path().map_err(archive_error)?)?;
+        let mut data = Vec::with_capacity(entry.size().min(MAX_PREALLOCATION) as usize);
+        entry.read_to_end(&mut data).map_err(archive_error)?;
+        if sender
+            .blocking_send(Ok(ArchiveEntry { path, data }))
+            .is_err()
+        {
+            // The receiver is gone, nobody wants the remaining files.
+            return Ok(());
+        }
+    }
+    Ok(())
+}
+
+fn read_zip(file: File, sender: &Sender) -> Result<(), Error> {
+    let mut archive = zip::ZipArchive::new(file).map_err(archive_error)?;
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i).map_err(archive_error)?;
+        if !file.is_file() {
+            continue;
+        }
+        let path = entry_path(Path::new(file.name()))?;
+        let mut data = Vec::with_capacity(file.size().min(MAX_PREALLOCATION) as usize);
+        file.read_to_end(&mut data).map_err(archive_error)?;
+        if sender
+            .blocking_send(Ok(ArchiveEntry { path, data }))
+            .is_err()
+        {
+            return Ok(());
+        }
+    }
+    Ok(())
+}
+
+/// `/` separated form of the path of an entry, rejecting paths that escape the archive.
+fn entry_path(path: &Path) -> Result<String, Error> {
+    let parts = path
+        .components()
+        .filter(|c| *c != Component::CurDir)
+        .map(|c| match c {
+            Component::Normal(part) => part.to_str().ok_or_else(|| {
+                Error::ArchiveError(format!("{} is not valid UTF-8", path.display()))
+            }),
+            _ => Err(Error::ArchiveError(format!(
+                "unsupported path {}",
+                path.display()
+            ))),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(parts.join("/"))
+}
+
+fn archive_error(e: impl std::fmt::Display) -> Error {
+    Error::ArchiveError(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Write, path::Path};
+
+    use super::{entry_path, read_entries, ArchiveEntry, ArchiveFormat};
+
+    /// Writes `files` as a zip archive at `path`.
+    fn write_zip(path: &Path, files: &[(&str, &[u8])]) {
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(path).unwrap());
+        for (name, data) in files {
+            zip.start_file(*name, zip::write::FileOptions::default())
+                .unwrap();
+            zip.write_all(data).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    /// Writes `files` as a tar archive at `path`.
+    fn write_tar(path: &Path, files: &[(&str, &[u8])]) {
+        let mut tar = tar::Builder::new(std::fs::File::create(path).unwrap());
+        for (name, data) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            tar.append_data(&mut header, name, *data).unwrap();
+        }
+        tar.finish().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_read_entries() {
+        let files: &[(&str, &[u8])] = &[("index.html", b"<html>"), ("./css/site.css", b"body")];
+        let expected = vec![
+            ArchiveEntry {
+                path: "index.html".to_string(),
+                data: b"<html>".to_vec(),
+            },
+            ArchiveEntry {
+                path: "css/site.css".to_string(),
+                data: b"body".to_vec(),
+            },
+        ];
+
+        let dir = tempfile::tempdir().unwrap();
+        let tar_path = dir.path().join("site.tar");
+        write_tar(&tar_path, files);
+        let zip_path = dir.path().join("site.zip");
+        write_zip(&zip_path, files);
+
+        for path in [tar_path, zip_path] {
+            let format = ArchiveFormat::from_path(&path).unwrap();
+            let mut receiver = read_entries(path.clone(), format);
+            let mut entries = Vec::new();
+            while let Some(entry) = receiver.recv().await {
+                entries.push(entry.unwrap());
+            }
+            assert_eq!(entries, expected);
+        }
+
+        assert_eq!(
+            ArchiveFormat::from_path(Path::new("site.TGZ")),
+            Some(ArchiveFormat::TarGz)
+        );
+        assert_eq!(ArchiveFormat::from_path(Path::new("site.rar")), None);
+        assert!(entry_path(Path::new("../etc/passwd")).is_err());
+        assert!(entry_path(Path::new("/etc/passwd")).is_err());
+    }
+}
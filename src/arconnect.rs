@@ -0,0 +1,89 @@
+//! Verification of payloads signed in the browser by ArConnect, or any wallet implementing
+//! its `window.arweaveWallet` API, so backends can trust client-side signatures.
+//!
+//! All three flows produce RSA-PSS SHA-256 signatures by the wallet's key. The owner passed
+//! to these helpers is the wallet's public modulus, as returned by
+//! `arweaveWallet.getActivePublicKey()`.
+
+use std::str::FromStr;
+
+use crate::{
+    crypto::{base64::Base64, hash::sha256, sign::verify_pss},
+    error::Error,
+    signer::ArweaveSigner,
+    transaction::Tx,
+};
+
+/// Verifies a signature made by `arweaveWallet.signature(data, { name: "RSA-PSS" })`, which
+/// signs `data` as is.
+pub fn verify_signature(owner: &Base64, data: &[u8], signature: &Base64) -> Result<(), Error> {
+    verify_pss(&owner.0, data, &signature.0)
+}
+
+/// Verifies a signature made by `arweaveWallet.signMessage(data)`, which signs the SHA-256
+/// digest of `data`.
+pub fn verify_message(owner: &Base64, data: &[u8], signature: &Base64) -> Result<(), Error> {
+    verify_pss(&owner.0, &sha256(data), &signature.0)
+}
+
+/// Parses a transaction signed by `arweaveWallet.sign(transaction)` from its JSON form and
+/// verifies its id and signature against its owner. Returns the transaction when valid.
+pub fn verify_signed_transaction(json: &str) -> Result<Tx, Error> {
+    let transaction = Tx::from_str(json)?;
+    ArweaveSigner::verify_transaction(&transaction)?;
+    Ok(transaction)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{verify_message, verify_signature, verify_signed_transaction};
+    use crate::{
+        crypto::{base64::Base64, hash::sha256},
+        error::Error,
+        signer::ArweaveSigner,
+        transaction::Tx,
+    };
+
+    #[test]
+    fn test_verify_signature_and_message() {
+        let signer = ArweaveSigner::default();
+        let owner = signer.get_public_key();
+        let data = b"login nonce 42";
+
+        let signature = signer.sign(data);
+        assert!(verify_signature(&owner, data, &signature).is_ok());
+        assert!(verify_message(&owner, data, &signature).is_err());
+
+        let signature = signer.sign(&sha256(data));
+        assert!(verify_message(&owner, data, &signature).is_ok());
+        assert!(matches!(
+            verify_message(&owner, b"login nonce 43", &signature),
+            Err(Error::InvalidSignature)
+        ));
+    }
+
+    #[test]
+    fn test_verify_signed_transaction() -> Result<(), Error> {
+        let signer = ArweaveSigner::default();
+        let tx = Tx::new(
+            signer.get_provider(),
+            Base64::empty(),
+            b"signed in the browser".to_vec(),
+            0,
+            1234,
+            Base64::from(vec![1; 32]),
+            vec![],
+            false,
+        )?;
+        let signed = signer.sign_transaction(tx)?;
+        let json = serde_json::to_string(&signed).unwrap();
+
+        let verified = verify_signed_transaction(&json)?;
+        assert_eq!(verified.id, signed.id);
+
+        let tampered = json.replace("\"reward\":\"1234\"", "\"reward\":\"1\"");
+        assert_ne!(tampered, json);
+        assert!(verify_signed_transaction(&tampered).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,154 @@
+//! Encryption of private ArFS drives, compatible with ArDrive.
+//!
+//! The drive key is derived with HKDF-SHA256 from a deterministic wallet signature of
+//! `"drive" || drive id` and the drive password. File keys are derived from the drive key and
+//! the file id. Metadata and file contents are sealed with AES-256-GCM under a random 12 byte
+//! IV, carried base64 encoded in the `Cipher-IV` tag.
+
+use hkdf::Hkdf;
+use sha2::Sha256;
+use uuid::Uuid;
+use zeroize::ZeroizeOnDrop;
+
+use crate::{
+    crypto::{
+        aes::{AesKey, IV_LENGTH, KEY_LENGTH},
+        base64::Base64,
+    },
+    error::Error,
+    signer::ArweaveSigner,
+    transaction::tags::{FromUtf8Strs, Tag},
+};
+
+pub const CIPHER: &str = "Cipher";
+pub const CIPHER_IV: &str = "Cipher-IV";
+pub const DRIVE_AUTH_MODE: &str = "Drive-Auth-Mode";
+
+pub const AES256_GCM: &str = "AES256-GCM";
+pub const PASSWORD_AUTH_MODE: &str = "password";
+/// `Content-Type` of encrypted metadata and file data.
+pub const PRIVATE_CONTENT_TYPE: &str = "application/octet-stream";
+
+/// AES-256-GCM key of a private drive or file, scrubbed from memory on drop.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ArfsKey(AesKey);
+
+impl ZeroizeOnDrop for ArfsKey {}
+
+impl ArfsKey {
+    pub fn from_bytes(bytes: [u8; KEY_LENGTH]) -> Self {
+        Self(AesKey::new(bytes))
+    }
+
+    pub fn as_bytes(&self) -> &[u8; KEY_LENGTH] {
+        self.0.as_bytes()
+    }
+
+    /// Key of drive `drive_id`, unlocked by the wallet of `signer` and `password`.
+    pub fn drive(signer: &ArweaveSigner, drive_id: &str, password: &str) -> Result<Self, Error> {
+        let mut message = b"drive".to_vec();
+        message.extend_from_slice(parse_uuid(drive_id)?.as_bytes());
+        let signature = signer.sign_unsalted(&message);
+        Ok(Self::from_bytes(expand(&signature.0, password.as_bytes())))
+    }
+
+    /// Key of file `file_id`, derived from its drive key.
+    pub fn file(&self, file_id: &str) -> Result<Self, Error> {
+        Ok(Self::from_bytes(expand(
+            self.as_bytes(),
+            parse_uuid(file_id)?.as_bytes(),
+        )))
+    }
+
+    /// Encrypts `plaintext` under a fresh IV and returns the `Cipher` and `Cipher-IV` tags
+    /// with the ciphertext.
+    pub fn seal(&self, plaintext: &[u8]) -> Result<(Vec<Tag<Base64>>, Vec<u8>), Error> {
+        let (iv, ciphertext) = self
+            .0
+            .encrypt(plaintext)
+            .map_err(|e| Error::CryptoError(e.to_string()))?;
+        let tags = vec![
+            Tag::<Base64>::from_utf8_strs(CIPHER, AES256_GCM)?,
+            Tag::<Base64>::from_utf8_strs(CIPHER_IV, &base64::encode(iv))?,
+        ];
+        Ok((tags, ciphertext))
+    }
+
+    /// Decrypts data sealed by [`ArfsKey::seal`], reading the IV from `tags`.
+    pub fn open(&self, tags: &[Tag<String>], ciphertext: &[u8]) -> Result<Vec<u8>, Error> {
+        let value = |name: &str| {
+            tags.iter()
+                .find(|t| t.name == name)
+                .map(|t| t.value.as_str())
+                .ok_or_else(|| Error::ArfsError(format!("missing {} tag", name)))
+        };
+        if value(CIPHER)? != AES256_GCM {
+            return Err(Error::ArfsError(format!(
+                "unsupported cipher {}",
+                value(CIPHER)?
+            )));
+        }
+        let iv = base64::decode(value(CIPHER_IV)?)
+            .ok()
+            .filter(|iv| iv.len() == IV_LENGTH)
+            .ok_or_else(|| Error::ArfsError(format!("invalid {} tag", CIPHER_IV)))?;
+        self.0
+            .decrypt(&iv, ciphertext)
+            .map_err(|_| Error::ArfsError("decryption failed, wrong key or password".to_string()))
+    }
+}
+
+fn expand(ikm: &[u8], info: &[u8]) -> [u8; KEY_LENGTH] {
+    let mut key = [0; KEY_LENGTH];
+    Hkdf::<Sha256>::new(None, ikm)
+        .expand(info, &mut key)
+        .expect("32 bytes is a valid HKDF-SHA256 output length");
+    key
+}
+
+fn parse_uuid(id: &str) -> Result<Uuid, Error> {
+    Uuid::parse_str(id).map_err(|e| Error::ArfsError(format!("invalid id {}: {}", id, e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ArfsKey;
+    use crate::{signer::ArweaveSigner, transaction::tags::Tag};
+
+    const DRIVE_ID: &str = "e93cf9c4-5f20-4d7a-87c4-034777cbb51e";
+    const FILE_ID: &str = "2d4a4f53-4e7b-4b2d-8b53-1c1f5d6f8a10";
+
+    #[test]
+    fn test_key_derivation_is_deterministic() {
+        let signer = ArweaveSigner::default();
+        let drive_key = ArfsKey::drive(&signer, DRIVE_ID, "secret").unwrap();
+
+        assert_eq!(
+            ArfsKey::drive(&signer, DRIVE_ID, "secret").unwrap(),
+            drive_key
+        );
+        assert_ne!(
+            ArfsKey::drive(&signer, DRIVE_ID, "other").unwrap(),
+            drive_key
+        );
+        assert_ne!(drive_key.file(FILE_ID).unwrap(), drive_key);
+        assert!(ArfsKey::drive(&signer, "not-a-uuid", "secret").is_err());
+    }
+
+    #[test]
+    fn test_seal_and_open() {
+        let key = ArfsKey::from_bytes([7; 32]);
+        let (tags, ciphertext) = key.seal(b"private metadata").unwrap();
+        let tags: Vec<Tag<String>> = tags
+            .iter()
+            .map(Tag::try_from)
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        assert_eq!(ciphertext.len(), b"private metadata".len() + 16);
+        assert_eq!(key.open(&tags, &ciphertext).unwrap(), b"private metadata");
+        assert!(ArfsKey::from_bytes([8; 32])
+            .open(&tags, &ciphertext)
+            .is_err());
+    }
+}
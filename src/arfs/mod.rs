@@ -0,0 +1,458 @@
+//! ArFS entities: the drive, folder and file metadata transactions used by ArDrive.
+//!
+//! Every entity is a transaction whose tags identify it and whose data is a small JSON
+//! document with its metadata. File contents are uploaded separately and referenced from the
+//! file entity by transaction id. Entities of private drives are encrypted, see [`cipher`].
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::{
+    crypto::base64::Base64,
+    error::Error,
+    transaction::tags::{FromUtf8Strs, Tag, CONTENT_TYPE, UNIX_TIME},
+};
+use cipher::{ArfsKey, DRIVE_AUTH_MODE, PASSWORD_AUTH_MODE, PRIVATE_CONTENT_TYPE};
+
+pub mod cipher;
+
+pub const ARFS: &str = "ArFS";
+pub const ENTITY_TYPE: &str = "Entity-Type";
+pub const DRIVE_ID: &str = "Drive-Id";
+pub const FOLDER_ID: &str = "Folder-Id";
+pub const FILE_ID: &str = "File-Id";
+pub const PARENT_FOLDER_ID: &str = "Parent-Folder-Id";
+pub const DRIVE_PRIVACY: &str = "Drive-Privacy";
+
+/// ArFS version written by this crate.
+pub const ARFS_VERSION: &str = "0.11";
+/// `Content-Type` of public entity metadata.
+pub const METADATA_CONTENT_TYPE: &str = "application/json";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum EntityType {
+    Drive,
+    Folder,
+    File,
+}
+
+impl EntityType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EntityType::Drive => "drive",
+            EntityType::Folder => "folder",
+            EntityType::File => "file",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DrivePrivacy {
+    Public,
+    Private,
+}
+
+impl DrivePrivacy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DrivePrivacy::Public => "public",
+            DrivePrivacy::Private => "private",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DriveEntity {
+    pub drive_id: String,
+    pub privacy: DrivePrivacy,
+    pub name: String,
+    pub root_folder_id: String,
+    pub unix_time: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FolderEntity {
+    pub drive_id: String,
+    pub folder_id: String,
+    /// `None` for the root folder of a drive.
+    pub parent_folder_id: Option<String>,
+    pub name: String,
+    pub unix_time: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileEntity {
+    pub drive_id: String,
+    pub file_id: String,
+    pub parent_folder_id: String,
+    pub name: String,
+    pub size: u64,
+    /// Last modification of the source file, in milliseconds since the epoch.
+    pub last_modified_date: u64,
+    /// Id of the transaction holding the file contents.
+    pub data_tx_id: String,
+    pub data_content_type: String,
+    pub unix_time: u64,
+}
+
+impl DriveEntity {
+    /// New public drive together with its root folder.
+    pub fn new(name: &str) -> (Self, FolderEntity) {
+        Self::with_privacy(name, DrivePrivacy::Public)
+    }
+
+    /// New private drive together with its root folder. Its entities must be posted sealed
+    /// with the drive key, see [`Entity::seal`].
+    pub fn new_private(name: &str) -> (Self, FolderEntity) {
+        Self::with_privacy(name, DrivePrivacy::Private)
+    }
+
+    fn with_privacy(name: &str, privacy: DrivePrivacy) -> (Self, FolderEntity) {
+        let unix_time = now();
+        let drive = Self {
+            drive_id: new_id(),
+            privacy,
+            name: name.to_string(),
+            root_folder_id: new_id(),
+            unix_time,
+        };
+        let root = FolderEntity {
+            drive_id: drive.drive_id.clone(),
+            folder_id: drive.root_folder_id.clone(),
+            parent_folder_id: None,
+            name: name.to_string(),
+            unix_time,
+        };
+        (drive, root)
+    }
+}
+
+impl FolderEntity {
+    pub fn new(drive_id: &str, parent_folder_id: &str, name: &str) -> Self {
+        Self {
+            drive_id: drive_id.to_string(),
+            folder_id: new_id(),
+            parent_folder_id: Some(parent_folder_id.to_string()),
+            name: name.to_string(),
+            unix_time: now(),
+        }
+    }
+}
+
+impl FileEntity {
+    pub fn new(
+        drive_id: &str,
+        parent_folder_id: &str,
+        name: &str,
+        size: u64,
+        last_modified_date: u64,
+        data_tx_id: &str,
+        data_content_type: &str,
+    ) -> Self {
+        Self {
+            drive_id: drive_id.to_string(),
+            file_id: new_id(),
+            parent_folder_id: parent_folder_id.to_string(),
+            name: name.to_string(),
+            size,
+            last_modified_date,
+            data_tx_id: data_tx_id.to_string(),
+            data_content_type: data_content_type.to_string(),
+            unix_time: now(),
+        }
+    }
+}
+
+/// Any ArFS entity.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Entity {
+    Drive(DriveEntity),
+    Folder(FolderEntity),
+    File(FileEntity),
+}
+
+impl Entity {
+    pub fn entity_type(&self) -> EntityType {
+        match self {
+            Entity::Drive(_) => EntityType::Drive,
+            Entity::Folder(_) => EntityType::Folder,
+            Entity::File(_) => EntityType::File,
+        }
+    }
+
+    pub fn drive_id(&self) -> &str {
+        match self {
+            Entity::Drive(drive) => &drive.drive_id,
+            Entity::Folder(folder) => &folder.drive_id,
+            Entity::File(file) => &file.drive_id,
+        }
+    }
+
+    /// Tags of the metadata transaction.
+    pub fn tags(&self) -> Result<Vec<Tag<Base64>>, Error> {
+        self.tags_with_content_type(METADATA_CONTENT_TYPE)
+    }
+
+    /// Tags and encrypted metadata of the entity in a private drive. File entities are sealed
+    /// with their file key, drives and folders with `drive_key` itself.
+    pub fn seal(&self, drive_key: &ArfsKey) -> Result<(Vec<Tag<Base64>>, Vec<u8>), Error> {
+        let mut tags = self.tags_with_content_type(PRIVATE_CONTENT_TYPE)?;
+        if let Entity::Drive(_) = self {
+            tags.push(Tag::<Base64>::from_utf8_strs(
+                DRIVE_AUTH_MODE,
+                PASSWORD_AUTH_MODE,
+            )?);
+        }
+        let (cipher_tags, ciphertext) = self.key(drive_key)?.seal(&self.metadata()?)?;
+        tags.extend(cipher_tags);
+        Ok((tags, ciphertext))
+    }
+
+    fn key(&self, drive_key: &ArfsKey) -> Result<ArfsKey, Error> {
+        match self {
+            Entity::File(file) => drive_key.file(&file.file_id),
+            _ => Ok(drive_key.clone()),
+        }
+    }
+
+    fn tags_with_content_type(&self, content_type: &str) -> Result<Vec<Tag<Base64>>, Error> {
+        let mut tags = vec![
+            (ARFS, ARFS_VERSION.to_string()),
+            (CONTENT_TYPE, content_type.to_string()),
+            (ENTITY_TYPE, self.entity_type().as_str().to_string()),
+            (DRIVE_ID, self.drive_id().to_string()),
+        ];
+        let unix_time = match self {
+            Entity::Drive(drive) => {
+                tags.push((DRIVE_PRIVACY, drive.privacy.as_str().to_string()));
+                drive.unix_time
+            }
+            Entity::Folder(folder) => {
+                tags.push((FOLDER_ID, folder.folder_id.clone()));
+                if let Some(parent) = &folder.parent_folder_id {
+                    tags.push((PARENT_FOLDER_ID, parent.clone()));
+                }
+                folder.unix_time
+            }
+            Entity::File(file) => {
+                tags.push((FILE_ID, file.file_id.clone()));
+                tags.push((PARENT_FOLDER_ID, file.parent_folder_id.clone()));
+                file.unix_time
+            }
+        };
+        tags.push((UNIX_TIME, unix_time.to_string()));
+
+        tags.iter()
+            .map(|(name, value)| Tag::<Base64>::from_utf8_strs(name, value))
+            .collect()
+    }
+
+    /// JSON metadata stored as the transaction data.
+    pub fn metadata(&self) -> Result<Vec<u8>, Error> {
+        let metadata = match self {
+            Entity::Drive(drive) => serde_json::json!({
+                "name": drive.name,
+                "rootFolderId": drive.root_folder_id,
+            }),
+            Entity::Folder(folder) => serde_json::json!({ "name": folder.name }),
+            Entity::File(file) => serde_json::json!({
+                "name": file.name,
+                "size": file.size,
+                "lastModifiedDate": file.last_modified_date,
+                "dataTxId": file.data_tx_id,
+                "dataContentType": file.data_content_type,
+            }),
+        };
+        serde_json::to_vec(&metadata).map_err(|e| Error::ArfsError(e.to_string()))
+    }
+
+    /// Decrypts and parses an entity of a private drive, the counterpart of [`Entity::seal`].
+    pub fn parse_private(
+        tags: &[Tag<String>],
+        ciphertext: &[u8],
+        drive_key: &ArfsKey,
+    ) -> Result<Self, Error> {
+        let file_id = tags
+            .iter()
+            .find(|t| t.name == ENTITY_TYPE && t.value == EntityType::File.as_str())
+            .and_then(|_| tags.iter().find(|t| t.name == FILE_ID));
+        let key = match file_id {
+            Some(file_id) => drive_key.file(&file_id.value)?,
+            None => drive_key.clone(),
+        };
+        Self::parse(tags, &key.open(tags, ciphertext)?)
+    }
+
+    /// Parses an entity from the tags and data of its metadata transaction.
+    pub fn parse(tags: &[Tag<String>], metadata: &[u8]) -> Result<Self, Error> {
+        let tag = |name: &str| {
+            tags.iter()
+                .find(|t| t.name == name)
+                .map(|t| t.value.clone())
+                .ok_or_else(|| Error::ArfsError(format!("missing {} tag", name)))
+        };
+        tag(ARFS)?;
+        let entity_type: EntityType = serde_json::from_value(Value::String(tag(ENTITY_TYPE)?))
+            .map_err(|e| Error::ArfsError(e.to_string()))?;
+        let drive_id = tag(DRIVE_ID)?;
+        let unix_time = match tag(UNIX_TIME) {
+            Ok(time) => time
+                .parse()
+                .map_err(|_| Error::ArfsError(format!("invalid {} tag {}", UNIX_TIME, time)))?,
+            Err(_) => 0,
+        };
+
+        let metadata: Value =
+            serde_json::from_slice(metadata).map_err(|e| Error::ArfsError(e.to_string()))?;
+        let field = |name: &str| {
+            metadata[name]
+                .as_str()
+                .map(str::to_string)
+                .ok_or_else(|| Error::ArfsError(format!("missing {} metadata", name)))
+        };
+        let number = |name: &str| {
+            metadata[name]
+                .as_u64()
+                .ok_or_else(|| Error::ArfsError(format!("missing {} metadata", name)))
+        };
+
+        Ok(match entity_type {
+            EntityType::Drive => {
+                let privacy = serde_json::from_value(Value::String(tag(DRIVE_PRIVACY)?))
+                    .map_err(|e| Error::ArfsError(e.to_string()))?;
+                Entity::Drive(DriveEntity {
+                    drive_id,
+                    privacy,
+                    name: field("name")?,
+                    root_folder_id: field("rootFolderId")?,
+                    unix_time,
+                })
+            }
+            EntityType::Folder => Entity::Folder(FolderEntity {
+                drive_id,
+                folder_id: tag(FOLDER_ID)?,
+                parent_folder_id: tag(PARENT_FOLDER_ID).ok(),
+                name: field("name")?,
+                unix_time,
+            }),
+            EntityType::File => Entity::File(FileEntity {
+                drive_id,
+                file_id: tag(FILE_ID)?,
+                parent_folder_id: tag(PARENT_FOLDER_ID)?,
+                name: field("name")?,
+                size: number("size")?,
+                last_modified_date: number("lastModifiedDate")?,
+                data_tx_id: field("dataTxId")?,
+                data_content_type: field("dataContentType")?,
+                unix_time,
+            }),
+        })
+    }
+}
+
+fn new_id() -> String {
+    Uuid::new_v4().to_string()
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("System time before unix epoch")
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cipher::ArfsKey, DriveEntity, DrivePrivacy, Entity, FileEntity, FolderEntity};
+    use crate::transaction::tags::Tag;
+
+    fn round_trip(entity: Entity) -> Entity {
+        let tags: Vec<Tag<String>> = entity
+            .tags()
+            .unwrap()
+            .iter()
+            .map(Tag::try_from)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        Entity::parse(&tags, &entity.metadata().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_entities_round_trip() {
+        let (drive, root) = DriveEntity::new("photos");
+        assert_eq!(drive.privacy, DrivePrivacy::Public);
+        assert_eq!(root.folder_id, drive.root_folder_id);
+        assert_eq!(root.parent_folder_id, None);
+
+        let folder = FolderEntity::new(&drive.drive_id, &root.folder_id, "2022");
+        let file = FileEntity::new(
+            &drive.drive_id,
+            &folder.folder_id,
+            "beach.jpg",
+            1024,
+            1_660_000_000_000,
+            "data-tx",
+            "image/jpeg",
+        );
+
+        for entity in [
+            Entity::Drive(drive),
+            Entity::Folder(root),
+            Entity::Folder(folder),
+            Entity::File(file),
+        ] {
+            assert_eq!(round_trip(entity.clone()), entity);
+        }
+    }
+
+    #[test]
+    fn test_parse_rejects_non_arfs() {
+        let tags = vec![Tag {
+            name: "Entity-Type".to_string(),
+            value: "drive".to_string(),
+        }];
+        assert!(Entity::parse(&tags, b"{}").is_err());
+    }
+
+    #[test]
+    fn test_private_entities_round_trip() {
+        let (drive, root) = DriveEntity::new_private("secrets");
+        assert_eq!(drive.privacy, DrivePrivacy::Private);
+        let file = FileEntity::new(
+            &drive.drive_id,
+            &root.folder_id,
+            "keys.txt",
+            12,
+            1_660_000_000_000,
+            "data-tx",
+            "text/plain",
+        );
+        let drive_key = ArfsKey::from_bytes([3; 32]);
+
+        for entity in [
+            Entity::Drive(drive),
+            Entity::Folder(root),
+            Entity::File(file),
+        ] {
+            let (tags, ciphertext) = entity.seal(&drive_key).unwrap();
+            let tags: Vec<Tag<String>> = tags
+                .iter()
+                .map(Tag::try_from)
+                .collect::<Result<_, _>>()
+                .unwrap();
+            assert!(Entity::parse(&tags, &ciphertext).is_err());
+            assert_eq!(
+                Entity::parse_private(&tags, &ciphertext, &drive_key).unwrap(),
+                entity
+            );
+            assert!(
+                Entity::parse_private(&tags, &ciphertext, &ArfsKey::from_bytes([4; 32])).is_err()
+            );
+        }
+    }
+}
@@ -0,0 +1,208 @@
+//! Structured audit trail of posted transactions, for billing reconciliation.
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fs::{File, OpenOptions},
+    future::Future,
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{crypto::base64::Base64, error::Error};
+
+/// What happened while posting one transaction.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AuditRecord {
+    /// Id the transaction was signed with.
+    pub id: String,
+    /// Id it was accepted under, which differs from `id` when it was re-anchored. `None` if
+    /// it was not accepted.
+    pub posted_id: Option<String>,
+    /// Anchor of the last attempt.
+    pub anchor: String,
+    /// Latest fee quoted for the transaction's data size, if one was requested.
+    pub fee_quoted: Option<u64>,
+    /// Reward the transaction pays.
+    pub fee_paid: u64,
+    pub data_size: u64,
+    /// Base URL of the gateway that accepted the transaction, `None` if it was not accepted
+    /// or the gateway doesn't report it.
+    #[serde(default)]
+    pub gateway: Option<String>,
+    /// When posting started, in milliseconds since the epoch.
+    pub started_at: u128,
+    /// Time taken to post the transaction, re-anchoring included, in milliseconds.
+    pub duration_ms: u128,
+    /// Number of requests sent again: re-anchored posts, and posts or chunks retried after
+    /// a failure or a rate limit.
+    pub retries: u16,
+    pub dry_run: bool,
+    /// Why posting failed, if it did.
+    pub error: Option<String>,
+}
+
+/// What happened while posting, noted by the code sending the requests.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Trace {
+    pub retries: u16,
+    pub gateway: Option<String>,
+    /// Anchor of the last attempt, if the transaction was re-anchored.
+    pub anchor: Option<Base64>,
+}
+
+tokio::task_local! {
+    static TRACE: RefCell<Trace>;
+}
+
+/// Runs `f`, collecting what is noted while it runs.
+pub(crate) async fn traced<F: Future>(f: F) -> (F::Output, Trace) {
+    TRACE
+        .scope(RefCell::new(Trace::default()), async {
+            let output = f.await;
+            (output, TRACE.with(|trace| trace.take()))
+        })
+        .await
+}
+
+fn note(f: impl FnOnce(&mut Trace)) {
+    // Outside of [`traced`], nothing is collected.
+    let _ = TRACE.try_with(|trace| f(&mut trace.borrow_mut()));
+}
+
+/// Counts a request sent again.
+pub(crate) fn note_retry() {
+    note(|trace| trace.retries = trace.retries.saturating_add(1));
+}
+
+/// Notes that the gateway at `url` accepted the transaction.
+pub(crate) fn note_gateway(url: &url::Url) {
+    note(|trace| trace.gateway = Some(url.to_string()));
+}
+
+/// Notes that the transaction was re-anchored to `anchor`.
+pub(crate) fn note_anchor(anchor: &Base64) {
+    note(|trace| trace.anchor = Some(anchor.clone()));
+}
+
+/// Keeps the last [`AuditRecord`] and, optionally, appends every record to a JSON lines
+/// file.
+#[derive(Default)]
+pub struct AuditLog {
+    path: Option<PathBuf>,
+    file: Option<Mutex<File>>,
+    last: Mutex<Option<AuditRecord>>,
+    quotes: Mutex<HashMap<u64, u64>>,
+}
+
+impl AuditLog {
+    /// Audit log kept in memory only.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Audit log appending to `path`, created if needed.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref().to_path_buf();
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| Error::IoError(e.to_string()))?;
+        Ok(Self {
+            path: Some(path),
+            file: Some(Mutex::new(file)),
+            ..Self::default()
+        })
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Notes that `fee` was quoted for `data_size` bytes, to be reported as `fee_quoted` by
+    /// the next record of that size.
+    pub fn quoted(&self, data_size: u64, fee: u64) {
+        self.quotes.lock().unwrap().insert(data_size, fee);
+    }
+
+    /// Takes the fee last quoted for `data_size` bytes.
+    pub fn take_quote(&self, data_size: u64) -> Option<u64> {
+        self.quotes.lock().unwrap().remove(&data_size)
+    }
+
+    /// Keeps `record` as the last one and appends it to the file, if any.
+    pub fn record(&self, record: AuditRecord) -> Result<(), Error> {
+        if let Some(file) = &self.file {
+            let mut line =
+                serde_json::to_vec(&record).map_err(|e| Error::IoError(e.to_string()))?;
+            line.push(b'\n');
+            let mut file = file.lock().unwrap();
+            file.write_all(&line)
+                .and_then(|_| file.sync_data())
+                .map_err(|e| Error::IoError(e.to_string()))?;
+        }
+        *self.last.lock().unwrap() = Some(record);
+        Ok(())
+    }
+
+    pub fn last(&self) -> Option<AuditRecord> {
+        self.last.lock().unwrap().clone()
+    }
+
+    /// Reads back the records exported to `path`.
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Vec<AuditRecord>, Error> {
+        let reader = BufReader::new(File::open(path).map_err(|e| Error::IoError(e.to_string()))?);
+        reader
+            .lines()
+            .map(|line| {
+                let line = line.map_err(|e| Error::IoError(e.to_string()))?;
+                serde_json::from_str(&line).map_err(|e| Error::IoError(e.to_string()))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AuditLog, AuditRecord};
+
+    fn record(id: &str) -> AuditRecord {
+        AuditRecord {
+            id: id.to_string(),
+            posted_id: Some(id.to_string()),
+            anchor: "anchor".to_string(),
+            fee_quoted: Some(10),
+            fee_paid: 10,
+            data_size: 7,
+            gateway: Some("https://arweave.net/".to_string()),
+            started_at: 0,
+            duration_ms: 12,
+            retries: 0,
+            dry_run: false,
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_exports_records() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.jsonl");
+        let log = AuditLog::open(&path).unwrap();
+
+        log.quoted(7, 10);
+        assert_eq!(log.take_quote(7), Some(10));
+        assert_eq!(log.take_quote(7), None);
+
+        log.record(record("a")).unwrap();
+        log.record(record("b")).unwrap();
+        assert_eq!(log.last(), Some(record("b")));
+        assert_eq!(
+            AuditLog::read(&path).unwrap(),
+            vec![record("a"), record("b")]
+        );
+    }
+}
@@ -0,0 +1,54 @@
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+
+use crate::{crypto::base64::Base64, error::Error, transaction::Tx, Arweave};
+
+use super::{SignedUpload, Upload, UploadBackend};
+
+/// In-memory [`UploadBackend`] keeping every posted upload, signed as a transaction without
+/// contacting the gateway.
+#[derive(Default)]
+pub struct MockBackend {
+    uploads: Mutex<Vec<SignedUpload>>,
+}
+
+impl MockBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Uploads posted so far, oldest first.
+    pub fn uploads(&self) -> Vec<SignedUpload> {
+        self.uploads.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl UploadBackend for MockBackend {
+    async fn sign(&self, arweave: &Arweave, upload: Upload) -> Result<SignedUpload, Error> {
+        let signer = arweave.current_signer()?;
+        let transaction = Tx::new(
+            signer.get_provider(),
+            Base64::empty(),
+            upload.data,
+            0,
+            upload.fee,
+            Base64::from(vec![0; 32]),
+            upload.tags,
+            upload.auto_content_tag,
+        )?;
+        Ok(SignedUpload::Transaction(
+            signer.sign_transaction(transaction)?,
+        ))
+    }
+
+    async fn post(&self, _arweave: &Arweave, signed: SignedUpload) -> Result<(String, u64), Error> {
+        let res = match &signed {
+            SignedUpload::Transaction(tx) => (tx.id.to_string(), tx.reward),
+            SignedUpload::DataItem(item) => (item.id().to_string(), 0),
+        };
+        self.uploads.lock().unwrap().push(signed);
+        Ok(res)
+    }
+}
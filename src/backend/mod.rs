@@ -0,0 +1,101 @@
+//! Submission paths for uploads, so [`crate::Arweave::upload_file_from_path`] can post to the
+//! base layer, a bundler or an upload service without changes in callers.
+
+use async_trait::async_trait;
+
+use crate::{
+    crypto::base64::Base64,
+    data_item::DataItem,
+    error::Error,
+    transaction::{tags::Tag, Tx},
+    Arweave,
+};
+
+pub mod mock;
+
+pub use mock::MockBackend;
+
+/// Data to upload, with the fee quoted for it on the base layer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Upload {
+    pub data: Vec<u8>,
+    pub tags: Vec<Tag<Base64>>,
+    pub fee: u64,
+    /// Whether to add a `Content-Type` tag guessed from the data.
+    pub auto_content_tag: bool,
+}
+
+/// An upload signed by a backend, ready to be posted.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SignedUpload {
+    Transaction(Tx),
+    DataItem(DataItem),
+}
+
+impl SignedUpload {
+    pub fn id(&self) -> String {
+        match self {
+            SignedUpload::Transaction(tx) => tx.id.to_string(),
+            SignedUpload::DataItem(item) => item.id().to_string(),
+        }
+    }
+}
+
+#[async_trait]
+pub trait UploadBackend: Send + Sync {
+    /// Signs `upload` with the current signer of `arweave`.
+    async fn sign(&self, arweave: &Arweave, upload: Upload) -> Result<SignedUpload, Error>;
+
+    /// Posts an upload returned by [`UploadBackend::sign`]. Returns its id and the reward
+    /// paid on the base layer, 0 when the backend charges its own balance.
+    async fn post(&self, arweave: &Arweave, signed: SignedUpload) -> Result<(String, u64), Error>;
+}
+
+/// Posts transactions to the gateway of the client, uploading their data in chunks.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BaseLayer;
+
+#[async_trait]
+impl UploadBackend for BaseLayer {
+    async fn sign(&self, arweave: &Arweave, upload: Upload) -> Result<SignedUpload, Error> {
+        let transaction = arweave
+            .create_transaction(
+                Base64::empty(),
+                upload.tags,
+                upload.data,
+                0,
+                upload.fee,
+                upload.auto_content_tag,
+            )
+            .await?;
+        let signed_transaction = arweave.sign_transaction_async(transaction).await?;
+        Ok(SignedUpload::Transaction(signed_transaction))
+    }
+
+    async fn post(&self, arweave: &Arweave, signed: SignedUpload) -> Result<(String, u64), Error> {
+        match signed {
+            SignedUpload::Transaction(tx) => arweave.post_signed_transaction(tx).await,
+            SignedUpload::DataItem(item) => Err(Error::DataItemError(format!(
+                "{} can't be posted to the base layer",
+                item.id()
+            ))),
+        }
+    }
+}
+
+/// Signs `upload` as a data item with the current signer of `arweave`, for bundling
+/// backends.
+pub(crate) async fn sign_data_item(arweave: &Arweave, upload: Upload) -> Result<DataItem, Error> {
+    let signer = arweave.current_signer()?;
+    let mut tags = Vec::with_capacity(upload.tags.len() + 1);
+    if upload.auto_content_tag {
+        let content_type = infer::get(&upload.data)
+            .map(|kind| kind.mime_type())
+            .unwrap_or("application/octet-stream");
+        tags.push(Tag::<Base64>::content_type(content_type)?);
+    }
+    tags.extend(upload.tags);
+    tokio::task::spawn_blocking(move || DataItem::new(upload.data, tags).sign(&signer))
+        .await
+        .map_err(|e| Error::TaskError(e.to_string()))?
+}
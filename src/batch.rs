@@ -0,0 +1,475 @@
+//! Concurrent upload of many files sharing one anchor and one fee quote per size bucket.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs,
+    ops::ControlFlow,
+    path::PathBuf,
+    time::SystemTime,
+};
+
+use futures::{stream, StreamExt};
+use tokio::sync::{Mutex, OnceCell};
+
+use crate::{
+    consts::BLOCK_SIZE,
+    crypto::base64::Base64,
+    error::Error,
+    events::UploadEvent,
+    journal::{PendingUpload, UploadJournal},
+    schedule::{Schedule, SnapshotJob},
+    transaction::{tags::Tag, Tx},
+    Arweave,
+};
+
+/// Number of chunks of a journaled upload posted concurrently.
+const JOURNALED_CHUNKS_BUFFER: usize = 100;
+
+/// Uploads batches of files through an [`Arweave`] instance with at most `concurrency`
+/// files in flight.
+///
+/// The anchor is fetched once and reused by every transaction of the manager, and fees are
+/// quoted once per [`BLOCK_SIZE`] bucket, since the network prices data in whole blocks.
+/// Anchors expire after ~50 blocks, so long running managers should be paired with
+/// [`Arweave::set_re_anchor`].
+///
+/// With an [`UploadJournal`] set, every posted header and acknowledged chunk is journaled so
+/// that [`UploadManager::recover`] can resume the uploads interrupted by a crash.
+pub struct UploadManager<'a> {
+    arweave: &'a Arweave,
+    concurrency: usize,
+    anchor: OnceCell<Base64>,
+    fees: Mutex<HashMap<u64, u64>>,
+    journal: Option<UploadJournal>,
+}
+
+impl<'a> UploadManager<'a> {
+    pub fn new(arweave: &'a Arweave, concurrency: usize) -> Self {
+        Self {
+            arweave,
+            concurrency: concurrency.max(1),
+            anchor: OnceCell::new(),
+            fees: Mutex::new(HashMap::new()),
+            journal: None,
+        }
+    }
+
+    pub fn set_journal(&mut self, journal: UploadJournal) {
+        self.journal = Some(journal);
+    }
+
+    pub fn journal(&self) -> Option<&UploadJournal> {
+        self.journal.as_ref()
+    }
+
+    /// Resumes every upload the journal holds no completion record for, reading the data
+    /// back from the original files and skipping the steps already journaled. Fails when no
+    /// journal is set or it can't be read.
+    pub async fn recover(&self) -> Result<Vec<(PathBuf, Result<(String, u64), Error>)>, Error> {
+        let journal = self
+            .journal
+            .as_ref()
+            .ok_or_else(|| Error::IoError("no upload journal set".to_string()))?;
+        let pending = journal.pending()?;
+
+        Ok(stream::iter(pending)
+            .map(|upload| async move {
+                let path = upload.path.clone();
+                let res = self.resume(journal, upload).await;
+                (path, res)
+            })
+            .buffered(self.concurrency)
+            .collect()
+            .await)
+    }
+
+    async fn resume(
+        &self,
+        journal: &UploadJournal,
+        upload: PendingUpload,
+    ) -> Result<(String, u64), Error> {
+        let data = fs::read(&upload.path).map_err(|e| Error::IoError(e.to_string()))?;
+        let header = upload.tx;
+        let transaction = tokio::task::spawn_blocking(move || header.with_data(data))
+            .await
+            .map_err(|e| Error::TaskError(e.to_string()))??;
+        self.post_journaled(journal, transaction, upload.header_posted, upload.acked)
+            .await
+    }
+
+    /// Uploads every file in `file_paths`, tagging each with `additional_tags` and its
+    /// content type. Results are returned in the order of `file_paths`.
+    pub async fn upload_files(
+        &self,
+        file_paths: Vec<PathBuf>,
+        additional_tags: Vec<Tag<Base64>>,
+    ) -> Vec<(PathBuf, Result<(String, u64), Error>)> {
+        stream::iter(file_paths)
+            .map(|file_path| {
+                let additional_tags = additional_tags.clone();
+                async move {
+                    let res = self.upload_file(file_path.clone(), additional_tags).await;
+                    (file_path, res)
+                }
+            })
+            .buffered(self.concurrency)
+            .collect()
+            .await
+    }
+
+    /// Uploads the files of `job`'s directory that are new or changed since its last
+    /// snapshot, and records the digest of those uploaded successfully.
+    pub async fn snapshot(
+        &self,
+        job: &mut SnapshotJob,
+    ) -> Result<Vec<(PathBuf, Result<(String, u64), Error>)>, Error> {
+        // Hashing the whole directory takes a while; keep it off the executor.
+        let scan = job.clone();
+        let changed = tokio::task::spawn_blocking(move || scan.changed_files())
+            .await
+            .map_err(|e| Error::TaskError(e.to_string()))??;
+        let mut digests: HashMap<PathBuf, _> = changed.into_iter().collect();
+        let mut paths: Vec<PathBuf> = digests.keys().cloned().collect();
+        paths.sort();
+        let results = self.upload_files(paths, job.tags().to_vec()).await;
+        for (path, res) in &results {
+            if let (Ok(_), Some(digest)) = (res, digests.remove(path)) {
+                job.record(path.clone(), digest);
+            }
+        }
+        Ok(results)
+    }
+
+    /// Snapshots `job` at every run of `schedule`, handing the outcome of each snapshot to
+    /// `on_snapshot` until it breaks.
+    pub async fn run_scheduled<F>(
+        &self,
+        job: &mut SnapshotJob,
+        schedule: Schedule,
+        mut on_snapshot: F,
+    ) where
+        F: FnMut(Result<Vec<(PathBuf, Result<(String, u64), Error>)>, Error>) -> ControlFlow<()>,
+    {
+        loop {
+            let now = SystemTime::now();
+            let wait = schedule
+                .next_after(now)
+                .duration_since(now)
+                .unwrap_or_default();
+            tokio::time::sleep(wait).await;
+            if on_snapshot(self.snapshot(job).await).is_break() {
+                return;
+            }
+        }
+    }
+
+    async fn upload_file(
+        &self,
+        file_path: PathBuf,
+        additional_tags: Vec<Tag<Base64>>,
+    ) -> Result<(String, u64), Error> {
+        let (tags, auto_content_tag) = Arweave::file_tags(&file_path, additional_tags)?;
+        let data = fs::read(&file_path).map_err(|e| Error::IoError(e.to_string()))?;
+        let fee = self.fee(data.len() as u64).await?;
+        let anchor = self
+            .anchor
+            .get_or_try_init(|| self.arweave.get_last_tx())
+            .await?
+            .clone();
+
+        let signer = self.arweave.current_signer()?;
+        let transaction = tokio::task::spawn_blocking(move || {
+            Tx::new(
+                signer.get_provider(),
+                Base64::empty(),
+                data,
+                0,
+                fee,
+                anchor,
+                tags,
+                auto_content_tag,
+            )
+        })
+        .await
+        .map_err(|e| Error::TaskError(e.to_string()))??;
+        let signed_transaction = self.arweave.sign_transaction_async(transaction).await?;
+        match &self.journal {
+            Some(journal) => {
+                journal.started(&file_path, &signed_transaction)?;
+                self.post_journaled(journal, signed_transaction, None, BTreeSet::new())
+                    .await
+            }
+            None => {
+                self.arweave
+                    .post_signed_transaction(signed_transaction)
+                    .await
+            }
+        }
+    }
+
+    /// Posts the header unless `header_posted` holds the id it was accepted under, then the
+    /// chunks missing from `acked`, journaling each step.
+    async fn post_journaled(
+        &self,
+        journal: &UploadJournal,
+        signed_transaction: Tx,
+        header_posted: Option<String>,
+        acked: BTreeSet<usize>,
+    ) -> Result<(String, u64), Error> {
+        let journal_id = signed_transaction.id.to_string();
+        let chunked = signed_transaction.data.0.len() > self.arweave.network.max_tx_data as usize;
+        if chunked {
+            signed_transaction.validate_chunk_layout()?;
+        }
+
+        let id = match header_posted {
+            Some(id) => id,
+            None => {
+                let header = if chunked {
+                    signed_transaction.clone_with_no_data()?
+                } else {
+                    signed_transaction.clone()
+                };
+                let (id, _) = self.arweave.post_transaction(&header).await?;
+                self.arweave
+                    .emit(UploadEvent::HeaderPosted { id: id.clone() });
+                journal.header_posted(&journal_id, &id)?;
+                id
+            }
+        };
+
+        if chunked && !self.arweave.dry_run {
+            let remaining = (0..signed_transaction.chunks.len())
+                .filter_map(|i| signed_transaction.get_chunk(i).ok())
+                .filter(|chunk| !acked.contains(&chunk.offset))
+                .map(|chunk| chunk.chunk.len() as u64)
+                .sum();
+            self.arweave.uploader.expect_bytes(remaining);
+
+            let results: Vec<Result<usize, Error>> = Arweave::upload_transaction_chunks_stream(
+                self.arweave,
+                &signed_transaction,
+                &acked,
+                JOURNALED_CHUNKS_BUFFER,
+            )
+            .then(|res| {
+                let (id, journal_id) = (&id, &journal_id);
+                async move {
+                    let offset = res?;
+                    journal.chunk_acked(journal_id, offset)?;
+                    self.arweave.emit(UploadEvent::ChunkPosted {
+                        id: id.clone(),
+                        offset,
+                    });
+                    Ok(offset)
+                }
+            })
+            .collect()
+            .await;
+            results.into_iter().collect::<Result<Vec<usize>, Error>>()?;
+        }
+
+        journal.completed(&journal_id)?;
+        Ok((id, signed_transaction.reward))
+    }
+
+    /// Fee for `data_size` bytes, quoted once per block bucket at the bucket's upper bound.
+    async fn fee(&self, data_size: u64) -> Result<u64, Error> {
+        let bucket = data_size.div_ceil(BLOCK_SIZE);
+        let mut fees = self.fees.lock().await;
+        if let Some(fee) = fees.get(&bucket) {
+            return Ok(*fee);
+        }
+        let fee = self
+            .arweave
+            .get_fee_by_size(bucket * BLOCK_SIZE, None)
+            .await?;
+        fees.insert(bucket, fee);
+        Ok(fee)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, ops::ControlFlow, path::PathBuf, str::FromStr, sync::Arc, time::Duration};
+
+    use httpmock::{
+        Method::{GET, POST},
+        MockServer,
+    };
+
+    use super::UploadManager;
+    use crate::{
+        consts::{BLOCK_SIZE, MAX_TX_DATA},
+        crypto::base64::Base64,
+        gateway::{Gateway, MockGateway},
+        journal::UploadJournal,
+        schedule::{Schedule, SnapshotJob},
+        transaction::Tx,
+        Arweave,
+    };
+
+    #[tokio::test]
+    async fn test_shares_anchor_and_fee_quotes() {
+        let server = MockServer::start_async().await;
+        let anchor = server.mock(|when, then| {
+            when.method(GET).path("/tx_anchor");
+            then.status(200)
+                .body("ddvXNxatQmS3LeKi_x1RJn6g9G0esUaTEgT40a6f_WYyawZaSK3w8WC2czAuLgmT");
+        });
+        let one_block = server.mock(|when, then| {
+            when.method(GET).path(format!("/price/{}", BLOCK_SIZE));
+            then.status(200).body("100");
+        });
+        let two_blocks = server.mock(|when, then| {
+            when.method(GET).path(format!("/price/{}", 2 * BLOCK_SIZE));
+            then.status(200).body("200");
+        });
+        let post = server.mock(|when, then| {
+            when.method(POST).path("/tx");
+            then.status(200);
+        });
+
+        let dir = tempfile::tempdir().unwrap();
+        let sizes = [10, 20_000, BLOCK_SIZE as usize + 1];
+        let paths: Vec<PathBuf> = sizes
+            .iter()
+            .enumerate()
+            .map(|(i, size)| {
+                let path = dir.path().join(format!("{}.bin", i));
+                fs::write(&path, vec![i as u8; *size]).unwrap();
+                path
+            })
+            .collect();
+
+        let arweave = Arweave::from_keypair_path(
+            PathBuf::from_str("res/test_wallet.json").unwrap(),
+            url::Url::parse(&server.url("")).unwrap(),
+        )
+        .unwrap();
+        let results = UploadManager::new(&arweave, 2)
+            .upload_files(paths.clone(), vec![])
+            .await;
+
+        let rewards: Vec<u64> = results
+            .into_iter()
+            .zip(paths)
+            .map(|((path, res), expected)| {
+                assert_eq!(path, expected);
+                res.unwrap().1
+            })
+            .collect();
+        assert_eq!(rewards, vec![100, 100, 200]);
+        anchor.assert_hits(1);
+        one_block.assert_hits(1);
+        two_blocks.assert_hits(1);
+        post.assert_hits(3);
+    }
+
+    #[tokio::test]
+    async fn test_scheduled_snapshots_upload_changed_files() {
+        let gateway = Arc::new(MockGateway::new());
+        let mut arweave = Arweave::from_keypair_path(
+            PathBuf::from_str("res/test_wallet.json").unwrap(),
+            url::Url::parse("http://127.0.0.1:1").unwrap(),
+        )
+        .unwrap();
+        arweave.set_gateway(gateway.clone());
+
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().to_path_buf();
+        fs::write(dir.join("a.txt"), "a").unwrap();
+        fs::write(dir.join("b.txt"), "b").unwrap();
+
+        let manager = UploadManager::new(&arweave, 2);
+        let mut job = SnapshotJob::new(dir.clone());
+        let mut uploaded = Vec::new();
+        manager
+            .run_scheduled(
+                &mut job,
+                Schedule::Every(Duration::from_millis(10)),
+                |res| {
+                    let paths: Vec<PathBuf> =
+                        res.unwrap().into_iter().map(|(path, _)| path).collect();
+                    uploaded.push(paths);
+                    fs::write(dir.join("b.txt"), "c").unwrap();
+                    if uploaded.len() < 3 {
+                        ControlFlow::Continue(())
+                    } else {
+                        ControlFlow::Break(())
+                    }
+                },
+            )
+            .await;
+
+        assert_eq!(
+            uploaded,
+            vec![
+                vec![dir.join("a.txt"), dir.join("b.txt")],
+                vec![dir.join("b.txt")],
+                vec![]
+            ]
+        );
+        assert_eq!(gateway.posted_ids().len(), 3);
+        assert_eq!(job.digests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_recover_resumes_interrupted_upload() {
+        let gateway = Arc::new(MockGateway::new());
+        let mut arweave = Arweave::from_keypair_path(
+            PathBuf::from_str("res/test_wallet.json").unwrap(),
+            url::Url::parse("http://127.0.0.1:1").unwrap(),
+        )
+        .unwrap();
+        arweave.set_gateway(gateway.clone());
+
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("data.bin");
+        let journal_path = dir.path().join("journal.jsonl");
+        let data: Vec<u8> = (0..MAX_TX_DATA + 1).map(|i| i as u8).collect();
+        fs::write(&file_path, &data).unwrap();
+
+        let tx = Tx::new(
+            arweave.signer.get_provider(),
+            Base64::empty(),
+            data,
+            0,
+            0,
+            gateway.anchor.clone(),
+            vec![],
+            false,
+        )
+        .unwrap();
+        let signed_tx = arweave.sign_transaction(tx).unwrap();
+        let id = signed_tx.id.to_string();
+
+        // Crash after the header and the first chunk were accepted.
+        let journal = UploadJournal::open(&journal_path).unwrap();
+        journal.started(&file_path, &signed_tx).unwrap();
+        gateway
+            .post_tx(&signed_tx.clone_with_no_data().unwrap())
+            .await
+            .unwrap();
+        journal.header_posted(&id, &id).unwrap();
+        let first = signed_tx.get_chunk(0).unwrap();
+        gateway.post_chunk(first).await.unwrap();
+        journal.chunk_acked(&id, first.offset).unwrap();
+        drop(journal);
+
+        let mut manager = UploadManager::new(&arweave, 2);
+        manager.set_journal(UploadJournal::open(&journal_path).unwrap());
+        let results = manager.recover().await.unwrap();
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0, file_path);
+        assert_eq!(results[0].1.as_ref().unwrap().0, id);
+        assert_eq!(
+            gateway.chunk_count(&signed_tx.data_root),
+            signed_tx.chunks.len()
+        );
+        assert_eq!(gateway.posted_ids(), vec![id]);
+        assert_eq!(arweave.uploader().remaining_bytes(), 0);
+        assert!(manager.journal().unwrap().pending().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,78 @@
+//! Lookups of blocks by time, to turn "everything uploaded in March" into the block range
+//! fed to [`crate::Arweave::iter_txs_in_blocks`] or a GraphQL query.
+//!
+//! Block timestamps are assumed to increase with height, which holds on the network up to
+//! the few seconds of clock drift allowed between miners.
+
+use std::ops::Range;
+
+use crate::{error::Error, gateway::Gateway};
+
+/// Lowest height of `heights` whose block was mined at or after `timestamp`, in seconds
+/// since the epoch. `heights.end` when every block is older.
+pub async fn first_height_since(
+    gateway: &dyn Gateway,
+    timestamp: u64,
+    heights: Range<u64>,
+) -> Result<u64, Error> {
+    let (mut low, mut high) = (heights.start, heights.end);
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if gateway.get_block(mid).await?.timestamp < timestamp {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+    Ok(low)
+}
+
+/// Height of the block of `heights` mined nearest to `timestamp`, in seconds since the epoch.
+pub async fn height_at_timestamp(
+    gateway: &dyn Gateway,
+    timestamp: u64,
+    heights: Range<u64>,
+) -> Result<u64, Error> {
+    if heights.is_empty() {
+        return Err(Error::NetworkInfoError("empty block range".to_string()));
+    }
+    let after = first_height_since(gateway, timestamp, heights.clone()).await?;
+    if after == heights.start {
+        return Ok(after);
+    }
+    let before = after - 1;
+    if after == heights.end {
+        return Ok(before);
+    }
+    let before_gap = timestamp.abs_diff(gateway.get_block(before).await?.timestamp);
+    let after_gap = timestamp.abs_diff(gateway.get_block(after).await?.timestamp);
+    Ok(if after_gap < before_gap {
+        after
+    } else {
+        before
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{first_height_since, height_at_timestamp};
+    use crate::gateway::MockGateway;
+
+    #[tokio::test]
+    async fn test_finds_heights_by_time() {
+        let gateway = MockGateway::new();
+        for height in 0..10 {
+            gateway.add_block(height, 1000 + 120 * height, vec![]);
+        }
+
+        assert_eq!(first_height_since(&gateway, 1240, 0..10).await.unwrap(), 2);
+        assert_eq!(first_height_since(&gateway, 1241, 0..10).await.unwrap(), 3);
+        assert_eq!(first_height_since(&gateway, 9999, 0..10).await.unwrap(), 10);
+
+        assert_eq!(height_at_timestamp(&gateway, 1250, 0..10).await.unwrap(), 2);
+        assert_eq!(height_at_timestamp(&gateway, 1350, 0..10).await.unwrap(), 3);
+        assert_eq!(height_at_timestamp(&gateway, 0, 0..10).await.unwrap(), 0);
+        assert_eq!(height_at_timestamp(&gateway, 9999, 0..10).await.unwrap(), 9);
+        assert!(height_at_timestamp(&gateway, 1250, 5..5).await.is_err());
+    }
+}
@@ -0,0 +1,104 @@
+//! Spending limits enforced before transactions are signed or posted.
+
+use std::sync::Mutex;
+
+use crate::error::Error;
+
+/// Optional caps, in winston, on what a single [`crate::Arweave`] instance is allowed to spend.
+///
+/// The cost of a transaction is its reward plus the quantity transferred to the target.
+#[derive(Debug, Default)]
+pub struct Budget {
+    pub max_per_tx: Option<u128>,
+    pub max_per_session: Option<u128>,
+    spent: Mutex<u128>,
+}
+
+impl Budget {
+    pub fn new(max_per_tx: Option<u128>, max_per_session: Option<u128>) -> Self {
+        Self {
+            max_per_tx,
+            max_per_session,
+            spent: Mutex::new(0),
+        }
+    }
+
+    /// Total amount recorded against the session limit so far.
+    pub fn spent(&self) -> u128 {
+        *self.spent.lock().unwrap()
+    }
+
+    /// Amount left before hitting the session limit, if there is one.
+    pub fn remaining(&self) -> Option<u128> {
+        self.max_per_session
+            .map(|max| max.saturating_sub(self.spent()))
+    }
+
+    /// Checks `amount` against both limits without recording it.
+    pub fn check(&self, amount: u128) -> Result<(), Error> {
+        self.check_with_spent(amount, self.spent())
+    }
+
+    /// Checks `amount` against both limits and, if it fits, records it as spent.
+    pub fn record(&self, amount: u128) -> Result<(), Error> {
+        let mut spent = self.spent.lock().unwrap();
+        self.check_with_spent(amount, *spent)?;
+        *spent = spent
+            .checked_add(amount)
+            .ok_or_else(|| Error::AmountOverflow(format!("{} + {}", *spent, amount)))?;
+        Ok(())
+    }
+
+    /// Gives back an amount previously recorded, e.g. when posting failed.
+    pub fn release(&self, amount: u128) {
+        let mut spent = self.spent.lock().unwrap();
+        *spent = spent.saturating_sub(amount);
+    }
+
+    fn check_with_spent(&self, amount: u128, spent: u128) -> Result<(), Error> {
+        if let Some(max) = self.max_per_tx {
+            if amount > max {
+                return Err(Error::BudgetExceeded(format!(
+                    "transaction cost {} exceeds per-transaction limit {}",
+                    amount, max
+                )));
+            }
+        }
+        if let Some(max) = self.max_per_session {
+            if spent.saturating_add(amount) > max {
+                return Err(Error::BudgetExceeded(format!(
+                    "transaction cost {} exceeds remaining session budget {}",
+                    amount,
+                    max.saturating_sub(spent)
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Budget;
+    use crate::error::Error;
+
+    #[test]
+    fn test_per_tx_limit() {
+        let budget = Budget::new(Some(100), None);
+        assert!(budget.check(100).is_ok());
+        assert!(matches!(budget.check(101), Err(Error::BudgetExceeded(_))));
+    }
+
+    #[test]
+    fn test_session_limit() {
+        let budget = Budget::new(None, Some(250));
+        budget.record(100).unwrap();
+        budget.record(100).unwrap();
+        assert_eq!(budget.remaining(), Some(50));
+        assert!(matches!(budget.record(51), Err(Error::BudgetExceeded(_))));
+        assert_eq!(budget.spent(), 200);
+
+        budget.release(100);
+        assert!(budget.record(150).is_ok());
+    }
+}
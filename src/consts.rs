@@ -2,12 +2,24 @@ pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 pub const ARWEAVE_BASE_URL: &str = "https://arweave.net/";
 
+/// Gateway of the public testnet, an ArLocal instance whose tokens are free.
+pub const TESTNET_BASE_URL: &str = "https://testnet.redstone.tools/";
+
+/// Default address of an ArLocal node.
+pub const ARLOCAL_BASE_URL: &str = "http://localhost:1984/";
+
+/// Environment variable read by `from_base64_env` signers: a base64 encoded JWK keyfile.
+pub const JWK_ENV_VAR: &str = "ARWEAVE_JWK_B64";
+
 /// Block size used for pricing calculations = 256 KB
 pub const BLOCK_SIZE: u64 = 1024 * 256;
 
 /// Maximum data size to send to `tx/` endpoint. Sent to `chunk/` endpoint above this.
 pub const MAX_TX_DATA: u64 = 10_000_000;
 
+/// Maximum total size in bytes of the names and values of a transaction's tags.
+pub const MAX_TAGS_SIZE: usize = 2048;
+
 /// Multiplier applied to the buffer argument from the cli to determine the maximum number
 /// of simultaneous request to the `chunk/ endpoint`.
 pub const CHUNKS_BUFFER_FACTOR: usize = 20;
@@ -18,6 +30,86 @@ pub const CHUNKS_RETRIES: u16 = 10;
 /// Number of seconds to wait between retying to post a failed chunk.
 pub const CHUNKS_RETRY_SLEEP: u64 = 1;
 
+/// Longest `Retry-After` delay, in seconds, waited out before reposting a rate limited chunk.
+pub const MAX_RETRY_AFTER: u64 = 60;
+
+/// Number of seconds of chunk uploads averaged to compute the upload rate.
+pub const UPLOAD_RATE_WINDOW: u64 = 10;
+
+/// Average number of seconds between two mainnet blocks.
+pub const BLOCK_TIME: u64 = 120;
+
+/// Number of times a transaction rejected for a stale anchor is re-anchored and re-posted.
+pub const ANCHOR_RETRIES: u16 = 3;
+
+/// Number of upload events buffered for slow subscribers before the oldest are dropped.
+pub const UPLOAD_EVENTS_CAPACITY: usize = 1024;
+
+/// Maximum total size, in bytes, of response bodies kept for `If-None-Match` revalidation.
+pub const ETAG_CACHE_SIZE: usize = 64 * 1024 * 1024;
+
+/// Number of seconds a `/price` quote is reused for.
+pub const PRICE_CACHE_TTL: u64 = 30;
+
+/// Number of seconds a `/tx_anchor` response is reused for.
+pub const ANCHOR_CACHE_TTL: u64 = 10;
+
+/// Number of seconds an `/info` response is reused for.
+pub const INFO_CACHE_TTL: u64 = 5;
+
+/// Relative change of the fee over a sampled period below which its trend is flat.
+pub const PRICE_TREND_THRESHOLD: f64 = 0.01;
+
+/// Values a single filter of a GraphQL query may list before the query is split, to stay
+/// under the complexity limit of gateways.
+pub const GRAPHQL_MAX_FILTER_VALUES: usize = 100;
+
+/// Size of the blocks IPFS splits payloads into by default.
+pub const IPFS_CHUNK_SIZE: usize = 256 * 1024;
+
+/// Upload service of ArDrive Turbo.
+pub const TURBO_UPLOAD_URL: &str = "https://upload.ardrive.io/";
+
+/// Payment service of ArDrive Turbo, holding credit balances.
+pub const TURBO_PAYMENT_URL: &str = "https://payment.ardrive.io/";
+
+/// Number of times the upload of an S3 object is attempted before it is reported failed.
+pub const S3_OBJECT_RETRIES: u16 = 3;
+
+/// Number of seconds to wait between two attempts to upload an S3 object.
+pub const S3_RETRY_SLEEP: u64 = 1;
+
+/// Number of blocks, or transactions, fetched concurrently when scanning the chain.
+pub const SCAN_CONCURRENCY: usize = 8;
+
+/// Consecutive failures after which a gateway of a pool is skipped.
+pub const CIRCUIT_BREAKER_THRESHOLD: u32 = 3;
+
+/// Number of seconds a tripped gateway is skipped for.
+pub const CIRCUIT_BREAKER_COOLDOWN: u64 = 30;
+
+/// Number of peers probed when discovering gateways.
+pub const DISCOVERY_SAMPLE_SIZE: usize = 20;
+
+/// Number of seconds a peer has to answer a discovery probe.
+pub const DISCOVERY_TIMEOUT: u64 = 5;
+
+/// Blocks a discovered peer may lag behind the seed node and still be used.
+pub const DISCOVERY_MAX_HEIGHT_LAG: u64 = 5;
+
+/// Number of seconds between two status checks of watched transactions.
+pub const CONFIRMATION_POLL_INTERVAL: u64 = 30;
+
+/// Number of seconds between two GraphQL polls for the transactions of a watched address.
+pub const ACTIVITY_POLL_INTERVAL: u64 = 30;
+
+/// Number of recently scanned blocks whose hashes are kept to detect reorgs when scanning
+/// for deposits.
+pub const DEPOSIT_REORG_DEPTH: usize = 50;
+
+/// Confirmations after which a transaction is considered final.
+pub const FINAL_CONFIRMATIONS: u64 = 10;
+
 // First block to use V2 block format
 pub const V2_BLOCK_HEIGHT: u32 = 269510;
 
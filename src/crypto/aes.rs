@@ -0,0 +1,93 @@
+//! AES-256-GCM keys shared by the ArFS drive cipher and the two-party key envelopes.
+
+use aes_gcm::{
+    aead::{self, Aead, KeyInit},
+    Aes256Gcm, Nonce,
+};
+use rand::{thread_rng, RngCore};
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use super::utils::ct_eq;
+
+pub(crate) const KEY_LENGTH: usize = 32;
+pub(crate) const IV_LENGTH: usize = 12;
+
+/// AES-256-GCM key, scrubbed from memory on drop and compared in constant time.
+#[derive(Clone)]
+pub(crate) struct AesKey([u8; KEY_LENGTH]);
+
+impl Zeroize for AesKey {
+    fn zeroize(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl Drop for AesKey {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl ZeroizeOnDrop for AesKey {}
+
+impl PartialEq for AesKey {
+    fn eq(&self, other: &Self) -> bool {
+        ct_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for AesKey {}
+
+impl std::fmt::Debug for AesKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("AesKey(..)")
+    }
+}
+
+impl AesKey {
+    pub(crate) fn new(bytes: [u8; KEY_LENGTH]) -> Self {
+        Self(bytes)
+    }
+
+    pub(crate) fn as_bytes(&self) -> &[u8; KEY_LENGTH] {
+        &self.0
+    }
+
+    /// Encrypts `plaintext` under a fresh random IV, returned with the ciphertext.
+    pub(crate) fn encrypt(&self, plaintext: &[u8]) -> aead::Result<([u8; IV_LENGTH], Vec<u8>)> {
+        let mut iv = [0; IV_LENGTH];
+        thread_rng().fill_bytes(&mut iv);
+        let ciphertext = self.cipher().encrypt(Nonce::from_slice(&iv), plaintext)?;
+        Ok((iv, ciphertext))
+    }
+
+    /// Decrypts `ciphertext` sealed under `iv`, which must be [`IV_LENGTH`] bytes long.
+    pub(crate) fn decrypt(&self, iv: &[u8], ciphertext: &[u8]) -> aead::Result<Vec<u8>> {
+        if iv.len() != IV_LENGTH {
+            return Err(aead::Error);
+        }
+        self.cipher().decrypt(Nonce::from_slice(iv), ciphertext)
+    }
+
+    fn cipher(&self) -> Aes256Gcm {
+        Aes256Gcm::new(&self.0.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AesKey, IV_LENGTH};
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let key = AesKey::new([7; 32]);
+        let (iv, ciphertext) = key.encrypt(b"secret").unwrap();
+
+        assert_eq!(key.decrypt(&iv, &ciphertext).unwrap(), b"secret");
+        assert!(AesKey::new([8; 32]).decrypt(&iv, &ciphertext).is_err());
+        assert!(key.decrypt(&iv[..IV_LENGTH - 1], &ciphertext).is_err());
+        assert_eq!(key, AesKey::new([7; 32]));
+        assert_ne!(key, AesKey::new([8; 32]));
+        assert_eq!(format!("{:?}", key), "AesKey(..)");
+    }
+}
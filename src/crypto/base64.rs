@@ -1,11 +1,14 @@
-use std::str::FromStr;
+use std::{ops::Deref, str::FromStr};
 
+use bytes::Bytes;
 use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 
-use crate::error::Error;
+use crate::{crypto::utils::ct_eq, error::Error};
 
+/// Bytes encoded as unpadded url-safe base64 on the wire. Backed by [`Bytes`], so cloning a
+/// payload shared between tags, chunks and serialization does not copy it.
 #[derive(Debug, Clone, Eq, PartialEq, Default)]
-pub struct Base64(pub Vec<u8>);
+pub struct Base64(pub Bytes);
 
 impl std::fmt::Display for Base64 {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
@@ -16,15 +19,74 @@ impl std::fmt::Display for Base64 {
 
 impl From<&[u8]> for Base64 {
     fn from(u: &[u8]) -> Self {
-        Base64(u.to_vec())
+        Base64(Bytes::copy_from_slice(u))
+    }
+}
+
+impl From<Vec<u8>> for Base64 {
+    fn from(v: Vec<u8>) -> Self {
+        Base64(Bytes::from(v))
+    }
+}
+
+impl From<Bytes> for Base64 {
+    fn from(b: Bytes) -> Self {
+        Base64(b)
+    }
+}
+
+impl From<Base64> for Bytes {
+    fn from(b: Base64) -> Self {
+        b.0
+    }
+}
+
+impl Deref for Base64 {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for Base64 {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
     }
 }
 
 impl FromStr for Base64 {
-    type Err = base64::DecodeError;
+    type Err = Error;
     fn from_str(str: &str) -> Result<Self, Self::Err> {
-        let result = base64::decode_config(str, base64::URL_SAFE_NO_PAD)?;
-        Ok(Self(result))
+        let result = base64::decode_config(str, base64::URL_SAFE_NO_PAD)
+            .map_err(|e| Error::InvalidBase64(describe(e)))?;
+        Ok(Self::from(result))
+    }
+}
+
+/// Spells out where decoding failed, `base64::DecodeError` only prints raw byte values.
+fn describe(error: base64::DecodeError) -> String {
+    match error {
+        base64::DecodeError::InvalidByte(position, byte) => {
+            format!(
+                "invalid character {:?} at position {}",
+                byte as char, position
+            )
+        }
+        base64::DecodeError::InvalidLastSymbol(position, byte) => {
+            format!(
+                "invalid trailing character {:?} at position {}",
+                byte as char, position
+            )
+        }
+        base64::DecodeError::InvalidLength => "invalid length".to_string(),
+    }
+}
+
+impl TryFrom<&str> for Base64 {
+    type Error = Error;
+    fn try_from(str: &str) -> Result<Self, Self::Error> {
+        Self::from_str(str)
     }
 }
 
@@ -33,15 +95,37 @@ impl Base64 {
         self.0.is_empty()
     }
 
+    /// Wraps `bytes` without copying them.
+    pub const fn from_static(bytes: &'static [u8]) -> Self {
+        Base64(Bytes::from_static(bytes))
+    }
+
     pub fn from_utf8_str(str: &str) -> Result<Self, Error> {
-        Ok(Self(str.as_bytes().to_vec()))
+        Ok(Self(Bytes::copy_from_slice(str.as_bytes())))
     }
     pub fn to_utf8_string(&self) -> Result<String, Error> {
-        Ok(String::from_utf8(self.0.clone()).expect("Could not convert from utf8"))
+        std::str::from_utf8(&self.0)
+            .map(str::to_string)
+            .map_err(|e| {
+                Error::InvalidBase64(format!("invalid utf-8 at position {}", e.valid_up_to()))
+            })
+    }
+
+    /// Constant-time equality, see [`ct_eq`].
+    pub fn ct_eq(&self, other: &Base64) -> bool {
+        ct_eq(&self.0, &other.0)
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn into_bytes(self) -> Bytes {
+        self.0
     }
 
     pub fn empty() -> Self {
-        Base64(vec![])
+        Base64(Bytes::new())
     }
 }
 
@@ -62,9 +146,7 @@ impl<'de> Deserialize<'de> for Base64 {
             }
 
             fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
-                base64::decode_config(v, base64::URL_SAFE_NO_PAD)
-                    .map(Base64)
-                    .map_err(|_| de::Error::custom("failed to decode base64 string"))
+                Base64::from_str(v).map_err(de::Error::custom)
             }
         }
         deserializer.deserialize_str(Vis)
@@ -79,7 +161,7 @@ mod tests {
 
     #[test]
     fn test_deserialize_base64() {
-        let base_64 = Base64(vec![44; 7]);
+        let base_64 = Base64::from(vec![44; 7]);
         assert_eq!(base_64.0, vec![44; 7]);
         assert_eq!(format!("{}", base_64), "LCwsLCwsLA");
 
@@ -93,7 +175,7 @@ mod tests {
         let foo_b64 = Base64::from_utf8_str("foo").unwrap();
         assert_eq!(foo_b64.0, vec![102, 111, 111]);
 
-        let foo_b64 = Base64(vec![102, 111, 111]);
+        let foo_b64 = Base64::from_static(b"foo");
         assert_eq!(foo_b64.to_utf8_string().unwrap(), "foo".to_string());
     }
 
@@ -102,7 +184,41 @@ mod tests {
         let foo_b64 = Base64::from_str("LCwsLCwsLA").unwrap();
         assert_eq!(foo_b64.0, vec![44; 7]);
 
-        let foo_b64 = Base64(vec![44; 7]);
+        let foo_b64 = Base64::from(vec![44; 7]);
         assert_eq!(foo_b64.to_string(), "LCwsLCwsLA".to_string());
+
+        let foo_b64 = Base64::try_from("LCwsLCwsLA").unwrap();
+        assert_eq!(foo_b64, Base64::from(vec![44; 7]));
+        assert!(Base64::try_from("not base64!").is_err());
+    }
+
+    #[test]
+    fn test_describes_invalid_input() {
+        assert_eq!(
+            Base64::from_str("LCws!CwsLA").unwrap_err().to_string(),
+            "Invalid base64: invalid character '!' at position 4"
+        );
+        assert_eq!(
+            Base64::from_str("L").unwrap_err().to_string(),
+            "Invalid base64: invalid length"
+        );
+        assert_eq!(
+            Base64::from(vec![102, 0xff])
+                .to_utf8_string()
+                .unwrap_err()
+                .to_string(),
+            "Invalid base64: invalid utf-8 at position 1"
+        );
+
+        let err = serde_json::from_str::<Base64>("\"LC=w\"").unwrap_err();
+        assert!(err.to_string().contains("position 2"));
+    }
+
+    #[test]
+    fn test_clone_shares_bytes() {
+        let data = Base64::from(vec![1; 1024]);
+        let clone = data.clone();
+        assert_eq!(data.as_ptr(), clone.as_ptr());
+        assert_eq!(&clone[..], &[1; 1024][..]);
     }
 }
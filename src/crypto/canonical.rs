@@ -0,0 +1,159 @@
+//! JSON Canonicalization Scheme ([RFC 8785](https://www.rfc-editor.org/rfc/rfc8785)), so
+//! JSON signed here verifies in any language with a JCS implementation, whatever its
+//! serializer does with whitespace, key order or numbers.
+
+use serde::Serialize;
+use serde_json::{Number, Value};
+
+use crate::error::Error;
+
+/// Canonical form of `value`: no whitespace, object keys sorted by their UTF-16 code units,
+/// numbers formatted as ECMAScript does.
+pub fn canonicalize(value: &impl Serialize) -> Result<String, Error> {
+    let value =
+        serde_json::to_value(value).map_err(|e| Error::CanonicalJsonError(e.to_string()))?;
+    let mut out = String::new();
+    write_value(&value, &mut out)?;
+    Ok(out)
+}
+
+fn write_value(value: &Value, out: &mut String) -> Result<(), Error> {
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Number(n) => out.push_str(&format_number(n)?),
+        Value::String(s) => write_string(s, out),
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_value(item, out)?;
+            }
+            out.push(']');
+        }
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+            out.push('{');
+            for (i, (key, value)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_string(key, out);
+                out.push(':');
+                write_value(value, out)?;
+            }
+            out.push('}');
+        }
+    }
+    Ok(())
+}
+
+/// serde_json escapes strings as `JSON.stringify` does: only `"`, `\` and control
+/// characters, with lowercase hex digits.
+fn write_string(s: &str, out: &mut String) {
+    out.push_str(&serde_json::to_string(s).expect("strings always serialize"));
+}
+
+/// Formats a number as ECMAScript's `Number.prototype.toString`. Integers beyond 2^53 have
+/// no exact double representation, and are rejected rather than silently rounded.
+fn format_number(n: &Number) -> Result<String, Error> {
+    const MAX_SAFE_INTEGER: u64 = (1 << 53) - 1;
+    if let Some(i) = n.as_u64() {
+        if i > MAX_SAFE_INTEGER {
+            return Err(Error::CanonicalJsonError(format!(
+                "{} is not a safe integer",
+                i
+            )));
+        }
+        return Ok(i.to_string());
+    }
+    if let Some(i) = n.as_i64() {
+        if i.unsigned_abs() > MAX_SAFE_INTEGER {
+            return Err(Error::CanonicalJsonError(format!(
+                "{} is not a safe integer",
+                i
+            )));
+        }
+        return Ok(i.to_string());
+    }
+    let f = n
+        .as_f64()
+        .ok_or_else(|| Error::CanonicalJsonError(format!("unsupported number {}", n)))?;
+    Ok(format_f64(f))
+}
+
+fn format_f64(f: f64) -> String {
+    if f == 0.0 {
+        return "0".to_string();
+    }
+    let sign = if f < 0.0 { "-" } else { "" };
+    // Shortest round-tripping digits, as `d.ddde±x`.
+    let scientific = format!("{:e}", f.abs());
+    let (mantissa, exponent) = scientific.split_once('e').expect("exponent is present");
+    let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+    let k = digits.len() as i32;
+    let n = exponent.parse::<i32>().expect("exponent is an integer") + 1;
+
+    let body = if k <= n && n <= 21 {
+        format!("{}{}", digits, "0".repeat((n - k) as usize))
+    } else if 0 < n && n <= 21 {
+        format!("{}.{}", &digits[..n as usize], &digits[n as usize..])
+    } else if -6 < n && n <= 0 {
+        format!("0.{}{}", "0".repeat(-n as usize), digits)
+    } else {
+        let fraction = if k > 1 {
+            format!(".{}", &digits[1..])
+        } else {
+            String::new()
+        };
+        let exp_sign = if n > 0 { "+" } else { "-" };
+        format!("{}{}e{}{}", &digits[..1], fraction, exp_sign, (n - 1).abs())
+    };
+    format!("{}{}", sign, body)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{canonicalize, format_f64};
+
+    #[test]
+    fn test_canonicalize_rfc8785_examples() {
+        let value: serde_json::Value = serde_json::from_str(
+            r#"{
+                "numbers": [333333333.33333329, 1E30, 4.50, 2e-3, 0.000000000000000000000000001],
+                "string": "\u20ac$\u000F\u000aA'\u0042\u0022\u005c\\\"\/",
+                "literals": [null, true, false]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            canonicalize(&value).unwrap(),
+            r#"{"literals":[null,true,false],"numbers":[333333333.3333333,1e+30,4.5,0.002,1e-27],"string":"€$\u000f\nA'B\"\\\\\"/"}"#
+        );
+
+        let sorted = json!({
+            "\u{20ac}": 0, "\r": 1, "\u{fb33}": 2, "1": 3, "\u{1f600}": 4, "\u{80}": 5, "\u{f6}": 6
+        });
+        assert_eq!(
+            canonicalize(&sorted).unwrap(),
+            "{\"\\r\":1,\"1\":3,\"\u{80}\":5,\"\u{f6}\":6,\"\u{20ac}\":0,\"\u{1f600}\":4,\"\u{fb33}\":2}"
+        );
+    }
+
+    #[test]
+    fn test_format_numbers() {
+        assert_eq!(format_f64(-0.0), "0");
+        assert_eq!(format_f64(1e21), "1e+21");
+        assert_eq!(format_f64(1e20), "100000000000000000000");
+        assert_eq!(format_f64(-1.5e-7), "-1.5e-7");
+        assert_eq!(format_f64(0.000001), "0.000001");
+        assert_eq!(format_f64(123.456), "123.456");
+        assert_eq!(canonicalize(&json!(-5)).unwrap(), "-5");
+        assert!(canonicalize(&json!(u64::MAX)).is_err());
+    }
+}
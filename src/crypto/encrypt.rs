@@ -0,0 +1,127 @@
+//! Encryption to a wallet, for private messages readable only by the owner of an address.
+//!
+//! Payloads small enough are encrypted with RSA-OAEP SHA-256 under the wallet's key. Larger
+//! ones are sealed with AES-256-GCM under a random key, itself encrypted with RSA-OAEP. The
+//! first byte of the ciphertext tells which:
+//!
+//! - [`OAEP`]: followed by the RSA-OAEP ciphertext.
+//! - [`HYBRID`]: followed by the RSA-OAEP encrypted AES key, as long as the modulus, the 12
+//!   byte IV, and the AES-256-GCM ciphertext.
+
+use aes_gcm::{
+    aead::{Aead, KeyInit},
+    Aes256Gcm, Nonce,
+};
+use rand::{thread_rng, RngCore};
+use rsa::{PaddingScheme, PublicKey, PublicKeyParts};
+use zeroize::Zeroizing;
+
+use crate::{
+    crypto::{base64::Base64, sign::rsa_public_key},
+    error::Error,
+    signer::ArweaveSigner,
+};
+
+pub const OAEP: u8 = 1;
+pub const HYBRID: u8 = 2;
+
+const KEY_LENGTH: usize = 32;
+const IV_LENGTH: usize = 12;
+/// Bytes of OAEP padding with SHA-256: two digests and two bytes.
+const OAEP_OVERHEAD: usize = 2 * 32 + 2;
+
+/// Encrypts `plaintext` to the wallet with modulus `owner`, e.g. the owner of a transaction.
+pub fn encrypt_for_owner(owner: &Base64, plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+    let key = rsa_public_key(&owner.0)?;
+    let oaep = |message: &[u8]| {
+        key.encrypt(
+            &mut thread_rng(),
+            PaddingScheme::new_oaep::<sha2::Sha256>(),
+            message,
+        )
+        .map_err(|e| Error::CryptoError(e.to_string()))
+    };
+
+    if plaintext.len() + OAEP_OVERHEAD <= key.size() {
+        let mut ciphertext = vec![OAEP];
+        ciphertext.extend(oaep(plaintext)?);
+        return Ok(ciphertext);
+    }
+
+    let mut aes_key = Zeroizing::new([0; KEY_LENGTH]);
+    let mut iv = [0; IV_LENGTH];
+    thread_rng().fill_bytes(aes_key.as_mut());
+    thread_rng().fill_bytes(&mut iv);
+    let sealed = Aes256Gcm::new(&(*aes_key).into())
+        .encrypt(Nonce::from_slice(&iv), plaintext)
+        .map_err(|e| Error::CryptoError(e.to_string()))?;
+
+    let mut ciphertext = vec![HYBRID];
+    ciphertext.extend(oaep(aes_key.as_ref())?);
+    ciphertext.extend_from_slice(&iv);
+    ciphertext.extend(sealed);
+    Ok(ciphertext)
+}
+
+/// Decrypts what [`encrypt_for_owner`] encrypted to the wallet of `signer`.
+pub fn decrypt_with_signer(
+    signer: &ArweaveSigner,
+    ciphertext: &[u8],
+) -> Result<Zeroizing<Vec<u8>>, Error> {
+    let rsa = &signer.get_provider().signer;
+    match ciphertext.split_first() {
+        Some((&OAEP, encrypted)) => rsa.decrypt_oaep(encrypted),
+        Some((&HYBRID, rest)) => {
+            let key_size = signer.public_rsa_key().size();
+            if rest.len() < key_size + IV_LENGTH {
+                return Err(Error::CryptoError("truncated ciphertext".to_string()));
+            }
+            let (wrapped, rest) = rest.split_at(key_size);
+            let (iv, sealed) = rest.split_at(IV_LENGTH);
+            let aes_key = rsa.decrypt_oaep(wrapped)?;
+            if aes_key.len() != KEY_LENGTH {
+                return Err(Error::CryptoError("invalid wrapped key".to_string()));
+            }
+            Aes256Gcm::new_from_slice(&aes_key)
+                .map_err(|e| Error::CryptoError(e.to_string()))?
+                .decrypt(Nonce::from_slice(iv), sealed)
+                .map(Zeroizing::new)
+                .map_err(|_| Error::CryptoError("decryption failed".to_string()))
+        }
+        Some((scheme, _)) => Err(Error::CryptoError(format!("unknown scheme {}", scheme))),
+        None => Err(Error::CryptoError("empty ciphertext".to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{decrypt_with_signer, encrypt_for_owner, HYBRID, OAEP};
+    use crate::signer::ArweaveSigner;
+
+    #[test]
+    fn test_encrypt_for_owner() {
+        let signer = ArweaveSigner::default();
+        let owner = signer.keypair_modulus();
+
+        let short = encrypt_for_owner(&owner, b"hello").unwrap();
+        assert_eq!(short[0], OAEP);
+        assert_eq!(&*decrypt_with_signer(&signer, &short).unwrap(), b"hello");
+
+        let long = vec![42; 4096];
+        let mut ciphertext = encrypt_for_owner(&owner, &long).unwrap();
+        assert_eq!(ciphertext[0], HYBRID);
+        assert_eq!(*decrypt_with_signer(&signer, &ciphertext).unwrap(), long);
+
+        let other =
+            ArweaveSigner::from_pem_str(&fs::read_to_string("res/test_wallet_2.pem").unwrap())
+                .unwrap();
+        assert!(decrypt_with_signer(&other, &short).is_err());
+
+        let last = ciphertext.len() - 1;
+        ciphertext[last] ^= 1;
+        assert!(decrypt_with_signer(&signer, &ciphertext).is_err());
+        assert!(decrypt_with_signer(&signer, &[]).is_err());
+    }
+}
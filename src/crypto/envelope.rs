@@ -0,0 +1,167 @@
+//! Symmetric keys shared by two wallets, for encrypted two-party channels over public
+//! transactions.
+//!
+//! RSA has no key agreement, so the key is transported: the sender draws a random secret,
+//! encrypts it to the recipient with [`encrypt_for_owner`] and signs the resulting
+//! [`KeyEnvelope`]. Both sides derive the same [`SharedKey`] from the secret and their
+//! addresses with HKDF-SHA256, and seal messages with AES-256-GCM.
+
+use hkdf::Hkdf;
+use rand::{thread_rng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use zeroize::{ZeroizeOnDrop, Zeroizing};
+
+use crate::{
+    crypto::{
+        aes::{AesKey, IV_LENGTH, KEY_LENGTH},
+        base64::Base64,
+        encrypt::{decrypt_with_signer, encrypt_for_owner},
+        hash::{deep_hash, sha256, DeepHashItem},
+        sign::verify_pss,
+    },
+    error::Error,
+    signer::ArweaveSigner,
+};
+
+const ENVELOPE_DOMAIN: &[u8] = b"arweave-rs key envelope";
+
+/// AES-256-GCM key shared by two wallets, scrubbed from memory on drop.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SharedKey(AesKey);
+
+impl ZeroizeOnDrop for SharedKey {}
+
+impl SharedKey {
+    fn derive(secret: &[u8], envelope: &KeyEnvelope) -> Self {
+        let mut info = ENVELOPE_DOMAIN.to_vec();
+        info.extend_from_slice(&sha256(&envelope.sender.0));
+        info.extend_from_slice(&envelope.recipient.0);
+        let mut key = [0; KEY_LENGTH];
+        Hkdf::<Sha256>::new(None, secret)
+            .expand(&info, &mut key)
+            .expect("32 bytes is a valid HKDF-SHA256 output length");
+        Self(AesKey::new(key))
+    }
+
+    /// Encrypts `plaintext` under a fresh IV, prepended to the ciphertext.
+    pub fn seal(&self, plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+        let (iv, sealed) = self
+            .0
+            .encrypt(plaintext)
+            .map_err(|e| Error::CryptoError(e.to_string()))?;
+        Ok([&iv[..], &sealed].concat())
+    }
+
+    /// Decrypts a message sealed by [`SharedKey::seal`].
+    pub fn open(&self, ciphertext: &[u8]) -> Result<Vec<u8>, Error> {
+        if ciphertext.len() < IV_LENGTH {
+            return Err(Error::CryptoError("truncated ciphertext".to_string()));
+        }
+        let (iv, sealed) = ciphertext.split_at(IV_LENGTH);
+        self.0
+            .decrypt(iv, sealed)
+            .map_err(|_| Error::CryptoError("decryption failed".to_string()))
+    }
+}
+
+/// Secret sent by a wallet to another, from which both derive their [`SharedKey`]. Can be
+/// published, e.g. as the data of a transaction to the recipient.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct KeyEnvelope {
+    /// Modulus of the sender, whose signature authenticates the envelope.
+    pub sender: Base64,
+    /// Address of the recipient.
+    pub recipient: Base64,
+    /// The secret, encrypted to the recipient.
+    pub secret: Base64,
+    pub signature: Base64,
+}
+
+impl KeyEnvelope {
+    /// Envelope from `sender` to the wallet with modulus `recipient`, and the key it shares.
+    pub fn seal(sender: &ArweaveSigner, recipient: &Base64) -> Result<(Self, SharedKey), Error> {
+        let mut secret = Zeroizing::new([0; KEY_LENGTH]);
+        thread_rng().fill_bytes(secret.as_mut());
+        let mut envelope = KeyEnvelope {
+            sender: sender.keypair_modulus(),
+            recipient: Base64::from(sha256(&recipient.0).to_vec()),
+            secret: Base64::from(encrypt_for_owner(recipient, secret.as_ref())?),
+            signature: Base64::empty(),
+        };
+        envelope.signature = sender.sign(&envelope.signature_data());
+        let key = SharedKey::derive(secret.as_ref(), &envelope);
+        Ok((envelope, key))
+    }
+
+    /// Checks that the envelope is signed by its sender and addressed to `recipient`, and
+    /// returns the key it shares.
+    pub fn open(&self, recipient: &ArweaveSigner) -> Result<SharedKey, Error> {
+        self.verify()?;
+        if !self.recipient.ct_eq(&recipient.wallet_address()) {
+            return Err(Error::CryptoError(format!(
+                "envelope is addressed to {}",
+                self.recipient
+            )));
+        }
+        let secret = decrypt_with_signer(recipient, &self.secret.0)?;
+        Ok(SharedKey::derive(&secret, self))
+    }
+
+    /// Checks the signature of the sender.
+    pub fn verify(&self) -> Result<(), Error> {
+        verify_pss(&self.sender.0, &self.signature_data(), &self.signature.0)
+    }
+
+    /// Address of the sender.
+    pub fn sender_address(&self) -> Base64 {
+        Base64::from(sha256(&self.sender.0).to_vec())
+    }
+
+    fn signature_data(&self) -> [u8; 48] {
+        deep_hash(DeepHashItem::from_children(
+            [
+                ENVELOPE_DOMAIN,
+                &self.sender.0,
+                &self.recipient.0,
+                &self.secret.0,
+            ]
+            .into_iter()
+            .map(DeepHashItem::from_item)
+            .collect(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::KeyEnvelope;
+    use crate::{crypto::base64::Base64, signer::ArweaveSigner};
+
+    #[test]
+    fn test_key_envelope() {
+        let alice = ArweaveSigner::default();
+        let bob =
+            ArweaveSigner::from_pem_str(&fs::read_to_string("res/test_wallet_2.pem").unwrap())
+                .unwrap();
+
+        let (envelope, alice_key) = KeyEnvelope::seal(&alice, &bob.keypair_modulus()).unwrap();
+        assert_eq!(envelope.sender_address(), alice.wallet_address());
+        let json = serde_json::to_string(&envelope).unwrap();
+        let received: KeyEnvelope = serde_json::from_str(&json).unwrap();
+        let bob_key = received.open(&bob).unwrap();
+        assert_eq!(bob_key, alice_key);
+
+        let message = alice_key.seal(b"meet at block 1000000").unwrap();
+        assert_eq!(bob_key.open(&message).unwrap(), b"meet at block 1000000");
+
+        assert!(envelope.open(&alice).is_err());
+        let mut forged = envelope;
+        forged.sender = bob.keypair_modulus();
+        assert!(forged.open(&bob).is_err());
+        forged.recipient = Base64::from(vec![0; 32]);
+        assert!(forged.verify().is_err());
+    }
+}
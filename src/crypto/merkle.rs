@@ -2,8 +2,12 @@
 
 use crate::error::Error;
 use borsh::BorshDeserialize;
+use rayon::prelude::*;
 
-use super::hash::{hash_all_sha256, sha256};
+use super::{
+    hash::{hash_all_sha256, sha256},
+    utils::ct_eq,
+};
 
 /// Single struct used for original data chunks (Leaves) and branch nodes (hashes of pairs of child nodes).
 #[derive(Debug, PartialEq, Clone)]
@@ -23,6 +27,14 @@ pub struct Proof {
     pub proof: Vec<u8>,
 }
 
+/// Leaf a merkle path resolves to: the hash it commits to and the byte range it covers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PathLeaf {
+    pub hash: [u8; HASH_SIZE],
+    pub left_bound: u64,
+    pub right_bound: u64,
+}
+
 /// Populated with data from deserialized [`Proof`] for original data chunk (Leaf [`Node`]).
 #[repr(C)]
 #[derive(BorshDeserialize, Debug, Eq, PartialEq, Clone)]
@@ -84,14 +96,44 @@ impl Helpers<usize> for usize {
         note
     }
 }
-/// Generates data chunks from which the calculation of root id starts.
-pub fn generate_leaves(data: Vec<u8>) -> Result<Vec<Node>, Error> {
+
+/// How data is split into chunks. The default matches arweave-js: chunks of
+/// [`MAX_CHUNK_SIZE`] bytes, the last two split evenly when the last one would be smaller
+/// than [`MIN_CHUNK_SIZE`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkingConfig {
+    /// Whether a short last chunk is merged with the one before and the pair split evenly.
+    pub rebalance: bool,
+    /// Size below which the last chunk is rebalanced.
+    pub min_chunk_size: usize,
+}
+
+impl Default for ChunkingConfig {
+    fn default() -> Self {
+        Self {
+            rebalance: true,
+            min_chunk_size: MIN_CHUNK_SIZE,
+        }
+    }
+}
+
+/// Generates data chunks from which the calculation of root id starts. Chunks are hashed in
+/// parallel.
+pub fn generate_leaves(data: &[u8]) -> Result<Vec<Node>, Error> {
+    generate_leaves_with(data, &ChunkingConfig::default())
+}
+
+/// [`generate_leaves`] splitting the data as `config` says.
+pub fn generate_leaves_with(data: &[u8], config: &ChunkingConfig) -> Result<Vec<Node>, Error> {
     let mut data_chunks: Vec<&[u8]> = data.chunks(MAX_CHUNK_SIZE).collect();
 
     #[allow(unused_assignments)]
     let mut last_two = Vec::new();
 
-    if data_chunks.len() > 1 && data_chunks.last().unwrap().len() < MIN_CHUNK_SIZE {
+    if config.rebalance
+        && data_chunks.len() > 1
+        && data_chunks.last().unwrap().len() < config.min_chunk_size
+    {
         last_two = data_chunks.split_off(data_chunks.len() - 2).concat();
         let chunk_size = last_two.len() / 2 + (last_two.len() % 2 != 0) as usize;
         data_chunks.append(&mut last_two.chunks(chunk_size).collect::<Vec<&[u8]>>());
@@ -101,24 +143,34 @@ pub fn generate_leaves(data: Vec<u8>) -> Result<Vec<Node>, Error> {
         data_chunks.push(&[]);
     }
 
-    let mut leaves = Vec::<Node>::new();
     let mut min_byte_range = 0;
-    for chunk in data_chunks.into_iter() {
-        let data_hash = sha256(chunk);
-        let max_byte_range = min_byte_range + chunk.len();
-        let offset = max_byte_range.to_note_vec();
-        let id = hash_all_sha256(vec![&data_hash, &offset]);
-
-        leaves.push(Node {
-            id,
-            data_hash: Some(data_hash),
-            min_byte_range,
-            max_byte_range,
-            left_child: None,
-            right_child: None,
-        });
-        min_byte_range += chunk.len()
-    }
+    let ranges: Vec<(&[u8], usize)> = data_chunks
+        .into_iter()
+        .map(|chunk| {
+            let range = (chunk, min_byte_range);
+            min_byte_range += chunk.len();
+            range
+        })
+        .collect();
+
+    let leaves = ranges
+        .into_par_iter()
+        .map(|(chunk, min_byte_range)| {
+            let data_hash = sha256(chunk);
+            let max_byte_range = min_byte_range + chunk.len();
+            let offset = max_byte_range.to_note_vec();
+            let id = hash_all_sha256(vec![&data_hash, &offset]);
+
+            Node {
+                id,
+                data_hash: Some(data_hash),
+                min_byte_range,
+                max_byte_range,
+                left_child: None,
+                right_child: None,
+            }
+        })
+        .collect();
     Ok(leaves)
 }
 
@@ -136,18 +188,20 @@ pub fn hash_branch(left: Node, right: Node) -> Result<Node, Error> {
     })
 }
 
-/// Builds one layer of branch nodes from a layer of child nodes.
+/// Builds one layer of branch nodes from a layer of child nodes, hashing pairs in parallel.
 pub fn build_layer(nodes: Vec<Node>) -> Result<Vec<Node>, Error> {
-    let mut layer = Vec::<Node>::with_capacity(nodes.len() / 2 + (nodes.len() % 2 != 0) as usize);
+    let mut pairs = Vec::<(Node, Option<Node>)>::with_capacity(nodes.len().div_ceil(2));
     let mut nodes_iter = nodes.into_iter();
     while let Some(left) = nodes_iter.next() {
-        if let Some(right) = nodes_iter.next() {
-            layer.push(hash_branch(left, right).unwrap());
-        } else {
-            layer.push(left);
-        }
+        pairs.push((left, nodes_iter.next()));
     }
-    Ok(layer)
+    pairs
+        .into_par_iter()
+        .map(|pair| match pair {
+            (left, Some(right)) => hash_branch(left, right),
+            (left, None) => Ok(left),
+        })
+        .collect()
 }
 
 /// Builds all layers from leaves up to single root node.
@@ -239,7 +293,7 @@ pub fn validate_chunk(
                 ]);
 
                 // Ensure calculated id correct.
-                if id != root_id {
+                if !ct_eq(&id, &root_id) {
                     return Err(Error::InvalidProof);
                 }
 
@@ -253,7 +307,7 @@ pub fn validate_chunk(
 
             // Validate leaf: both id and data_hash are correct.
             let id = hash_all_sha256(vec![&data_hash, &max_byte_range.to_note_vec()]);
-            if id != root_id && data_hash != leaf_proof.data_hash {
+            if !ct_eq(&id, &root_id) || !ct_eq(&data_hash, &leaf_proof.data_hash) {
                 return Err(Error::InvalidProof);
             }
         }
@@ -264,6 +318,64 @@ pub fn validate_chunk(
     Ok(())
 }
 
+/// Walks `path` from `root_id` down to the leaf covering byte `dest` of `0..right_bound`,
+/// checking that every node hashes to the id its parent commits to. Validates the
+/// `data_path` of a chunk against a data root as well as the `tx_path` of a transaction
+/// against a block's tx root, whose leaves hold data roots.
+pub fn validate_path(
+    mut root_id: [u8; HASH_SIZE],
+    dest: u64,
+    right_bound: u64,
+    mut path: &[u8],
+) -> Result<PathLeaf, Error> {
+    if right_bound == 0 {
+        return Err(Error::InvalidProof);
+    }
+    let dest = dest.min(right_bound - 1);
+    let (mut left_bound, mut right_bound) = (0, right_bound);
+    loop {
+        if path.len() == HASH_SIZE + NOTE_SIZE {
+            let (hash, note) = path.split_at(HASH_SIZE);
+            if !ct_eq(&hash_all_sha256(vec![hash, note]), &root_id) {
+                return Err(Error::InvalidProof);
+            }
+            let end = note_to_u64(note)?;
+            return Ok(PathLeaf {
+                hash: hash.try_into().unwrap(),
+                left_bound,
+                right_bound: right_bound.min(end).max(left_bound + 1),
+            });
+        }
+        if path.len() < 2 * HASH_SIZE + NOTE_SIZE {
+            return Err(Error::InvalidProof);
+        }
+        let (left_id, rest) = path.split_at(HASH_SIZE);
+        let (right_id, rest) = rest.split_at(HASH_SIZE);
+        let (note, rest) = rest.split_at(NOTE_SIZE);
+        if !ct_eq(&hash_all_sha256(vec![left_id, right_id, note]), &root_id) {
+            return Err(Error::InvalidProof);
+        }
+        let offset = note_to_u64(note)?;
+        if dest < offset {
+            root_id = left_id.try_into().unwrap();
+            right_bound = right_bound.min(offset);
+        } else {
+            root_id = right_id.try_into().unwrap();
+            left_bound = left_bound.max(offset);
+        }
+        path = rest;
+    }
+}
+
+/// Offset held by a note, rejecting those that don't fit 64 bits.
+fn note_to_u64(note: &[u8]) -> Result<u64, Error> {
+    let (high, low) = note.split_at(NOTE_SIZE - 8);
+    if high.iter().any(|b| *b != 0) {
+        return Err(Error::InvalidProof);
+    }
+    Ok(u64::from_be_bytes(low.try_into().unwrap()))
+}
+
 #[cfg(test)]
 mod tests {
     use crate::crypto::base64::Base64;
@@ -278,7 +390,7 @@ mod tests {
     #[tokio::test]
     async fn test_generate_leaves() -> Result<(), Error> {
         let data = fs::read(ONE_MB_BIN).await.unwrap();
-        let leaves: Vec<Node> = generate_leaves(data).unwrap();
+        let leaves: Vec<Node> = generate_leaves(&data).unwrap();
         assert_eq!(
             leaves[1],
             Node {
@@ -302,7 +414,7 @@ mod tests {
     #[tokio::test]
     async fn test_hash_branch() -> Result<(), Error> {
         let data = fs::read(ONE_MB_BIN).await.unwrap();
-        let leaves: Vec<Node> = generate_leaves(data).unwrap();
+        let leaves: Vec<Node> = generate_leaves(&data).unwrap();
         let mut nodes_iter = leaves.into_iter();
         let left = nodes_iter.next().unwrap();
         let right = nodes_iter.next().unwrap();
@@ -329,7 +441,7 @@ mod tests {
     #[tokio::test]
     async fn test_build_layer() -> Result<(), Error> {
         let data = fs::read(ONE_MB_BIN).await.unwrap();
-        let leaves: Vec<Node> = generate_leaves(data).unwrap();
+        let leaves: Vec<Node> = generate_leaves(&data).unwrap();
         let layer = build_layer(leaves).unwrap();
         assert_eq!(
             layer[0].id,
@@ -351,7 +463,7 @@ mod tests {
             13, 66, 76, 111, 151, 198, 191, 18, 129, 188, 244, 243, 122, 39, 159, 246, 73, 77, 231,
             100, 200, 2, 138, 245, 233, 31, 171, 188, 172, 188, 68, 16,
         ];
-        let leaves: Vec<Node> = generate_leaves(data).unwrap();
+        let leaves: Vec<Node> = generate_leaves(&data).unwrap();
         let root = generate_data_root(leaves).unwrap();
         assert_eq!(root.id, root_actual);
         Ok(())
@@ -361,7 +473,7 @@ mod tests {
     async fn test_generate_proof() -> Result<(), Error> {
         let proof_actual = Base64::from_str("7EAC9FsACQRwe4oIzu7Mza9KjgWKT4toYxDYGjWrCdp0QgsrYS6AueMJ_rM6ZEGslGqjUekzD3WSe7B5_fwipgAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAACAAAnH6dASdQCigcL43lp0QclqBaSncF4TspuvxoFbn2L18EXpQrP1wkbwdIjSSWQQRt_F31yNvxtc09KkPFtzMKAwAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAABAAAIHiHU9QwOImFzjqSlfxkJJCtSbAox6TbbFhQvlEapSgAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAQAAA").unwrap();
         let data = fs::read(REBAR3).await.unwrap();
-        let leaves: Vec<Node> = generate_leaves(data).unwrap();
+        let leaves: Vec<Node> = generate_leaves(&data).unwrap();
         let root = generate_data_root(leaves).unwrap();
 
         let proofs = resolve_proofs(root, None).unwrap();
@@ -369,7 +481,7 @@ mod tests {
             proofs[0],
             Proof {
                 offset: 262143,
-                proof: proof_actual.0,
+                proof: proof_actual.0.to_vec(),
             },
         );
         Ok(())
@@ -377,25 +489,78 @@ mod tests {
     #[tokio::test]
     async fn test_validate_chunks() -> Result<(), Error> {
         let data = fs::read(ONE_MB_BIN).await.unwrap();
-        let leaves: Vec<Node> = generate_leaves(data).unwrap();
+        let leaves: Vec<Node> = generate_leaves(&data).unwrap();
         let root = generate_data_root(leaves.clone()).unwrap();
         let root_id = root.id;
         let proofs = resolve_proofs(root, None).unwrap();
         println!("proofs_len: {}", proofs.len());
         assert_eq!(leaves.len(), proofs.len());
 
-        for (chunk, proof) in leaves.into_iter().zip(proofs.into_iter()) {
+        for (chunk, proof) in leaves.into_iter().zip(proofs) {
             assert!(validate_chunk(root_id, chunk, proof,).is_ok());
         }
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_validate_tampered_chunk() -> Result<(), Error> {
+        let data = fs::read(ONE_MB_BIN).await.unwrap();
+        let leaves: Vec<Node> = generate_leaves(&data).unwrap();
+        let root = generate_data_root(leaves.clone()).unwrap();
+        let root_id = root.id;
+        let proofs = resolve_proofs(root, None).unwrap();
+
+        let mut chunk = leaves[1].clone();
+        chunk.data_hash = Some(sha256(b"tampered"));
+        assert!(matches!(
+            validate_chunk(root_id, chunk, proofs[1].clone()),
+            Err(Error::InvalidProof)
+        ));
+
+        let mut proof = proofs[1].clone();
+        let len = proof.proof.len();
+        proof.proof[len - HASH_SIZE - NOTE_SIZE] ^= 1;
+        assert!(matches!(
+            validate_chunk(root_id, leaves[1].clone(), proof),
+            Err(Error::InvalidProof)
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_validate_path() -> Result<(), Error> {
+        let data = fs::read(REBAR3).await.unwrap();
+        let leaves: Vec<Node> = generate_leaves(&data).unwrap();
+        let root = generate_data_root(leaves.clone()).unwrap();
+        let root_id = root.id;
+        let proofs = resolve_proofs(root, None).unwrap();
+
+        for (leaf, proof) in leaves.iter().zip(&proofs) {
+            for dest in [leaf.min_byte_range, leaf.max_byte_range - 1] {
+                let resolved =
+                    validate_path(root_id, dest as u64, data.len() as u64, &proof.proof)?;
+                assert_eq!(resolved.hash, leaf.data_hash.unwrap());
+                assert_eq!(resolved.left_bound, leaf.min_byte_range as u64);
+                assert_eq!(resolved.right_bound, leaf.max_byte_range as u64);
+            }
+        }
+
+        // The path of the first chunk doesn't lead to bytes of the second one.
+        let second = leaves[1].min_byte_range as u64;
+        assert!(validate_path(root_id, second, data.len() as u64, &proofs[0].proof).is_err());
+        let mut tampered = proofs[0].proof.clone();
+        tampered[0] ^= 1;
+        assert!(validate_path(root_id, 0, data.len() as u64, &tampered).is_err());
+        assert!(validate_path(root_id, 0, 0, &proofs[0].proof).is_err());
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_valid_root() -> Result<(), Error> {
         let data_root_actual =
             Base64::from_str("t-GCOnjPWxdox950JsrFMu3nzOE4RktXpMcIlkqSUTw").unwrap();
         let data = fs::read(REBAR3).await.unwrap();
-        let leaves: Vec<Node> = generate_leaves(data).unwrap();
+        let leaves: Vec<Node> = generate_leaves(&data).unwrap();
         let root = generate_data_root(leaves).unwrap();
         assert_eq!(root.id.to_vec(), data_root_actual.0);
         Ok(())
@@ -409,7 +574,7 @@ mod tests {
             13, 66, 76, 111, 151, 198, 191, 18, 129, 188, 244, 243, 122, 39, 159, 246, 73, 77, 231,
             100, 200, 2, 138, 245, 233, 31, 171, 188, 172, 188, 68, 16,
         ];
-        let leaves: Vec<Node> = generate_leaves(data).unwrap();
+        let leaves: Vec<Node> = generate_leaves(&data).unwrap();
         let root = generate_data_root(leaves).unwrap();
         println!("{:?} {:?}", root.id, &root_actual);
         assert_eq!(root.id, root_actual);
@@ -421,9 +586,9 @@ mod tests {
         let data = vec![0; 256 * 1024 + 1];
         // root id as calculate by arweave-js
         let root_actual = Base64::from_str("br1Vtl3TS_NGWdHmYqBh3-MxrlckoluHCZGmUZk-dJc").unwrap();
-        let leaves: Vec<Node> = generate_leaves(data).unwrap();
+        let leaves: Vec<Node> = generate_leaves(&data).unwrap();
         let root = generate_data_root(leaves).unwrap();
-        println!("{}", Base64(root.id.to_vec()));
+        println!("{}", Base64::from(root.id.to_vec()));
         assert_eq!(root.id, root_actual.0.as_ref());
         Ok(())
     }
@@ -431,7 +596,7 @@ mod tests {
     #[tokio::test]
     async fn test_even_chunks() -> Result<(), Error> {
         let data = fs::read(ONE_MB_BIN).await.unwrap();
-        let leaves: Vec<Node> = generate_leaves(data).unwrap();
+        let leaves: Vec<Node> = generate_leaves(&data).unwrap();
         println!("{:?}", leaves[4]);
         assert_eq!(leaves.len(), 8);
         Ok(())
@@ -440,9 +605,31 @@ mod tests {
     #[test]
     fn test_small_last_chunk() -> Result<(), Error> {
         let data = vec![0; 256 * 1024 + 1];
-        let leaves: Vec<Node> = generate_leaves(data).unwrap();
+        let leaves: Vec<Node> = generate_leaves(&data).unwrap();
         assert_eq!(131073, leaves[0].max_byte_range);
         assert_eq!(131072, leaves[1].max_byte_range - leaves[1].min_byte_range);
         Ok(())
     }
+
+    #[test]
+    fn test_chunking_config() -> Result<(), Error> {
+        let data = vec![0; 256 * 1024 + 1];
+        let no_rebalance = ChunkingConfig {
+            rebalance: false,
+            ..Default::default()
+        };
+        let leaves = generate_leaves_with(&data, &no_rebalance)?;
+        assert_eq!(262144, leaves[0].max_byte_range);
+        assert_eq!(1, leaves[1].max_byte_range - leaves[1].min_byte_range);
+
+        let data = vec![0; 256 * 1024 + 40 * 1024];
+        let higher_min = ChunkingConfig {
+            min_chunk_size: 64 * 1024,
+            ..Default::default()
+        };
+        let leaves = generate_leaves_with(&data, &higher_min)?;
+        assert_eq!(data.len() / 2, leaves[0].max_byte_range);
+        assert_eq!(generate_leaves(&data)?[0].max_byte_range, 262144);
+        Ok(())
+    }
 }
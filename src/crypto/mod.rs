@@ -1,16 +1,25 @@
 use std::path::PathBuf;
 
+use rand::RngCore;
+
 use self::{
     base64::Base64,
     hash::{deep_hash, sha256, DeepHashItem},
-    sign::Signer,
+    sign::{RsaPublicKey, Signer},
 };
 
+pub(crate) mod aes;
 pub mod base64;
+pub mod canonical;
+pub mod encrypt;
+pub mod envelope;
 pub mod hash;
 pub mod merkle;
 pub mod sign;
 pub mod utils;
+pub mod vectors;
+
+pub use encrypt::{decrypt_with_signer, encrypt_for_owner};
 
 pub struct Provider {
     pub signer: Box<Signer>,
@@ -45,6 +54,16 @@ impl Provider {
         self.signer.sign(message).expect("Valid message")
     }
 
+    pub fn sign_with_rng(&self, message: &[u8], rng: impl RngCore + 'static) -> Base64 {
+        self.signer
+            .sign_with_rng(message, rng)
+            .expect("Valid message")
+    }
+
+    pub fn sign_unsalted(&self, message: &[u8]) -> Base64 {
+        self.signer.sign_unsalted(message).expect("Valid message")
+    }
+
     pub fn verify(&self, pub_key: &[u8], message: &[u8], signature: &[u8]) -> bool {
         self.signer.verify(pub_key, message, signature).is_ok()
     }
@@ -66,6 +85,10 @@ impl Provider {
     pub fn public_key(&self) -> Base64 {
         self.signer.public_key()
     }
+
+    pub fn public_rsa_key(&self) -> RsaPublicKey {
+        self.signer.public_rsa_key()
+    }
 }
 
 #[cfg(test)]
@@ -74,7 +97,7 @@ mod tests {
 
     #[test]
     fn test_sign_verify() {
-        let message = Base64(
+        let message = Base64::from(
             [
                 9, 214, 233, 210, 242, 45, 194, 247, 28, 234, 14, 86, 105, 40, 41, 251, 52, 39,
                 236, 214, 54, 13, 53, 254, 179, 53, 220, 205, 129, 37, 244, 142, 230, 32, 209, 103,
@@ -1,23 +1,40 @@
 //! Functionality for creating and verifying signatures and hashing.
 
 use crate::error::Error;
-use data_encoding::BASE64URL;
 use jsonwebkey as jwk;
-use rand::thread_rng;
+use rand::{thread_rng, RngCore};
 use rsa::{
-    pkcs8::{DecodePrivateKey, DecodePublicKey},
-    PaddingScheme, PublicKey, PublicKeyParts, RsaPrivateKey, RsaPublicKey,
+    pkcs1::DecodeRsaPrivateKey, pkcs8::DecodePrivateKey, BigUint, PaddingScheme, PublicKey,
+    PublicKeyParts, RsaPrivateKey,
 };
 use sha2::Digest;
-use std::{fs, path::PathBuf, str::FromStr};
+use std::{env, fs, path::PathBuf, str::FromStr};
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
 
 use super::base64::Base64;
 
-/// Struct for for crypto methods.
+/// Re-exported so callers use the `rsa` version this crate builds with.
+pub use rsa::RsaPublicKey;
+
+/// Struct for for crypto methods. The private key is scrubbed from memory on drop.
 pub struct Signer {
     priv_key: RsaPrivateKey,
 }
 
+impl Zeroize for Signer {
+    fn zeroize(&mut self) {
+        self.priv_key.zeroize();
+    }
+}
+
+impl Drop for Signer {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl ZeroizeOnDrop for Signer {}
+
 impl Default for Signer {
     fn default() -> Self {
         let path = PathBuf::from_str("res/test_wallet.json").expect("Could not open .wallet.json");
@@ -31,41 +48,132 @@ impl Signer {
     }
 
     pub fn from_jwk(jwk: jwk::JsonWebKey) -> Self {
-        let pem = jwk.key.to_pem();
-        let priv_key = RsaPrivateKey::from_pkcs8_pem(&pem).unwrap();
+        Self::try_from_jwk(jwk).expect("Invalid private key")
+    }
 
-        Self::new(priv_key)
+    /// Like [`Signer::from_jwk`], failing with [`Error::InvalidKey`] on a malformed key.
+    pub fn try_from_jwk(jwk: jwk::JsonWebKey) -> Result<Self, Error> {
+        let invalid = |msg: &str| Error::InvalidKey(msg.to_string());
+        let private = match &*jwk.key {
+            jwk::Key::RSA {
+                private: Some(private),
+                ..
+            } => private,
+            _ => return Err(invalid("JWK holds no RSA private key")),
+        };
+        // The rsa crate divides by p - 1 and q - 1, panicking on primes below 2.
+        for prime in [&private.p, &private.q] {
+            let prime = prime
+                .as_deref()
+                .ok_or_else(|| invalid("JWK lacks the prime factors"))?;
+            let start = prime.iter().position(|b| *b != 0).unwrap_or(prime.len());
+            if matches!(prime[start..], [] | [1]) {
+                return Err(invalid("JWK prime factor below 2"));
+            }
+        }
+        let pem = Zeroizing::new(jwk.key.to_pem());
+        let priv_key =
+            RsaPrivateKey::from_pkcs8_pem(&pem).map_err(|e| Error::InvalidKey(e.to_string()))?;
+        priv_key
+            .validate()
+            .map_err(|e| Error::InvalidKey(e.to_string()))?;
+        Ok(Self::new(priv_key))
     }
 
     pub fn from_keypair_path(keypair_path: PathBuf) -> Result<Self, Error> {
-        let data = fs::read_to_string(keypair_path).expect("Could not open file");
+        let data = Zeroizing::new(fs::read_to_string(keypair_path).expect("Could not open file"));
         let jwk_parsed: jwk::JsonWebKey = data.parse().expect("Could not parse key");
 
         Ok(Self::from_jwk(jwk_parsed))
     }
 
+    /// Signer from a JWK keyfile's JSON contents.
+    pub fn from_jwk_str(jwk: &str) -> Result<Self, Error> {
+        let jwk_parsed: jwk::JsonWebKey = jwk
+            .parse()
+            .map_err(|e: jwk::Error| Error::InvalidKey(e.to_string()))?;
+        if !jwk_parsed.key.is_private() {
+            return Err(Error::InvalidKey("JWK holds no private key".to_string()));
+        }
+        Self::try_from_jwk(jwk_parsed)
+    }
+
+    /// Signer from a PEM encoded PKCS#8 (`PRIVATE KEY`) or PKCS#1 (`RSA PRIVATE KEY`) key.
+    pub fn from_pem_str(pem: &str) -> Result<Self, Error> {
+        RsaPrivateKey::from_pkcs8_pem(pem)
+            .or_else(|_| RsaPrivateKey::from_pkcs1_pem(pem))
+            .map(Self::new)
+            .map_err(|e| Error::InvalidKey(e.to_string()))
+    }
+
+    /// Signer from a DER encoded PKCS#8 or PKCS#1 key.
+    pub fn from_der_bytes(der: &[u8]) -> Result<Self, Error> {
+        RsaPrivateKey::from_pkcs8_der(der)
+            .or_else(|_| RsaPrivateKey::from_pkcs1_der(der))
+            .map(Self::new)
+            .map_err(|e| Error::InvalidKey(e.to_string()))
+    }
+
+    /// Signer from a JWK keyfile stored base64 encoded in environment variable `var`, usually
+    /// [`crate::consts::JWK_ENV_VAR`].
+    pub fn from_base64_env(var: &str) -> Result<Self, Error> {
+        let encoded = Zeroizing::new(
+            env::var(var).map_err(|e| Error::InvalidKey(format!("{}: {}", var, e)))?,
+        );
+        Self::from_base64_jwk(&encoded).map_err(|e| match e {
+            Error::InvalidKey(msg) => Error::InvalidKey(format!("{}: {}", var, msg)),
+            e => e,
+        })
+    }
+
+    /// Signer from a JWK keyfile encoded in standard or URL-safe base64, as stored by
+    /// [`Signer::from_base64_env`].
+    pub fn from_base64_jwk(encoded: &str) -> Result<Self, Error> {
+        let encoded = encoded.trim();
+        let json = Zeroizing::new(
+            base64::decode(encoded)
+                .or_else(|_| base64::decode_config(encoded, base64::URL_SAFE_NO_PAD))
+                .map_err(|e| Error::InvalidKey(e.to_string()))?,
+        );
+        let json = std::str::from_utf8(&json).map_err(|e| Error::InvalidKey(e.to_string()))?;
+        Self::from_jwk_str(json)
+    }
+
     pub fn public_key(&self) -> Base64 {
-        Base64(self.priv_key.to_public_key().n().to_bytes_be())
+        Base64::from(self.priv_key.to_public_key().n().to_bytes_be())
+    }
+
+    pub fn public_rsa_key(&self) -> RsaPublicKey {
+        self.priv_key.to_public_key()
     }
 
     pub fn keypair_modulus(&self) -> Result<Base64, Error> {
         let modulus = self.priv_key.to_public_key().n().to_bytes_be();
-        Ok(Base64(modulus.to_vec()))
+        Ok(Base64::from(modulus.to_vec()))
     }
 
     pub fn wallet_address(&self) -> Result<Base64, Error> {
         let mut context = sha2::Sha256::new();
         context.update(&self.keypair_modulus()?.0[..]);
-        let wallet_address = Base64(context.finalize().to_vec());
+        let wallet_address = Base64::from(context.finalize().to_vec());
         Ok(wallet_address)
     }
 
     pub fn sign(&self, message: &[u8]) -> Result<Base64, Error> {
+        self.sign_with_rng(message, thread_rng())
+    }
+
+    /// Signs `message` drawing the PSS salt from `rng`. A seeded rng yields the same signature
+    /// on every run, which is useful for test vectors.
+    pub fn sign_with_rng(
+        &self,
+        message: &[u8],
+        rng: impl RngCore + 'static,
+    ) -> Result<Base64, Error> {
         let mut hasher = sha2::Sha256::new();
-        hasher.update(&message);
+        hasher.update(message);
         let hashed = hasher.finalize();
 
-        let rng = thread_rng();
         let padding = PaddingScheme::PSS {
             salt_rng: Box::new(rng),
             digest: Box::new(sha2::Sha256::new()),
@@ -77,32 +185,69 @@ impl Signer {
             .sign(padding, &hashed)
             .map_err(|e| Error::SigningError(e.to_string()))?;
 
-        Ok(Base64(signature))
+        Ok(Base64::from(signature))
     }
 
-    pub fn verify(&self, pub_key: &[u8], message: &[u8], signature: &[u8]) -> Result<(), Error> {
-        let jwt_str = format!(
-            "{{\"kty\":\"RSA\",\"e\":\"AQAB\",\"n\":\"{}\"}}",
-            BASE64URL.encode(pub_key)
-        );
-        let jwk: jwk::JsonWebKey = jwt_str.parse().unwrap();
-
-        let pub_key = RsaPublicKey::from_public_key_der(jwk.key.to_der().as_slice()).unwrap();
+    /// Signs `message` with an empty PSS salt, so the signature is the same on every call.
+    /// Used to derive keys from the wallet, never to sign transactions.
+    pub fn sign_unsalted(&self, message: &[u8]) -> Result<Base64, Error> {
         let mut hasher = sha2::Sha256::new();
-        hasher.update(&message);
-        let hashed = &hasher.finalize();
+        hasher.update(message);
+        let hashed = hasher.finalize();
 
-        let rng = thread_rng();
         let padding = PaddingScheme::PSS {
-            salt_rng: Box::new(rng),
+            salt_rng: Box::new(thread_rng()),
             digest: Box::new(sha2::Sha256::new()),
-            salt_len: None,
+            salt_len: Some(0),
         };
-        pub_key
-            .verify(padding, hashed, signature)
-            .map(|_| ())
-            .map_err(|_| Error::InvalidSignature)
+
+        let signature = self
+            .priv_key
+            .sign(padding, &hashed)
+            .map_err(|e| Error::SigningError(e.to_string()))?;
+
+        Ok(Base64::from(signature))
+    }
+
+    /// Decrypts RSA-OAEP SHA-256 `ciphertext` encrypted to this key, with blinding.
+    pub fn decrypt_oaep(&self, ciphertext: &[u8]) -> Result<Zeroizing<Vec<u8>>, Error> {
+        self.priv_key
+            .decrypt_blinded(
+                &mut thread_rng(),
+                PaddingScheme::new_oaep::<sha2::Sha256>(),
+                ciphertext,
+            )
+            .map(Zeroizing::new)
+            .map_err(|e| Error::CryptoError(e.to_string()))
     }
+
+    pub fn verify(&self, pub_key: &[u8], message: &[u8], signature: &[u8]) -> Result<(), Error> {
+        verify_pss(pub_key, message, signature)
+    }
+}
+
+/// Public key of the wallet with modulus `modulus`, Arweave keys all having exponent 65537.
+pub fn rsa_public_key(modulus: &[u8]) -> Result<RsaPublicKey, Error> {
+    RsaPublicKey::new(BigUint::from_bytes_be(modulus), BigUint::from(65537u32))
+        .map_err(|e| Error::InvalidKey(e.to_string()))
+}
+
+/// Verifies an RSA-PSS SHA-256 signature of `message` by the wallet with modulus `pub_key`,
+/// whatever salt length it was made with. Needs no private key.
+pub fn verify_pss(pub_key: &[u8], message: &[u8], signature: &[u8]) -> Result<(), Error> {
+    let pub_key = rsa_public_key(pub_key)?;
+    let mut hasher = sha2::Sha256::new();
+    hasher.update(message);
+    let hashed = &hasher.finalize();
+
+    let padding = PaddingScheme::PSS {
+        salt_rng: Box::new(thread_rng()),
+        digest: Box::new(sha2::Sha256::new()),
+        salt_len: None,
+    };
+    pub_key
+        .verify(padding, hashed, signature)
+        .map_err(|_| Error::InvalidSignature)
 }
 
 #[cfg(test)]
@@ -111,7 +256,7 @@ mod tests {
 
     use crate::{
         crypto::{base64::Base64, sign::Signer},
-        error,
+        error::{self, Error},
     };
 
     #[test]
@@ -124,9 +269,77 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_from_pem_der_and_env() {
+        use rsa::{
+            pkcs1::{EncodeRsaPrivateKey, LineEnding},
+            pkcs8::EncodePrivateKey,
+        };
+
+        let expected = Signer::default().wallet_address().unwrap();
+        let key = &Signer::default().priv_key;
+
+        let pkcs8 = key.to_pkcs8_pem(LineEnding::LF).unwrap();
+        let pkcs1 = key.to_pkcs1_pem(LineEnding::LF).unwrap();
+        let der = key.to_pkcs8_der().unwrap();
+        for signer in [
+            Signer::from_pem_str(&pkcs8).unwrap(),
+            Signer::from_pem_str(&pkcs1).unwrap(),
+            Signer::from_der_bytes(der.as_ref()).unwrap(),
+        ] {
+            assert_eq!(signer.wallet_address().unwrap(), expected);
+        }
+        assert!(Signer::from_pem_str("not a key").is_err());
+    }
+
+    #[test]
+    fn test_from_base64_jwk() {
+        let expected = Signer::default().wallet_address().unwrap();
+        let jwk = std::fs::read("res/test_wallet.json").unwrap();
+        for encoded in [
+            base64::encode(&jwk),
+            base64::encode_config(&jwk, base64::URL_SAFE_NO_PAD),
+        ] {
+            let signer = Signer::from_base64_jwk(&encoded).unwrap();
+            assert_eq!(signer.wallet_address().unwrap(), expected);
+        }
+        assert!(matches!(
+            Signer::from_base64_jwk("not base64!"),
+            Err(Error::InvalidKey(_))
+        ));
+
+        // Private JWKs whose exponent or primes don't match the modulus.
+        for (field, value) in [("d", "AQAB"), ("p", "AA"), ("q", "AQ")] {
+            let mut malformed: serde_json::Value = serde_json::from_slice(&jwk).unwrap();
+            malformed[field] = value.into();
+            assert!(matches!(
+                Signer::from_base64_jwk(&base64::encode(malformed.to_string())),
+                Err(Error::InvalidKey(_))
+            ));
+        }
+    }
+
+    /// The only test touching the environment, which is shared by the tests running in
+    /// parallel.
+    #[test]
+    fn test_from_base64_env() {
+        let jwk = std::fs::read("res/test_wallet.json").unwrap();
+        std::env::set_var("ARWEAVE_RS_TEST_JWK_B64", base64::encode(jwk));
+        let signer = Signer::from_base64_env("ARWEAVE_RS_TEST_JWK_B64");
+        std::env::remove_var("ARWEAVE_RS_TEST_JWK_B64");
+        assert_eq!(
+            signer.unwrap().wallet_address().unwrap(),
+            Signer::default().wallet_address().unwrap()
+        );
+        assert!(matches!(
+            Signer::from_base64_env("ARWEAVE_RS_TEST_UNSET"),
+            Err(Error::InvalidKey(msg)) if msg.starts_with("ARWEAVE_RS_TEST_UNSET")
+        ));
+    }
+
     #[test]
     fn test_sign_verify() -> Result<(), error::Error> {
-        let message = Base64(
+        let message = Base64::from(
             [
                 74, 15, 74, 255, 248, 205, 47, 229, 107, 195, 69, 76, 215, 249, 34, 186, 197, 31,
                 178, 163, 72, 54, 78, 179, 19, 178, 1, 132, 183, 231, 131, 213, 146, 203, 6, 99,
@@ -145,4 +358,27 @@ mod tests {
         //provider.verify(&pubk.0, &message.0, &signature.0)
         Ok(())
     }
+
+    #[test]
+    fn test_sign_with_seeded_rng() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let message = b"deterministic";
+        let provider = Signer::default();
+        let first = provider
+            .sign_with_rng(message, StdRng::seed_from_u64(7))
+            .unwrap();
+        let second = provider
+            .sign_with_rng(message, StdRng::seed_from_u64(7))
+            .unwrap();
+        let other = provider
+            .sign_with_rng(message, StdRng::seed_from_u64(8))
+            .unwrap();
+
+        assert_eq!(first, second);
+        assert_ne!(first, other);
+        assert!(provider
+            .verify(&provider.public_key().0, message, &first.0)
+            .is_ok());
+    }
 }
@@ -0,0 +1,181 @@
+//! Golden test vectors of transaction signing, to cross-check other implementations (e.g.
+//! arweave-js, goar) against this crate: for a transaction, every blob and list fed to the
+//! deep hash with its own hash, down to the signature data. The vectors of
+//! `res/signature_vectors.json` are checked by the tests.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    crypto::{
+        base64::Base64,
+        hash::{deep_hash, DeepHashItem, ToItems},
+    },
+    error::Error,
+    transaction::{parser::deserialize_tx, Tx},
+};
+
+/// A [`DeepHashItem`] along with its deep hash.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum HashTrace {
+    Blob { value: Base64, hash: Base64 },
+    List { items: Vec<HashTrace>, hash: Base64 },
+}
+
+impl HashTrace {
+    pub fn of(item: &DeepHashItem) -> Self {
+        let hash = Base64::from(deep_hash(item.clone()).to_vec());
+        match item {
+            DeepHashItem::Blob(value) => HashTrace::Blob {
+                value: Base64::from(value.clone()),
+                hash,
+            },
+            DeepHashItem::List(items) => HashTrace::List {
+                items: items.iter().map(HashTrace::of).collect(),
+                hash,
+            },
+        }
+    }
+
+    pub fn hash(&self) -> &Base64 {
+        match self {
+            HashTrace::Blob { hash, .. } | HashTrace::List { hash, .. } => hash,
+        }
+    }
+
+    /// Path of the first node differing from `other`, as indexes from the root, preferring
+    /// the deepest one, or `None` if both are equal.
+    pub fn first_difference(&self, other: &HashTrace) -> Option<Vec<usize>> {
+        match (self, other) {
+            (HashTrace::List { items: a, .. }, HashTrace::List { items: b, .. })
+                if a.len() == b.len() =>
+            {
+                a.iter()
+                    .zip(b)
+                    .enumerate()
+                    .find_map(|(i, (a, b))| {
+                        a.first_difference(b).map(|mut path| {
+                            path.insert(0, i);
+                            path
+                        })
+                    })
+                    .or_else(|| (self != other).then(Vec::new))
+            }
+            _ => (self != other).then(Vec::new),
+        }
+    }
+}
+
+/// Signature data of a transaction, and how it is computed.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SignatureVector {
+    #[serde(deserialize_with = "deserialize_tx")]
+    pub tx: Tx,
+    /// Input of the deep hash, the hash of its root being the signature data.
+    pub deep_hash: HashTrace,
+    /// Message signed with RSA-PSS.
+    pub signature_data: Base64,
+    /// Id derived from the signature, if the transaction is signed.
+    pub id: Option<Base64>,
+}
+
+impl SignatureVector {
+    pub fn of(tx: &Tx) -> Result<Self, Error> {
+        let deep_hash = HashTrace::of(&tx.to_deep_hash_item()?);
+        Ok(Self {
+            signature_data: deep_hash.hash().clone(),
+            id: tx.compute_id().ok(),
+            tx: tx.clone(),
+            deep_hash,
+        })
+    }
+
+    /// Vectors of `txs`, as pretty printed JSON.
+    pub fn dump(txs: &[Tx]) -> Result<String, Error> {
+        let vectors = txs.iter().map(Self::of).collect::<Result<Vec<_>, _>>()?;
+        serde_json::to_string_pretty(&vectors)
+            .map_err(|e| Error::TransactionInfoError(e.to_string()))
+    }
+
+    /// Checks the vector against this crate, e.g. one produced by another implementation.
+    /// Fails with [`Error::DigestMismatch`] naming the first node computed differently.
+    pub fn check(&self) -> Result<(), Error> {
+        let expected = Self::of(&self.tx)?;
+        if let Some(path) = expected.deep_hash.first_difference(&self.deep_hash) {
+            return Err(Error::DigestMismatch(format!(
+                "deep hash of {} differs at {:?}",
+                self.tx.id, path
+            )));
+        }
+        if self.signature_data != expected.signature_data || self.id != expected.id {
+            return Err(Error::DigestMismatch(format!(
+                "signature data or id of {} differs",
+                self.tx.id
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, str::FromStr};
+
+    use super::{HashTrace, SignatureVector};
+    use crate::{
+        crypto::{base64::Base64, hash::DeepHashItem},
+        error::Error,
+        transaction::{
+            tags::{FromUtf8Strs, Tag},
+            Tx,
+        },
+    };
+
+    fn sample_txs() -> Vec<Tx> {
+        let signed = Tx::from_str(&fs::read_to_string("res/sample_tx.json").unwrap()).unwrap();
+        let v1 = Tx {
+            format: 1,
+            data: Base64::from(b"hello world".to_vec()),
+            data_size: 11,
+            signature: Base64::default(),
+            ..signed.clone()
+        };
+        let bare = Tx {
+            format: 2,
+            owner: signed.owner.clone(),
+            last_tx: signed.last_tx.clone(),
+            tags: vec![Tag::<Base64>::from_utf8_strs("Content-Type", "text/plain").unwrap()],
+            data_root: Base64::from(vec![9; 32]),
+            data_size: 1024,
+            reward: 1,
+            ..Default::default()
+        };
+        vec![signed, v1, bare]
+    }
+
+    #[test]
+    fn test_signature_vectors() {
+        let fixture = fs::read_to_string("res/signature_vectors.json").unwrap();
+        assert_eq!(
+            SignatureVector::dump(&sample_txs()).unwrap(),
+            fixture.trim_end()
+        );
+
+        let mut vectors: Vec<SignatureVector> = serde_json::from_str(&fixture).unwrap();
+        assert_eq!(vectors.len(), 3);
+        assert_eq!(
+            vectors[0].id.as_ref().unwrap().to_string(),
+            "t3K1b8IhvtGWxAGsipZE5NafmEGrtj3OAcYikJ0edeU"
+        );
+        assert!(vectors[2].id.is_none());
+        for vector in vectors.iter() {
+            vector.check().unwrap();
+        }
+
+        if let HashTrace::List { items, .. } = &mut vectors[0].deep_hash {
+            items[6] = HashTrace::of(&DeepHashItem::List(vec![]));
+        }
+        let err = vectors[0].check().unwrap_err();
+        assert!(matches!(err, Error::DigestMismatch(msg) if msg.ends_with("[6]")));
+    }
+}
@@ -14,6 +14,191 @@ pub struct Currency {
     winston: u64, //decimal
 }
 
+/// Number of decimals of AR, i.e. log<sub>10</sub> of [`WINSTONS_PER_AR`].
+pub const AR_DECIMALS: usize = 12;
+
+impl Currency {
+    /// Total amount expressed in winstons.
+    pub fn winstons(&self) -> u128 {
+        self.arweave as u128 * WINSTONS_PER_AR as u128 + self.winston as u128
+    }
+
+    /// Like `Currency::from(u128)`, but fails with [`Error::AmountOverflow`] instead of
+    /// panicking when the amount exceeds `u64::MAX` AR.
+    pub fn checked_from_winstons(winstons: u128) -> Result<Self, Error> {
+        if winstons / WINSTONS_PER_AR as u128 > u64::MAX as u128 {
+            return Err(Error::AmountOverflow(format!("{} winstons", winstons)));
+        }
+        Ok(Currency::from(winstons))
+    }
+
+    /// Formats the amount in AR rounded to at most `precision` decimals, without trailing
+    /// zeros, e.g. `12.5 AR`.
+    pub fn format_ar(&self, precision: usize) -> String {
+        let precision = precision.min(AR_DECIMALS);
+        let scale = 10u128.pow((AR_DECIMALS - precision) as u32);
+        let rounded = (self.winstons() + scale / 2) / scale;
+        let unit = 10u128.pow(precision as u32);
+
+        let integer = group_thousands(&(rounded / unit).to_string());
+        let decimals = format!("{:0width$}", rounded % unit, width = precision);
+        let decimals = decimals.trim_end_matches('0');
+        if decimals.is_empty() {
+            format!("{} AR", integer)
+        } else {
+            format!("{}.{} AR", integer, decimals)
+        }
+    }
+
+    /// Formats the amount in winstons with thousands separators, e.g. `1,000 winston`.
+    pub fn format_winston(&self) -> String {
+        format!("{} winston", group_thousands(&self.winstons().to_string()))
+    }
+
+    /// Parses a human readable amount such as `12.5 AR`, `0.5` or `1,000 winston`.
+    ///
+    /// Parsing is locale-free: `.` is the only decimal separator and `,`, `_` and spaces are
+    /// accepted as digit grouping. Amounts without a unit are read as AR. Winston amounts
+    /// can't have decimals and AR amounts can have at most 12.
+    pub fn parse_human(s: &str) -> Result<Self, Error> {
+        let invalid = || Error::InvalidAmount(s.to_string());
+        let lower = s.trim().to_ascii_lowercase();
+        let (amount, in_ar) = if let Some(amount) = lower
+            .strip_suffix("winstons")
+            .or_else(|| lower.strip_suffix("winston"))
+        {
+            (amount, false)
+        } else {
+            (lower.strip_suffix("ar").unwrap_or(&lower), true)
+        };
+        let amount: String = amount
+            .chars()
+            .filter(|c| !matches!(c, ',' | '_' | ' '))
+            .collect();
+
+        let (integer, decimals) = match amount.split_once('.') {
+            Some((integer, decimals)) if in_ar => (integer, decimals),
+            Some(_) => return Err(invalid()),
+            None => (amount.as_str(), ""),
+        };
+        let is_digits = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+        if (integer.is_empty() && decimals.is_empty())
+            || !is_digits(integer)
+            || !is_digits(decimals)
+            || decimals.len() > AR_DECIMALS
+        {
+            return Err(invalid());
+        }
+
+        let integer = if integer.is_empty() {
+            0
+        } else {
+            integer.parse::<u128>().map_err(|_| invalid())?
+        };
+        let winstons = if in_ar {
+            let decimals = format!("{:0<width$}", decimals, width = AR_DECIMALS);
+            integer
+                .checked_mul(WINSTONS_PER_AR as u128)
+                .and_then(|w| w.checked_add(decimals.parse::<u128>().unwrap()))
+                .ok_or_else(|| Error::AmountOverflow(s.to_string()))?
+        } else {
+            integer
+        };
+
+        Currency::checked_from_winstons(winstons)
+    }
+}
+
+/// Converts an AR amount to winstons, rounding to the nearest winston.
+///
+/// The amount is taken at the shortest decimal representation of the float, so `0.1` is
+/// exactly 100,000,000,000 winstons. Negative, non-finite and out of range amounts are
+/// rejected with [`Error::InvalidAmount`], amounts above `u64::MAX` AR with
+/// [`Error::AmountOverflow`].
+pub fn ar_to_winston(ar: f64) -> Result<u128, Error> {
+    if !ar.is_finite() || (ar.is_sign_negative() && ar != 0.0) {
+        return Err(Error::InvalidAmount(ar.to_string()));
+    }
+    let repr = ar.abs().to_string();
+    let (integer, decimals) = repr.split_once('.').unwrap_or((&repr, ""));
+    let (decimals, round_up) = if decimals.len() > AR_DECIMALS {
+        let (kept, dropped) = decimals.split_at(AR_DECIMALS);
+        (kept, dropped.as_bytes()[0] >= b'5')
+    } else {
+        (decimals, false)
+    };
+
+    let winstons = Currency::parse_human(&format!("{}.{} AR", integer, decimals))?.winstons();
+    if round_up {
+        winstons
+            .checked_add(1)
+            .ok_or_else(|| Error::AmountOverflow(ar.to_string()))
+    } else {
+        Ok(winstons)
+    }
+}
+
+/// Sums fees, e.g. the rewards of every transaction in a batch of bundles, failing with
+/// [`Error::AmountOverflow`] instead of wrapping.
+pub fn checked_sum_fees(fees: impl IntoIterator<Item = u64>) -> Result<u64, Error> {
+    fees.into_iter().try_fold(0u64, |total, fee| {
+        total
+            .checked_add(fee)
+            .ok_or_else(|| Error::AmountOverflow(format!("{} + {}", total, fee)))
+    })
+}
+
+/// Scales `fee` by `multiplier`, rounding up so the result never underquotes. Fails with
+/// [`Error::InvalidAmount`] for negative or non-finite multipliers and with
+/// [`Error::AmountOverflow`] when the result doesn't fit a `u64`.
+pub fn checked_fee_multiply(fee: u64, multiplier: f64) -> Result<u64, Error> {
+    if !multiplier.is_finite() || multiplier < 0.0 {
+        return Err(Error::InvalidAmount(format!(
+            "fee multiplier {}",
+            multiplier
+        )));
+    }
+    let scaled = (fee as f64 * multiplier).ceil();
+    if scaled >= u64::MAX as f64 {
+        return Err(Error::AmountOverflow(format!("{} * {}", fee, multiplier)));
+    }
+    Ok(scaled as u64)
+}
+
+/// Converts winstons to AR. Amounts above 2<sup>53</sup> winstons (about 9,007 AR) can't be
+/// represented exactly and are rounded; use [`Currency::format_ar`] for exact output.
+pub fn winston_to_ar(winstons: u128) -> f64 {
+    let per_ar = WINSTONS_PER_AR as u128;
+    (winstons / per_ar) as f64 + (winstons % per_ar) as f64 / WINSTONS_PER_AR as f64
+}
+
+/// Deserializes winstons sent either as a JSON number or, when too large for one, as a
+/// string.
+pub(crate) fn deserialize_winstons<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<u128, D::Error> {
+    match Value::deserialize(deserializer)? {
+        Value::String(s) => s.parse().map_err(de::Error::custom),
+        Value::Number(n) => n
+            .as_u64()
+            .map(u128::from)
+            .ok_or_else(|| de::Error::custom(format!("invalid amount {}", n))),
+        other => Err(de::Error::custom(format!("invalid amount {}", other))),
+    }
+}
+
+/// Inserts a `,` every three digits from the right.
+fn group_thousands(digits: &str) -> String {
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(c);
+    }
+    grouped
+}
+
 impl From<u128> for Currency {
     fn from(u: u128) -> Self {
         let s = u.to_string();
@@ -56,15 +241,15 @@ impl FromStr for Currency {
     }
 }
 
-impl ToString for Currency {
-    fn to_string(&self) -> String {
+impl std::fmt::Display for Currency {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         let decimal = format!("{:#012}", self.winston);
         if self.arweave == 0 && self.winston == 0 {
-            '0'.to_string()
+            write!(f, "0")
         } else if self.arweave == 0 {
-            decimal.trim_start_matches('0').to_string()
+            write!(f, "{}", decimal.trim_start_matches('0'))
         } else {
-            self.arweave.to_string() + &decimal
+            write!(f, "{}{}", self.arweave, decimal)
         }
     }
 }
@@ -88,7 +273,8 @@ impl<'de> Deserialize<'de> for Currency {
 mod tests {
     use std::str::FromStr;
 
-    use super::Currency;
+    use super::{ar_to_winston, checked_fee_multiply, checked_sum_fees, winston_to_ar, Currency};
+    use crate::error::Error;
 
     #[test]
     fn test_str_parse() {
@@ -155,4 +341,122 @@ mod tests {
         assert_eq!(curr.arweave, 0);
         assert_eq!(curr.to_string(), "10000");
     }
+
+    #[test]
+    fn test_winstons() {
+        assert_eq!(
+            Currency::from(999_123_123_123_123).winstons(),
+            999_123_123_123_123
+        );
+        assert_eq!(Currency::from(10000).winstons(), 10000);
+        assert_eq!(Currency::default().winstons(), 0);
+    }
+
+    #[test]
+    fn test_human_format() {
+        let curr = Currency::from(12_500_000_000_000);
+        assert_eq!(curr.format_ar(12), "12.5 AR");
+        assert_eq!(curr.format_winston(), "12,500,000,000,000 winston");
+
+        let curr = Currency::from(1_234_567_891_234_567);
+        assert_eq!(curr.format_ar(12), "1,234.567891234567 AR");
+        assert_eq!(curr.format_ar(2), "1,234.57 AR");
+        assert_eq!(curr.format_ar(0), "1,235 AR");
+
+        assert_eq!(Currency::from(1000).format_winston(), "1,000 winston");
+        assert_eq!(Currency::from(999).format_winston(), "999 winston");
+        assert_eq!(Currency::from(1000).format_ar(6), "0 AR");
+        assert_eq!(Currency::default().format_ar(12), "0 AR");
+    }
+
+    #[test]
+    fn test_human_parse() {
+        assert_eq!(
+            Currency::parse_human("12.5 AR").unwrap().winstons(),
+            12_500_000_000_000
+        );
+        assert_eq!(
+            Currency::parse_human("0.5").unwrap().winstons(),
+            500_000_000_000
+        );
+        assert_eq!(
+            Currency::parse_human(".000000000001ar").unwrap().winstons(),
+            1
+        );
+        assert_eq!(
+            Currency::parse_human("1,000 winston").unwrap().winstons(),
+            1000
+        );
+        assert_eq!(
+            Currency::parse_human("1_000_000 Winstons")
+                .unwrap()
+                .winstons(),
+            1_000_000
+        );
+
+        let curr = Currency::from(1_234_567_891_234_567);
+        assert_eq!(Currency::parse_human(&curr.format_ar(12)).unwrap(), curr);
+        assert_eq!(Currency::parse_human(&curr.format_winston()).unwrap(), curr);
+
+        for invalid in [
+            "",
+            "AR",
+            "1,5.0.1 AR",
+            "1.5 winston",
+            "0.0000000000001 AR",
+            "-1 AR",
+            "1e3",
+        ] {
+            assert!(Currency::parse_human(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_checked_conversions() {
+        assert_eq!(ar_to_winston(1.0).unwrap(), 1_000_000_000_000);
+        assert_eq!(ar_to_winston(0.1).unwrap(), 100_000_000_000);
+        assert_eq!(ar_to_winston(12.5).unwrap(), 12_500_000_000_000);
+        assert_eq!(ar_to_winston(0.0).unwrap(), 0);
+        assert_eq!(ar_to_winston(1e-12).unwrap(), 1);
+        // 0.30000000000000004 rounds to the nearest winston.
+        assert_eq!(ar_to_winston(0.1 + 0.2).unwrap(), 300_000_000_000);
+        assert_eq!(ar_to_winston(4e-13).unwrap(), 0);
+        assert_eq!(ar_to_winston(6e-13).unwrap(), 1);
+
+        assert!(ar_to_winston(-1.0).is_err());
+        assert!(ar_to_winston(f64::NAN).is_err());
+        assert!(ar_to_winston(f64::INFINITY).is_err());
+        assert!(matches!(ar_to_winston(1e30), Err(Error::AmountOverflow(_))));
+
+        assert_eq!(winston_to_ar(12_500_000_000_000), 12.5);
+        assert_eq!(winston_to_ar(1), 1e-12);
+        assert_eq!(winston_to_ar(0), 0.0);
+    }
+
+    #[test]
+    fn test_checked_fee_math() {
+        assert_eq!(checked_sum_fees([1, 2, 3]).unwrap(), 6);
+        assert_eq!(checked_sum_fees([]).unwrap(), 0);
+        assert!(matches!(
+            checked_sum_fees([u64::MAX, 1]),
+            Err(Error::AmountOverflow(_))
+        ));
+
+        assert_eq!(checked_fee_multiply(1000, 1.5).unwrap(), 1500);
+        assert_eq!(checked_fee_multiply(3, 1.1).unwrap(), 4);
+        assert!(matches!(
+            checked_fee_multiply(u64::MAX / 2, 3.0),
+            Err(Error::AmountOverflow(_))
+        ));
+        assert!(matches!(
+            checked_fee_multiply(1, -1.0),
+            Err(Error::InvalidAmount(_))
+        ));
+
+        assert!(Currency::checked_from_winstons(u128::MAX).is_err());
+        assert_eq!(
+            Currency::checked_from_winstons(1_000).unwrap(),
+            Currency::from(1_000)
+        );
+    }
 }
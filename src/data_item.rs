@@ -0,0 +1,306 @@
+//! ANS-104 data items, the signed payloads bundlers pack into bundle transactions.
+//!
+//! Only Arweave (RSA-PSS, 4096 bit) signatures are supported.
+
+use crate::{
+    crypto::{
+        base64::Base64,
+        hash::{deep_hash, sha256, DeepHashItem},
+        sign::verify_pss,
+    },
+    error::Error,
+    signer::ArweaveSigner,
+    transaction::tags::Tag,
+};
+
+/// Signature type of Arweave signed data items.
+pub const ARWEAVE_SIGNATURE_TYPE: u16 = 1;
+
+/// Length of the signature and owner of an Arweave signed data item.
+const ARWEAVE_KEY_LENGTH: usize = 512;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DataItem {
+    pub signature: Base64,
+    /// Public modulus of the signer.
+    pub owner: Base64,
+    /// Empty, or the 32 byte address the item is addressed to.
+    pub target: Base64,
+    /// Empty, or 32 arbitrary bytes.
+    pub anchor: Base64,
+    pub tags: Vec<Tag<Base64>>,
+    pub data: Vec<u8>,
+}
+
+impl DataItem {
+    /// Unsigned data item holding `data`.
+    pub fn new(data: Vec<u8>, tags: Vec<Tag<Base64>>) -> Self {
+        Self {
+            tags,
+            data,
+            ..Self::default()
+        }
+    }
+
+    /// Id of the item: the SHA-256 of its signature.
+    pub fn id(&self) -> Base64 {
+        Base64::from(sha256(&self.signature).to_vec())
+    }
+
+    /// Message signed by the owner: the deep hash of
+    /// `["dataitem", "1", signature type, owner, target, anchor, tags, data]`.
+    pub fn signature_data(&self) -> [u8; 48] {
+        let items = vec![
+            DeepHashItem::from_item(b"dataitem"),
+            DeepHashItem::from_item(b"1"),
+            DeepHashItem::from_item(ARWEAVE_SIGNATURE_TYPE.to_string().as_bytes()),
+            DeepHashItem::from_item(&self.owner),
+            DeepHashItem::from_item(&self.target),
+            DeepHashItem::from_item(&self.anchor),
+            DeepHashItem::from_item(&encode_tags(&self.tags)),
+            DeepHashItem::from_item(&self.data),
+        ];
+        deep_hash(DeepHashItem::from_children(items))
+    }
+
+    /// Sets `signer` as the owner and signs the item.
+    pub fn sign(mut self, signer: &ArweaveSigner) -> Result<Self, Error> {
+        self.owner = signer.keypair_modulus();
+        self.check_layout()?;
+        self.signature = signer.sign(&self.signature_data());
+        Ok(self)
+    }
+
+    pub fn verify(&self) -> Result<(), Error> {
+        if self.signature.is_empty() {
+            return Err(Error::UnsignedTransaction);
+        }
+        verify_pss(&self.owner, &self.signature_data(), &self.signature)
+    }
+
+    /// Binary encoding of the item, as posted to bundlers.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        self.check_layout()?;
+        if self.signature.len() != ARWEAVE_KEY_LENGTH {
+            return Err(Error::DataItemError("item is not signed".to_string()));
+        }
+        let tags = encode_tags(&self.tags);
+        let mut bytes = Vec::with_capacity(1100 + tags.len() + self.data.len());
+        bytes.extend_from_slice(&ARWEAVE_SIGNATURE_TYPE.to_le_bytes());
+        bytes.extend_from_slice(&self.signature);
+        bytes.extend_from_slice(&self.owner);
+        for optional in [&self.target, &self.anchor] {
+            bytes.push(!optional.is_empty() as u8);
+            bytes.extend_from_slice(optional);
+        }
+        bytes.extend_from_slice(&(self.tags.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(&(tags.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(&tags);
+        bytes.extend_from_slice(&self.data);
+        Ok(bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let mut reader = Reader(bytes);
+        let signature_type = u16::from_le_bytes(reader.array()?);
+        if signature_type != ARWEAVE_SIGNATURE_TYPE {
+            return Err(Error::DataItemError(format!(
+                "unsupported signature type {}",
+                signature_type
+            )));
+        }
+        let signature = Base64::from(reader.take(ARWEAVE_KEY_LENGTH)?);
+        let owner = Base64::from(reader.take(ARWEAVE_KEY_LENGTH)?);
+        let target = reader.optional()?;
+        let anchor = reader.optional()?;
+        let tag_count = u64::from_le_bytes(reader.array()?);
+        let tags_length = u64::from_le_bytes(reader.array()?);
+        let tags = decode_tags(reader.take(tags_length as usize)?)?;
+        if tags.len() as u64 != tag_count {
+            return Err(Error::DataItemError(format!(
+                "expected {} tags, found {}",
+                tag_count,
+                tags.len()
+            )));
+        }
+        Ok(Self {
+            signature,
+            owner,
+            target,
+            anchor,
+            tags,
+            data: reader.0.to_vec(),
+        })
+    }
+
+    fn check_layout(&self) -> Result<(), Error> {
+        if self.owner.len() != ARWEAVE_KEY_LENGTH {
+            return Err(Error::DataItemError(format!(
+                "owner must be {} bytes, found {}",
+                ARWEAVE_KEY_LENGTH,
+                self.owner.len()
+            )));
+        }
+        for (field, value) in [("target", &self.target), ("anchor", &self.anchor)] {
+            if !value.is_empty() && value.len() != 32 {
+                return Err(Error::DataItemError(format!(
+                    "{} must be empty or 32 bytes, found {}",
+                    field,
+                    value.len()
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Avro encoding of `tags` as an array of `{ name: bytes, value: bytes }` records, empty when
+/// there are no tags.
+fn encode_tags(tags: &[Tag<Base64>]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    if tags.is_empty() {
+        return bytes;
+    }
+    write_long(&mut bytes, tags.len() as i64);
+    for tag in tags {
+        for field in [&tag.name, &tag.value] {
+            write_long(&mut bytes, field.len() as i64);
+            bytes.extend_from_slice(field);
+        }
+    }
+    write_long(&mut bytes, 0);
+    bytes
+}
+
+fn decode_tags(bytes: &[u8]) -> Result<Vec<Tag<Base64>>, Error> {
+    let mut reader = Reader(bytes);
+    let mut tags = Vec::new();
+    while !reader.0.is_empty() {
+        let mut count = reader.long()?;
+        if count == 0 {
+            break;
+        }
+        if count < 0 {
+            // Negative counts are followed by the size of the block in bytes.
+            count = -count;
+            reader.long()?;
+        }
+        for _ in 0..count {
+            let name = reader.bytes()?;
+            let value = reader.bytes()?;
+            tags.push(Tag { name, value });
+        }
+    }
+    Ok(tags)
+}
+
+/// Zigzag varint encoding of `n`.
+fn write_long(bytes: &mut Vec<u8>, n: i64) {
+    let mut n = ((n << 1) ^ (n >> 63)) as u64;
+    while n >= 0x80 {
+        bytes.push(n as u8 | 0x80);
+        n >>= 7;
+    }
+    bytes.push(n as u8);
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        if self.0.len() < len {
+            return Err(Error::DataItemError("unexpected end of item".to_string()));
+        }
+        let (taken, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], Error> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn optional(&mut self) -> Result<Base64, Error> {
+        match self.take(1)?[0] {
+            0 => Ok(Base64::empty()),
+            1 => Ok(Base64::from(self.take(32)?)),
+            flag => Err(Error::DataItemError(format!(
+                "invalid presence byte {}",
+                flag
+            ))),
+        }
+    }
+
+    fn long(&mut self) -> Result<i64, Error> {
+        let mut n = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.take(1)?[0];
+            n |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok((n >> 1) as i64 ^ -((n & 1) as i64));
+            }
+        }
+        Err(Error::DataItemError("varint too long".to_string()))
+    }
+
+    fn bytes(&mut self) -> Result<Base64, Error> {
+        let len = self.long()?;
+        if len < 0 {
+            return Err(Error::DataItemError(format!("negative length {}", len)));
+        }
+        Ok(Base64::from(self.take(len as usize)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_tags, encode_tags, DataItem};
+    use crate::{
+        crypto::base64::Base64,
+        error::Error,
+        signer::ArweaveSigner,
+        transaction::tags::{FromUtf8Strs, Tag},
+    };
+
+    #[test]
+    fn test_tags_encoding() {
+        let tags = vec![
+            Tag::<Base64>::from_utf8_strs("Content-Type", "text/plain").unwrap(),
+            Tag::<Base64>::from_utf8_strs("App-Name", "").unwrap(),
+        ];
+        let bytes = encode_tags(&tags);
+        // 2 items, "Content-Type" is 12 bytes long, both zigzag encoded.
+        assert_eq!(&bytes[..3], &[4, 24, b'C']);
+        assert_eq!(*bytes.last().unwrap(), 0);
+        assert_eq!(decode_tags(&bytes).unwrap(), tags);
+        assert!(encode_tags(&[]).is_empty());
+        assert!(decode_tags(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_sign_and_parse() -> Result<(), Error> {
+        let pem = std::fs::read_to_string("res/test_wallet_4096.pem").unwrap();
+        let signer = ArweaveSigner::from_pem_str(&pem)?;
+        assert!(DataItem::new(vec![], vec![])
+            .sign(&ArweaveSigner::default())
+            .is_err());
+        let mut item = DataItem::new(
+            b"hello".to_vec(),
+            vec![Tag::<Base64>::from_utf8_strs("Content-Type", "text/plain")?],
+        );
+        item.anchor = Base64::from(vec![7; 32]);
+        let item = item.sign(&signer)?;
+        item.verify()?;
+
+        let bytes = item.to_bytes()?;
+        let parsed = DataItem::from_bytes(&bytes)?;
+        assert_eq!(parsed, item);
+        assert_eq!(parsed.id(), item.id());
+
+        let mut tampered = parsed;
+        tampered.data = b"hellp".to_vec();
+        assert!(matches!(tampered.verify(), Err(Error::InvalidSignature)));
+        assert!(DataItem::from_bytes(&bytes[..600]).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,305 @@
+//! Block scanner crediting AR deposits to watched addresses, the way exchanges do: deposits
+//! are reported when seen, confirmed after enough blocks, and reverted when their block is
+//! orphaned. Reorgs are detected by checking that every block builds on the previous one
+//! scanned.
+
+use std::collections::{HashSet, VecDeque};
+
+use futures::{stream, StreamExt, TryStreamExt};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    consts::{DEPOSIT_REORG_DEPTH, SCAN_CONCURRENCY},
+    crypto::base64::Base64,
+    error::Error,
+    finality::FinalityPolicy,
+    gateway::Gateway,
+};
+
+/// A transfer of AR to a watched address.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Deposit {
+    pub tx_id: Base64,
+    pub from: Base64,
+    pub to: Base64,
+    /// Winstons transferred.
+    pub quantity: u128,
+    pub height: u64,
+    /// Independent hash of the block holding the transaction.
+    pub block: Base64,
+}
+
+impl Deposit {
+    /// Confirmations once the chain reaches `tip`, counting the block of the deposit.
+    pub fn confirmations(&self, tip: u64) -> u64 {
+        (tip + 1).saturating_sub(self.height)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DepositEvent {
+    /// Seen in a newly scanned block, not to be credited yet.
+    Detected(Deposit),
+    /// Is final according to the [`FinalityPolicy`] of the scanner, and can be credited.
+    Confirmed(Deposit),
+    /// Its block was orphaned: revert the credit if it was confirmed. The transaction may
+    /// show up again, in another block.
+    Reorged(Deposit),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+struct Tracked {
+    deposit: Deposit,
+    confirmed: bool,
+}
+
+/// Progress of a scan, serializable so a scan can resume where it stopped.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScanCursor {
+    /// Next height to scan.
+    pub height: u64,
+    /// Heights and hashes of the last scanned blocks, oldest first.
+    recent: VecDeque<(u64, Base64)>,
+    /// Deposits of the blocks of `recent`.
+    tracked: Vec<Tracked>,
+}
+
+impl ScanCursor {
+    /// Cursor scanning from block `height` on.
+    pub fn at(height: u64) -> Self {
+        Self {
+            height,
+            ..Default::default()
+        }
+    }
+}
+
+/// Scans blocks for transfers to a set of deposit addresses.
+#[derive(Debug, Clone)]
+pub struct DepositScanner {
+    addresses: HashSet<String>,
+    finality: FinalityPolicy,
+    cursor: ScanCursor,
+}
+
+impl DepositScanner {
+    pub fn new(addresses: Vec<Base64>, finality: FinalityPolicy, cursor: ScanCursor) -> Self {
+        Self {
+            addresses: addresses.iter().map(Base64::to_string).collect(),
+            finality,
+            cursor,
+        }
+    }
+
+    pub fn watch(&mut self, address: &Base64) {
+        self.addresses.insert(address.to_string());
+    }
+
+    pub fn unwatch(&mut self, address: &Base64) {
+        self.addresses.remove(&address.to_string());
+    }
+
+    /// Cursor to persist after handling the events of a scan.
+    pub fn cursor(&self) -> &ScanCursor {
+        &self.cursor
+    }
+
+    /// Scans the blocks up to height `tip`, and reports deposits detected, confirmed or
+    /// orphaned since the previous scan. A reorg deeper than [`DEPOSIT_REORG_DEPTH`] blocks
+    /// fails with [`Error::ReorgTooDeep`], leaving the cursor untouched.
+    pub async fn scan(
+        &mut self,
+        gateway: &dyn Gateway,
+        tip: u64,
+    ) -> Result<Vec<DepositEvent>, Error> {
+        let mut cursor = self.cursor.clone();
+        let mut events = Vec::new();
+        while cursor.height <= tip {
+            let block = gateway.get_block(cursor.height).await?;
+            if let Some((height, hash)) = cursor.recent.back() {
+                if !block.previous_block.ct_eq(hash) {
+                    let orphaned = *height;
+                    cursor.recent.pop_back();
+                    if cursor.recent.is_empty() {
+                        return Err(Error::ReorgTooDeep(format!(
+                            "block {} was orphaned, and no earlier block is known",
+                            orphaned
+                        )));
+                    }
+                    let (kept, reorged): (Vec<_>, Vec<_>) = cursor
+                        .tracked
+                        .drain(..)
+                        .partition(|t| t.deposit.height < orphaned);
+                    cursor.tracked = kept;
+                    events.extend(
+                        reorged
+                            .into_iter()
+                            .map(|t| DepositEvent::Reorged(t.deposit)),
+                    );
+                    cursor.height = orphaned;
+                    continue;
+                }
+            }
+
+            let txs: Vec<_> = stream::iter(block.txs.iter())
+                .map(|id| async move {
+                    match gateway.get_tx(id).await? {
+                        (_, Some(tx)) => Ok(tx),
+                        (status, None) => Err(Error::TransactionInfoError(format!(
+                            "{} of block {}: {}",
+                            id, block.height, status
+                        ))),
+                    }
+                })
+                .buffered(SCAN_CONCURRENCY)
+                .try_collect()
+                .await?;
+            for tx in txs {
+                let quantity = tx.quantity.winstons();
+                if quantity == 0 || !self.addresses.contains(&tx.target.to_string()) {
+                    continue;
+                }
+                let deposit = Deposit {
+                    from: tx.owner_address(),
+                    tx_id: tx.id,
+                    to: tx.target,
+                    quantity,
+                    height: block.height,
+                    block: block.indep_hash.clone(),
+                };
+                events.push(DepositEvent::Detected(deposit.clone()));
+                cursor.tracked.push(Tracked {
+                    deposit,
+                    confirmed: false,
+                });
+            }
+
+            cursor
+                .recent
+                .push_back((block.height, block.indep_hash.clone()));
+            if cursor.recent.len() > DEPOSIT_REORG_DEPTH {
+                cursor.recent.pop_front();
+            }
+            cursor.height = block.height + 1;
+        }
+
+        for tracked in cursor.tracked.iter_mut() {
+            if !tracked.confirmed && self.finality.is_final(tracked.deposit.confirmations(tip)) {
+                tracked.confirmed = true;
+                events.push(DepositEvent::Confirmed(tracked.deposit.clone()));
+            }
+        }
+        // Deposits are tracked until final, however long the policy takes. Final ones are
+        // kept while their block can still be orphaned, to report the reorg.
+        let oldest = cursor.recent.front().map_or(0, |(height, _)| *height);
+        cursor
+            .tracked
+            .retain(|t| !t.confirmed || t.deposit.height >= oldest);
+
+        self.cursor = cursor;
+        Ok(events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DepositEvent, DepositScanner, ScanCursor};
+    use crate::{
+        crypto::base64::Base64,
+        currency::Currency,
+        finality::FinalityPolicy,
+        gateway::{Gateway, MockGateway},
+        transaction::Tx,
+    };
+
+    async fn transfer(gateway: &MockGateway, id: u8, to: &Base64, quantity: u128) -> Base64 {
+        let tx = Tx {
+            id: Base64::from(vec![id; 32]),
+            target: to.clone(),
+            quantity: Currency::from(quantity),
+            ..Default::default()
+        };
+        gateway.post_tx(&tx).await.unwrap();
+        tx.id
+    }
+
+    #[tokio::test]
+    async fn test_scan_deposits() {
+        let gateway = MockGateway::new();
+        let hot = Base64::from(vec![7; 32]);
+        let other = Base64::from(vec![8; 32]);
+        let deposit = transfer(&gateway, 1, &hot, 100).await;
+        let unrelated = transfer(&gateway, 2, &other, 100).await;
+        gateway.add_block(1, 0, vec![]);
+        gateway.add_block(2, 0, vec![deposit.clone(), unrelated]);
+
+        let mut scanner = DepositScanner::new(
+            vec![hot.clone()],
+            FinalityPolicy::Confirmations(2),
+            ScanCursor::at(1),
+        );
+        let events = scanner.scan(&gateway, 2).await.unwrap();
+        assert!(matches!(&events[..], [DepositEvent::Detected(d)] if d.tx_id == deposit));
+        assert_eq!(scanner.cursor().height, 3);
+
+        // Block 2 is replaced by one without the deposit.
+        let mut fork = gateway.get_block(2).await.unwrap();
+        fork.indep_hash = Base64::from(vec![2; 48]);
+        fork.txs = vec![];
+        gateway.set_block(fork.clone());
+        let mut next = gateway.get_block(2).await.unwrap();
+        next.height = 3;
+        next.indep_hash = Base64::from(vec![3; 48]);
+        next.previous_block = fork.indep_hash;
+        gateway.set_block(next);
+
+        let events = scanner.scan(&gateway, 3).await.unwrap();
+        assert!(matches!(&events[..], [DepositEvent::Reorged(d)] if d.tx_id == deposit));
+        assert_eq!(scanner.cursor().height, 4);
+
+        let late = transfer(&gateway, 3, &hot, 5).await;
+        gateway.add_block(4, 0, vec![late.clone()]);
+        let mut block = gateway.get_block(4).await.unwrap();
+        block.previous_block = gateway.get_block(3).await.unwrap().indep_hash;
+        gateway.set_block(block);
+        let events = scanner.scan(&gateway, 4).await.unwrap();
+        assert!(matches!(&events[..], [DepositEvent::Detected(_)]));
+
+        let resumed = scanner.cursor().clone();
+        let json = serde_json::to_string(&resumed).unwrap();
+        let mut scanner = DepositScanner::new(
+            vec![hot],
+            FinalityPolicy::Confirmations(2),
+            serde_json::from_str(&json).unwrap(),
+        );
+        gateway.add_block(5, 0, vec![]);
+        let events = scanner.scan(&gateway, 5).await.unwrap();
+        assert!(
+            matches!(&events[..], [DepositEvent::Confirmed(d)] if d.tx_id == late && d.quantity == 5)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_confirm_beyond_reorg_depth() {
+        let gateway = MockGateway::new();
+        let hot = Base64::from(vec![7; 32]);
+        let deposit = transfer(&gateway, 1, &hot, 100).await;
+        gateway.add_block(1, 0, vec![deposit.clone()]);
+        for height in 2..=100 {
+            gateway.add_block(height, 0, vec![]);
+        }
+
+        let mut scanner = DepositScanner::new(
+            vec![hot],
+            FinalityPolicy::Confirmations(80),
+            ScanCursor::at(1),
+        );
+        let events = scanner.scan(&gateway, 60).await.unwrap();
+        assert!(matches!(&events[..], [DepositEvent::Detected(_)]));
+        assert!(scanner.scan(&gateway, 79).await.unwrap().is_empty());
+        let events = scanner.scan(&gateway, 80).await.unwrap();
+        assert!(matches!(&events[..], [DepositEvent::Confirmed(d)] if d.tx_id == deposit));
+        assert!(scanner.scan(&gateway, 100).await.unwrap().is_empty());
+        assert!(scanner.cursor().tracked.is_empty());
+    }
+}
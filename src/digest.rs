@@ -0,0 +1,211 @@
+//! Content digests attached as `File-Hash` tags, to check downloaded data independently of
+//! the transaction's merkle root.
+
+use std::{
+    fmt,
+    fs::File,
+    io::{self, Read},
+    path::Path,
+    str::FromStr,
+};
+
+use sha2::Digest as _;
+
+use crate::{
+    crypto::base64::Base64,
+    error::Error,
+    transaction::tags::{Tag, FILE_HASH},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DigestAlgorithm {
+    Sha256,
+    Blake3,
+}
+
+impl DigestAlgorithm {
+    pub fn name(&self) -> &'static str {
+        match self {
+            DigestAlgorithm::Sha256 => "sha256",
+            DigestAlgorithm::Blake3 => "blake3",
+        }
+    }
+
+    pub fn hasher(&self) -> Hasher {
+        match self {
+            DigestAlgorithm::Sha256 => Hasher::Sha256(sha2::Sha256::new()),
+            DigestAlgorithm::Blake3 => Hasher::Blake3(Box::default()),
+        }
+    }
+}
+
+impl FromStr for DigestAlgorithm {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sha256" => Ok(DigestAlgorithm::Sha256),
+            "blake3" => Ok(DigestAlgorithm::Blake3),
+            _ => Err(Error::InvalidTagValue(format!(
+                "{}: unknown algorithm {:?}",
+                FILE_HASH, s
+            ))),
+        }
+    }
+}
+
+/// Incremental digest, for data streamed in pieces.
+pub enum Hasher {
+    Sha256(sha2::Sha256),
+    Blake3(Box<blake3::Hasher>),
+}
+
+impl Hasher {
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Sha256(hasher) => hasher.update(data),
+            Hasher::Blake3(hasher) => {
+                hasher.update(data);
+            }
+        }
+    }
+
+    pub fn finalize(self) -> Digest {
+        match self {
+            Hasher::Sha256(hasher) => Digest {
+                algorithm: DigestAlgorithm::Sha256,
+                hash: hasher.finalize().into(),
+            },
+            Hasher::Blake3(hasher) => Digest {
+                algorithm: DigestAlgorithm::Blake3,
+                hash: *hasher.finalize().as_bytes(),
+            },
+        }
+    }
+}
+
+/// A digest, written `<algorithm>:<hex>` in `File-Hash` tags.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Digest {
+    pub algorithm: DigestAlgorithm,
+    pub hash: [u8; 32],
+}
+
+impl Digest {
+    pub fn compute(algorithm: DigestAlgorithm, data: &[u8]) -> Self {
+        let mut hasher = algorithm.hasher();
+        hasher.update(data);
+        hasher.finalize()
+    }
+
+    /// Digest of the file at `path`, read in pieces.
+    pub fn of_file(algorithm: DigestAlgorithm, path: &Path) -> Result<Self, Error> {
+        let io_error = |e: io::Error| Error::IoError(format!("{}: {}", path.display(), e));
+        let mut file = File::open(path).map_err(io_error)?;
+        let mut hasher = algorithm.hasher();
+        let mut buffer = vec![0; 64 * 1024];
+        loop {
+            match file.read(&mut buffer).map_err(io_error)? {
+                0 => return Ok(hasher.finalize()),
+                n => hasher.update(&buffer[..n]),
+            }
+        }
+    }
+
+    /// [`Digest::of_file`] on the blocking thread pool, for async callers.
+    pub async fn of_file_async(algorithm: DigestAlgorithm, path: &Path) -> Result<Self, Error> {
+        let path = path.to_path_buf();
+        tokio::task::spawn_blocking(move || Self::of_file(algorithm, &path))
+            .await
+            .map_err(|e| Error::TaskError(e.to_string()))?
+    }
+
+    pub fn matches(&self, data: &[u8]) -> bool {
+        Self::compute(self.algorithm, data) == *self
+    }
+
+    /// `File-Hash` tag holding the digest.
+    pub fn to_tag(&self) -> Result<Tag<Base64>, Error> {
+        Tag::<Base64>::file_hash(&self.to_string())
+    }
+
+    /// Digest of the first `File-Hash` tag of `tags`, if any.
+    pub fn from_tags(tags: &[Tag<Base64>]) -> Result<Option<Self>, Error> {
+        tags.iter()
+            .find(|tag| tag.name.0 == FILE_HASH.as_bytes())
+            .map(|tag| tag.value.to_utf8_string()?.parse())
+            .transpose()
+    }
+}
+
+impl fmt::Display for Digest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:", self.algorithm.name())?;
+        self.hash.iter().try_for_each(|b| write!(f, "{:02x}", b))
+    }
+}
+
+impl FromStr for Digest {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::InvalidTagValue(format!("{}: {:?} is not a digest", FILE_HASH, s));
+        let (algorithm, hex) = s.split_once(':').ok_or_else(invalid)?;
+        if hex.len() != 64 || !hex.is_ascii() {
+            return Err(invalid());
+        }
+        let mut hash = [0; 32];
+        for (i, byte) in hash.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).map_err(|_| invalid())?;
+        }
+        Ok(Self {
+            algorithm: algorithm.parse()?,
+            hash,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Digest, DigestAlgorithm};
+
+    #[test]
+    fn test_digests() {
+        let sha256 = Digest::compute(DigestAlgorithm::Sha256, b"abc");
+        assert_eq!(
+            sha256.to_string(),
+            "sha256:ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        let blake3 = Digest::compute(DigestAlgorithm::Blake3, b"");
+        assert_eq!(
+            blake3.to_string(),
+            "blake3:af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262"
+        );
+
+        let mut hasher = DigestAlgorithm::Blake3.hasher();
+        hasher.update(b"ab");
+        hasher.update(b"c");
+        assert_eq!(
+            hasher.finalize(),
+            Digest::compute(DigestAlgorithm::Blake3, b"abc")
+        );
+
+        assert_eq!(sha256.to_string().parse::<Digest>().unwrap(), sha256);
+        let tags = vec![sha256.to_tag().unwrap()];
+        assert_eq!(Digest::from_tags(&tags).unwrap(), Some(sha256));
+        assert!(sha256.matches(b"abc"));
+        assert!(!sha256.matches(b"abd"));
+        assert!("md5:00".parse::<Digest>().is_err());
+        assert!("sha256:zz".parse::<Digest>().is_err());
+    }
+
+    #[test]
+    fn test_digest_of_file() {
+        let path = std::path::Path::new("res/test_image.jpg");
+        assert_eq!(
+            Digest::of_file(DigestAlgorithm::Blake3, path).unwrap(),
+            Digest::compute(DigestAlgorithm::Blake3, &std::fs::read(path).unwrap())
+        );
+        assert!(Digest::of_file(DigestAlgorithm::Sha256, std::path::Path::new("missing")).is_err());
+    }
+}
@@ -1,7 +1,7 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-#[derive(Debug, Error, Deserialize)]
+#[derive(Debug, Error, Deserialize, Serialize)]
 pub enum Error {
     #[error("Error getting oracle price: {0}")]
     OracleGetPriceError(String),
@@ -53,4 +53,115 @@ pub enum Error {
 
     #[error("Error signin: {0}")]
     SigningError(String),
+
+    #[error("Budget exceeded: {0}")]
+    BudgetExceeded(String),
+
+    #[error("Invalid anchor (last_tx): not found or too old.")]
+    InvalidAnchor,
+
+    #[error("GraphQL error: {0}")]
+    GraphqlError(String),
+
+    #[error("Invalid tag value: {0}")]
+    InvalidTagValue(String),
+
+    #[error("Invalid bundle tags: {0}")]
+    InvalidBundleTags(String),
+
+    #[error("Fixture error: {0}")]
+    FixtureError(String),
+
+    #[error("Invalid amount: {0}")]
+    InvalidAmount(String),
+
+    #[error("Amount overflow: {0}")]
+    AmountOverflow(String),
+
+    #[error("IO error: {0}")]
+    IoError(String),
+
+    #[error("Rate limited, retry after {0}s")]
+    RateLimited(u64),
+
+    #[error("No gateway available: {0}")]
+    GatewayUnavailable(String),
+
+    #[error("Background task failed: {0}")]
+    TaskError(String),
+
+    #[error("Index error: {0}")]
+    IndexError(String),
+
+    #[error("Invalid manifest: {0}")]
+    ManifestError(String),
+
+    #[error("Invalid ArFS entity: {0}")]
+    ArfsError(String),
+
+    #[error("Invalid base64: {0}")]
+    InvalidBase64(String),
+
+    #[error("Invalid key: {0}")]
+    InvalidKey(String),
+
+    #[error("Invalid prepared transaction: {0}")]
+    PreparedTxError(String),
+
+    #[error("Host is not in the allowlist: {0}")]
+    UntrustedHost(String),
+
+    #[error("Invalid certificate: {0}")]
+    InvalidCertificate(String),
+
+    #[error("Could not build HTTP client: {0}")]
+    HttpClientError(String),
+
+    #[error("Invalid data item: {0}")]
+    DataItemError(String),
+
+    #[error("Bundler error: {0}")]
+    BundlerError(String),
+
+    #[error("Digest mismatch: {0}")]
+    DigestMismatch(String),
+
+    #[error("S3 error: {0}")]
+    S3Error(String),
+
+    #[error("Error fetching source: {0}")]
+    SourceError(String),
+
+    #[error("Invalid archive: {0}")]
+    ArchiveError(String),
+
+    #[error("Offset out of range: {0}")]
+    OffsetOutOfRange(String),
+
+    #[error("Invalid chunk layout: {0}")]
+    InvalidChunkLayout(String),
+
+    #[error("No transaction template named {0}")]
+    TemplateNotFound(String),
+
+    #[error("Invalid log entry: {0}")]
+    LogEntryError(String),
+
+    #[error("Reorg too deep: {0}")]
+    ReorgTooDeep(String),
+
+    #[error("Faucet error: {0}")]
+    FaucetError(String),
+
+    #[error("Insufficient funds: {0}")]
+    InsufficientFunds(String),
+
+    #[error("Cannot canonicalize JSON: {0}")]
+    CanonicalJsonError(String),
+
+    #[error("Vanity address search failed: {0}")]
+    VanityError(String),
+
+    #[error("Timed out: {0}")]
+    Timeout(String),
 }
@@ -0,0 +1,44 @@
+//! Lifecycle events emitted while uploading, for tracking and persisting upload state.
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+/// State transitions of an upload, broadcast to every receiver returned by
+/// [`crate::Arweave::subscribe_upload_events`].
+///
+/// Events before signing are keyed by file path, [`UploadEvent::Signed`] links the path to
+/// the transaction id and later events are keyed by id.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type")]
+pub enum UploadEvent {
+    FeeQuoted {
+        path: PathBuf,
+        fee: u64,
+    },
+    Signed {
+        path: PathBuf,
+        id: String,
+    },
+    /// The transaction, or only its header for chunked uploads, was accepted.
+    HeaderPosted {
+        id: String,
+    },
+    ChunkPosted {
+        id: String,
+        offset: usize,
+    },
+    Completed {
+        id: String,
+        reward: u64,
+    },
+    /// The wallet already uploaded the same data as `id`, so nothing was posted.
+    Deduplicated {
+        path: PathBuf,
+        id: String,
+    },
+    Failed {
+        path: PathBuf,
+        error: String,
+    },
+}
@@ -0,0 +1,59 @@
+//! When a transaction is final enough to act upon, set once with
+//! [`crate::Arweave::set_finality`] and applied by the confirmation watcher, the deposit
+//! scanner and [`crate::Arweave::wait_for_confirmation`].
+
+use serde::{Deserialize, Serialize};
+
+use crate::{consts::FINAL_CONFIRMATIONS, types::TxStatus};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FinalityPolicy {
+    /// Final with this many confirmations, the block holding the transaction counting as
+    /// the first one.
+    Confirmations(u64),
+    /// Final once mined, then followed by this many blocks.
+    BlocksAfterInclusion(u64),
+}
+
+impl Default for FinalityPolicy {
+    fn default() -> Self {
+        FinalityPolicy::Confirmations(FINAL_CONFIRMATIONS)
+    }
+}
+
+impl FinalityPolicy {
+    /// Confirmations a transaction needs to be final. Never zero: a pending transaction is
+    /// never final.
+    pub fn required_confirmations(&self) -> u64 {
+        match *self {
+            FinalityPolicy::Confirmations(confirmations) => confirmations.max(1),
+            FinalityPolicy::BlocksAfterInclusion(blocks) => blocks + 1,
+        }
+    }
+
+    pub fn is_final(&self, confirmations: u64) -> bool {
+        confirmations >= self.required_confirmations()
+    }
+
+    pub fn is_status_final(&self, status: &TxStatus) -> bool {
+        self.is_final(status.number_of_confirmations)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FinalityPolicy;
+
+    #[test]
+    fn test_finality_policy() {
+        assert_eq!(FinalityPolicy::Confirmations(3).required_confirmations(), 3);
+        assert_eq!(FinalityPolicy::Confirmations(0).required_confirmations(), 1);
+        assert_eq!(
+            FinalityPolicy::BlocksAfterInclusion(0).required_confirmations(),
+            1
+        );
+        assert!(FinalityPolicy::BlocksAfterInclusion(2).is_final(3));
+        assert!(!FinalityPolicy::BlocksAfterInclusion(2).is_final(2));
+        assert!(!FinalityPolicy::Confirmations(0).is_final(0));
+    }
+}
@@ -0,0 +1,256 @@
+//! Discovery of gateways among the peers of a node, to spread load away from a single
+//! gateway such as arweave.net.
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use futures::{stream, StreamExt};
+use rand::seq::SliceRandom;
+use reqwest::StatusCode;
+use serde_json::Value;
+
+use crate::{
+    consts::{DISCOVERY_MAX_HEIGHT_LAG, DISCOVERY_SAMPLE_SIZE, DISCOVERY_TIMEOUT},
+    error::Error,
+    transaction::client::TxClient,
+};
+
+use super::{DnsOverrides, Gateway, GatewayPool, TrustPolicy};
+
+/// How many peers to probe and which ones count as healthy.
+#[derive(Debug, Clone)]
+pub struct DiscoveryConfig {
+    /// Number of peers, picked at random, probed.
+    pub sample_size: usize,
+    /// Time a peer has to answer `/info`.
+    pub timeout: Duration,
+    /// Blocks a peer may be behind the seed node and still be healthy.
+    pub max_height_lag: u64,
+    /// When set, peers outside its allowlist are never contacted.
+    pub trust: Option<TrustPolicy>,
+    /// Addresses the seed, and any peer named by hostname, resolve to.
+    pub dns: DnsOverrides,
+}
+
+impl Default for DiscoveryConfig {
+    fn default() -> Self {
+        Self {
+            sample_size: DISCOVERY_SAMPLE_SIZE,
+            timeout: Duration::from_secs(DISCOVERY_TIMEOUT),
+            max_height_lag: DISCOVERY_MAX_HEIGHT_LAG,
+            trust: None,
+            dns: DnsOverrides::default(),
+        }
+    }
+}
+
+/// A peer that answered its probe.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Peer {
+    pub url: url::Url,
+    pub height: u64,
+    pub latency: Duration,
+}
+
+/// Pulls the peers of `seed`, probes a sample of them and returns the reachable ones at most
+/// `max_height_lag` blocks behind `seed`, fastest first.
+pub async fn discover_peers(seed: &url::Url, config: &DiscoveryConfig) -> Result<Vec<Peer>, Error> {
+    if let Some(trust) = &config.trust {
+        trust.check(seed)?;
+    }
+    let client = client_builder(config)
+        .timeout(config.timeout)
+        .build()
+        .map_err(|e| Error::NetworkInfoError(e.to_string()))?;
+
+    let seed_height = probe(&client, seed)
+        .await
+        .map(|peer| peer.height)
+        .ok_or_else(|| Error::NetworkInfoError(format!("seed {} did not answer", seed)))?;
+    let peers: Vec<String> = client
+        .get(
+            seed.join("peers")
+                .expect("Could not join base_url with /peers"),
+        )
+        .send()
+        .await
+        .map_err(|e| Error::NetworkInfoError(e.to_string()))?
+        .json()
+        .await
+        .map_err(|e| Error::NetworkInfoError(e.to_string()))?;
+
+    let scheme = if config.trust.as_ref().is_some_and(TrustPolicy::is_pinned) {
+        "https"
+    } else {
+        "http"
+    };
+    let candidates: Vec<url::Url> = peers
+        .iter()
+        .filter_map(|peer| url::Url::parse(&format!("{}://{}/", scheme, peer)).ok())
+        .filter(|url| {
+            config
+                .trust
+                .as_ref()
+                .is_none_or(|trust| trust.is_allowed(url))
+        })
+        .collect();
+    let sample: Vec<url::Url> = candidates
+        .choose_multiple(&mut rand::thread_rng(), config.sample_size)
+        .cloned()
+        .collect();
+    let mut healthy: Vec<Peer> = stream::iter(sample)
+        .map(|url| {
+            let client = &client;
+            async move { probe(client, &url).await }
+        })
+        .buffer_unordered(config.sample_size.max(1))
+        .filter_map(|peer| async move { peer })
+        .filter(|peer| {
+            let healthy = peer.height + config.max_height_lag >= seed_height;
+            async move { healthy }
+        })
+        .collect()
+        .await;
+    healthy.sort_by_key(|peer| peer.latency);
+    Ok(healthy)
+}
+
+/// Discovers healthy peers of `seed` and pools them, fastest first, with `fallback` last.
+pub async fn discover_gateways(
+    seed: &url::Url,
+    fallback: Arc<dyn Gateway>,
+    config: &DiscoveryConfig,
+) -> Result<(GatewayPool, Vec<Peer>), Error> {
+    let peers = discover_peers(seed, config).await?;
+    let mut gateways = peers
+        .iter()
+        .map(|peer| {
+            let client = client_builder(config)
+                .build()
+                .map_err(|e| Error::NetworkInfoError(e.to_string()))?;
+            TxClient::new(client, peer.url.clone())
+                .map(|client| Arc::new(client) as Arc<dyn Gateway>)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    gateways.push(fallback);
+    Ok((GatewayPool::new(gateways), peers))
+}
+
+fn client_builder(config: &DiscoveryConfig) -> reqwest::ClientBuilder {
+    let builder = config.dns.apply(reqwest::Client::builder());
+    match &config.trust {
+        Some(trust) => trust.apply(builder),
+        None => builder,
+    }
+}
+
+/// Fetches `/info` from `url`, returning `None` when it is unreachable or malformed.
+async fn probe(client: &reqwest::Client, url: &url::Url) -> Option<Peer> {
+    let started = Instant::now();
+    let res = client.get(url.join("info").ok()?).send().await.ok()?;
+    if res.status() != StatusCode::OK {
+        return None;
+    }
+    let info: Value = res.json().await.ok()?;
+    let height = match &info["height"] {
+        Value::Number(height) => height.as_u64()?,
+        Value::String(height) => height.parse().ok()?,
+        _ => return None,
+    };
+    Some(Peer {
+        url: url.clone(),
+        height,
+        latency: started.elapsed(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use httpmock::{Method::GET, MockServer};
+    use serde_json::json;
+
+    use super::{discover_gateways, discover_peers, DiscoveryConfig};
+    use crate::gateway::{Gateway, MockGateway, TrustPolicy};
+
+    async fn node(height: u64) -> MockServer {
+        let server = MockServer::start_async().await;
+        server.mock(|when, then| {
+            when.method(GET).path("/info");
+            then.status(200).json_body(json!({ "height": height }));
+        });
+        server
+    }
+
+    #[tokio::test]
+    async fn test_discovers_healthy_peers() {
+        let synced = node(1000).await;
+        let lagging = node(900).await;
+        let seed = node(1002).await;
+        seed.mock(|when, then| {
+            when.method(GET).path("/peers");
+            then.status(200).json_body(json!([
+                synced.address().to_string(),
+                lagging.address().to_string(),
+                "127.0.0.1:1",
+            ]));
+        });
+
+        let (pool, peers) = discover_gateways(
+            &url::Url::parse(&seed.url("/")).unwrap(),
+            Arc::new(MockGateway::new()),
+            &DiscoveryConfig::default(),
+        )
+        .await
+        .unwrap();
+        synced.mock(|when, then| {
+            when.method(GET).path("/price/10");
+            then.status(503);
+        });
+
+        assert_eq!(peers.len(), 1);
+        assert_eq!(peers[0].height, 1000);
+        assert_eq!(peers[0].url.port(), Some(synced.address().port()));
+        // The discovered peer fails to price, so the pool falls back to the mock gateway.
+        assert_eq!(pool.get_price(10, None).await.unwrap(), 10);
+    }
+
+    #[tokio::test]
+    async fn test_skips_untrusted_peers() {
+        let trusted = node(1000).await;
+        let untrusted = node(1000).await;
+        let seed = node(1000).await;
+        seed.mock(|when, then| {
+            when.method(GET).path("/peers");
+            then.status(200).json_body(json!([
+                trusted.address().to_string(),
+                untrusted.address().to_string(),
+            ]));
+        });
+        let seed_url = url::Url::parse(&seed.url("/")).unwrap();
+        let trust = TrustPolicy::new()
+            .allow_host(&seed.address().to_string())
+            .allow_host(&trusted.address().to_string());
+
+        let peers = discover_peers(
+            &seed_url,
+            &DiscoveryConfig {
+                trust: Some(trust.clone()),
+                ..DiscoveryConfig::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(peers.len(), 1);
+        assert_eq!(peers[0].url.port(), Some(trusted.address().port()));
+
+        let untrusted_seed = DiscoveryConfig {
+            trust: Some(TrustPolicy::new().allow_host(&trusted.address().to_string())),
+            ..DiscoveryConfig::default()
+        };
+        assert!(discover_peers(&seed_url, &untrusted_seed).await.is_err());
+    }
+}
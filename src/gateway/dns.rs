@@ -0,0 +1,75 @@
+//! Fixed addresses for gateway hostnames, e.g. to reach one's own nodes behind internal DNS
+//! without OS-level configuration.
+
+use std::{
+    collections::BTreeMap,
+    net::{IpAddr, SocketAddr},
+};
+
+use reqwest::ClientBuilder;
+
+/// Hostnames resolved to fixed IP addresses instead of going through the system resolver.
+///
+/// Only the address is overridden: the port still comes from the URL, or the scheme's
+/// default. TLS certificates are verified against the hostname as usual.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DnsOverrides {
+    hosts: BTreeMap<String, IpAddr>,
+}
+
+impl DnsOverrides {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Resolves `host` to `ip`.
+    pub fn pin(mut self, host: &str, ip: IpAddr) -> Self {
+        self.hosts.insert(host.to_ascii_lowercase(), ip);
+        self
+    }
+
+    /// Address `host` is pinned to, if any.
+    pub fn ip(&self, host: &str) -> Option<IpAddr> {
+        self.hosts.get(&host.to_ascii_lowercase()).copied()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hosts.is_empty()
+    }
+
+    /// Applies the overrides to `builder`.
+    pub fn apply(&self, builder: ClientBuilder) -> ClientBuilder {
+        self.hosts.iter().fold(builder, |builder, (host, ip)| {
+            builder.resolve(host, SocketAddr::new(*ip, 0))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use httpmock::{Method::GET, MockServer};
+
+    use super::DnsOverrides;
+
+    #[tokio::test]
+    async fn test_resolves_pinned_hosts() {
+        let server = MockServer::start_async().await;
+        let info = server.mock(|when, then| {
+            when.method(GET).path("/info");
+            then.status(200).body("ok");
+        });
+        let dns = DnsOverrides::new().pin("Gateway.Internal", IpAddr::V4(Ipv4Addr::LOCALHOST));
+        assert_eq!(
+            dns.ip("gateway.internal"),
+            Some(IpAddr::V4(Ipv4Addr::LOCALHOST))
+        );
+
+        let client = dns.apply(reqwest::Client::builder()).build().unwrap();
+        let url = format!("http://gateway.internal:{}/info", server.address().port());
+        let body = client.get(url).send().await.unwrap().text().await.unwrap();
+        assert_eq!(body, "ok");
+        info.assert();
+    }
+}
@@ -0,0 +1,289 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use pretend::StatusCode;
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+
+use crate::{
+    crypto::base64::Base64,
+    error::Error,
+    transaction::Tx,
+    types::{ChunkData, ChunkRef, Tx as JsonTx, TxOffset, TxStatus},
+};
+
+use super::Gateway;
+
+/// [`Gateway`] that records the results of another gateway to a JSON fixture file, or replays
+/// a previously recorded file without any network access.
+///
+/// Fixtures are keyed by call and arguments, e.g. `get_tx/{id}` or `get_price/{size}/{target}`,
+/// and errors are recorded and replayed like any other result.
+pub struct FixtureGateway {
+    inner: Option<Arc<dyn Gateway>>,
+    fixtures: Mutex<BTreeMap<String, Value>>,
+}
+
+impl FixtureGateway {
+    /// Forwards every call to `inner` and records its result.
+    pub fn record(inner: Arc<dyn Gateway>) -> Self {
+        Self {
+            inner: Some(inner),
+            fixtures: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Serves results from `fixtures` only. Calls that were never recorded fail with
+    /// [`Error::FixtureError`].
+    pub fn replay(fixtures: BTreeMap<String, Value>) -> Self {
+        Self {
+            inner: None,
+            fixtures: Mutex::new(fixtures),
+        }
+    }
+
+    /// Replays the fixtures stored at `path`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let text = fs::read_to_string(path).map_err(|e| Error::FixtureError(e.to_string()))?;
+        let fixtures =
+            serde_json::from_str(&text).map_err(|e| Error::FixtureError(e.to_string()))?;
+        Ok(Self::replay(fixtures))
+    }
+
+    /// Writes every fixture recorded so far to `path` as pretty-printed JSON.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let text = serde_json::to_string_pretty(&self.fixtures())
+            .map_err(|e| Error::FixtureError(e.to_string()))?;
+        fs::write(path, text).map_err(|e| Error::FixtureError(e.to_string()))
+    }
+
+    pub fn fixtures(&self) -> BTreeMap<String, Value> {
+        self.fixtures.lock().unwrap().clone()
+    }
+
+    fn store<T: Fixture>(&self, key: String, res: Result<T, Error>) -> Result<T, Error> {
+        let value = match &res {
+            Ok(ok) => json!({ "ok": ok.to_fixture() }),
+            Err(err) => json!({ "err": err }),
+        };
+        self.fixtures.lock().unwrap().insert(key, value);
+        res
+    }
+
+    fn load_fixture<T: Fixture>(&self, key: &str) -> Result<T, Error> {
+        let mut value = self
+            .fixtures
+            .lock()
+            .unwrap()
+            .get(key)
+            .cloned()
+            .ok_or_else(|| Error::FixtureError(format!("no fixture recorded for {}", key)))?;
+        if let Some(err) = value.get_mut("err") {
+            return Err(from_value(err.take())?);
+        }
+        match value.get_mut("ok") {
+            Some(ok) => T::from_fixture(ok.take()),
+            None => Err(Error::FixtureError(format!("malformed fixture {}", key))),
+        }
+    }
+}
+
+macro_rules! forward {
+    ($self:ident, $key:expr, $call:ident($($arg:expr),*)) => {{
+        let key: String = $key;
+        match &$self.inner {
+            Some(inner) => $self.store(key, inner.$call($($arg),*).await),
+            None => $self.load_fixture(&key),
+        }
+    }};
+}
+
+#[async_trait]
+impl Gateway for FixtureGateway {
+    async fn post_tx(&self, signed_transaction: &Tx) -> Result<(Base64, u64), Error> {
+        forward!(
+            self,
+            format!("post_tx/{}", signed_transaction.id),
+            post_tx(signed_transaction)
+        )
+    }
+
+    async fn get_tx(&self, id: &Base64) -> Result<(StatusCode, Option<Tx>), Error> {
+        forward!(self, format!("get_tx/{}", id), get_tx(id))
+    }
+
+    async fn get_tx_status(&self, id: &Base64) -> Result<(StatusCode, Option<TxStatus>), Error> {
+        forward!(self, format!("get_tx_status/{}", id), get_tx_status(id))
+    }
+
+    async fn get_tx_data(&self, id: &Base64) -> Result<Vec<u8>, Error> {
+        forward!(self, format!("get_tx_data/{}", id), get_tx_data(id))
+    }
+
+    async fn get_tx_offset(&self, id: &Base64) -> Result<TxOffset, Error> {
+        forward!(self, format!("get_tx_offset/{}", id), get_tx_offset(id))
+    }
+
+    async fn get_chunk(&self, offset: u64) -> Result<ChunkData, Error> {
+        forward!(self, format!("get_chunk/{}", offset), get_chunk(offset))
+    }
+
+    async fn get_price(&self, data_size: u64, target: Option<&Base64>) -> Result<u64, Error> {
+        let key = format!(
+            "get_price/{}/{}",
+            data_size,
+            target.map(Base64::to_string).unwrap_or_default()
+        );
+        forward!(self, key, get_price(data_size, target))
+    }
+
+    async fn get_anchor(&self) -> Result<Base64, Error> {
+        forward!(self, "get_anchor".to_string(), get_anchor())
+    }
+
+    async fn post_chunk(&self, chunk: ChunkRef<'_>) -> Result<usize, Error> {
+        forward!(
+            self,
+            format!("post_chunk/{}/{}", chunk.data_root, chunk.offset),
+            post_chunk(chunk)
+        )
+    }
+}
+
+/// Conversion between gateway results and their JSON fixture representation.
+trait Fixture: Sized {
+    fn to_fixture(&self) -> Value;
+    fn from_fixture(value: Value) -> Result<Self, Error>;
+}
+
+fn from_value<T: DeserializeOwned>(value: Value) -> Result<T, Error> {
+    serde_json::from_value(value).map_err(|e| Error::FixtureError(e.to_string()))
+}
+
+macro_rules! serde_fixture {
+    ($($ty:ty),*) => {
+        $(impl Fixture for $ty {
+            fn to_fixture(&self) -> Value {
+                json!(self)
+            }
+
+            fn from_fixture(value: Value) -> Result<Self, Error> {
+                from_value(value)
+            }
+        })*
+    };
+}
+
+serde_fixture!(
+    u64,
+    usize,
+    Base64,
+    (Base64, u64),
+    TxStatus,
+    TxOffset,
+    ChunkData
+);
+
+impl Fixture for Vec<u8> {
+    fn to_fixture(&self) -> Value {
+        json!(Base64::from(self.clone()))
+    }
+
+    fn from_fixture(value: Value) -> Result<Self, Error> {
+        from_value::<Base64>(value).map(|data| data.0.to_vec())
+    }
+}
+
+/// Transactions are stored in the node's JSON format, as served by `/tx/{id}`.
+impl Fixture for Tx {
+    fn to_fixture(&self) -> Value {
+        json!(self)
+    }
+
+    fn from_fixture(value: Value) -> Result<Self, Error> {
+        from_value::<JsonTx>(value).and_then(Tx::try_from)
+    }
+}
+
+impl<T: Fixture> Fixture for (StatusCode, Option<T>) {
+    fn to_fixture(&self) -> Value {
+        json!({
+            "status": self.0.as_u16(),
+            "body": self.1.as_ref().map(T::to_fixture),
+        })
+    }
+
+    fn from_fixture(mut value: Value) -> Result<Self, Error> {
+        let status = from_value::<u16>(value["status"].take())?;
+        let status =
+            StatusCode::from_u16(status).map_err(|e| Error::FixtureError(e.to_string()))?;
+        let body = match value["body"].take() {
+            Value::Null => None,
+            body => Some(T::from_fixture(body)?),
+        };
+        Ok((status, body))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{str::FromStr, sync::Arc};
+
+    use super::FixtureGateway;
+    use crate::{
+        crypto::base64::Base64,
+        error::Error,
+        gateway::{Gateway, MockGateway},
+        signer::ArweaveSigner,
+        transaction::Tx,
+    };
+
+    #[tokio::test]
+    async fn test_record_and_replay() {
+        let signer = ArweaveSigner::default();
+        let tx = Tx::new(
+            signer.get_provider(),
+            Base64::empty(),
+            b"fixture".to_vec(),
+            0,
+            7,
+            Base64::from(vec![0; 32]),
+            vec![],
+            true,
+        )
+        .unwrap();
+        let tx = signer.sign_transaction(tx).unwrap();
+
+        let recorder = FixtureGateway::record(Arc::new(MockGateway::new()));
+        recorder.post_tx(&tx).await.unwrap();
+        let fetched = recorder.get_tx(&tx.id).await.unwrap();
+        let price = recorder.get_price(1024, None).await.unwrap();
+        let missing = Base64::from_str("bWlzc2luZw").unwrap();
+        assert!(recorder.get_tx(&missing).await.is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("fixture.json");
+        recorder.save(&path).unwrap();
+        let replayer = FixtureGateway::load(&path).unwrap();
+
+        assert_eq!(replayer.post_tx(&tx).await.unwrap(), (tx.id.clone(), 7));
+        let replayed = replayer.get_tx(&tx.id).await.unwrap();
+        assert_eq!(replayed.0, fetched.0);
+        assert_eq!(replayed.1.as_ref().unwrap().id, tx.id);
+        assert!(ArweaveSigner::verify_transaction(replayed.1.as_ref().unwrap()).is_ok());
+        assert_eq!(replayer.get_price(1024, None).await.unwrap(), price);
+        assert!(matches!(
+            replayer.get_tx(&missing).await,
+            Err(Error::TransactionInfoError(_))
+        ));
+        assert!(matches!(
+            replayer.get_anchor().await,
+            Err(Error::FixtureError(_))
+        ));
+    }
+}
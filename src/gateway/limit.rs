@@ -0,0 +1,195 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::{stream::BoxStream, StreamExt};
+use pretend::StatusCode;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::{
+    crypto::base64::Base64,
+    error::Error,
+    transaction::Tx,
+    types::{BlockInfo, ChunkData, ChunkRef, TxOffset, TxStatus},
+};
+
+use super::Gateway;
+
+/// [`Gateway`] that holds a permit of a shared [`Semaphore`] for the duration of every call,
+/// bounding the number of requests in flight. Streams hold their permit until dropped.
+pub struct LimitedGateway {
+    inner: Arc<dyn Gateway>,
+    permits: Arc<Semaphore>,
+}
+
+impl LimitedGateway {
+    pub fn new(inner: Arc<dyn Gateway>, permits: Arc<Semaphore>) -> Self {
+        Self { inner, permits }
+    }
+
+    async fn acquire(&self) -> Result<OwnedSemaphorePermit, Error> {
+        acquire(&self.permits).await
+    }
+}
+
+pub(crate) async fn acquire(permits: &Arc<Semaphore>) -> Result<OwnedSemaphorePermit, Error> {
+    permits
+        .clone()
+        .acquire_owned()
+        .await
+        .map_err(|e| Error::GatewayUnavailable(e.to_string()))
+}
+
+#[async_trait]
+impl Gateway for LimitedGateway {
+    async fn post_tx(&self, signed_transaction: &Tx) -> Result<(Base64, u64), Error> {
+        let _permit = self.acquire().await?;
+        self.inner.post_tx(signed_transaction).await
+    }
+
+    async fn get_tx(&self, id: &Base64) -> Result<(StatusCode, Option<Tx>), Error> {
+        let _permit = self.acquire().await?;
+        self.inner.get_tx(id).await
+    }
+
+    async fn get_tx_status(&self, id: &Base64) -> Result<(StatusCode, Option<TxStatus>), Error> {
+        let _permit = self.acquire().await?;
+        self.inner.get_tx_status(id).await
+    }
+
+    async fn get_tx_data(&self, id: &Base64) -> Result<Vec<u8>, Error> {
+        let _permit = self.acquire().await?;
+        self.inner.get_tx_data(id).await
+    }
+
+    fn stream_tx_data(&self, id: Base64) -> BoxStream<'_, Result<Bytes, Error>> {
+        async_stream::try_stream! {
+            let _permit = self.acquire().await?;
+            let mut data = self.inner.stream_tx_data(id);
+            while let Some(bytes) = data.next().await {
+                yield bytes?;
+            }
+        }
+        .boxed()
+    }
+
+    async fn get_price(&self, data_size: u64, target: Option<&Base64>) -> Result<u64, Error> {
+        let _permit = self.acquire().await?;
+        self.inner.get_price(data_size, target).await
+    }
+
+    async fn get_anchor(&self) -> Result<Base64, Error> {
+        let _permit = self.acquire().await?;
+        self.inner.get_anchor().await
+    }
+
+    async fn post_chunk(&self, chunk: ChunkRef<'_>) -> Result<usize, Error> {
+        let _permit = self.acquire().await?;
+        self.inner.post_chunk(chunk).await
+    }
+
+    async fn get_block(&self, height: u64) -> Result<BlockInfo, Error> {
+        let _permit = self.acquire().await?;
+        self.inner.get_block(height).await
+    }
+
+    async fn get_tx_offset(&self, id: &Base64) -> Result<TxOffset, Error> {
+        let _permit = self.acquire().await?;
+        self.inner.get_tx_offset(id).await
+    }
+
+    async fn get_chunk(&self, offset: u64) -> Result<ChunkData, Error> {
+        let _permit = self.acquire().await?;
+        self.inner.get_chunk(offset).await
+    }
+
+    async fn get_balance(&self, address: &Base64) -> Result<u128, Error> {
+        let _permit = self.acquire().await?;
+        self.inner.get_balance(address).await
+    }
+
+    async fn tx_exists(&self, id: &Base64) -> Result<bool, Error> {
+        let _permit = self.acquire().await?;
+        self.inner.tx_exists(id).await
+    }
+
+    async fn data_available(&self, id: &Base64) -> Result<Option<u64>, Error> {
+        let _permit = self.acquire().await?;
+        self.inner.data_available(id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use async_trait::async_trait;
+    use pretend::StatusCode;
+    use tokio::sync::Semaphore;
+
+    use super::LimitedGateway;
+    use crate::{
+        crypto::base64::Base64,
+        error::Error,
+        gateway::{Gateway, MockGateway},
+        transaction::Tx,
+        types::{ChunkRef, TxStatus},
+    };
+
+    /// Gateway recording the highest number of concurrent `get_anchor` calls.
+    #[derive(Default)]
+    struct SlowGateway {
+        mock: MockGateway,
+        current: AtomicUsize,
+        max: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl Gateway for SlowGateway {
+        async fn post_tx(&self, tx: &Tx) -> Result<(Base64, u64), Error> {
+            self.mock.post_tx(tx).await
+        }
+        async fn get_tx(&self, id: &Base64) -> Result<(StatusCode, Option<Tx>), Error> {
+            self.mock.get_tx(id).await
+        }
+        async fn get_tx_status(
+            &self,
+            id: &Base64,
+        ) -> Result<(StatusCode, Option<TxStatus>), Error> {
+            self.mock.get_tx_status(id).await
+        }
+        async fn get_tx_data(&self, id: &Base64) -> Result<Vec<u8>, Error> {
+            self.mock.get_tx_data(id).await
+        }
+        async fn get_price(&self, size: u64, target: Option<&Base64>) -> Result<u64, Error> {
+            self.mock.get_price(size, target).await
+        }
+        async fn get_anchor(&self) -> Result<Base64, Error> {
+            let current = self.current.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max.fetch_max(current, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            self.current.fetch_sub(1, Ordering::SeqCst);
+            self.mock.get_anchor().await
+        }
+        async fn post_chunk(&self, chunk: ChunkRef<'_>) -> Result<usize, Error> {
+            self.mock.post_chunk(chunk).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_bounds_requests_in_flight() {
+        let slow = Arc::new(SlowGateway::default());
+        let gateway = LimitedGateway::new(slow.clone(), Arc::new(Semaphore::new(3)));
+
+        let results = futures::future::join_all((0..10).map(|_| gateway.get_anchor())).await;
+
+        assert!(results.iter().all(Result::is_ok));
+        assert_eq!(slow.max.load(Ordering::SeqCst), 3);
+    }
+}
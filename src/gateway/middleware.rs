@@ -0,0 +1,164 @@
+//! Hooks run around every HTTP request of a [`crate::transaction::client::TxClient`], to
+//! inject auth, sign requests for private gateways or log traffic without forking it.
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use reqwest::{Client, Method, Request, RequestBuilder, Response};
+
+use crate::error::Error;
+
+/// A layer around outgoing requests.
+///
+/// Closures taking a `&mut Request` are middlewares that only act before the request.
+pub trait Middleware: Send + Sync {
+    /// Called before `request` is sent. It may add headers or sign it; an error aborts it.
+    fn on_request(&self, _request: &mut Request) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Called once the request completed, with its response or transport error and the time
+    /// it took.
+    fn on_response(
+        &self,
+        _method: &Method,
+        _url: &url::Url,
+        _result: Result<&Response, &reqwest::Error>,
+        _elapsed: Duration,
+    ) {
+    }
+}
+
+impl<F> Middleware for F
+where
+    F: Fn(&mut Request) -> Result<(), Error> + Send + Sync,
+{
+    fn on_request(&self, request: &mut Request) -> Result<(), Error> {
+        self(request)
+    }
+}
+
+/// Middlewares applied in order before a request, and in reverse order after it.
+#[derive(Clone, Default)]
+pub struct MiddlewareStack(Vec<Arc<dyn Middleware>>);
+
+impl MiddlewareStack {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, middleware: Arc<dyn Middleware>) {
+        self.0.push(middleware);
+    }
+
+    pub fn with(mut self, middleware: Arc<dyn Middleware>) -> Self {
+        self.push(middleware);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Builds `request`, runs it through the stack and sends it with `client`. Transport
+    /// errors are reported with `error`.
+    pub async fn send(
+        &self,
+        client: &Client,
+        request: RequestBuilder,
+        error: fn(String) -> Error,
+    ) -> Result<Response, Error> {
+        let mut request = request.build().map_err(|e| error(e.to_string()))?;
+        for middleware in &self.0 {
+            middleware.on_request(&mut request)?;
+        }
+        let (method, url) = (request.method().clone(), request.url().clone());
+        let started = Instant::now();
+        let res = client.execute(request).await;
+        for middleware in self.0.iter().rev() {
+            middleware.on_response(&method, &url, res.as_ref(), started.elapsed());
+        }
+        res.map_err(|e| error(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use httpmock::{Method::GET, MockServer};
+    use reqwest::{header::AUTHORIZATION, Method, Request, Response};
+
+    use super::{Middleware, MiddlewareStack};
+    use crate::error::Error;
+
+    #[derive(Default)]
+    struct Log(Mutex<Vec<String>>);
+
+    impl Middleware for Log {
+        fn on_response(
+            &self,
+            method: &Method,
+            url: &url::Url,
+            result: Result<&Response, &reqwest::Error>,
+            _elapsed: Duration,
+        ) {
+            let status = result.map(|res| res.status().as_u16()).unwrap_or_default();
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("{} {} {}", method, url.path(), status));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_runs_hooks_around_requests() {
+        let server = MockServer::start_async().await;
+        let info = server.mock(|when, then| {
+            when.method(GET)
+                .path("/info")
+                .header("Authorization", "Bearer secret");
+            then.status(200);
+        });
+        let log = Arc::new(Log::default());
+        let stack = MiddlewareStack::new()
+            .with(Arc::new(|request: &mut Request| {
+                request
+                    .headers_mut()
+                    .insert(AUTHORIZATION, "Bearer secret".parse().unwrap());
+                Ok(())
+            }))
+            .with(log.clone());
+
+        let client = reqwest::Client::new();
+        let res = stack
+            .send(
+                &client,
+                client.get(server.url("/info")),
+                Error::NetworkInfoError,
+            )
+            .await
+            .unwrap();
+        assert_eq!(res.status(), 200);
+        info.assert();
+        assert_eq!(*log.0.lock().unwrap(), vec!["GET /info 200".to_string()]);
+
+        let reject = MiddlewareStack::new().with(Arc::new(|_: &mut Request| {
+            Err(Error::NetworkInfoError("rejected".to_string()))
+        }));
+        assert!(reject
+            .send(
+                &client,
+                client.get(server.url("/info")),
+                Error::NetworkInfoError
+            )
+            .await
+            .is_err());
+        info.assert_hits(1);
+    }
+}
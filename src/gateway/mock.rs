@@ -0,0 +1,374 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Mutex,
+};
+
+use async_trait::async_trait;
+use pretend::StatusCode;
+
+use crate::{
+    crypto::{base64::Base64, hash::hash_all_sha256, merkle::Helpers},
+    error::Error,
+    transaction::Tx,
+    types::{BlockInfo, Chunk, ChunkData, ChunkRef, ProofOfAccess, TxOffset, TxStatus},
+};
+
+use super::Gateway;
+
+/// In-memory [`Gateway`] that accepts posted transactions and chunks and serves them back.
+/// Every posted transaction is reported as confirmed in block `height`, with one confirmation
+/// unless overridden by [`MockGateway::set_confirmations`]. The data of posted transactions
+/// is laid out in the weave in posting order, starting at offset 0, each transaction mined
+/// alone in a block spanning its data.
+pub struct MockGateway {
+    pub anchor: Base64,
+    /// Price charged per byte of data, in winstons.
+    pub price_per_byte: u64,
+    pub height: u128,
+    txs: Mutex<HashMap<String, Tx>>,
+    chunks: Mutex<HashMap<String, Vec<Chunk>>>,
+    confirmations: Mutex<HashMap<String, u64>>,
+    offsets: Mutex<HashMap<String, TxOffset>>,
+    blocks: Mutex<BTreeMap<u64, BlockInfo>>,
+}
+
+impl Default for MockGateway {
+    fn default() -> Self {
+        Self {
+            anchor: Base64::from(vec![0; 32]),
+            price_per_byte: 1,
+            height: 1,
+            txs: Mutex::new(HashMap::new()),
+            chunks: Mutex::new(HashMap::new()),
+            confirmations: Mutex::new(HashMap::new()),
+            offsets: Mutex::new(HashMap::new()),
+            blocks: Mutex::new(BTreeMap::new()),
+        }
+    }
+}
+
+impl MockGateway {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ids of all transactions posted so far.
+    pub fn posted_ids(&self) -> Vec<String> {
+        self.txs.lock().unwrap().keys().cloned().collect()
+    }
+
+    /// Number of chunks posted for `data_root`.
+    pub fn chunk_count(&self, data_root: &Base64) -> usize {
+        self.chunks
+            .lock()
+            .unwrap()
+            .get(&data_root.to_string())
+            .map_or(0, Vec::len)
+    }
+
+    /// Adds a synthetic block at `height`, mined at `timestamp`, holding `txs`.
+    pub fn add_block(&self, height: u64, timestamp: u64, txs: Vec<Base64>) {
+        let hash = Base64::from(height.to_be_bytes().to_vec());
+        let block = BlockInfo {
+            nonce: Base64::empty(),
+            previous_block: Base64::from(height.saturating_sub(1).to_be_bytes().to_vec()),
+            timestamp,
+            last_retarget: timestamp,
+            diff: "0".to_string(),
+            height,
+            hash: hash.clone(),
+            indep_hash: hash,
+            txs,
+            wallet_list: Base64::empty(),
+            reward_addr: Base64::empty(),
+            tags: vec![],
+            reward_pool: 0,
+            weave_size: 0,
+            block_size: 0,
+            reward: None,
+            cumulative_diff: None,
+            hash_list_merkle: None,
+            tx_root: Base64::empty(),
+            tx_tree: vec![],
+            poa: ProofOfAccess::default(),
+        };
+        self.set_block(block);
+    }
+
+    /// Serves `block` at its height, replacing any block there, e.g. to simulate a reorg.
+    pub fn set_block(&self, block: BlockInfo) {
+        self.blocks.lock().unwrap().insert(block.height, block);
+    }
+
+    /// Tx root of the block holding transaction `id` alone, whose `tx_path` is the data root
+    /// followed by the data size.
+    pub fn tx_root(&self, id: &Base64) -> Option<Base64> {
+        let txs = self.txs.lock().unwrap();
+        let tx = txs.get(&id.to_string())?;
+        let size = (tx.data_size as usize).to_note_vec();
+        Some(Base64::from(
+            hash_all_sha256(vec![&tx.data_root, &size]).to_vec(),
+        ))
+    }
+
+    /// Data of `tx`'s chunks, as `(relative end offset, data path, chunk)`, from its posted
+    /// chunks or the data it was posted with.
+    fn tx_chunks(&self, tx: &Tx) -> Vec<(usize, Base64, Base64)> {
+        if let Some(chunks) = self.chunks.lock().unwrap().get(&tx.data_root.to_string()) {
+            return chunks
+                .iter()
+                .map(|c| (c.offset, c.data_path.clone(), c.chunk.clone()))
+                .collect();
+        }
+        (0..tx.chunks.len())
+            .filter_map(|i| tx.get_chunk(i).ok())
+            .map(|c| {
+                (
+                    c.offset,
+                    Base64::from(c.data_path.to_vec()),
+                    Base64::from(c.chunk.to_vec()),
+                )
+            })
+            .collect()
+    }
+
+    /// Sets the confirmations reported for `id`. Zero reports the transaction as pending.
+    pub fn set_confirmations(&self, id: &Base64, confirmations: u64) {
+        self.confirmations
+            .lock()
+            .unwrap()
+            .insert(id.to_string(), confirmations);
+    }
+}
+
+#[async_trait]
+impl Gateway for MockGateway {
+    async fn post_tx(&self, signed_transaction: &Tx) -> Result<(Base64, u64), Error> {
+        if signed_transaction.id.is_empty() {
+            return Err(Error::UnsignedTransaction);
+        }
+        self.txs.lock().unwrap().insert(
+            signed_transaction.id.to_string(),
+            signed_transaction.clone(),
+        );
+        let mut offsets = self.offsets.lock().unwrap();
+        let size = signed_transaction.data_size;
+        if size > 0 && !offsets.contains_key(&signed_transaction.id.to_string()) {
+            let start = offsets.values().map(|o| o.offset + 1).max().unwrap_or(0);
+            offsets.insert(
+                signed_transaction.id.to_string(),
+                TxOffset {
+                    size,
+                    offset: start + size - 1,
+                },
+            );
+        }
+        Ok((signed_transaction.id.clone(), signed_transaction.reward))
+    }
+
+    async fn get_tx(&self, id: &Base64) -> Result<(StatusCode, Option<Tx>), Error> {
+        match self.txs.lock().unwrap().get(&id.to_string()) {
+            Some(tx) => Ok((StatusCode::OK, Some(tx.clone()))),
+            None => Err(Error::TransactionInfoError(
+                StatusCode::NOT_FOUND.to_string(),
+            )),
+        }
+    }
+
+    async fn get_tx_status(&self, id: &Base64) -> Result<(StatusCode, Option<TxStatus>), Error> {
+        if !self.txs.lock().unwrap().contains_key(&id.to_string()) {
+            return Err(Error::TransactionInfoError(
+                StatusCode::NOT_FOUND.to_string(),
+            ));
+        }
+        let confirmations = self
+            .confirmations
+            .lock()
+            .unwrap()
+            .get(&id.to_string())
+            .copied()
+            .unwrap_or(1);
+        if confirmations == 0 {
+            return Ok((StatusCode::ACCEPTED, None));
+        }
+        Ok((
+            StatusCode::OK,
+            Some(TxStatus {
+                block_height: self.height,
+                block_indep_hash: Base64::from(vec![0; 48]),
+                number_of_confirmations: confirmations,
+            }),
+        ))
+    }
+
+    async fn get_tx_data(&self, id: &Base64) -> Result<Vec<u8>, Error> {
+        let tx = match self.get_tx(id).await? {
+            (_, Some(tx)) => tx,
+            (status, None) => return Err(Error::TransactionInfoError(status.to_string())),
+        };
+        if !tx.data.is_empty() || tx.data_size == 0 {
+            return Ok(tx.data.0.to_vec());
+        }
+
+        let mut chunks = self
+            .chunks
+            .lock()
+            .unwrap()
+            .get(&tx.data_root.to_string())
+            .cloned()
+            .unwrap_or_default();
+        chunks.sort_by_key(|c| c.offset);
+        let data: Vec<u8> = chunks.into_iter().flat_map(|c| c.chunk.0).collect();
+        if data.len() as u64 != tx.data_size {
+            return Err(Error::TransactionInfoError(
+                StatusCode::NOT_FOUND.to_string(),
+            ));
+        }
+        Ok(data)
+    }
+
+    async fn get_price(&self, data_size: u64, _target: Option<&Base64>) -> Result<u64, Error> {
+        Ok(data_size * self.price_per_byte)
+    }
+
+    async fn get_block(&self, height: u64) -> Result<BlockInfo, Error> {
+        self.blocks
+            .lock()
+            .unwrap()
+            .get(&height)
+            .cloned()
+            .ok_or_else(|| Error::NetworkInfoError(StatusCode::NOT_FOUND.to_string()))
+    }
+
+    async fn get_anchor(&self) -> Result<Base64, Error> {
+        Ok(self.anchor.clone())
+    }
+
+    async fn post_chunk(&self, chunk: ChunkRef<'_>) -> Result<usize, Error> {
+        let mut chunks = self.chunks.lock().unwrap();
+        let chunks = chunks.entry(chunk.data_root.to_string()).or_default();
+        if !chunks.iter().any(|c| c.offset == chunk.offset) {
+            chunks.push(chunk.to_chunk());
+        }
+        Ok(chunk.offset)
+    }
+
+    async fn get_tx_offset(&self, id: &Base64) -> Result<TxOffset, Error> {
+        self.offsets
+            .lock()
+            .unwrap()
+            .get(&id.to_string())
+            .copied()
+            .ok_or_else(|| Error::TransactionInfoError(StatusCode::NOT_FOUND.to_string()))
+    }
+
+    async fn get_chunk(&self, offset: u64) -> Result<ChunkData, Error> {
+        let not_found = || Error::TransactionInfoError(StatusCode::NOT_FOUND.to_string());
+        let (id, tx_offset) = self
+            .offsets
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|(_, o)| o.start().is_ok_and(|start| start <= offset) && offset <= o.offset)
+            .map(|(id, o)| (id.clone(), *o))
+            .ok_or_else(not_found)?;
+        let tx = self
+            .txs
+            .lock()
+            .unwrap()
+            .get(&id)
+            .cloned()
+            .ok_or_else(not_found)?;
+        let relative = (offset - tx_offset.start()?) as usize;
+        let (_, data_path, chunk) = self
+            .tx_chunks(&tx)
+            .into_iter()
+            .find(|(end, _, chunk)| end + 1 - chunk.len() <= relative && relative <= *end)
+            .ok_or_else(not_found)?;
+        let mut tx_path = tx.data_root.0.to_vec();
+        tx_path.extend((tx.data_size as usize).to_note_vec());
+        Ok(ChunkData {
+            chunk,
+            data_path,
+            tx_path: Base64::from(tx_path),
+        })
+    }
+
+    async fn tx_exists(&self, id: &Base64) -> Result<bool, Error> {
+        Ok(self.txs.lock().unwrap().contains_key(&id.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::PathBuf, str::FromStr, sync::Arc};
+
+    use super::MockGateway;
+    use crate::{crypto::base64::Base64, gateway::Gateway, transaction::Tx, Arweave};
+
+    #[tokio::test]
+    async fn test_round_trip() {
+        let gateway = Arc::new(MockGateway::new());
+        let mut arweave = Arweave::from_keypair_path(
+            PathBuf::from_str("res/test_wallet.json").unwrap(),
+            url::Url::parse("http://127.0.0.1:1").unwrap(),
+        )
+        .unwrap();
+        arweave.set_gateway(gateway.clone());
+
+        let data = b"hello permaweb".to_vec();
+        let fee = arweave
+            .get_fee(Base64::empty(), data.clone())
+            .await
+            .unwrap();
+        assert_eq!(fee, data.len() as u64);
+
+        let tx = arweave
+            .create_transaction(Base64::empty(), vec![], data.clone(), 0, fee, true)
+            .await
+            .unwrap();
+        assert_eq!(tx.last_tx, gateway.anchor);
+        let signed_tx = arweave.sign_transaction(tx).unwrap();
+        let (id, _) = arweave.post_transaction(&signed_tx).await.unwrap();
+        assert_eq!(gateway.posted_ids(), vec![id.clone()]);
+
+        let fetched = arweave
+            .get_tx_with_data(Base64::from_str(&id).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(fetched.data, data);
+        assert!(arweave.verify_transaction(&fetched.tx).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_reassembles_chunks() {
+        let gateway = MockGateway::new();
+        let arweave = Arweave::default();
+        let data = vec![7; 300 * 1024];
+        let tx = Tx::new(
+            arweave.signer.get_provider(),
+            Base64::empty(),
+            data.clone(),
+            0,
+            0,
+            gateway.anchor.clone(),
+            vec![],
+            false,
+        )
+        .unwrap();
+        let signed_tx = arweave.sign_transaction(tx).unwrap();
+        gateway
+            .post_tx(&signed_tx.clone_with_no_data().unwrap())
+            .await
+            .unwrap();
+        for i in 0..signed_tx.chunks.len() {
+            gateway
+                .post_chunk(signed_tx.get_chunk(i).unwrap())
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(gateway.chunk_count(&signed_tx.data_root), 2);
+        assert_eq!(gateway.get_tx_data(&signed_tx.id).await.unwrap(), data);
+    }
+}
@@ -0,0 +1,231 @@
+//! Abstraction over the node/gateway endpoints used to post and fetch transactions, so code
+//! built on [`crate::Arweave`] can be exercised without network access.
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::{stream::BoxStream, StreamExt};
+use pretend::StatusCode;
+
+use crate::{
+    crypto::base64::Base64,
+    error::Error,
+    transaction::Tx,
+    types::{BlockInfo, ChunkData, ChunkRef, TxOffset, TxStatus},
+};
+
+pub mod discovery;
+pub mod dns;
+pub mod fixture;
+pub mod limit;
+pub mod middleware;
+pub mod mock;
+pub mod pool;
+pub mod simulator;
+pub mod trust;
+
+pub use dns::DnsOverrides;
+pub use fixture::FixtureGateway;
+pub use limit::LimitedGateway;
+pub use middleware::{Middleware, MiddlewareStack};
+pub use mock::MockGateway;
+pub use pool::GatewayPool;
+pub use simulator::SimulatedGateway;
+pub use trust::TrustPolicy;
+
+/// Message of the errors gateways report their failures with.
+fn gateway_error_message(err: &Error) -> Option<&str> {
+    match err {
+        Error::TransactionInfoError(msg)
+        | Error::NetworkInfoError(msg)
+        | Error::PostChunkError(msg)
+        | Error::WalletError(msg)
+        | Error::GetPriceError(msg) => Some(msg),
+        _ => None,
+    }
+}
+
+/// Status of the response a gateway error reports, the errors of non-success responses
+/// starting with it, e.g. `404 Not Found`.
+pub(crate) fn error_status(err: &Error) -> Option<StatusCode> {
+    let msg = gateway_error_message(err)?;
+    match msg.as_bytes() {
+        [a, b, c] | [a, b, c, b' ', ..] => StatusCode::from_bytes(&[*a, *b, *c]).ok(),
+        _ => None,
+    }
+}
+
+fn is_not_found(status: StatusCode) -> bool {
+    status == StatusCode::NOT_FOUND || status == StatusCode::GONE
+}
+
+/// Only an unreachable or failing gateway is retried, on another one or later: transport
+/// errors, 5xx and 429. Other answers, such as a 404 for a transaction not propagated yet,
+/// are valid and returned as-is.
+pub(crate) fn is_gateway_failure(err: &Error) -> bool {
+    match err {
+        Error::RateLimited(_) | Error::GatewayUnavailable(_) => true,
+        err => match error_status(err) {
+            Some(status) => status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS,
+            None => is_transport_error(err),
+        },
+    }
+}
+
+/// Whether the gateway couldn't be reached or stopped answering: connection failures,
+/// timeouts and interrupted bodies, as reported by reqwest.
+pub(crate) fn is_transport_error(err: &Error) -> bool {
+    gateway_error_message(err).is_some_and(|msg| {
+        msg.starts_with("error sending request")
+            || msg.starts_with("request or response body error")
+    })
+}
+
+#[async_trait]
+pub trait Gateway: Send + Sync {
+    /// Posts a signed transaction, returning its id and reward.
+    async fn post_tx(&self, signed_transaction: &Tx) -> Result<(Base64, u64), Error>;
+
+    /// Fetches a transaction. `None` with [`StatusCode::ACCEPTED`] means it is still pending.
+    async fn get_tx(&self, id: &Base64) -> Result<(StatusCode, Option<Tx>), Error>;
+
+    async fn get_tx_status(&self, id: &Base64) -> Result<(StatusCode, Option<TxStatus>), Error>;
+
+    async fn get_tx_data(&self, id: &Base64) -> Result<Vec<u8>, Error>;
+
+    /// Streams the data of a transaction. Defaults to a single item holding all of it.
+    fn stream_tx_data(&self, id: Base64) -> BoxStream<'_, Result<Bytes, Error>> {
+        futures::stream::once(async move { self.get_tx_data(&id).await.map(Bytes::from) }).boxed()
+    }
+
+    /// Price in winstons of `data_size` bytes, including the new wallet fee when `target`
+    /// has never been seen by the network.
+    async fn get_price(&self, data_size: u64, target: Option<&Base64>) -> Result<u64, Error>;
+
+    /// Anchor to use as `last_tx` for new transactions.
+    async fn get_anchor(&self) -> Result<Base64, Error>;
+
+    /// Posts a single chunk, returning its offset.
+    async fn post_chunk(&self, chunk: ChunkRef<'_>) -> Result<usize, Error>;
+
+    /// Block at `height`. Defaults to failing, for gateways that don't serve blocks.
+    async fn get_block(&self, height: u64) -> Result<BlockInfo, Error> {
+        Err(Error::NetworkInfoError(format!(
+            "block {} is not served by this gateway",
+            height
+        )))
+    }
+
+    /// Position of the data of transaction `id` in the weave. Defaults to failing, for
+    /// gateways that don't serve offsets.
+    async fn get_tx_offset(&self, id: &Base64) -> Result<TxOffset, Error> {
+        Err(Error::TransactionInfoError(format!(
+            "offset of {} is not served by this gateway",
+            id
+        )))
+    }
+
+    /// Chunk containing absolute weave `offset`, with its proofs. Defaults to failing, for
+    /// gateways that don't serve chunks.
+    async fn get_chunk(&self, offset: u64) -> Result<ChunkData, Error> {
+        Err(Error::TransactionInfoError(format!(
+            "chunk at {} is not served by this gateway",
+            offset
+        )))
+    }
+
+    /// Balance of wallet `address` in winstons. Defaults to failing, for gateways that don't
+    /// serve balances.
+    async fn get_balance(&self, address: &Base64) -> Result<u128, Error> {
+        Err(Error::WalletError(format!(
+            "balance of {} is not served by this gateway",
+            address
+        )))
+    }
+
+    /// Whether transaction `id` is known, pending or mined. Defaults to fetching its header.
+    /// Only a 404 or 410 answer means unknown; other failures are returned, so callers don't
+    /// take a transient error for a missing transaction.
+    async fn tx_exists(&self, id: &Base64) -> Result<bool, Error> {
+        match self.get_tx(id).await {
+            Ok((status, _)) if is_not_found(status) => Ok(false),
+            Ok(_) => Ok(true),
+            Err(err) if error_status(&err).is_some_and(is_not_found) => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Size of the data of transaction `id` when it can be served, `None` if the gateway
+    /// answers 404 or 410. Defaults to downloading the data.
+    async fn data_available(&self, id: &Base64) -> Result<Option<u64>, Error> {
+        match self.get_tx_data(id).await {
+            Ok(data) => Ok(Some(data.len() as u64)),
+            Err(err) if error_status(&err).is_some_and(is_not_found) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+    use pretend::StatusCode;
+
+    use super::Gateway;
+    use crate::{
+        crypto::base64::Base64,
+        error::Error,
+        transaction::Tx,
+        types::{ChunkRef, TxStatus},
+    };
+
+    /// Gateway failing every request with the same message.
+    struct Failing(&'static str);
+
+    impl Failing {
+        fn fail<T>(&self) -> Result<T, Error> {
+            Err(Error::TransactionInfoError(self.0.to_string()))
+        }
+    }
+
+    #[async_trait]
+    impl Gateway for Failing {
+        async fn post_tx(&self, _: &Tx) -> Result<(Base64, u64), Error> {
+            self.fail()
+        }
+        async fn get_tx(&self, _: &Base64) -> Result<(StatusCode, Option<Tx>), Error> {
+            self.fail()
+        }
+        async fn get_tx_status(&self, _: &Base64) -> Result<(StatusCode, Option<TxStatus>), Error> {
+            self.fail()
+        }
+        async fn get_tx_data(&self, _: &Base64) -> Result<Vec<u8>, Error> {
+            self.fail()
+        }
+        async fn get_price(&self, _: u64, _: Option<&Base64>) -> Result<u64, Error> {
+            self.fail()
+        }
+        async fn get_anchor(&self) -> Result<Base64, Error> {
+            self.fail()
+        }
+        async fn post_chunk(&self, _: ChunkRef<'_>) -> Result<usize, Error> {
+            self.fail()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_only_not_found_means_missing() {
+        let id = Base64::from(vec![1; 32]);
+        for missing in ["404 Not Found", "410 Gone"] {
+            assert!(!Failing(missing).tx_exists(&id).await.unwrap());
+            assert_eq!(Failing(missing).data_available(&id).await.unwrap(), None);
+        }
+        for transient in [
+            "503 Service Unavailable",
+            "error sending request for url (http://gateway/): operation timed out",
+            "expected value at line 1 column 1",
+        ] {
+            assert!(Failing(transient).tx_exists(&id).await.is_err());
+            assert!(Failing(transient).data_available(&id).await.is_err());
+        }
+    }
+}
@@ -0,0 +1,310 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+use pretend::StatusCode;
+
+use crate::{
+    consts::{CIRCUIT_BREAKER_COOLDOWN, CIRCUIT_BREAKER_THRESHOLD},
+    crypto::base64::Base64,
+    error::Error,
+    transaction::Tx,
+    types::{BlockInfo, ChunkData, ChunkRef, TxOffset, TxStatus},
+};
+
+use super::{is_gateway_failure, Gateway};
+
+/// [`Gateway`] that spreads calls over several gateways, in order, failing over to the next
+/// one when a gateway is unreachable or fails.
+///
+/// Each gateway has a circuit breaker: after `failure_threshold` consecutive failures it is
+/// skipped for `cooldown`, after which it gets one trial call again.
+pub struct GatewayPool {
+    members: Vec<Member>,
+    failure_threshold: u32,
+    cooldown: Duration,
+}
+
+struct Member {
+    gateway: Arc<dyn Gateway>,
+    breaker: Mutex<Breaker>,
+}
+
+#[derive(Default)]
+struct Breaker {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+}
+
+impl GatewayPool {
+    pub fn new(gateways: Vec<Arc<dyn Gateway>>) -> Self {
+        Self {
+            members: gateways
+                .into_iter()
+                .map(|gateway| Member {
+                    gateway,
+                    breaker: Mutex::new(Breaker::default()),
+                })
+                .collect(),
+            failure_threshold: CIRCUIT_BREAKER_THRESHOLD,
+            cooldown: Duration::from_secs(CIRCUIT_BREAKER_COOLDOWN),
+        }
+    }
+
+    pub fn set_failure_threshold(&mut self, failure_threshold: u32) {
+        self.failure_threshold = failure_threshold.max(1);
+    }
+
+    pub fn set_cooldown(&mut self, cooldown: Duration) {
+        self.cooldown = cooldown;
+    }
+
+    /// Whether the gateway at `index` is currently accepting calls.
+    pub fn is_available(&self, index: usize) -> bool {
+        self.members
+            .get(index)
+            .is_some_and(|member| Self::available(member, Instant::now()))
+    }
+
+    fn available(member: &Member, now: Instant) -> bool {
+        match member.breaker.lock().unwrap().open_until {
+            Some(open_until) => now >= open_until,
+            None => true,
+        }
+    }
+
+    fn record(&self, member: &Member, res: &Result<impl Sized, Error>) {
+        let mut breaker = member.breaker.lock().unwrap();
+        match res {
+            Err(err) if is_gateway_failure(err) => {
+                breaker.consecutive_failures += 1;
+                if breaker.consecutive_failures >= self.failure_threshold {
+                    breaker.open_until = Some(Instant::now() + self.cooldown);
+                }
+            }
+            _ => *breaker = Breaker::default(),
+        }
+    }
+
+    async fn call<'a, T>(
+        &'a self,
+        f: impl Fn(&'a dyn Gateway) -> BoxFuture<'a, Result<T, Error>>,
+    ) -> Result<T, Error> {
+        let mut last_err = None;
+        for member in &self.members {
+            if !Self::available(member, Instant::now()) {
+                continue;
+            }
+            let res = f(member.gateway.as_ref()).await;
+            self.record(member, &res);
+            match res {
+                Err(err) if is_gateway_failure(&err) => last_err = Some(err),
+                res => return res,
+            }
+        }
+        Err(last_err.unwrap_or_else(|| {
+            Error::GatewayUnavailable("every gateway is cooling down".to_string())
+        }))
+    }
+}
+
+#[async_trait]
+impl Gateway for GatewayPool {
+    async fn post_tx(&self, signed_transaction: &Tx) -> Result<(Base64, u64), Error> {
+        self.call(|gateway| gateway.post_tx(signed_transaction))
+            .await
+    }
+
+    async fn get_tx(&self, id: &Base64) -> Result<(StatusCode, Option<Tx>), Error> {
+        self.call(|gateway| gateway.get_tx(id)).await
+    }
+
+    async fn get_tx_status(&self, id: &Base64) -> Result<(StatusCode, Option<TxStatus>), Error> {
+        self.call(|gateway| gateway.get_tx_status(id)).await
+    }
+
+    async fn get_tx_data(&self, id: &Base64) -> Result<Vec<u8>, Error> {
+        self.call(|gateway| gateway.get_tx_data(id)).await
+    }
+
+    async fn get_price(&self, data_size: u64, target: Option<&Base64>) -> Result<u64, Error> {
+        self.call(|gateway| gateway.get_price(data_size, target))
+            .await
+    }
+
+    async fn get_anchor(&self) -> Result<Base64, Error> {
+        self.call(|gateway| gateway.get_anchor()).await
+    }
+
+    async fn post_chunk(&self, chunk: ChunkRef<'_>) -> Result<usize, Error> {
+        self.call(|gateway| gateway.post_chunk(chunk)).await
+    }
+
+    async fn get_block(&self, height: u64) -> Result<BlockInfo, Error> {
+        self.call(|gateway| gateway.get_block(height)).await
+    }
+
+    async fn get_tx_offset(&self, id: &Base64) -> Result<TxOffset, Error> {
+        self.call(|gateway| gateway.get_tx_offset(id)).await
+    }
+
+    async fn get_chunk(&self, offset: u64) -> Result<ChunkData, Error> {
+        self.call(|gateway| gateway.get_chunk(offset)).await
+    }
+
+    async fn get_balance(&self, address: &Base64) -> Result<u128, Error> {
+        self.call(|gateway| gateway.get_balance(address)).await
+    }
+
+    async fn tx_exists(&self, id: &Base64) -> Result<bool, Error> {
+        self.call(|gateway| gateway.tx_exists(id)).await
+    }
+
+    async fn data_available(&self, id: &Base64) -> Result<Option<u64>, Error> {
+        self.call(|gateway| gateway.data_available(id)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use async_trait::async_trait;
+    use pretend::StatusCode;
+
+    use super::GatewayPool;
+    use crate::{
+        crypto::base64::Base64,
+        error::Error,
+        gateway::{Gateway, MockGateway},
+        transaction::Tx,
+        types::{ChunkRef, TxStatus},
+    };
+
+    /// Gateway whose every call fails with `error`, as if the endpoint were down by default.
+    struct DeadGateway {
+        calls: AtomicUsize,
+        error: fn() -> Error,
+    }
+
+    impl Default for DeadGateway {
+        fn default() -> Self {
+            Self {
+                calls: AtomicUsize::new(0),
+                error: || {
+                    Error::TransactionInfoError(
+                        "error sending request for url (http://dead/): connection refused"
+                            .to_string(),
+                    )
+                },
+            }
+        }
+    }
+
+    impl DeadGateway {
+        fn fail<T>(&self) -> Result<T, Error> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Err((self.error)())
+        }
+    }
+
+    #[async_trait]
+    impl Gateway for DeadGateway {
+        async fn post_tx(&self, _: &Tx) -> Result<(Base64, u64), Error> {
+            self.fail()
+        }
+        async fn get_tx(&self, _: &Base64) -> Result<(StatusCode, Option<Tx>), Error> {
+            self.fail()
+        }
+        async fn get_tx_status(&self, _: &Base64) -> Result<(StatusCode, Option<TxStatus>), Error> {
+            self.fail()
+        }
+        async fn get_tx_data(&self, _: &Base64) -> Result<Vec<u8>, Error> {
+            self.fail()
+        }
+        async fn get_price(&self, _: u64, _: Option<&Base64>) -> Result<u64, Error> {
+            self.fail()
+        }
+        async fn get_anchor(&self) -> Result<Base64, Error> {
+            self.fail()
+        }
+        async fn post_chunk(&self, _: ChunkRef<'_>) -> Result<usize, Error> {
+            self.fail()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_circuit_breaker() {
+        let dead = Arc::new(DeadGateway::default());
+        let mut pool = GatewayPool::new(vec![dead.clone(), Arc::new(MockGateway::new())]);
+        pool.set_failure_threshold(2);
+        pool.set_cooldown(Duration::from_millis(100));
+
+        for _ in 0..5 {
+            assert_eq!(pool.get_price(10, None).await.unwrap(), 10);
+        }
+        // Tripped after two failures, then skipped.
+        assert_eq!(dead.calls.load(Ordering::SeqCst), 2);
+        assert!(!pool.is_available(0));
+        assert!(pool.is_available(1));
+
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert!(pool.is_available(0));
+        pool.get_anchor().await.unwrap();
+        // One trial call after the cooldown, which re-trips the breaker.
+        assert_eq!(dead.calls.load(Ordering::SeqCst), 3);
+        assert!(!pool.is_available(0));
+    }
+
+    #[tokio::test]
+    async fn test_all_gateways_down() {
+        let mut pool = GatewayPool::new(vec![Arc::new(DeadGateway::default())]);
+        pool.set_failure_threshold(1);
+
+        assert!(matches!(
+            pool.get_anchor().await,
+            Err(Error::TransactionInfoError(_))
+        ));
+        assert!(matches!(
+            pool.get_anchor().await,
+            Err(Error::GatewayUnavailable(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_not_found_is_an_answer() {
+        let missing = Arc::new(DeadGateway {
+            calls: AtomicUsize::new(0),
+            error: || Error::TransactionInfoError("404 Not Found".to_string()),
+        });
+        let mut pool = GatewayPool::new(vec![missing.clone(), Arc::new(MockGateway::new())]);
+        pool.set_failure_threshold(2);
+
+        for _ in 0..5 {
+            assert!(matches!(
+                pool.get_tx(&Base64::from(vec![1; 32])).await,
+                Err(Error::TransactionInfoError(msg)) if msg == "404 Not Found"
+            ));
+        }
+        assert_eq!(missing.calls.load(Ordering::SeqCst), 5);
+        assert!(pool.is_available(0));
+
+        let busy = Arc::new(DeadGateway {
+            calls: AtomicUsize::new(0),
+            error: || Error::TransactionInfoError("503 Service Unavailable".to_string()),
+        });
+        let mut pool = GatewayPool::new(vec![busy, Arc::new(MockGateway::new())]);
+        pool.set_failure_threshold(1);
+        pool.get_anchor().await.unwrap();
+        assert!(!pool.is_available(0));
+    }
+}
@@ -0,0 +1,248 @@
+//! In-memory network for end-to-end tests of code built on [`crate::Arweave`]: posted
+//! transactions are checked and paid for like on a node, so uploads, transfers and downloads
+//! can be exercised without network access or AR.
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Mutex,
+};
+
+use async_trait::async_trait;
+use pretend::StatusCode;
+
+use crate::{
+    crypto::base64::Base64,
+    error::Error,
+    signer::ArweaveSigner,
+    transaction::Tx,
+    types::{BlockInfo, ChunkData, ChunkRef, TxOffset, TxStatus},
+};
+
+use super::{Gateway, MockGateway};
+
+/// [`MockGateway`] tracking wallet balances. Posted transactions must be validly signed,
+/// and their owner must afford their reward and quantity, which are debited, the quantity
+/// being credited to the target. Fund wallets with [`SimulatedGateway::fund`].
+#[derive(Default)]
+pub struct SimulatedGateway {
+    inner: MockGateway,
+    ledger: Mutex<Ledger>,
+}
+
+/// Balances, and the transactions already paid for, updated together so a transaction
+/// posted twice at once is paid once.
+#[derive(Default)]
+struct Ledger {
+    balances: HashMap<String, u128>,
+    paid: HashSet<String>,
+}
+
+impl SimulatedGateway {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Underlying mock, e.g. to set confirmations or add blocks.
+    pub fn mock(&self) -> &MockGateway {
+        &self.inner
+    }
+
+    /// Credits `winstons` to wallet `address`.
+    pub fn fund(&self, address: &Base64, winstons: u128) {
+        *self
+            .ledger
+            .lock()
+            .unwrap()
+            .balances
+            .entry(address.to_string())
+            .or_default() += winstons;
+    }
+
+    /// Debits the owner of `tx` for it, unless it is already paid for. The check and the
+    /// debit happen under one lock, or concurrent posts of a transaction would all pay.
+    fn pay(&self, tx: &Tx) -> Result<(), Error> {
+        let cost = tx.cost()?;
+        let quantity = tx.quantity.winstons();
+        let owner = tx.owner_address().to_string();
+        let mut ledger = self.ledger.lock().unwrap();
+        if ledger.paid.contains(&tx.id.to_string()) {
+            return Ok(());
+        }
+        let balance = ledger.balances.get(&owner).copied().unwrap_or_default();
+        if balance < cost {
+            return Err(Error::InsufficientFunds(format!(
+                "{} holds {} winstons, {} needs {}",
+                owner, balance, tx.id, cost
+            )));
+        }
+        ledger.balances.insert(owner, balance - cost);
+        if quantity > 0 {
+            *ledger.balances.entry(tx.target.to_string()).or_default() += quantity;
+        }
+        ledger.paid.insert(tx.id.to_string());
+        Ok(())
+    }
+
+    pub fn balance(&self, address: &Base64) -> u128 {
+        self.ledger
+            .lock()
+            .unwrap()
+            .balances
+            .get(&address.to_string())
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+#[async_trait]
+impl Gateway for SimulatedGateway {
+    async fn post_tx(&self, signed_transaction: &Tx) -> Result<(Base64, u64), Error> {
+        if self.inner.tx_exists(&signed_transaction.id).await? {
+            return self.inner.post_tx(signed_transaction).await;
+        }
+        ArweaveSigner::verify_transaction(signed_transaction)?;
+
+        self.pay(signed_transaction)?;
+        self.inner.post_tx(signed_transaction).await
+    }
+
+    async fn get_tx(&self, id: &Base64) -> Result<(StatusCode, Option<Tx>), Error> {
+        self.inner.get_tx(id).await
+    }
+
+    async fn get_tx_status(&self, id: &Base64) -> Result<(StatusCode, Option<TxStatus>), Error> {
+        self.inner.get_tx_status(id).await
+    }
+
+    async fn get_tx_data(&self, id: &Base64) -> Result<Vec<u8>, Error> {
+        self.inner.get_tx_data(id).await
+    }
+
+    async fn get_price(&self, data_size: u64, target: Option<&Base64>) -> Result<u64, Error> {
+        self.inner.get_price(data_size, target).await
+    }
+
+    async fn get_anchor(&self) -> Result<Base64, Error> {
+        self.inner.get_anchor().await
+    }
+
+    async fn post_chunk(&self, chunk: ChunkRef<'_>) -> Result<usize, Error> {
+        self.inner.post_chunk(chunk).await
+    }
+
+    async fn get_block(&self, height: u64) -> Result<BlockInfo, Error> {
+        self.inner.get_block(height).await
+    }
+
+    async fn get_tx_offset(&self, id: &Base64) -> Result<TxOffset, Error> {
+        self.inner.get_tx_offset(id).await
+    }
+
+    async fn get_chunk(&self, offset: u64) -> Result<ChunkData, Error> {
+        self.inner.get_chunk(offset).await
+    }
+
+    async fn get_balance(&self, address: &Base64) -> Result<u128, Error> {
+        Ok(self.balance(address))
+    }
+
+    async fn tx_exists(&self, id: &Base64) -> Result<bool, Error> {
+        self.inner.tx_exists(id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::PathBuf, str::FromStr, sync::Arc};
+
+    use pretend::Url;
+
+    use super::SimulatedGateway;
+    use crate::{crypto::base64::Base64, error::Error, gateway::Gateway, Arweave};
+
+    #[tokio::test]
+    async fn test_round_trip_with_balances() {
+        let gateway = Arc::new(SimulatedGateway::new());
+        let mut arweave = Arweave::from_keypair_path(
+            PathBuf::from("res/test_wallet.json"),
+            Url::from_str("http://127.0.0.1:1").unwrap(),
+        )
+        .unwrap();
+        arweave.set_gateway(gateway.clone());
+        let wallet = arweave.signer.wallet_address();
+        let friend = Base64::from(vec![5; 32]);
+        gateway.fund(&wallet, 10_000);
+
+        let data = b"simulated upload".to_vec();
+        let fee = arweave.get_fee(friend.clone(), data.clone()).await.unwrap();
+        let tx = arweave
+            .create_transaction(friend.clone(), vec![], data.clone(), 1_000, fee, false)
+            .await
+            .unwrap();
+        let tx = arweave.sign_transaction(tx).unwrap();
+        arweave.post_transaction(&tx).await.unwrap();
+
+        assert_eq!(
+            arweave.get_balance(&wallet).await.unwrap(),
+            9_000 - fee as u128
+        );
+        assert_eq!(arweave.get_balance(&friend).await.unwrap(), 1_000);
+        assert_eq!(
+            arweave.get_tx_with_data(tx.id.clone()).await.unwrap().data,
+            data
+        );
+        // Posting again doesn't pay twice.
+        arweave.post_transaction(&tx).await.unwrap();
+        assert_eq!(gateway.balance(&friend), 1_000);
+
+        let broke = arweave
+            .create_transaction(friend.clone(), vec![], vec![], 1_000_000, 1, false)
+            .await
+            .unwrap();
+        let broke = arweave.sign_transaction(broke).unwrap();
+        assert!(matches!(
+            arweave.post_transaction(&broke).await,
+            Err(Error::InsufficientFunds(_))
+        ));
+
+        let mut forged = arweave
+            .sign_transaction(
+                arweave
+                    .create_transaction(friend, vec![], vec![], 1, 1, false)
+                    .await
+                    .unwrap(),
+            )
+            .unwrap();
+        forged.quantity = 2u128.into();
+        assert!(arweave.post_transaction(&forged).await.is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_posts_pay_once() {
+        let gateway = Arc::new(SimulatedGateway::new());
+        let mut arweave = Arweave::from_keypair_path(
+            PathBuf::from("res/test_wallet.json"),
+            Url::from_str("http://127.0.0.1:1").unwrap(),
+        )
+        .unwrap();
+        arweave.set_gateway(gateway.clone());
+        let wallet = arweave.signer.wallet_address();
+        gateway.fund(&wallet, 10_000);
+
+        let tx = arweave
+            .create_transaction(Base64::from(vec![5; 32]), vec![], vec![], 100, 1, false)
+            .await
+            .unwrap();
+        let tx = Arc::new(arweave.sign_transaction(tx).unwrap());
+        let posts: Vec<_> = (0..16)
+            .map(|_| {
+                let (gateway, tx) = (gateway.clone(), tx.clone());
+                tokio::spawn(async move { gateway.post_tx(&tx).await })
+            })
+            .collect();
+        for post in posts {
+            post.await.unwrap().unwrap();
+        }
+        assert_eq!(gateway.balance(&wallet), 10_000 - 101);
+    }
+}
@@ -0,0 +1,189 @@
+//! Restricts the nodes a client talks to, so deployments can guarantee data only flows to
+//! approved infrastructure.
+
+use std::collections::BTreeSet;
+
+use reqwest::{redirect, Certificate, Client, ClientBuilder};
+
+use crate::error::Error;
+
+/// Redirects followed per request, as reqwest does by default.
+const MAX_REDIRECTS: usize = 10;
+
+/// Allowlist of node hosts and, optionally, the certificates their TLS connections must
+/// chain to.
+///
+/// Hosts are matched either by name (`arweave.net`), allowing any port, or by name and port
+/// (`10.0.0.5:1984`). Once a certificate is pinned, the system roots are no longer trusted
+/// and plain HTTP is refused.
+#[derive(Debug, Clone, Default)]
+pub struct TrustPolicy {
+    hosts: BTreeSet<String>,
+    pinned: Vec<Certificate>,
+}
+
+impl TrustPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn allow_host(mut self, host: &str) -> Self {
+        self.hosts.insert(host.to_ascii_lowercase());
+        self
+    }
+
+    /// Pins a PEM encoded certificate.
+    pub fn pin_certificate_pem(mut self, pem: &[u8]) -> Result<Self, Error> {
+        let cert =
+            Certificate::from_pem(pem).map_err(|e| Error::InvalidCertificate(e.to_string()))?;
+        self.pinned.push(cert);
+        Ok(self)
+    }
+
+    /// Pins a DER encoded certificate.
+    pub fn pin_certificate_der(mut self, der: &[u8]) -> Result<Self, Error> {
+        let cert =
+            Certificate::from_der(der).map_err(|e| Error::InvalidCertificate(e.to_string()))?;
+        self.pinned.push(cert);
+        Ok(self)
+    }
+
+    pub fn is_pinned(&self) -> bool {
+        !self.pinned.is_empty()
+    }
+
+    pub fn is_allowed(&self, url: &url::Url) -> bool {
+        let host = match url.host_str() {
+            Some(host) => host.to_ascii_lowercase(),
+            None => return false,
+        };
+        if self.is_pinned() && url.scheme() != "https" {
+            return false;
+        }
+        self.hosts.contains(&host)
+            || url
+                .port_or_known_default()
+                .is_some_and(|port| self.hosts.contains(&format!("{}:{}", host, port)))
+    }
+
+    /// Fails with [`Error::UntrustedHost`] unless `url` is allowed.
+    pub fn check(&self, url: &url::Url) -> Result<(), Error> {
+        if self.is_allowed(url) {
+            Ok(())
+        } else {
+            Err(Error::UntrustedHost(url.to_string()))
+        }
+    }
+
+    /// Applies the pinned certificates to `builder`, and refuses redirects to hosts that are
+    /// not allowed.
+    pub fn apply(&self, builder: ClientBuilder) -> ClientBuilder {
+        let policy = self.clone();
+        let builder = builder.redirect(redirect::Policy::custom(move |attempt| {
+            if attempt.previous().len() > MAX_REDIRECTS {
+                attempt.error("too many redirects")
+            } else if policy.is_allowed(attempt.url()) {
+                attempt.follow()
+            } else {
+                let url = attempt.url().to_string();
+                attempt.error(Error::UntrustedHost(url))
+            }
+        }));
+        if !self.is_pinned() {
+            return builder;
+        }
+        self.pinned
+            .iter()
+            .fold(builder, |builder, cert| {
+                builder.add_root_certificate(cert.clone())
+            })
+            .tls_built_in_root_certs(false)
+            .https_only(true)
+    }
+
+    /// HTTP client honouring the pinned certificates.
+    pub fn client(&self) -> Result<Client, Error> {
+        self.apply(Client::builder())
+            .build()
+            .map_err(|e| Error::InvalidCertificate(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use httpmock::{Method::GET, MockServer};
+
+    use super::TrustPolicy;
+    use crate::error::Error;
+
+    const CERT: &[u8] = include_bytes!("../../res/test_cert.pem");
+
+    fn url(url: &str) -> url::Url {
+        url::Url::parse(url).unwrap()
+    }
+
+    #[test]
+    fn test_allowlist() {
+        let policy = TrustPolicy::new()
+            .allow_host("Arweave.net")
+            .allow_host("10.0.0.5:1984");
+
+        assert!(policy.is_allowed(&url("https://arweave.net/")));
+        assert!(policy.is_allowed(&url("http://arweave.net:1984/")));
+        assert!(policy.is_allowed(&url("http://10.0.0.5:1984/info")));
+        assert!(!policy.is_allowed(&url("http://10.0.0.5:1985/")));
+        assert!(!policy.is_allowed(&url("https://arweave.dev/")));
+        assert!(matches!(
+            policy.check(&url("https://evil.arweave.net/")),
+            Err(Error::UntrustedHost(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_redirects() {
+        let trusted = MockServer::start_async().await;
+        let untrusted = MockServer::start_async().await;
+        trusted.mock(|when, then| {
+            when.method(GET).path("/moved");
+            then.status(301).header("location", trusted.url("/info"));
+        });
+        trusted.mock(|when, then| {
+            when.method(GET).path("/leak");
+            then.status(302).header("location", untrusted.url("/info"));
+        });
+        trusted.mock(|when, then| {
+            when.method(GET).path("/info");
+            then.status(200).body("ok");
+        });
+        let leaked = untrusted.mock(|when, then| {
+            when.method(GET).path("/info");
+            then.status(200);
+        });
+
+        let client = TrustPolicy::new()
+            .allow_host(&trusted.address().to_string())
+            .client()
+            .unwrap();
+        let res = client.get(trusted.url("/moved")).send().await.unwrap();
+        assert_eq!(res.text().await.unwrap(), "ok");
+        let err = client.get(trusted.url("/leak")).send().await.unwrap_err();
+        assert!(err.is_redirect(), "{}", err);
+        leaked.assert_hits(0);
+    }
+
+    #[test]
+    fn test_pinning() {
+        let policy = TrustPolicy::new()
+            .allow_host("arweave.net")
+            .pin_certificate_pem(CERT)
+            .unwrap();
+
+        assert!(policy.is_allowed(&url("https://arweave.net/")));
+        assert!(!policy.is_allowed(&url("http://arweave.net/")));
+        assert!(policy.client().is_ok());
+        assert!(matches!(
+            TrustPolicy::new().pin_certificate_pem(b"not a certificate"),
+            Err(Error::InvalidCertificate(_))
+        ));
+    }
+}
@@ -0,0 +1,585 @@
+//! Query builder and client for the gateway's `/graphql` endpoint.
+
+use std::collections::HashSet;
+
+use reqwest::header::{ACCEPT, CONTENT_TYPE};
+use serde::{de, Deserialize, Deserializer};
+use serde_aux::prelude::*;
+use serde_json::{json, Value};
+
+use crate::{
+    consts::GRAPHQL_MAX_FILTER_VALUES, currency::Currency, error::Error, gateway::MiddlewareStack,
+    transaction::tags::Tag,
+};
+
+/// Fields requested for every transaction node.
+const TRANSACTION_FIELDS: &str = "id anchor signature recipient \
+owner { address key } fee { winston ar } quantity { winston ar } \
+data { size type } tags { name value } block { id timestamp height previous } \
+bundledIn { id }";
+
+/// Builder for a `transactions` query. Every filter left unset is omitted from the query.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct TransactionQuery {
+    pub ids: Vec<String>,
+    pub owners: Vec<String>,
+    pub recipients: Vec<String>,
+    pub tags: Vec<(String, Vec<String>)>,
+    pub block_min: Option<u64>,
+    pub block_max: Option<u64>,
+    pub first: Option<u32>,
+    pub after: Option<String>,
+}
+
+impl TransactionQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn ids(mut self, ids: Vec<String>) -> Self {
+        self.ids = ids;
+        self
+    }
+
+    pub fn owners(mut self, owners: Vec<String>) -> Self {
+        self.owners = owners;
+        self
+    }
+
+    pub fn recipients(mut self, recipients: Vec<String>) -> Self {
+        self.recipients = recipients;
+        self
+    }
+
+    /// Matches transactions having tag `name` with any of `values`.
+    pub fn tag(mut self, name: &str, values: &[&str]) -> Self {
+        self.tags.push((
+            name.to_string(),
+            values.iter().map(|v| v.to_string()).collect(),
+        ));
+        self
+    }
+
+    pub fn block_min(mut self, height: u64) -> Self {
+        self.block_min = Some(height);
+        self
+    }
+
+    pub fn block_max(mut self, height: u64) -> Self {
+        self.block_max = Some(height);
+        self
+    }
+
+    pub fn first(mut self, first: u32) -> Self {
+        self.first = Some(first);
+        self
+    }
+
+    /// Cursor of the last edge of the previous page.
+    pub fn after(mut self, cursor: &str) -> Self {
+        self.after = Some(cursor.to_string());
+        self
+    }
+
+    /// Splits the query into queries listing at most `max` values per filter, whose results
+    /// together are those of the query. Filters are ORed within and ANDed across, so a
+    /// query with two oversized filters is split into every combination of their chunks.
+    pub fn split(&self, max: usize) -> Vec<TransactionQuery> {
+        let max = max.max(1);
+        let mut queries = vec![self.clone()];
+        for filter in 0..3 + self.tags.len() {
+            queries = queries
+                .into_iter()
+                .flat_map(|mut query| {
+                    let values = std::mem::take(query.filter_mut(filter));
+                    if values.len() <= max {
+                        *query.filter_mut(filter) = values;
+                        return vec![query];
+                    }
+                    values
+                        .chunks(max)
+                        .map(|chunk| {
+                            let mut query = query.clone();
+                            *query.filter_mut(filter) = chunk.to_vec();
+                            query
+                        })
+                        .collect()
+                })
+                .collect();
+        }
+        queries
+    }
+
+    fn filter_mut(&mut self, filter: usize) -> &mut Vec<String> {
+        match filter {
+            0 => &mut self.ids,
+            1 => &mut self.owners,
+            2 => &mut self.recipients,
+            tag => &mut self.tags[tag - 3].1,
+        }
+    }
+
+    /// Renders the GraphQL query string.
+    pub fn to_query(&self) -> String {
+        let mut args = Vec::new();
+        if !self.ids.is_empty() {
+            args.push(format!("ids: {}", string_list(&self.ids)));
+        }
+        if !self.owners.is_empty() {
+            args.push(format!("owners: {}", string_list(&self.owners)));
+        }
+        if !self.recipients.is_empty() {
+            args.push(format!("recipients: {}", string_list(&self.recipients)));
+        }
+        if !self.tags.is_empty() {
+            let tags: Vec<String> = self
+                .tags
+                .iter()
+                .map(|(name, values)| {
+                    format!(
+                        "{{ name: \"{}\", values: {} }}",
+                        escape(name),
+                        string_list(values)
+                    )
+                })
+                .collect();
+            args.push(format!("tags: [{}]", tags.join(", ")));
+        }
+        if self.block_min.is_some() || self.block_max.is_some() {
+            let mut range = Vec::new();
+            if let Some(min) = self.block_min {
+                range.push(format!("min: {}", min));
+            }
+            if let Some(max) = self.block_max {
+                range.push(format!("max: {}", max));
+            }
+            args.push(format!("block: {{ {} }}", range.join(", ")));
+        }
+        if let Some(first) = self.first {
+            args.push(format!("first: {}", first));
+        }
+        if let Some(after) = &self.after {
+            args.push(format!("after: \"{}\"", escape(after)));
+        }
+
+        let args = if args.is_empty() {
+            String::new()
+        } else {
+            format!("({})", args.join(", "))
+        };
+        format!(
+            "query {{ transactions{} {{ pageInfo {{ hasNextPage }} edges {{ cursor node {{ {} }} }} }} }}",
+            args, TRANSACTION_FIELDS
+        )
+    }
+}
+
+/// Escapes `value` for use inside a double-quoted GraphQL string literal.
+pub fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn string_list(values: &[String]) -> String {
+    let quoted: Vec<String> = values
+        .iter()
+        .map(|v| format!("\"{}\"", escape(v)))
+        .collect();
+    format!("[{}]", quoted.join(", "))
+}
+
+/// A page of results of a `transactions` query.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionsPage {
+    pub page_info: PageInfo,
+    pub edges: Vec<TxEdge>,
+}
+
+impl TransactionsPage {
+    /// Cursor to pass to [`TransactionQuery::after`] for the next page, if there is one.
+    pub fn next_cursor(&self) -> Option<&str> {
+        match self.edges.last() {
+            Some(edge) if self.page_info.has_next_page => Some(&edge.cursor),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PageInfo {
+    pub has_next_page: bool,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct TxEdge {
+    pub cursor: String,
+    pub node: TxNode,
+}
+
+/// A transaction, or a data item of a bundle, as indexed by the gateway.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TxNode {
+    pub id: String,
+    pub anchor: String,
+    pub signature: String,
+    pub recipient: String,
+    pub owner: Owner,
+    pub fee: Amount,
+    pub quantity: Amount,
+    pub data: DataInfo,
+    /// Tags, decoded from base64url by the gateway.
+    pub tags: Vec<Tag<String>>,
+    /// `None` while the transaction is pending.
+    pub block: Option<Block>,
+    /// Bundle holding this data item, `None` for layer one transactions.
+    pub bundled_in: Option<BundleParent>,
+}
+
+impl TxNode {
+    /// Value of the first tag named `name`.
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|tag| tag.name == name)
+            .map(|tag| tag.value.as_str())
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Owner {
+    pub address: String,
+    /// Public modulus, base64url encoded.
+    pub key: String,
+}
+
+/// An amount as reported by the gateway: exact winstons and a display string in AR.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Amount {
+    #[serde(deserialize_with = "deserialize_winstons")]
+    pub winston: Currency,
+    pub ar: String,
+}
+
+/// Reward of a transaction.
+pub type Fee = Amount;
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct DataInfo {
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub size: u64,
+    /// Content type, from the `Content-Type` tag.
+    #[serde(rename = "type")]
+    pub content_type: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Block {
+    pub id: String,
+    pub timestamp: u64,
+    pub height: u64,
+    pub previous: String,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct BundleParent {
+    pub id: String,
+}
+
+fn deserialize_winstons<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Currency, D::Error> {
+    let winstons: u128 = deserialize_number_from_string(deserializer)?;
+    Currency::checked_from_winstons(winstons).map_err(de::Error::custom)
+}
+
+pub struct GraphqlClient {
+    client: reqwest::Client,
+    url: url::Url,
+    middleware: MiddlewareStack,
+    max_filter_values: usize,
+}
+
+impl GraphqlClient {
+    pub fn new(base_url: url::Url) -> Self {
+        Self::with_client(base_url, reqwest::Client::new())
+    }
+
+    pub fn with_client(base_url: url::Url, client: reqwest::Client) -> Self {
+        let url = base_url
+            .join("graphql")
+            .expect("Could not join base_url with /graphql");
+        Self {
+            client,
+            url,
+            middleware: MiddlewareStack::default(),
+            max_filter_values: GRAPHQL_MAX_FILTER_VALUES,
+        }
+    }
+
+    /// Values a filter may list before [`GraphqlClient::transactions_all`] splits the query.
+    pub fn with_max_filter_values(mut self, max: usize) -> Self {
+        self.max_filter_values = max;
+        self
+    }
+
+    /// Runs every query through `middleware`.
+    pub fn with_middleware(mut self, middleware: MiddlewareStack) -> Self {
+        self.middleware = middleware;
+        self
+    }
+
+    /// Runs a raw GraphQL query and returns the `data` object of the response.
+    pub async fn query_raw(&self, query: &str) -> Result<Value, Error> {
+        let request = self
+            .client
+            .post(self.url.clone())
+            .json(&json!({ "query": query }))
+            .header(&ACCEPT, "application/json")
+            .header(&CONTENT_TYPE, "application/json");
+        let res = self
+            .middleware
+            .send(&self.client, request, Error::GraphqlError)
+            .await?;
+
+        if !res.status().is_success() {
+            return Err(Error::GraphqlError(res.status().to_string()));
+        }
+
+        let mut body = res
+            .json::<Value>()
+            .await
+            .map_err(|e| Error::GraphqlError(e.to_string()))?;
+        if let Some(errors) = body.get("errors") {
+            return Err(Error::GraphqlError(errors.to_string()));
+        }
+        Ok(body["data"].take())
+    }
+
+    pub async fn transactions(&self, query: &TransactionQuery) -> Result<Value, Error> {
+        self.query_raw(&query.to_query()).await
+    }
+
+    /// Like [`GraphqlClient::transactions`], parsed into typed models.
+    pub async fn transactions_page(
+        &self,
+        query: &TransactionQuery,
+    ) -> Result<TransactionsPage, Error> {
+        let mut data = self.transactions(query).await?;
+        serde_json::from_value(data["transactions"].take())
+            .map_err(|e| Error::GraphqlError(e.to_string()))
+    }
+
+    /// Fetches every page of `query`. Filters listing too many values for one query are
+    /// split across several, whose results are merged without duplicates.
+    pub async fn transactions_all(&self, query: &TransactionQuery) -> Result<Vec<TxNode>, Error> {
+        let mut seen = HashSet::new();
+        let mut nodes = Vec::new();
+        for mut query in query.split(self.max_filter_values) {
+            loop {
+                let page = self.transactions_page(&query).await?;
+                let cursor = page.next_cursor().map(str::to_string);
+                for edge in page.edges {
+                    if seen.insert(edge.node.id.clone()) {
+                        nodes.push(edge.node);
+                    }
+                }
+                match cursor {
+                    Some(cursor) => query = query.after(&cursor),
+                    None => break,
+                }
+            }
+        }
+        Ok(nodes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use httpmock::{Method::POST, MockServer};
+    use pretend::Url;
+    use serde_json::json;
+    use tokio_test::block_on;
+
+    use super::{escape, GraphqlClient, TransactionQuery, TransactionsPage};
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("plain"), "plain");
+        assert_eq!(escape("a\"b\\c"), "a\\\"b\\\\c");
+        assert_eq!(escape("line\nbreak\u{1}"), "line\\nbreak\\u0001");
+    }
+
+    #[test]
+    fn test_query_arguments() {
+        let query = TransactionQuery::new()
+            .owners(vec!["owner".to_string()])
+            .recipients(vec!["recipient".to_string()])
+            .tag("App-Name", &["my \"app\""])
+            .block_min(10)
+            .block_max(20)
+            .first(5)
+            .after("cursor")
+            .to_query();
+
+        assert!(query.starts_with(
+            "query { transactions(owners: [\"owner\"], recipients: [\"recipient\"], \
+             tags: [{ name: \"App-Name\", values: [\"my \\\"app\\\"\"] }], \
+             block: { min: 10, max: 20 }, first: 5, after: \"cursor\") {"
+        ));
+        assert!(!TransactionQuery::new().to_query().contains("transactions("));
+    }
+
+    #[test]
+    fn test_transactions() {
+        let server = MockServer::start();
+        let mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/graphql")
+                .body_contains("owners: [\\\"owner\\\"]");
+            then.status(200)
+                .header("Content-Type", "application/json")
+                .json_body(json!({ "data": { "transactions": { "edges": [] } } }));
+        });
+
+        let client = GraphqlClient::new(Url::parse(&server.url("")).unwrap());
+        let query = TransactionQuery::new().owners(vec!["owner".to_string()]);
+        let data = block_on(client.transactions(&query)).unwrap();
+
+        mock.assert();
+        assert_eq!(data["transactions"]["edges"], json!([]));
+    }
+
+    fn node(id: &str) -> serde_json::Value {
+        json!({
+            "id": id,
+            "anchor": "",
+            "signature": "",
+            "recipient": "",
+            "owner": { "address": "addr", "key": "key" },
+            "fee": { "winston": "0", "ar": "0" },
+            "quantity": { "winston": "0", "ar": "0" },
+            "data": { "size": "0", "type": null },
+            "tags": [],
+            "block": null,
+            "bundledIn": null
+        })
+    }
+
+    #[test]
+    fn test_split() {
+        let owners: Vec<String> = (0..5).map(|i| format!("owner{}", i)).collect();
+        let query = TransactionQuery::new()
+            .owners(owners.clone())
+            .tag("App-Name", &["a", "b", "c"])
+            .first(10);
+
+        assert_eq!(query.split(5), vec![query.clone()]);
+        let queries = query.split(2);
+        assert_eq!(queries.len(), 6);
+        assert!(queries
+            .iter()
+            .all(|q| q.owners.len() <= 2 && q.tags[0].1.len() <= 2 && q.first == Some(10)));
+        let mut split_owners: Vec<String> = queries
+            .iter()
+            .filter(|q| q.tags[0].1[0] == "a")
+            .flat_map(|q| q.owners.clone())
+            .collect();
+        split_owners.sort();
+        assert_eq!(split_owners, owners);
+    }
+
+    #[test]
+    fn test_transactions_all() {
+        let server = MockServer::start();
+        let first = server.mock(|when, then| {
+            when.method(POST).path("/graphql").body_contains("owner0");
+            then.status(200)
+                .json_body(json!({ "data": { "transactions": {
+                "pageInfo": { "hasNextPage": false },
+                "edges": [{ "cursor": "c", "node": node("a") }]
+            } } }));
+        });
+        let second = server.mock(|when, then| {
+            when.method(POST).path("/graphql").body_contains("owner2");
+            then.status(200)
+                .json_body(json!({ "data": { "transactions": {
+                "pageInfo": { "hasNextPage": false },
+                "edges": [
+                    { "cursor": "c", "node": node("a") },
+                    { "cursor": "d", "node": node("b") }
+                ]
+            } } }));
+        });
+
+        let client =
+            GraphqlClient::new(Url::parse(&server.url("")).unwrap()).with_max_filter_values(2);
+        let owners = (0..3).map(|i| format!("owner{}", i)).collect();
+        let nodes =
+            block_on(client.transactions_all(&TransactionQuery::new().owners(owners))).unwrap();
+
+        first.assert();
+        second.assert();
+        let ids: Vec<&str> = nodes.iter().map(|node| node.id.as_str()).collect();
+        assert_eq!(ids, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_typed_page() {
+        let page: TransactionsPage = serde_json::from_value(json!({
+            "pageInfo": { "hasNextPage": true },
+            "edges": [{
+                "cursor": "c1",
+                "node": {
+                    "id": "tx",
+                    "anchor": "anchor",
+                    "signature": "sig",
+                    "recipient": "",
+                    "owner": { "address": "addr", "key": "key" },
+                    "fee": { "winston": "1500000000000", "ar": "1.500000000000" },
+                    "quantity": { "winston": "0", "ar": "0.000000000000" },
+                    "data": { "size": "42", "type": "text/plain" },
+                    "tags": [{ "name": "App-Name", "value": "my-app" }],
+                    "block": { "id": "b", "timestamp": 1700000000, "height": 1300000, "previous": "a" },
+                    "bundledIn": { "id": "bundle" }
+                }
+            }, {
+                "cursor": "c2",
+                "node": {
+                    "id": "pending",
+                    "anchor": "",
+                    "signature": "",
+                    "recipient": "",
+                    "owner": { "address": "addr", "key": "key" },
+                    "fee": { "winston": "0", "ar": "0" },
+                    "quantity": { "winston": "0", "ar": "0" },
+                    "data": { "size": "0", "type": null },
+                    "tags": [],
+                    "block": null,
+                    "bundledIn": null
+                }
+            }]
+        }))
+        .unwrap();
+
+        let node = &page.edges[0].node;
+        assert_eq!(node.fee.winston.winstons(), 1_500_000_000_000);
+        assert_eq!(node.data.size, 42);
+        assert_eq!(node.tag("App-Name"), Some("my-app"));
+        assert_eq!(node.block.as_ref().unwrap().height, 1_300_000);
+        assert_eq!(node.bundled_in.as_ref().unwrap().id, "bundle");
+        assert_eq!(page.edges[1].node.block, None);
+        assert_eq!(page.next_cursor(), Some("c2"));
+    }
+}
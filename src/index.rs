@@ -0,0 +1,355 @@
+//! Local SQLite index of transactions, filled by tailing the gateway's GraphQL endpoint.
+//!
+//! Enabled with the `index` feature.
+
+use std::{path::Path, sync::Mutex};
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde_json::Value;
+
+use crate::{error::Error, graphql::TransactionQuery, Arweave};
+
+/// Page size used while tailing the gateway.
+const SYNC_PAGE_SIZE: u32 = 100;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS txs (
+    id TEXT PRIMARY KEY,
+    owner TEXT NOT NULL,
+    recipient TEXT,
+    block_height INTEGER
+);
+CREATE TABLE IF NOT EXISTS tags (
+    tx_id TEXT NOT NULL REFERENCES txs(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    value TEXT NOT NULL,
+    PRIMARY KEY (tx_id, position)
+);
+CREATE INDEX IF NOT EXISTS txs_owner ON txs(owner);
+CREATE INDEX IF NOT EXISTS tags_name_value ON tags(name, value);
+";
+
+/// A transaction as stored in the index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexedTx {
+    pub id: String,
+    pub owner: String,
+    pub recipient: Option<String>,
+    pub block_height: Option<u64>,
+    pub tags: Vec<(String, String)>,
+}
+
+impl IndexedTx {
+    /// Builds a record from a GraphQL transaction `node`.
+    pub fn from_node(node: &Value) -> Result<Self, Error> {
+        let id = node["id"]
+            .as_str()
+            .ok_or_else(|| Error::IndexError("node without id".to_string()))?;
+        let owner = node["owner"]["address"]
+            .as_str()
+            .ok_or_else(|| Error::IndexError(format!("node {} without owner", id)))?;
+        let recipient = node["recipient"]
+            .as_str()
+            .filter(|r| !r.is_empty())
+            .map(str::to_string);
+        let tags = node["tags"]
+            .as_array()
+            .map(|tags| {
+                tags.iter()
+                    .filter_map(|t| Some((t["name"].as_str()?.into(), t["value"].as_str()?.into())))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(Self {
+            id: id.to_string(),
+            owner: owner.to_string(),
+            recipient,
+            block_height: node["block"]["height"].as_u64(),
+            tags,
+        })
+    }
+}
+
+/// SQLite-backed index of transactions, queryable by owner and tag.
+pub struct TxIndex {
+    conn: Mutex<Connection>,
+}
+
+impl TxIndex {
+    /// Opens or creates the index database at `path`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Self::init(Connection::open(path).map_err(sql_error)?)
+    }
+
+    pub fn open_in_memory() -> Result<Self, Error> {
+        Self::init(Connection::open_in_memory().map_err(sql_error)?)
+    }
+
+    fn init(conn: Connection) -> Result<Self, Error> {
+        conn.execute_batch("PRAGMA foreign_keys = ON;")
+            .and_then(|_| conn.execute_batch(SCHEMA))
+            .map_err(sql_error)?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    /// Inserts `tx`, replacing any previous record with the same id.
+    pub fn insert(&self, tx: &IndexedTx) -> Result<(), Error> {
+        self.insert_all(std::slice::from_ref(tx))
+    }
+
+    /// Inserts every record in a single transaction.
+    pub fn insert_all(&self, txs: &[IndexedTx]) -> Result<(), Error> {
+        let mut conn = self.conn.lock().unwrap();
+        let db = conn.transaction().map_err(sql_error)?;
+        for tx in txs {
+            db.execute(
+                "INSERT OR REPLACE INTO txs (id, owner, recipient, block_height) VALUES (?1, ?2, ?3, ?4)",
+                params![tx.id, tx.owner, tx.recipient, tx.block_height],
+            )
+            .map_err(sql_error)?;
+            db.execute("DELETE FROM tags WHERE tx_id = ?1", params![tx.id])
+                .map_err(sql_error)?;
+            for (position, (name, value)) in tx.tags.iter().enumerate() {
+                db.execute(
+                    "INSERT INTO tags (tx_id, position, name, value) VALUES (?1, ?2, ?3, ?4)",
+                    params![tx.id, position, name, value],
+                )
+                .map_err(sql_error)?;
+            }
+        }
+        db.commit().map_err(sql_error)
+    }
+
+    /// Indexes every edge of a `transactions` GraphQL response and returns how many were stored.
+    pub fn ingest(&self, data: &Value) -> Result<usize, Error> {
+        let txs = edges(data)
+            .iter()
+            .map(|edge| IndexedTx::from_node(&edge["node"]))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.insert_all(&txs)?;
+        Ok(txs.len())
+    }
+
+    pub fn get(&self, id: &str) -> Result<Option<IndexedTx>, Error> {
+        let conn = self.conn.lock().unwrap();
+        let row = conn
+            .query_row(
+                "SELECT id, owner, recipient, block_height FROM txs WHERE id = ?1",
+                params![id],
+                read_row,
+            )
+            .optional()
+            .map_err(sql_error)?;
+        match row {
+            Some(tx) => Ok(Some(with_tags(&conn, tx)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn by_owner(&self, owner: &str) -> Result<Vec<IndexedTx>, Error> {
+        self.select(
+            "SELECT id, owner, recipient, block_height FROM txs WHERE owner = ?1 \
+             ORDER BY block_height, id",
+            params![owner],
+        )
+    }
+
+    /// Returns the transactions carrying tag `name` with value `value`.
+    pub fn by_tag(&self, name: &str, value: &str) -> Result<Vec<IndexedTx>, Error> {
+        self.select(
+            "SELECT DISTINCT t.id, t.owner, t.recipient, t.block_height FROM txs t \
+             JOIN tags g ON g.tx_id = t.id WHERE g.name = ?1 AND g.value = ?2 \
+             ORDER BY t.block_height, t.id",
+            params![name, value],
+        )
+    }
+
+    /// Highest block height indexed so far.
+    pub fn last_height(&self) -> Result<Option<u64>, Error> {
+        self.conn
+            .lock()
+            .unwrap()
+            .query_row("SELECT MAX(block_height) FROM txs", [], |row| row.get(0))
+            .map_err(sql_error)
+    }
+
+    pub fn len(&self) -> Result<usize, Error> {
+        self.conn
+            .lock()
+            .unwrap()
+            .query_row("SELECT COUNT(*) FROM txs", [], |row| row.get(0))
+            .map_err(sql_error)
+    }
+
+    pub fn is_empty(&self) -> Result<bool, Error> {
+        Ok(self.len()? == 0)
+    }
+
+    /// Pages through `query` from the last indexed height onwards and indexes every result.
+    /// Transactions of the boundary block are fetched again, which is harmless since inserts
+    /// replace existing records. Returns the number of transactions ingested.
+    pub async fn sync(&self, arweave: &Arweave, query: TransactionQuery) -> Result<usize, Error> {
+        let mut query = query.first(SYNC_PAGE_SIZE);
+        if let Some(height) = self.last_height()? {
+            query = query.block_min(height);
+        }
+
+        let mut ingested = 0;
+        loop {
+            let data = arweave.query_transactions(&query).await?;
+            ingested += self.ingest(&data)?;
+
+            let has_next = data["transactions"]["pageInfo"]["hasNextPage"]
+                .as_bool()
+                .unwrap_or(false);
+            match edges(&data).last().and_then(|edge| edge["cursor"].as_str()) {
+                Some(cursor) if has_next => query = query.after(cursor),
+                _ => return Ok(ingested),
+            }
+        }
+    }
+
+    fn select<P: rusqlite::Params>(&self, sql: &str, params: P) -> Result<Vec<IndexedTx>, Error> {
+        let conn = self.conn.lock().unwrap();
+        let rows = {
+            let mut stmt = conn.prepare(sql).map_err(sql_error)?;
+            let rows = stmt
+                .query_map(params, read_row)
+                .map_err(sql_error)?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(sql_error)?;
+            rows
+        };
+        rows.into_iter().map(|tx| with_tags(&conn, tx)).collect()
+    }
+}
+
+fn edges(data: &Value) -> &[Value] {
+    data["transactions"]["edges"]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+}
+
+fn read_row(row: &rusqlite::Row) -> rusqlite::Result<IndexedTx> {
+    Ok(IndexedTx {
+        id: row.get(0)?,
+        owner: row.get(1)?,
+        recipient: row.get(2)?,
+        block_height: row.get(3)?,
+        tags: Vec::new(),
+    })
+}
+
+fn with_tags(conn: &Connection, mut tx: IndexedTx) -> Result<IndexedTx, Error> {
+    let mut stmt = conn
+        .prepare_cached("SELECT name, value FROM tags WHERE tx_id = ?1 ORDER BY position")
+        .map_err(sql_error)?;
+    tx.tags = stmt
+        .query_map(params![tx.id], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(sql_error)?
+        .collect::<Result<_, _>>()
+        .map_err(sql_error)?;
+    Ok(tx)
+}
+
+fn sql_error(e: rusqlite::Error) -> Error {
+    Error::IndexError(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::PathBuf, str::FromStr};
+
+    use httpmock::{Method::POST, MockServer};
+    use pretend::Url;
+    use serde_json::json;
+    use tokio_test::block_on;
+
+    use super::{IndexedTx, TxIndex};
+    use crate::{graphql::TransactionQuery, Arweave};
+
+    fn node(id: &str, owner: &str, height: u64, app: &str) -> serde_json::Value {
+        json!({
+            "id": id,
+            "recipient": "",
+            "owner": { "address": owner },
+            "tags": [{ "name": "App-Name", "value": app }],
+            "block": { "height": height }
+        })
+    }
+
+    #[test]
+    fn test_query_by_owner_and_tag() {
+        let index = TxIndex::open_in_memory().unwrap();
+        let data = json!({ "transactions": { "edges": [
+            { "cursor": "a", "node": node("tx1", "alice", 10, "app") },
+            { "cursor": "b", "node": node("tx2", "bob", 11, "app") },
+            { "cursor": "c", "node": node("tx3", "alice", 12, "other") },
+        ] } });
+
+        assert_eq!(index.ingest(&data).unwrap(), 3);
+        assert_eq!(index.len().unwrap(), 3);
+        assert_eq!(index.last_height().unwrap(), Some(12));
+
+        let ids = |txs: Vec<IndexedTx>| txs.into_iter().map(|tx| tx.id).collect::<Vec<_>>();
+        assert_eq!(ids(index.by_owner("alice").unwrap()), vec!["tx1", "tx3"]);
+        assert_eq!(
+            ids(index.by_tag("App-Name", "app").unwrap()),
+            vec!["tx1", "tx2"]
+        );
+
+        let tx = index.get("tx3").unwrap().unwrap();
+        assert_eq!(tx.recipient, None);
+        assert_eq!(tx.tags, vec![("App-Name".to_string(), "other".to_string())]);
+
+        // Re-indexing replaces the previous tags.
+        index
+            .ingest(&json!({ "transactions": { "edges": [
+                { "cursor": "c", "node": node("tx3", "alice", 12, "renamed") }
+            ] } }))
+            .unwrap();
+        assert!(index.by_tag("App-Name", "other").unwrap().is_empty());
+        assert_eq!(index.len().unwrap(), 3);
+    }
+
+    #[test]
+    fn test_sync_pages() {
+        let server = MockServer::start();
+        let second = server.mock(|when, then| {
+            when.method(POST)
+                .path("/graphql")
+                .body_contains("after: \\\"c1\\\"");
+            then.status(200)
+                .json_body(json!({ "data": { "transactions": {
+                "pageInfo": { "hasNextPage": false },
+                "edges": [{ "cursor": "c2", "node": node("tx2", "bob", 6, "app") }]
+            } } }));
+        });
+        let first = server.mock(|when, then| {
+            when.method(POST).path("/graphql");
+            then.status(200)
+                .json_body(json!({ "data": { "transactions": {
+                "pageInfo": { "hasNextPage": true },
+                "edges": [{ "cursor": "c1", "node": node("tx1", "alice", 5, "app") }]
+            } } }));
+        });
+
+        let arweave = Arweave::from_keypair_path(
+            PathBuf::from("res/test_wallet.json"),
+            Url::from_str(&server.url("/")).unwrap(),
+        )
+        .unwrap();
+        let index = TxIndex::open_in_memory().unwrap();
+        let synced = block_on(index.sync(&arweave, TransactionQuery::new())).unwrap();
+
+        first.assert_hits(1);
+        second.assert_hits(1);
+        assert_eq!(synced, 2);
+        assert_eq!(index.last_height().unwrap(), Some(6));
+    }
+}
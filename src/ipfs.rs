@@ -0,0 +1,203 @@
+//! IPFS content identifiers, attached as `IPFS-Add` tags so uploads can be found by CID on
+//! both networks.
+//!
+//! Only CIDv1 with a SHA-256 multihash is supported. [`Cid::raw`] yields the CID of a
+//! payload stored as a single raw block, the one `ipfs add --cid-version 1 --raw-leaves`
+//! gives for payloads up to [`IPFS_CHUNK_SIZE`]. Larger payloads are split into a UnixFS
+//! DAG by IPFS, whose CID is not computed here.
+
+use std::{fmt, str::FromStr};
+
+use crate::{
+    consts::IPFS_CHUNK_SIZE,
+    crypto::{base64::Base64, hash::sha256},
+    error::Error,
+    transaction::tags::{Tag, IPFS_ADD},
+};
+
+/// Multicodec of raw binary blocks.
+pub const RAW_CODEC: u64 = 0x55;
+
+/// Multicodec of dag-pb nodes, used by UnixFS.
+pub const DAG_PB_CODEC: u64 = 0x70;
+
+/// Multihash code of SHA-256.
+const SHA2_256: u64 = 0x12;
+
+/// RFC 4648 base32 alphabet, lowercased as in multibase `b` strings.
+const BASE32_ALPHABET: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Cid {
+    codec: u64,
+    digest: [u8; 32],
+}
+
+impl Cid {
+    pub fn new(codec: u64, digest: [u8; 32]) -> Self {
+        Self { codec, digest }
+    }
+
+    /// CID of `data` as a single raw block. Fails for payloads IPFS would split into
+    /// several blocks.
+    pub fn raw(data: &[u8]) -> Result<Self, Error> {
+        if data.len() > IPFS_CHUNK_SIZE {
+            return Err(Error::InvalidTagValue(format!(
+                "{}: payloads over {} bytes span several IPFS blocks",
+                IPFS_ADD, IPFS_CHUNK_SIZE
+            )));
+        }
+        Ok(Self::new(RAW_CODEC, sha256(data)))
+    }
+
+    pub fn codec(&self) -> u64 {
+        self.codec
+    }
+
+    /// SHA-256 digest of the block.
+    pub fn digest(&self) -> &[u8; 32] {
+        &self.digest
+    }
+
+    /// Whether `data` is the raw block this CID identifies.
+    pub fn matches(&self, data: &[u8]) -> bool {
+        self.codec == RAW_CODEC && sha256(data) == self.digest
+    }
+
+    /// Binary form: version, codec and multihash, as unsigned varints.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(36);
+        for n in [1, self.codec, SHA2_256, 32] {
+            write_varint(&mut bytes, n);
+        }
+        bytes.extend_from_slice(&self.digest);
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let invalid = |reason: &str| Error::InvalidTagValue(format!("CID: {}", reason));
+        let mut rest = bytes;
+        let mut next = || read_varint(&mut rest).ok_or_else(|| invalid("truncated"));
+        if next()? != 1 {
+            return Err(invalid("only CIDv1 is supported"));
+        }
+        let codec = next()?;
+        if next()? != SHA2_256 || next()? != 32 {
+            return Err(invalid("only SHA-256 multihashes are supported"));
+        }
+        let digest = rest
+            .try_into()
+            .map_err(|_| invalid("digest is not 32 bytes"))?;
+        Ok(Self { codec, digest })
+    }
+
+    /// `IPFS-Add` tag holding the CID.
+    pub fn to_tag(&self) -> Result<Tag<Base64>, Error> {
+        Tag::<Base64>::ipfs_add(&self.to_string())
+    }
+
+    /// CID of the first `IPFS-Add` tag of `tags`, if any.
+    pub fn from_tags(tags: &[Tag<Base64>]) -> Result<Option<Self>, Error> {
+        tags.iter()
+            .find(|tag| tag.name.0 == IPFS_ADD.as_bytes())
+            .map(|tag| tag.value.to_utf8_string()?.parse())
+            .transpose()
+    }
+}
+
+/// Multibase base32 string, e.g. `bafkrei...` for raw blocks.
+impl fmt::Display for Cid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bytes = self.to_bytes();
+        let mut encoded = String::with_capacity(1 + (bytes.len() * 8).div_ceil(5));
+        encoded.push('b');
+        let (mut buffer, mut bits) = (0u16, 0);
+        for byte in bytes {
+            buffer = (buffer << 8) | byte as u16;
+            bits += 8;
+            while bits >= 5 {
+                bits -= 5;
+                encoded.push(BASE32_ALPHABET[((buffer >> bits) & 31) as usize] as char);
+            }
+        }
+        if bits > 0 {
+            encoded.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 31) as usize] as char);
+        }
+        f.write_str(&encoded)
+    }
+}
+
+impl FromStr for Cid {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason: &str| Error::InvalidTagValue(format!("CID {:?}: {}", s, reason));
+        let encoded = s
+            .strip_prefix('b')
+            .ok_or_else(|| invalid("only base32 CIDv1 strings are supported"))?;
+        let mut bytes = Vec::with_capacity(encoded.len() * 5 / 8);
+        let (mut buffer, mut bits) = (0u16, 0);
+        for c in encoded.bytes() {
+            let value = BASE32_ALPHABET
+                .iter()
+                .position(|a| *a == c.to_ascii_lowercase())
+                .ok_or_else(|| invalid("invalid base32"))?;
+            buffer = (buffer << 5) | value as u16;
+            bits += 5;
+            if bits >= 8 {
+                bits -= 8;
+                bytes.push((buffer >> bits) as u8);
+            }
+        }
+        Self::from_bytes(&bytes)
+    }
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut n: u64) {
+    while n >= 0x80 {
+        bytes.push(n as u8 | 0x80);
+        n >>= 7;
+    }
+    bytes.push(n as u8);
+}
+
+fn read_varint(bytes: &mut &[u8]) -> Option<u64> {
+    let mut n = 0u64;
+    for shift in (0..64).step_by(7) {
+        let (byte, rest) = bytes.split_first()?;
+        *bytes = rest;
+        n |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Some(n);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Cid, RAW_CODEC};
+    use crate::consts::IPFS_CHUNK_SIZE;
+
+    #[test]
+    fn test_raw_cid() {
+        let cid = Cid::raw(b"").unwrap();
+        assert_eq!(
+            cid.to_string(),
+            "bafkreihdwdcefgh4dqkjv67uzcmw7ojee6xedzdetojuzjevtenxquvyku"
+        );
+        assert_eq!(cid.to_string().parse::<Cid>().unwrap(), cid);
+        assert_eq!(cid.codec(), RAW_CODEC);
+        assert!(cid.matches(b""));
+        assert!(!cid.matches(b"x"));
+
+        let tags = vec![cid.to_tag().unwrap()];
+        assert_eq!(Cid::from_tags(&tags).unwrap(), Some(cid));
+        assert_eq!(Cid::from_tags(&[]).unwrap(), None);
+
+        assert!(Cid::raw(&vec![0; IPFS_CHUNK_SIZE + 1]).is_err());
+        assert!("QmYwAPJzv5CZsnA625s3Xf2nemtYgPpHdWEz79ojWnPbdG"
+            .parse::<Cid>()
+            .is_err());
+    }
+}
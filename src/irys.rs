@@ -0,0 +1,415 @@
+//! Support for Irys (formerly Bundlr) bundler services.
+
+use std::{collections::BTreeMap, str::FromStr};
+
+use async_trait::async_trait;
+use reqwest::{header::CONTENT_TYPE, Response};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{
+    backend::{sign_data_item, SignedUpload, Upload, UploadBackend},
+    crypto::{
+        base64::Base64,
+        hash::{deep_hash, DeepHashItem},
+    },
+    currency::deserialize_winstons,
+    data_item::{DataItem, ARWEAVE_SIGNATURE_TYPE},
+    error::Error,
+    signer::ArweaveSigner,
+    Arweave,
+};
+
+/// Currency balances are funded and uploads paid in.
+pub const CURRENCY: &str = "arweave";
+
+/// Header naming the account an upload is charged to, when it is not the data item's owner.
+/// The account must allow the owner to spend from its balance.
+pub const PAID_BY_HEADER: &str = "x-irys-paid-by";
+
+/// Receipt returned by a bundler when it accepts a data item, promising to settle it on
+/// Arweave no later than `deadline_height`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Receipt {
+    pub id: String,
+    pub timestamp: u64,
+    pub version: String,
+    pub public: Base64,
+    pub signature: Base64,
+    pub deadline_height: u64,
+    #[serde(default)]
+    pub block: Option<u64>,
+    #[serde(default)]
+    pub validator_signatures: Vec<serde_json::Value>,
+}
+
+/// Whether the data item of a [`Receipt`] landed on chain in time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReceiptStatus {
+    /// Included at `height`, at or before the deadline.
+    Confirmed { height: u64 },
+    /// Not included yet, but the deadline has not passed.
+    Pending,
+    /// Included after the deadline, or not included and the deadline has passed.
+    DeadlineMissed,
+}
+
+impl Receipt {
+    /// Message signed by the bundler: the deep hash of
+    /// `["Bundlr", version, id, deadline_height, timestamp]`.
+    pub fn signature_data(&self) -> [u8; 48] {
+        let items = [
+            "Bundlr".to_string(),
+            self.version.clone(),
+            self.id.clone(),
+            self.deadline_height.to_string(),
+            self.timestamp.to_string(),
+        ]
+        .iter()
+        .map(|field| DeepHashItem::from_item(field.as_bytes()))
+        .collect();
+        deep_hash(DeepHashItem::from_children(items))
+    }
+
+    /// Verifies the bundler's signature offline against the public key in the receipt.
+    pub fn verify(&self) -> Result<(), Error> {
+        ArweaveSigner::verify(&self.public.0, &self.signature_data(), &self.signature.0)
+    }
+
+    /// Status of the receipt given the height the data item was included at, if any, and the
+    /// current network height.
+    pub fn status(&self, included_at: Option<u64>, current_height: u64) -> ReceiptStatus {
+        match included_at {
+            Some(height) if height <= self.deadline_height => ReceiptStatus::Confirmed { height },
+            Some(_) => ReceiptStatus::DeadlineMissed,
+            None if current_height > self.deadline_height => ReceiptStatus::DeadlineMissed,
+            None => ReceiptStatus::Pending,
+        }
+    }
+}
+
+/// What a bundler serves at `/info`.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct BundlerInfo {
+    pub version: String,
+    /// Deposit address for each currency.
+    pub addresses: BTreeMap<String, String>,
+    pub gateway: String,
+}
+
+#[derive(Deserialize)]
+struct Balance {
+    #[serde(deserialize_with = "deserialize_winstons")]
+    balance: u128,
+}
+
+/// A withdrawal accepted by the bundler, amounts in winstons.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Withdrawal {
+    /// Transaction paying out the withdrawal.
+    pub tx_id: String,
+    #[serde(deserialize_with = "deserialize_winstons")]
+    pub requested: u128,
+    /// Network fee of the payout, taken from the balance on top of `requested`.
+    #[serde(deserialize_with = "deserialize_winstons")]
+    pub fee: u128,
+    /// Amount received.
+    #[serde(rename = "final", deserialize_with = "deserialize_winstons")]
+    pub received: u128,
+}
+
+/// Client of a bundler node's REST API.
+pub struct IrysClient {
+    client: reqwest::Client,
+    url: url::Url,
+}
+
+impl IrysClient {
+    pub fn new(url: url::Url) -> Self {
+        Self::with_client(url, reqwest::Client::new())
+    }
+
+    pub fn with_client(url: url::Url, client: reqwest::Client) -> Self {
+        Self { client, url }
+    }
+
+    pub fn url(&self) -> &url::Url {
+        &self.url
+    }
+
+    pub async fn info(&self) -> Result<BundlerInfo, Error> {
+        let res = self.send(self.client.get(self.join("info")?)).await?;
+        res.json()
+            .await
+            .map_err(|e| Error::BundlerError(e.to_string()))
+    }
+
+    /// Address the bundler takes AR deposits on.
+    pub async fn deposit_address(&self) -> Result<Base64, Error> {
+        let info = self.info().await?;
+        let address = info
+            .addresses
+            .get(CURRENCY)
+            .ok_or_else(|| Error::BundlerError(format!("no deposit address for {}", CURRENCY)))?;
+        Base64::from_str(address)
+    }
+
+    /// Asks the bundler to credit transfer `tx_id` to its sender's balance.
+    pub async fn notify_funding(&self, tx_id: &str) -> Result<(), Error> {
+        let url = self.join(&format!("account/balance/{}", CURRENCY))?;
+        self.send(self.client.post(url).json(&json!({ "tx_id": tx_id })))
+            .await?;
+        Ok(())
+    }
+
+    /// Balance of `address` on the bundler, in winstons.
+    pub async fn balance(&self, address: &str) -> Result<u128, Error> {
+        let mut url = self.join(&format!("account/balance/{}", CURRENCY))?;
+        url.query_pairs_mut().append_pair("address", address);
+        let res = self.send(self.client.get(url)).await?;
+        let balance: Balance = res
+            .json()
+            .await
+            .map_err(|e| Error::BundlerError(e.to_string()))?;
+        Ok(balance.balance)
+    }
+
+    /// Winstons charged to upload `bytes` bytes.
+    pub async fn price(&self, bytes: u64) -> Result<u128, Error> {
+        let url = self.join(&format!("price/{}/{}", CURRENCY, bytes))?;
+        let res = self.send(self.client.get(url)).await?;
+        let price = res
+            .text()
+            .await
+            .map_err(|e| Error::BundlerError(e.to_string()))?;
+        price
+            .trim()
+            .parse()
+            .map_err(|_| Error::BundlerError(format!("invalid price {:?}", price)))
+    }
+
+    /// Nonce the next withdrawal of `address` must be signed with.
+    pub async fn withdrawal_nonce(&self, address: &str) -> Result<u64, Error> {
+        let mut url = self.join(&format!("account/withdrawals/{}", CURRENCY))?;
+        url.query_pairs_mut().append_pair("address", address);
+        let res = self.send(self.client.get(url)).await?;
+        let nonce = res
+            .text()
+            .await
+            .map_err(|e| Error::BundlerError(e.to_string()))?;
+        nonce
+            .trim()
+            .parse()
+            .map_err(|_| Error::BundlerError(format!("invalid nonce {:?}", nonce)))
+    }
+
+    /// Withdraws `amount` winstons from the balance of `signer` back to its wallet.
+    pub async fn withdraw(
+        &self,
+        signer: &ArweaveSigner,
+        amount: u128,
+    ) -> Result<Withdrawal, Error> {
+        let address = signer.wallet_address().to_string();
+        let nonce = self.withdrawal_nonce(&address).await?;
+        let signature = signer.sign(&withdrawal_signature_data(amount, nonce));
+        let body = json!({
+            "publicKey": signer.keypair_modulus(),
+            "currency": CURRENCY,
+            "amount": amount.to_string(),
+            "nonce": nonce,
+            "signature": signature,
+            "sigType": ARWEAVE_SIGNATURE_TYPE,
+        });
+        let url = self.join("account/withdraw")?;
+        let res = self.send(self.client.post(url).json(&body)).await?;
+        res.json()
+            .await
+            .map_err(|e| Error::BundlerError(e.to_string()))
+    }
+
+    /// Posts signed `item`, charged to `paid_by` if set, or to its owner.
+    pub async fn upload(&self, item: &DataItem, paid_by: Option<&str>) -> Result<Receipt, Error> {
+        let mut request = self
+            .client
+            .post(self.join(&format!("tx/{}", CURRENCY))?)
+            .header(CONTENT_TYPE, "application/octet-stream")
+            .body(item.to_bytes()?);
+        if let Some(paid_by) = paid_by {
+            request = request.header(PAID_BY_HEADER, paid_by);
+        }
+        let res = self.send(request).await?;
+        res.json()
+            .await
+            .map_err(|e| Error::BundlerError(e.to_string()))
+    }
+
+    fn join(&self, path: &str) -> Result<url::Url, Error> {
+        self.url
+            .join(path)
+            .map_err(|e| Error::BundlerError(e.to_string()))
+    }
+
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<Response, Error> {
+        let res = request
+            .send()
+            .await
+            .map_err(|e| Error::BundlerError(e.to_string()))?;
+        if !res.status().is_success() {
+            let status = res.status();
+            let body = res.text().await.unwrap_or_default();
+            return Err(Error::BundlerError(format!("{}: {}", status, body)));
+        }
+        Ok(res)
+    }
+}
+
+/// Message signed to withdraw: the deep hash of `[currency, amount, nonce]`.
+pub fn withdrawal_signature_data(amount: u128, nonce: u64) -> [u8; 48] {
+    let items = [CURRENCY.to_string(), amount.to_string(), nonce.to_string()]
+        .iter()
+        .map(|field| DeepHashItem::from_item(field.as_bytes()))
+        .collect();
+    deep_hash(DeepHashItem::from_children(items))
+}
+
+#[async_trait]
+impl UploadBackend for IrysClient {
+    async fn sign(&self, arweave: &Arweave, upload: Upload) -> Result<SignedUpload, Error> {
+        sign_data_item(arweave, upload)
+            .await
+            .map(SignedUpload::DataItem)
+    }
+
+    async fn post(&self, arweave: &Arweave, signed: SignedUpload) -> Result<(String, u64), Error> {
+        match signed {
+            SignedUpload::DataItem(item) => {
+                let receipt = arweave
+                    .post_data_item(
+                        &item,
+                        &self.url,
+                        self.price(item.to_bytes()?.len() as u64),
+                        self.upload(&item, None),
+                    )
+                    .await?;
+                Ok((receipt.map_or_else(|| item.id().to_string(), |r| r.id), 0))
+            }
+            SignedUpload::Transaction(tx) => Err(Error::DataItemError(format!(
+                "transaction {} can't be posted to bundler",
+                tx.id
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use httpmock::{
+        Method::{GET, POST},
+        MockServer,
+    };
+    use serde_json::json;
+    use url::Url;
+
+    use super::{withdrawal_signature_data, IrysClient, Receipt, ReceiptStatus};
+    use crate::{crypto::base64::Base64, error::Error, signer::ArweaveSigner};
+
+    fn signed_receipt() -> Receipt {
+        let signer = ArweaveSigner::default();
+        let mut receipt = Receipt {
+            id: "4lOqIxWjHyKgFlJtLmYc7GnXqSe8FjdTnWxcT9J6ytw".to_string(),
+            timestamp: 1_690_000_000_000,
+            version: "1.0.0".to_string(),
+            public: signer.get_public_key(),
+            signature: Base64::empty(),
+            deadline_height: 1_200_000,
+            block: None,
+            validator_signatures: vec![],
+        };
+        receipt.signature = signer.sign(&receipt.signature_data());
+        receipt
+    }
+
+    #[test]
+    fn test_verify_receipt() {
+        let mut receipt = signed_receipt();
+        assert!(receipt.verify().is_ok());
+
+        receipt.deadline_height += 1;
+        assert!(matches!(receipt.verify(), Err(Error::InvalidSignature)));
+    }
+
+    #[test]
+    fn test_receipt_json() {
+        let receipt = signed_receipt();
+        let json = serde_json::to_value(&receipt).unwrap();
+        assert_eq!(json["deadlineHeight"], 1_200_000);
+        let parsed: Receipt = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed, receipt);
+    }
+
+    #[test]
+    fn test_receipt_status() {
+        let receipt = signed_receipt();
+        assert_eq!(
+            receipt.status(Some(1_199_000), 1_199_500),
+            ReceiptStatus::Confirmed { height: 1_199_000 }
+        );
+        assert_eq!(
+            receipt.status(Some(1_200_001), 1_200_010),
+            ReceiptStatus::DeadlineMissed
+        );
+        assert_eq!(receipt.status(None, 1_199_999), ReceiptStatus::Pending);
+        assert_eq!(
+            receipt.status(None, 1_200_001),
+            ReceiptStatus::DeadlineMissed
+        );
+    }
+
+    #[tokio::test]
+    async fn test_balance_and_withdrawal() {
+        let server = MockServer::start_async().await;
+        let signer = ArweaveSigner::default();
+        let address = signer.wallet_address().to_string();
+        let balance = server.mock(|when, then| {
+            when.method(GET)
+                .path("/account/balance/arweave")
+                .query_param("address", &address);
+            then.status(200).json_body(json!({ "balance": "5000" }));
+        });
+        let nonce = server.mock(|when, then| {
+            when.method(GET)
+                .path("/account/withdrawals/arweave")
+                .query_param("address", &address);
+            then.status(200).body("3");
+        });
+        let withdraw = server.mock(|when, then| {
+            when.method(POST)
+                .path("/account/withdraw")
+                .json_body_partial(r#"{ "currency": "arweave", "amount": "1000", "nonce": 3 }"#);
+            then.status(200).json_body(json!({
+                "tx_id": "payout",
+                "requested": 1000,
+                "fee": "10",
+                "final": "1000"
+            }));
+        });
+
+        let irys = IrysClient::new(Url::parse(&server.url("/")).unwrap());
+        assert_eq!(irys.balance(&address).await.unwrap(), 5000);
+        let withdrawal = irys.withdraw(&signer, 1000).await.unwrap();
+        balance.assert();
+        nonce.assert();
+        withdraw.assert();
+        assert_eq!(withdrawal.tx_id, "payout");
+        assert_eq!(withdrawal.fee, 10);
+
+        let signature = signer.sign(&withdrawal_signature_data(1000, 3));
+        assert!(ArweaveSigner::verify(
+            &signer.keypair_modulus(),
+            &withdrawal_signature_data(1000, 3),
+            &signature
+        )
+        .is_ok());
+    }
+}
@@ -0,0 +1,295 @@
+//! Append-only, fsynced journal of chunked upload progress, so that uploads interrupted by a
+//! crash can be resumed with [`crate::batch::UploadManager::recover`].
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{
+    error::Error,
+    transaction::{parser, Tx},
+};
+
+/// One line of the journal.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "type")]
+enum Record {
+    /// The transaction header, without data, signed for the file at `path`.
+    Started {
+        path: PathBuf,
+        #[serde(serialize_with = "serialize_tx", deserialize_with = "deserialize_tx")]
+        tx: Box<Tx>,
+    },
+    /// `posted_id` differs from `id` when the header was re-anchored before being accepted.
+    HeaderPosted {
+        id: String,
+        posted_id: String,
+    },
+    ChunkAcked {
+        id: String,
+        offset: usize,
+    },
+    Completed {
+        id: String,
+    },
+}
+
+fn serialize_tx<S: Serializer>(tx: &Tx, serializer: S) -> Result<S::Ok, S::Error> {
+    tx.serialize(serializer)
+}
+
+fn deserialize_tx<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Box<Tx>, D::Error> {
+    parser::deserialize_tx(deserializer).map(Box::new)
+}
+
+/// An upload the journal holds no completion record for.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingUpload {
+    pub path: PathBuf,
+    /// Signed transaction header, without data, chunks or proofs.
+    pub tx: Tx,
+    /// Id the header was accepted under, once posted.
+    pub header_posted: Option<String>,
+    /// Offsets of the chunks already accepted by the gateway.
+    pub acked: BTreeSet<usize>,
+}
+
+/// Journal file recording every step of chunked uploads. Each record is flushed to disk
+/// before the call returns, and a record torn by a crash is ignored on replay.
+pub struct UploadJournal {
+    path: PathBuf,
+    file: Mutex<File>,
+}
+
+impl UploadJournal {
+    /// Opens the journal at `path`, creating it if needed.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref().to_path_buf();
+        let mut file = append_to(&path)?;
+        // Terminate a record torn by a crash so that the next one starts on its own line.
+        let contents = fs::read(&path).map_err(io_error)?;
+        if contents.last().is_some_and(|b| *b != b'\n') {
+            file.write_all(b"\n").map_err(io_error)?;
+            file.sync_data().map_err(io_error)?;
+        }
+        Ok(Self {
+            path,
+            file: Mutex::new(file),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Records that the data of `file_path` is about to be uploaded with `tx`.
+    pub fn started(&self, file_path: &Path, tx: &Tx) -> Result<(), Error> {
+        self.append(&Record::Started {
+            path: file_path.to_path_buf(),
+            tx: Box::new(tx.clone_with_no_data()?),
+        })
+    }
+
+    /// Records that the header of `id` was accepted, possibly re-anchored as `posted_id`.
+    pub fn header_posted(&self, id: &str, posted_id: &str) -> Result<(), Error> {
+        self.append(&Record::HeaderPosted {
+            id: id.to_string(),
+            posted_id: posted_id.to_string(),
+        })
+    }
+
+    pub fn chunk_acked(&self, id: &str, offset: usize) -> Result<(), Error> {
+        self.append(&Record::ChunkAcked {
+            id: id.to_string(),
+            offset,
+        })
+    }
+
+    pub fn completed(&self, id: &str) -> Result<(), Error> {
+        self.append(&Record::Completed { id: id.to_string() })
+    }
+
+    /// Replays the journal and returns the uploads not completed yet, in the order they
+    /// were started.
+    pub fn pending(&self) -> Result<Vec<PendingUpload>, Error> {
+        let _file = self.file.lock().unwrap();
+        Ok(replay(&self.path)?.into_values().collect())
+    }
+
+    /// Rewrites the journal keeping only the records of pending uploads.
+    pub fn compact(&self) -> Result<(), Error> {
+        let mut file = self.file.lock().unwrap();
+        let pending = replay(&self.path)?;
+
+        let tmp = self.path.with_extension("tmp");
+        let mut out = File::create(&tmp).map_err(io_error)?;
+        for upload in pending.into_values() {
+            let id = upload.tx.id.to_string();
+            write_record(
+                &mut out,
+                &Record::Started {
+                    path: upload.path,
+                    tx: Box::new(upload.tx),
+                },
+            )?;
+            if let Some(posted_id) = upload.header_posted {
+                write_record(
+                    &mut out,
+                    &Record::HeaderPosted {
+                        id: id.clone(),
+                        posted_id,
+                    },
+                )?;
+            }
+            for offset in upload.acked {
+                write_record(
+                    &mut out,
+                    &Record::ChunkAcked {
+                        id: id.clone(),
+                        offset,
+                    },
+                )?;
+            }
+        }
+        out.sync_all().map_err(io_error)?;
+        fs::rename(&tmp, &self.path).map_err(io_error)?;
+        *file = append_to(&self.path)?;
+        Ok(())
+    }
+
+    fn append(&self, record: &Record) -> Result<(), Error> {
+        let mut file = self.file.lock().unwrap();
+        write_record(&mut file, record)?;
+        file.sync_data().map_err(io_error)
+    }
+}
+
+fn append_to(path: &Path) -> Result<File, Error> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(io_error)
+}
+
+fn write_record(file: &mut File, record: &Record) -> Result<(), Error> {
+    let mut line = serde_json::to_vec(record).map_err(|e| Error::IoError(e.to_string()))?;
+    line.push(b'\n');
+    file.write_all(&line).map_err(io_error)
+}
+
+/// Pending uploads keyed by start order.
+fn replay(path: &Path) -> Result<BTreeMap<usize, PendingUpload>, Error> {
+    let reader = BufReader::new(File::open(path).map_err(io_error)?);
+    let mut pending = BTreeMap::new();
+    let mut order = BTreeMap::new();
+
+    for (n, line) in reader.lines().enumerate() {
+        let line = line.map_err(io_error)?;
+        // A record torn by a crash is skipped, and its step is simply redone.
+        let record = match serde_json::from_str::<Record>(&line) {
+            Ok(record) => record,
+            Err(_) => continue,
+        };
+        match record {
+            Record::Started { path, tx } => {
+                order.insert(tx.id.to_string(), n);
+                pending.insert(
+                    n,
+                    PendingUpload {
+                        path,
+                        tx: *tx,
+                        header_posted: None,
+                        acked: BTreeSet::new(),
+                    },
+                );
+            }
+            Record::HeaderPosted { id, posted_id } => {
+                if let Some(upload) = order.get(&id).and_then(|n| pending.get_mut(n)) {
+                    upload.header_posted = Some(posted_id);
+                }
+            }
+            Record::ChunkAcked { id, offset } => {
+                if let Some(upload) = order.get(&id).and_then(|n| pending.get_mut(n)) {
+                    upload.acked.insert(offset);
+                }
+            }
+            Record::Completed { id } => {
+                if let Some(n) = order.remove(&id) {
+                    pending.remove(&n);
+                }
+            }
+        }
+    }
+    Ok(pending)
+}
+
+fn io_error(e: std::io::Error) -> Error {
+    Error::IoError(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, io::Write, path::PathBuf};
+
+    use super::UploadJournal;
+    use crate::{crypto::base64::Base64, transaction::Tx};
+
+    fn tx(id: u8) -> Tx {
+        Tx {
+            format: 2,
+            id: Base64::from(vec![id; 32]),
+            data: Base64::from(vec![1, 2, 3]),
+            data_size: 3,
+            reward: 42,
+            ..Tx::default()
+        }
+    }
+
+    #[test]
+    fn test_replay_and_compact() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("journal.jsonl");
+        let journal = UploadJournal::open(&path).unwrap();
+
+        let (done, interrupted) = (tx(1), tx(2));
+        journal.started(&PathBuf::from("a"), &done).unwrap();
+        journal.started(&PathBuf::from("b"), &interrupted).unwrap();
+        journal
+            .header_posted(&done.id.to_string(), &done.id.to_string())
+            .unwrap();
+        journal.completed(&done.id.to_string()).unwrap();
+        journal
+            .header_posted(&interrupted.id.to_string(), "re-anchored")
+            .unwrap();
+        journal.chunk_acked(&interrupted.id.to_string(), 7).unwrap();
+        // Simulate a record torn by a crash.
+        fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(b"{\"type\":\"ChunkAck")
+            .unwrap();
+
+        let journal = UploadJournal::open(&path).unwrap();
+        let pending = journal.pending().unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].path, PathBuf::from("b"));
+        assert_eq!(pending[0].tx, interrupted.clone_with_no_data().unwrap());
+        assert_eq!(pending[0].header_posted.as_deref(), Some("re-anchored"));
+        assert_eq!(
+            pending[0].acked.iter().copied().collect::<Vec<_>>(),
+            vec![7]
+        );
+
+        journal.compact().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 3);
+        assert_eq!(journal.pending().unwrap(), pending);
+    }
+}
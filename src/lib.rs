@@ -1,33 +1,116 @@
-use std::{fs, path::PathBuf, str::FromStr};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    future::Future,
+    ops::Range,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 
-use consts::{ARWEAVE_BASE_URL, MAX_TX_DATA};
-use crypto::base64::Base64;
+use activity::{ActivityCursor, AddressActivity};
+#[cfg(feature = "archive")]
+use archive::ArchiveFormat;
+use arfs::{
+    cipher::{ArfsKey, PRIVATE_CONTENT_TYPE},
+    Entity,
+};
+use audit::{AuditLog, AuditRecord};
+use backend::{BaseLayer, Upload, UploadBackend};
+use budget::Budget;
+use bytes::Bytes;
+use consts::{
+    ACTIVITY_POLL_INTERVAL, ARWEAVE_BASE_URL, CONFIRMATION_POLL_INTERVAL, IPFS_CHUNK_SIZE,
+    MAX_TX_DATA, SCAN_CONCURRENCY, UPLOAD_EVENTS_CAPACITY,
+};
+use crypto::{
+    base64::Base64,
+    hash::sha256,
+    merkle::{validate_path, ChunkingConfig},
+};
+use data_item::DataItem;
+use deposits::{DepositEvent, DepositScanner, ScanCursor};
+use digest::{Digest, DigestAlgorithm};
 use error::Error;
-use futures::{stream, Stream, StreamExt};
+use events::UploadEvent;
+use finality::FinalityPolicy;
+use futures::{stream, Stream, StreamExt, TryStreamExt};
+use gateway::{
+    discovery::{discover_gateways, DiscoveryConfig, Peer},
+    DnsOverrides, Gateway, LimitedGateway, Middleware, MiddlewareStack, TrustPolicy,
+};
+use graphql::{GraphqlClient, TransactionQuery, TransactionsPage, TxNode};
+use ipfs::Cid;
+use irys::{IrysClient, Receipt, ReceiptStatus, Withdrawal};
+use log::{log_tags, LogCounters, LogEntry};
+use manifest::{Manifest, SyncReport, MANIFEST_CONTENT_TYPE};
+use network::{NetworkConfig, NetworkInfoClient};
+use payment::PaymentStatus;
 use pretend::StatusCode;
-use reqwest::Client;
+use price_history::{PriceHistory, PriceSample};
+#[cfg(feature = "s3")]
+use s3::{S3Client, S3Import};
+use sequence::{find_gaps, SequenceManager};
 use serde::{Deserialize, Serialize};
+use tokio::sync::{broadcast, OwnedSemaphorePermit, Semaphore};
 use transaction::{
+    cache::{CacheTtls, Metadata, MetadataCache},
     client::TxClient,
-    tags::{FromUtf8Strs, Tag},
-    Tx,
+    prepared::PreparedTx,
+    tags::{Tag, APP_NAME, BUNDLE_FORMAT, BUNDLE_VERSION, FILE_HASH, SEQUENCE},
+    template::TxTemplate,
+    Tx, TxWithData,
 };
-use types::TxStatus;
+use turbo::{TurboClient, TurboReceipt};
+use types::{BlockInfo, NetworkInfo, TxOffset, TxStatus, ValidatedChunk};
 use upload::Uploader;
+use watcher::ConfirmationWatcher;
 
-pub mod client;
+pub mod activity;
+#[cfg(feature = "archive")]
+pub mod archive;
+pub mod arconnect;
+pub mod arfs;
+pub mod audit;
+pub mod backend;
+pub mod batch;
+pub mod blocks;
+pub mod budget;
 pub mod consts;
 pub mod crypto;
 pub mod currency;
+pub mod data_item;
+pub mod deposits;
+pub mod digest;
 pub mod error;
+pub mod events;
+pub mod finality;
+pub mod gateway;
+pub mod graphql;
+#[cfg(feature = "index")]
+pub mod index;
+pub mod ipfs;
+pub mod irys;
+pub mod journal;
+pub mod log;
+pub mod manifest;
 pub mod network;
+pub mod payment;
+pub mod price_history;
+#[cfg(feature = "s3")]
+pub mod s3;
+pub mod schedule;
+pub mod sequence;
 pub mod signer;
 pub mod transaction;
+pub mod turbo;
 pub mod types;
 pub mod upload;
 pub mod wallet;
+pub mod watcher;
 
-pub use signer::ArweaveSigner;
+pub use signer::{ArweaveSigner, SignerContext, SignerResolver};
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct OraclePrice {
@@ -42,18 +125,63 @@ pub struct OraclePricePair {
 pub struct Arweave {
     pub base_url: url::Url,
     pub signer: ArweaveSigner,
-    tx_client: TxClient,
+    gateway: Arc<dyn Gateway>,
+    unlimited_gateway: Arc<dyn Gateway>,
+    in_flight: Option<Arc<Semaphore>>,
+    events: broadcast::Sender<UploadEvent>,
     uploader: Uploader,
+    budget: Option<Budget>,
+    dry_run: bool,
+    re_anchor: bool,
+    ipfs_tags: bool,
+    digest_tags: Option<DigestAlgorithm>,
+    dedup: bool,
+    idempotent: bool,
+    chunking: ChunkingConfig,
+    trust: Option<TrustPolicy>,
+    dns: DnsOverrides,
+    middleware: MiddlewareStack,
+    audit: Option<AuditLog>,
+    metadata: MetadataCache,
+    signer_resolver: Option<SignerResolver>,
+    backend: Arc<dyn UploadBackend>,
+    log_counters: LogCounters,
+    sequence: Option<Arc<SequenceManager>>,
+    finality: FinalityPolicy,
+    network: NetworkConfig,
 }
 
 impl Default for Arweave {
     fn default() -> Self {
         let arweave_url = url::Url::from_str(ARWEAVE_BASE_URL).unwrap();
+        let gateway: Arc<dyn Gateway> = Arc::new(TxClient::default());
         Self {
             base_url: arweave_url,
             signer: Default::default(),
-            tx_client: TxClient::default(),
+            gateway: gateway.clone(),
+            unlimited_gateway: gateway,
+            in_flight: None,
+            events: broadcast::channel(UPLOAD_EVENTS_CAPACITY).0,
             uploader: Default::default(),
+            budget: None,
+            dry_run: false,
+            re_anchor: false,
+            ipfs_tags: false,
+            digest_tags: None,
+            dedup: false,
+            idempotent: false,
+            chunking: ChunkingConfig::default(),
+            trust: None,
+            dns: DnsOverrides::default(),
+            middleware: MiddlewareStack::default(),
+            audit: None,
+            metadata: MetadataCache::default(),
+            signer_resolver: None,
+            backend: Arc::new(BaseLayer),
+            log_counters: LogCounters::default(),
+            sequence: None,
+            finality: FinalityPolicy::default(),
+            network: NetworkConfig::mainnet(),
         }
     }
 }
@@ -62,18 +190,422 @@ impl Arweave {
     pub fn from_keypair_path(keypair_path: PathBuf, base_url: url::Url) -> Result<Arweave, Error> {
         let signer =
             ArweaveSigner::from_keypair_path(keypair_path).expect("Could not create signer");
+        Self::from_signer(signer, base_url)
+    }
+
+    /// Client signing with `signer`, e.g. one built by [`ArweaveSigner::from_base64_env`].
+    pub fn from_signer(signer: ArweaveSigner, base_url: url::Url) -> Result<Arweave, Error> {
+        let metadata = MetadataCache::default();
         let tx_client = TxClient::new(reqwest::Client::new(), base_url.clone())
-            .expect("Could not create TxClient");
+            .expect("Could not create TxClient")
+            .with_metadata_cache(metadata.clone());
         let uploader = Uploader::new(base_url.clone());
+        let gateway: Arc<dyn Gateway> = Arc::new(tx_client);
         let arweave = Arweave {
+            network: NetworkConfig::mainnet().with_gateway(base_url.clone()),
             base_url,
             signer,
-            tx_client,
+            gateway: gateway.clone(),
+            unlimited_gateway: gateway,
+            in_flight: None,
+            events: broadcast::channel(UPLOAD_EVENTS_CAPACITY).0,
             uploader,
+            budget: None,
+            dry_run: false,
+            re_anchor: false,
+            ipfs_tags: false,
+            digest_tags: None,
+            dedup: false,
+            idempotent: false,
+            chunking: ChunkingConfig::default(),
+            trust: None,
+            dns: DnsOverrides::default(),
+            middleware: MiddlewareStack::default(),
+            audit: None,
+            metadata,
+            signer_resolver: None,
+            backend: Arc::new(BaseLayer),
+            log_counters: LogCounters::default(),
+            sequence: None,
+            finality: FinalityPolicy::default(),
         };
         Ok(arweave)
     }
 
+    /// Client signing with `signer` on `network`, through its gateway and with its limits and
+    /// retry counts.
+    pub fn for_network(signer: ArweaveSigner, network: NetworkConfig) -> Result<Arweave, Error> {
+        let mut arweave = Self::from_signer(signer, network.gateway_url.clone())?;
+        arweave.uploader = Uploader::new(network.gateway_url.clone())
+            .with_retries(network.chunk_retries)
+            .with_max_retry_after(network.max_retry_after);
+        arweave.network = network;
+        Ok(arweave)
+    }
+
+    pub fn network(&self) -> &NetworkConfig {
+        &self.network
+    }
+
+    /// Asks the faucet of the network for `winstons` to the wallet. Fails on networks
+    /// without a faucet, mainnet included.
+    pub async fn request_faucet(&self, winstons: u128) -> Result<(), Error> {
+        let faucet = self.network.faucet_url.as_ref().ok_or_else(|| {
+            Error::FaucetError(format!("network {} has no faucet", self.network.name))
+        })?;
+        let url = faucet
+            .join(&format!(
+                "mint/{}/{}",
+                self.current_signer()?.wallet_address(),
+                winstons
+            ))
+            .map_err(|e| Error::FaucetError(e.to_string()))?;
+        let res = self
+            .http_client()?
+            .get(url)
+            .send()
+            .await
+            .map_err(|e| Error::FaucetError(e.to_string()))?;
+        if !res.status().is_success() {
+            return Err(Error::FaucetError(res.status().to_string()));
+        }
+        Ok(())
+    }
+
+    /// Enforces `budget` on every transaction signed or posted from now on.
+    pub fn set_budget(&mut self, budget: Budget) {
+        self.budget = Some(budget);
+    }
+
+    pub fn budget(&self) -> Option<&Budget> {
+        self.budget.as_ref()
+    }
+
+    /// In dry-run mode every local step (anchor, fee quote, chunking, signing) still runs,
+    /// but nothing is submitted to the network. Posting returns the id and reward the
+    /// transaction would have been accepted with.
+    pub fn set_dry_run(&mut self, dry_run: bool) {
+        self.dry_run = dry_run;
+    }
+
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    /// Records an [`AuditRecord`] for every transaction posted from now on, dry runs and
+    /// failures included.
+    pub fn set_audit_log(&mut self, audit: AuditLog) {
+        self.audit = Some(audit);
+    }
+
+    pub fn audit_log(&self) -> Option<&AuditLog> {
+        self.audit.as_ref()
+    }
+
+    /// Audit record of the last transaction posted, if auditing is enabled.
+    pub fn last_audit(&self) -> Option<AuditRecord> {
+        self.audit.as_ref().and_then(AuditLog::last)
+    }
+
+    /// Replaces the HTTP gateway used to post and fetch transactions, e.g. with a
+    /// [`gateway::MockGateway`] in tests.
+    pub fn set_gateway(&mut self, gateway: Arc<dyn Gateway>) {
+        self.unlimited_gateway = gateway;
+        self.apply_in_flight_limit();
+    }
+
+    /// Restricts this instance to the hosts allowed by `trust`, verifying TLS connections
+    /// against its pinned certificates. Replaces the current gateway with one honouring the
+    /// policy, and fails if `base_url` itself isn't allowed.
+    pub fn set_trust_policy(&mut self, trust: TrustPolicy) -> Result<(), Error> {
+        trust.check(&self.base_url)?;
+        let previous = self.trust.replace(trust);
+        let res = self.reset_gateway();
+        if res.is_err() {
+            self.trust = previous;
+        }
+        res
+    }
+
+    pub fn trust_policy(&self) -> Option<&TrustPolicy> {
+        self.trust.as_ref()
+    }
+
+    /// Resolves the hostnames pinned by `dns` to their fixed addresses, for the gateway and
+    /// every other request of this instance. Replaces the current gateway.
+    pub fn set_dns_overrides(&mut self, dns: DnsOverrides) -> Result<(), Error> {
+        let previous = std::mem::replace(&mut self.dns, dns);
+        let res = self.reset_gateway();
+        if res.is_err() {
+            self.dns = previous;
+        }
+        res
+    }
+
+    pub fn dns_overrides(&self) -> &DnsOverrides {
+        &self.dns
+    }
+
+    /// Runs every HTTP request of the gateway and GraphQL queries through `middleware`, after
+    /// the middlewares added before it. Replaces the current gateway.
+    pub fn add_middleware(&mut self, middleware: Arc<dyn Middleware>) -> Result<(), Error> {
+        self.middleware.push(middleware);
+        self.reset_gateway()
+    }
+
+    /// Client for requests made outside of the gateway, honouring the trust policy and DNS
+    /// overrides.
+    fn http_client(&self) -> Result<reqwest::Client, Error> {
+        let mut builder = self.dns.apply(reqwest::Client::builder());
+        if let Some(trust) = &self.trust {
+            builder = trust.apply(builder);
+        }
+        builder
+            .build()
+            .map_err(|e| Error::HttpClientError(e.to_string()))
+    }
+
+    /// Points the gateway back at `base_url`, through a client built by
+    /// [`Arweave::http_client`].
+    fn reset_gateway(&mut self) -> Result<(), Error> {
+        let tx_client = TxClient::new(self.http_client()?, self.base_url.clone())?
+            .with_metadata_cache(self.metadata.clone())
+            .with_middleware(self.middleware.clone());
+        self.set_gateway(Arc::new(tx_client));
+        Ok(())
+    }
+
+    /// Changes how long price, anchor and info responses are reused by this instance.
+    /// [`CacheTtls::disabled`] turns the cache off.
+    pub fn set_cache_ttls(&self, ttls: CacheTtls) {
+        self.metadata.set_ttls(ttls);
+    }
+
+    /// Network info of `base_url`, reused for the info TTL of the cache.
+    pub async fn network_info(&self) -> Result<NetworkInfo, Error> {
+        let url = self
+            .base_url
+            .join("info")
+            .expect("Could not join base_url with /info");
+        if let Some(body) = self.metadata.get(Metadata::Info, url.as_str()) {
+            return serde_json::from_slice(&body)
+                .map_err(|e| Error::NetworkInfoError(e.to_string()));
+        }
+        let _permit = self.acquire_in_flight().await?;
+        let info = NetworkInfoClient::with_client(self.base_url.clone(), self.http_client()?)
+            .with_middleware(self.middleware.clone())
+            .network_info()
+            .await?;
+        let body = serde_json::to_vec(&info).map_err(|e| Error::NetworkInfoError(e.to_string()))?;
+        self.metadata
+            .insert(Metadata::Info, url.as_str(), Bytes::from(body));
+        Ok(info)
+    }
+
+    /// Probes the peers of `base_url` and routes requests through a [`gateway::GatewayPool`]
+    /// of the healthy ones, fastest first, keeping the current gateway as last resort.
+    /// Peers outside the trust policy, if any, are skipped. Returns the peers in use.
+    ///
+    /// The trust policy and DNS overrides of this instance apply unless `config` sets its
+    /// own.
+    pub async fn discover_gateways(
+        &mut self,
+        config: &DiscoveryConfig,
+    ) -> Result<Vec<Peer>, Error> {
+        let mut config = config.clone();
+        if config.trust.is_none() {
+            config.trust = self.trust.clone();
+        }
+        if config.dns.is_empty() {
+            config.dns = self.dns.clone();
+        }
+        let (pool, peers) =
+            discover_gateways(&self.base_url, self.unlimited_gateway.clone(), &config).await?;
+        self.set_gateway(Arc::new(pool));
+        Ok(peers)
+    }
+
+    /// Bounds the number of HTTP requests this instance has in flight at once, across chunk
+    /// uploads, metadata calls and downloads. `None` removes the limit.
+    pub fn set_max_in_flight(&mut self, max_in_flight: Option<usize>) {
+        self.in_flight = max_in_flight.map(|max| Arc::new(Semaphore::new(max.max(1))));
+        self.apply_in_flight_limit();
+    }
+
+    fn apply_in_flight_limit(&mut self) {
+        self.gateway = match &self.in_flight {
+            Some(permits) => Arc::new(LimitedGateway::new(
+                self.unlimited_gateway.clone(),
+                permits.clone(),
+            )),
+            None => self.unlimited_gateway.clone(),
+        };
+    }
+
+    /// Permit for a request made outside of the gateway, if requests are limited.
+    async fn acquire_in_flight(&self) -> Result<Option<OwnedSemaphorePermit>, Error> {
+        match &self.in_flight {
+            Some(permits) => gateway::limit::acquire(permits).await.map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Receives the [`UploadEvent`]s of every upload started from now on.
+    pub fn subscribe_upload_events(&self) -> broadcast::Receiver<UploadEvent> {
+        self.events.subscribe()
+    }
+
+    fn emit(&self, event: UploadEvent) {
+        // Sending only fails when nobody is subscribed.
+        let _ = self.events.send(event);
+    }
+
+    pub fn gateway(&self) -> &Arc<dyn Gateway> {
+        &self.gateway
+    }
+
+    /// Chunk uploader, exposing the upload rate and ETA for progress reporting.
+    pub fn uploader(&self) -> &Uploader {
+        &self.uploader
+    }
+
+    /// Spawns a [`ConfirmationWatcher`] polling this client's gateway at least once per block
+    /// of its network, with the finality policy of the client.
+    pub fn confirmation_watcher(&self) -> ConfirmationWatcher {
+        ConfirmationWatcher::spawn(self.gateway.clone(), self.poll_interval(), self.finality)
+    }
+
+    /// Interval between confirmation checks, so that every block is seen.
+    fn poll_interval(&self) -> Duration {
+        Duration::from_secs(CONFIRMATION_POLL_INTERVAL).min(self.network.block_time)
+    }
+
+    /// Sets when transactions are final, for the confirmation watcher, deposit scanners and
+    /// [`Arweave::wait_for_confirmation`]. Defaults to [`FINAL_CONFIRMATIONS`] confirmations.
+    ///
+    /// [`FINAL_CONFIRMATIONS`]: consts::FINAL_CONFIRMATIONS
+    pub fn set_finality(&mut self, finality: FinalityPolicy) {
+        self.finality = finality;
+    }
+
+    pub fn finality(&self) -> FinalityPolicy {
+        self.finality
+    }
+
+    /// Polls the status of transaction `id` until it is final, and returns it. Unreachable or
+    /// failing gateways are polled again; fails when the gateway doesn't know the transaction,
+    /// or with [`Error::Timeout`] when it isn't final within `timeout`.
+    pub async fn wait_for_confirmation(
+        &self,
+        id: &Base64,
+        timeout: Duration,
+    ) -> Result<TxStatus, Error> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            match self.gateway.get_tx_status(id).await {
+                Ok((_, Some(status))) if self.finality.is_status_final(&status) => {
+                    return Ok(status)
+                }
+                Ok(_) => {}
+                Err(err) if gateway::is_gateway_failure(&err) => {}
+                Err(err) => return Err(err),
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(Error::Timeout(format!(
+                    "{} is not final after {:?}",
+                    id, timeout
+                )));
+            }
+            tokio::time::sleep(self.poll_interval().min(remaining)).await;
+        }
+    }
+
+    /// When enabled, a transaction rejected because its anchor is unknown or too old gets a
+    /// fresh anchor, is re-signed and posted again. The returned id is the re-signed one.
+    pub fn set_re_anchor(&mut self, re_anchor: bool) {
+        self.re_anchor = re_anchor;
+    }
+
+    /// When enabled, files uploaded with [`Arweave::upload_file_from_path`] get an `IPFS-Add`
+    /// tag holding their CID, so they can be found by CID on both networks. Only payloads
+    /// fitting in one IPFS block get the tag, see [`Cid::raw`]; larger ones are uploaded
+    /// without it.
+    pub fn set_ipfs_tags(&mut self, ipfs_tags: bool) {
+        self.ipfs_tags = ipfs_tags;
+    }
+
+    /// When set, files uploaded with [`Arweave::upload_file_from_path`] get a `File-Hash` tag
+    /// holding their digest with `algorithm`, checked by [`Arweave::verify_digest`].
+    pub fn set_digest_tags(&mut self, algorithm: Option<DigestAlgorithm>) {
+        self.digest_tags = algorithm;
+    }
+
+    /// When enabled, [`Arweave::upload_file_from_path`] first looks for a transaction of the
+    /// wallet with the same `File-Hash` tag and returns its id, with a zero reward, instead of
+    /// paying for the data again. Uploads are tagged with their SHA-256 digest unless
+    /// [`Arweave::set_digest_tags`] picked another algorithm. Costs a GraphQL query per upload.
+    pub fn set_dedup(&mut self, dedup: bool) {
+        self.dedup = dedup;
+    }
+
+    /// When enabled, posting a transaction whose id the gateway already knows, pending or
+    /// mined, succeeds without submitting it again, so retried jobs are idempotent. Chunks
+    /// are skipped too when the gateway already serves the whole data. Costs a `HEAD`
+    /// request per post.
+    pub fn set_idempotent(&mut self, idempotent: bool) {
+        self.idempotent = idempotent;
+    }
+
+    /// Whether idempotent posting is enabled and the gateway already knows `transaction`.
+    async fn already_posted(&self, transaction: &Tx) -> Result<bool, Error> {
+        if !self.idempotent || self.dry_run || transaction.id.is_empty() {
+            return Ok(false);
+        }
+        self.gateway.tx_exists(&transaction.id).await
+    }
+
+    /// When set, transactions created from now on get a `Sequence` tag holding the next
+    /// number of `sequence`, unless their tags already carry one. See
+    /// [`Arweave::sequence_gaps`].
+    pub fn set_sequence(&mut self, sequence: Option<Arc<SequenceManager>>) {
+        self.sequence = sequence;
+    }
+
+    /// Splits the data of transactions created from now on as `config` says, for gateways
+    /// rejecting some final chunks the default rebalancing produces.
+    pub fn set_chunking(&mut self, config: ChunkingConfig) {
+        self.chunking = config;
+    }
+
+    /// Posts uploads of [`Arweave::upload_file_from_path`] through `backend` instead of the
+    /// base layer, e.g. an [`IrysClient`] or a [`TurboClient`].
+    pub fn set_upload_backend(&mut self, backend: Arc<dyn UploadBackend>) {
+        self.backend = backend;
+    }
+
+    /// Routes signing to the signer `resolver` picks for the tenant of the enclosing
+    /// [`Arweave::with_tenant`] scope, so one client can upload for many wallets. Outside of
+    /// a scope, `signer` is used.
+    pub fn set_signer_resolver(&mut self, resolver: SignerResolver) {
+        self.signer_resolver = Some(resolver);
+    }
+
+    /// Runs `f`, creating and signing its transactions with the key of `context`'s tenant.
+    pub async fn with_tenant<F: Future>(&self, context: SignerContext, f: F) -> F::Output {
+        context.scope(f).await
+    }
+
+    /// Signer of the current tenant, or `signer` outside of a [`Arweave::with_tenant`] scope.
+    pub fn current_signer(&self) -> Result<ArweaveSigner, Error> {
+        match (&self.signer_resolver, SignerContext::current()) {
+            (Some(resolver), Some(context)) => resolver(&context).ok_or_else(|| {
+                Error::SigningError(format!("no signer for tenant {}", context.tenant))
+            }),
+            _ => Ok(self.signer.clone()),
+        }
+    }
+
     pub async fn create_transaction(
         &self,
         target: Base64,
@@ -83,25 +615,135 @@ impl Arweave {
         fee: u64,
         auto_content_tag: bool,
     ) -> Result<Tx, Error> {
-        let last_tx = self.get_last_tx().await;
-        Tx::new(
-            self.signer.get_provider(),
-            target,
+        let last_tx = self.get_last_tx().await?;
+        let mut other_tags = other_tags;
+        if let Some(sequence) = &self.sequence {
+            if !other_tags.iter().any(|t| t.name.0 == SEQUENCE.as_bytes()) {
+                other_tags.push(Tag::<Base64>::sequence(sequence.next().await?));
+            }
+        }
+        // Chunking and hashing large payloads takes a while; keep it off the executor.
+        let signer = self.current_signer()?;
+        let chunking = self.chunking;
+        tokio::task::spawn_blocking(move || {
+            let tx = Tx::new(
+                signer.get_provider(),
+                target,
+                data,
+                quantity,
+                fee,
+                last_tx,
+                other_tags,
+                auto_content_tag,
+            )?;
+            if chunking == ChunkingConfig::default() {
+                Ok(tx)
+            } else {
+                tx.with_chunking(&chunking)
+            }
+        })
+        .await
+        .map_err(|e| Error::TaskError(e.to_string()))?
+    }
+
+    /// Creates an unsigned transaction paying the currently quoted fee, wrapped for handoff
+    /// to a signing service.
+    pub async fn prepare_transaction(
+        &self,
+        target: Base64,
+        other_tags: Vec<Tag<Base64>>,
+        data: Vec<u8>,
+        quantity: u128,
+        auto_content_tag: bool,
+    ) -> Result<PreparedTx, Error> {
+        let fee = match target.is_empty() {
+            true => self.get_fee_by_size(data.len() as u64, None).await?,
+            false => {
+                self.get_fee_by_size(data.len() as u64, Some(&target))
+                    .await?
+            }
+        };
+        let transaction = self
+            .create_transaction(target, other_tags, data, quantity, fee, auto_content_tag)
+            .await?;
+        PreparedTx::new(transaction)
+    }
+
+    /// Creates a transaction of `template` carrying `data`, with `extra_tags` after the
+    /// template's tags.
+    pub async fn create_from_template(
+        &self,
+        template: &TxTemplate,
+        data: Vec<u8>,
+        extra_tags: Vec<Tag<Base64>>,
+        fee: u64,
+    ) -> Result<Tx, Error> {
+        let quantity = template.quantity(&data)?;
+        self.create_transaction(
+            template.target().clone(),
+            template.tags_with(extra_tags),
             data,
             quantity,
             fee,
-            last_tx,
-            other_tags,
-            auto_content_tag,
+            template.auto_content_tag(),
         )
+        .await
+    }
+
+    /// Creates a transaction carrying an ANS-104 bundle. The `Bundle-Format` and `Bundle-Version`
+    /// tags are set automatically, replacing any supplied in `other_tags`.
+    pub async fn create_bundle_transaction(
+        &self,
+        bundle: Vec<u8>,
+        other_tags: Vec<Tag<Base64>>,
+        fee: u64,
+    ) -> Result<Tx, Error> {
+        let mut tags = Tx::bundle_tags();
+        tags.extend(other_tags.into_iter().filter(|t| {
+            t.name.0 != BUNDLE_FORMAT.as_bytes() && t.name.0 != BUNDLE_VERSION.as_bytes()
+        }));
+        self.create_transaction(Base64::empty(), tags, bundle, 0, fee, false)
+            .await
+    }
+
+    /// Posts a signed bundle transaction, rejecting it if its bundle tags are missing or wrong,
+    /// since such bundles are invisible to indexers.
+    pub async fn post_bundle_transaction(
+        &self,
+        signed_transaction: Tx,
+    ) -> Result<(String, u64), Error> {
+        signed_transaction.validate_bundle_tags()?;
+        self.post_signed_transaction(signed_transaction).await
     }
 
     pub fn sign_transaction(&self, transaction: Tx) -> Result<Tx, Error> {
-        self.signer.sign_transaction(transaction)
+        if let Some(budget) = &self.budget {
+            budget.check(transaction.cost()?)?;
+        }
+        self.current_signer()?.sign_transaction(transaction)
+    }
+
+    /// Like [`Arweave::sign_transaction`], but signs on tokio's blocking thread pool.
+    pub async fn sign_transaction_async(&self, transaction: Tx) -> Result<Tx, Error> {
+        if let Some(budget) = &self.budget {
+            budget.check(transaction.cost()?)?;
+        }
+        self.current_signer()?
+            .sign_transaction_async(transaction)
+            .await
+    }
+
+    pub fn sign(&self, message: &[u8]) -> Result<Vec<u8>, Error> {
+        Ok(self.current_signer()?.sign(message).0.to_vec())
     }
 
-    pub fn sign(&self, message: &[u8]) -> Vec<u8> {
-        self.signer.sign(message).0
+    /// See [`ArweaveSigner::sign_message`].
+    pub fn sign_message(&self, domain: &str, message: &[u8]) -> Result<Vec<u8>, Error> {
+        Ok(self
+            .current_signer()?
+            .sign_message(domain, message)
+            .0
+            .to_vec())
     }
 
     pub fn verify_transaction(&self, transaction: &Tx) -> Result<(), Error> {
@@ -112,142 +754,3163 @@ impl Arweave {
         ArweaveSigner::verify(pub_key, message, signature)
     }
 
+    pub fn verify_message(
+        pub_key: &[u8],
+        domain: &str,
+        message: &[u8],
+        signature: &[u8],
+    ) -> Result<(), Error> {
+        ArweaveSigner::verify_message(pub_key, domain, message, signature)
+    }
+
     pub async fn post_transaction(&self, signed_transaction: &Tx) -> Result<(String, u64), Error> {
-        self.tx_client
-            .post_transaction(signed_transaction)
-            .await
-            .map(|(id, reward)| (id.to_string(), reward))
+        self.audited(
+            signed_transaction,
+            self.submit_transaction(signed_transaction),
+        )
+        .await
     }
 
-    async fn get_last_tx(&self) -> Base64 {
-        self.tx_client.get_last_tx().await
+    /// Runs `post`, posting `signed_transaction`, and audits it.
+    async fn audited(
+        &self,
+        signed_transaction: &Tx,
+        post: impl Future<Output = Result<(String, u64), Error>>,
+    ) -> Result<(String, u64), Error> {
+        let started_at = SystemTime::now();
+        let started = Instant::now();
+        let (res, trace) = audit::traced(post).await;
+
+        if let Some(audit) = &self.audit {
+            let record = AuditRecord {
+                id: signed_transaction.id.to_string(),
+                posted_id: res.as_ref().ok().map(|(id, _)| id.clone()),
+                anchor: trace
+                    .anchor
+                    .unwrap_or_else(|| signed_transaction.last_tx.clone())
+                    .to_string(),
+                fee_quoted: audit.take_quote(signed_transaction.data_size),
+                fee_paid: signed_transaction.reward,
+                data_size: signed_transaction.data_size,
+                gateway: trace.gateway,
+                started_at: started_at
+                    .duration_since(UNIX_EPOCH)
+                    .map(|t| t.as_millis())
+                    .unwrap_or_default(),
+                duration_ms: started.elapsed().as_millis(),
+                retries: trace.retries,
+                dry_run: self.dry_run,
+                error: res.as_ref().err().map(ToString::to_string),
+            };
+            // The transaction may already be accepted: failing now would invite a retry
+            // paying twice, so a record that can't be exported is only kept in memory.
+            let _ = audit.record(record);
+        }
+        res
     }
 
-    pub async fn get_fee(&self, target: Base64, data: Vec<u8>) -> Result<u64, Error> {
-        self.tx_client.get_fee(target, data).await
+    async fn submit_transaction(&self, signed_transaction: &Tx) -> Result<(String, u64), Error> {
+        let cost = signed_transaction.cost()?;
+        if self.dry_run {
+            if signed_transaction.id.is_empty() {
+                return Err(Error::UnsignedTransaction);
+            }
+            if let Some(budget) = &self.budget {
+                budget.check(cost)?;
+            }
+            return Ok((signed_transaction.id.to_string(), signed_transaction.reward));
+        }
+        if self.already_posted(signed_transaction).await? {
+            return Ok((signed_transaction.id.to_string(), signed_transaction.reward));
+        }
+
+        if let Some(budget) = &self.budget {
+            budget.record(cost)?;
+        }
+
+        let res = self
+            .post_transaction_with_re_anchor(signed_transaction)
+            .await;
+
+        if let (Err(_), Some(budget)) = (&res, &self.budget) {
+            budget.release(cost);
+        }
+        res
     }
 
-    pub async fn get_tx(&self, id: Base64) -> Result<(StatusCode, Option<Tx>), Error> {
-        self.tx_client.get_tx(id).await
+    /// Posts data item `item` with `upload`, as [`Arweave::post_transaction`] posts
+    /// transactions: nothing is sent in dry-run mode, the price returned by `quote` is charged
+    /// to the budget, and the attempt is audited under the URL of `service`. `quote` is only
+    /// awaited when a budget or an audit log is set. Returns `None` in dry-run mode.
+    pub(crate) async fn post_data_item<T>(
+        &self,
+        item: &DataItem,
+        service: &url::Url,
+        quote: impl Future<Output = Result<u128, Error>>,
+        upload: impl Future<Output = Result<T, Error>>,
+    ) -> Result<Option<T>, Error> {
+        let started_at = SystemTime::now();
+        let started = Instant::now();
+        let data_size = item.data.len() as u64;
+        let cost = if self.budget.is_some() || self.audit.is_some() {
+            quote.await?
+        } else {
+            0
+        };
+        let res = async {
+            if self.dry_run {
+                if let Some(budget) = &self.budget {
+                    budget.check(cost)?;
+                }
+                return Ok(None);
+            }
+            if let Some(budget) = &self.budget {
+                budget.record(cost)?;
+            }
+            let res = upload.await;
+            if let (Err(_), Some(budget)) = (&res, &self.budget) {
+                budget.release(cost);
+            }
+            res.map(Some)
+        }
+        .await;
+
+        if let Some(audit) = &self.audit {
+            let record = AuditRecord {
+                id: item.id().to_string(),
+                posted_id: match &res {
+                    Ok(Some(_)) => Some(item.id().to_string()),
+                    _ => None,
+                },
+                anchor: item.anchor.to_string(),
+                fee_quoted: audit.take_quote(data_size),
+                fee_paid: u64::try_from(cost).unwrap_or(u64::MAX),
+                data_size,
+                gateway: res
+                    .as_ref()
+                    .is_ok_and(Option::is_some)
+                    .then(|| service.to_string()),
+                started_at: started_at
+                    .duration_since(UNIX_EPOCH)
+                    .map(|t| t.as_millis())
+                    .unwrap_or_default(),
+                duration_ms: started.elapsed().as_millis(),
+                retries: 0,
+                dry_run: self.dry_run,
+                error: res.as_ref().err().map(ToString::to_string),
+            };
+            let _ = audit.record(record);
+        }
+        res
     }
 
-    pub async fn get_tx_status(&self, id: Base64) -> Result<(StatusCode, Option<TxStatus>), Error> {
-        self.tx_client.get_tx_status(id).await
+    async fn post_transaction_with_re_anchor(
+        &self,
+        signed_transaction: &Tx,
+    ) -> Result<(String, u64), Error> {
+        let mut res = self.gateway.post_tx(signed_transaction).await;
+
+        let mut re_anchors = 0;
+        while self.re_anchor
+            && re_anchors < self.network.anchor_retries
+            && matches!(res, Err(Error::InvalidAnchor))
+        {
+            let mut transaction = signed_transaction.clone();
+            self.metadata.invalidate(Metadata::Anchor);
+            transaction.last_tx = self.get_last_tx().await?;
+            audit::note_anchor(&transaction.last_tx);
+            let transaction = self.current_signer()?.sign_transaction(transaction)?;
+            audit::note_retry();
+            res = self.gateway.post_tx(&transaction).await;
+            re_anchors += 1;
+        }
+
+        res.map(|(id, reward)| (id.to_string(), reward))
     }
 
-    pub fn get_pub_key(&self) -> String {
-        self.signer.keypair_modulus().to_string()
+    async fn get_last_tx(&self) -> Result<Base64, Error> {
+        self.gateway.get_anchor().await
     }
 
-    pub fn get_wallet_address(&self) -> String {
-        self.signer.wallet_address().to_string()
+    pub async fn get_fee(&self, target: Base64, data: Vec<u8>) -> Result<u64, Error> {
+        self.get_fee_by_size(data.len() as u64, Some(&target)).await
     }
 
-    pub async fn upload_file_from_path(
+    /// Fee for storing `data_size` bytes. Pass the transfer `target`, if any: sending to a
+    /// wallet the network has never seen costs an extra new wallet fee.
+    pub async fn get_fee_by_size(
         &self,
-        file_path: PathBuf,
-        additional_tags: Vec<Tag<Base64>>,
-        fee: u64,
-    ) -> Result<(String, u64), Error> {
-        let mut auto_content_tag = true;
-        let mut additional_tags = additional_tags;
+        data_size: u64,
+        target: Option<&Base64>,
+    ) -> Result<u64, Error> {
+        let fee = self.gateway.get_price(data_size, target).await?;
+        if let Some(audit) = &self.audit {
+            audit.quoted(data_size, fee);
+        }
+        Ok(fee)
+    }
 
-        if let Some(content_type) = mime_guess::from_path(file_path.clone()).first() {
-            auto_content_tag = false;
-            let content_tag: Tag<Base64> =
-                Tag::from_utf8_strs("Content-Type", content_type.as_ref())?;
-            additional_tags.push(content_tag);
+    /// Quotes the fee of each of `sizes` `samples` times, `interval` apart, bypassing the
+    /// price cache. The result exposes the trend of each size.
+    pub async fn get_price_history(
+        &self,
+        sizes: &[u64],
+        samples: usize,
+        interval: Duration,
+    ) -> Result<PriceHistory, Error> {
+        let mut history = PriceHistory::new();
+        for i in 0..samples {
+            if i > 0 {
+                tokio::time::sleep(interval).await;
+            }
+            self.metadata.invalidate(Metadata::Price);
+            let timestamp = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|t| t.as_secs())
+                .unwrap_or_default();
+            for &data_size in sizes {
+                let fee = self.gateway.get_price(data_size, None).await?;
+                history.record(PriceSample {
+                    timestamp,
+                    data_size,
+                    fee,
+                });
+            }
         }
+        Ok(history)
+    }
 
-        let data = fs::read(file_path).expect("Could not read file");
-        let transaction = self
-            .create_transaction(
-                Base64(b"".to_vec()),
-                additional_tags,
-                data,
-                0,
-                fee,
-                auto_content_tag,
-            )
-            .await
-            .expect("Could not create transaction");
-        let signed_transaction = self
-            .sign_transaction(transaction)
-            .expect("Could not sign tx");
-        let (id, reward) = if signed_transaction.data.0.len() > MAX_TX_DATA as usize {
-            self.post_transaction_chunks(signed_transaction, 100)
-                .await
-                .expect("Could not post transaction chunks")
-        } else {
-            self.post_transaction(&signed_transaction)
-                .await
-                .expect("Could not post transaction")
-        };
+    /// Fee for a plain AR transfer to `target`, without data.
+    pub async fn get_fee_for_transfer(&self, target: &Base64) -> Result<u64, Error> {
+        self.get_fee_by_size(0, Some(target)).await
+    }
 
-        Ok((id, reward))
+    pub async fn get_tx(&self, id: Base64) -> Result<(StatusCode, Option<Tx>), Error> {
+        self.gateway.get_tx(&id).await
     }
 
-    async fn post_transaction_chunks(
+    /// Position of the data of transaction `id` in the weave.
+    pub async fn get_tx_offset(&self, id: Base64) -> Result<TxOffset, Error> {
+        self.gateway.get_tx_offset(&id).await
+    }
+
+    /// Absolute weave offset of byte `relative` of the data of transaction `id`, the offset
+    /// to request from `/chunk` to read that byte.
+    pub async fn absolute_offset(&self, id: Base64, relative: u64) -> Result<u64, Error> {
+        self.get_tx_offset(id).await?.to_absolute(relative)
+    }
+
+    /// Offset within the data of transaction `id` of absolute weave offset `absolute`.
+    pub async fn relative_offset(&self, id: Base64, absolute: u64) -> Result<u64, Error> {
+        self.get_tx_offset(id).await?.to_relative(absolute)
+    }
+
+    /// Fetches the chunk at absolute weave offset `absolute_offset` and validates it: its
+    /// `tx_path` against `expected_tx_root`, the tx root of the block whose data spans
+    /// `block_range` (see [`BlockInfo::weave_range`]), then its `data_path` against the data
+    /// root that path proves, and the chunk itself against the leaf of the data path.
+    pub async fn get_chunk_validated(
         &self,
-        signed_transaction: Tx,
-        chunks_buffer: usize,
-    ) -> Result<(String, u64), Error> {
-        if signed_transaction.id.0.is_empty() {
-            return Err(error::Error::UnsignedTransaction);
+        absolute_offset: u64,
+        expected_tx_root: &Base64,
+        block_range: Range<u64>,
+    ) -> Result<ValidatedChunk, Error> {
+        if !block_range.contains(&absolute_offset) {
+            return Err(Error::OffsetOutOfRange(format!(
+                "weave offset {} is outside of block {:?}",
+                absolute_offset, block_range
+            )));
         }
+        let tx_root = expected_tx_root.0[..]
+            .try_into()
+            .map_err(|_| Error::InvalidProof)?;
+        let chunk = self.gateway.get_chunk(absolute_offset).await?;
 
-        let transaction_with_no_data = signed_transaction.clone_with_no_data()?;
-        let (id, reward) = self.post_transaction(&transaction_with_no_data).await?;
+        let block_offset = absolute_offset - block_range.start;
+        let tx = validate_path(
+            tx_root,
+            block_offset,
+            block_range.end - block_range.start,
+            &chunk.tx_path,
+        )?;
+        let data = validate_path(
+            tx.hash,
+            block_offset - tx.left_bound,
+            tx.right_bound - tx.left_bound,
+            &chunk.data_path,
+        )?;
+        if sha256(&chunk.chunk) != data.hash
+            || chunk.chunk.len() as u64 != data.right_bound - data.left_bound
+        {
+            return Err(Error::InvalidProof);
+        }
 
-        let results: Vec<Result<usize, Error>> =
-            Self::upload_transaction_chunks_stream(self, signed_transaction, chunks_buffer)
-                .collect()
-                .await;
+        let tx_start = block_range.start + tx.left_bound;
+        Ok(ValidatedChunk {
+            chunk: chunk.chunk.0.to_vec(),
+            data_root: Base64::from(tx.hash.to_vec()),
+            tx_range: tx_start..block_range.start + tx.right_bound,
+            chunk_range: tx_start + data.left_bound..tx_start + data.right_bound,
+        })
+    }
 
-        results.into_iter().collect::<Result<Vec<usize>, Error>>()?;
+    /// Scanner for deposits to `addresses` from `cursor` on, crediting them according to the
+    /// finality policy of the client.
+    pub fn deposit_scanner(&self, addresses: Vec<Base64>, cursor: ScanCursor) -> DepositScanner {
+        DepositScanner::new(addresses, self.finality, cursor)
+    }
 
-        Ok((id, reward))
+    /// Runs `scanner` up to the current height of the network. See [`DepositScanner::scan`].
+    pub async fn scan_deposits(
+        &self,
+        scanner: &mut DepositScanner,
+    ) -> Result<Vec<DepositEvent>, Error> {
+        let tip = self.network_info().await?.height as u64;
+        scanner.scan(self.gateway.as_ref(), tip).await
     }
 
-    fn upload_transaction_chunks_stream(
-        arweave: &Arweave,
-        signed_transaction: Tx,
-        buffer: usize,
-    ) -> impl Stream<Item = Result<usize, Error>> + '_ {
-        let client = Client::new();
-        stream::iter(0..signed_transaction.chunks.len())
-            .map(move |i| {
-                let chunk = signed_transaction.get_chunk(i).unwrap();
-                arweave
-                    .uploader
-                    .post_chunk_with_retries(chunk, client.clone())
+    /// Streams the blocks at `heights`, in order.
+    pub fn iter_blocks(
+        &self,
+        heights: Range<u64>,
+    ) -> impl Stream<Item = Result<BlockInfo, Error>> + '_ {
+        stream::iter(heights)
+            .map(move |height| self.gateway.get_block(height))
+            .buffered(SCAN_CONCURRENCY)
+    }
+
+    /// Streams the transactions mined in the blocks at `heights`, in block order, without
+    /// their data.
+    pub fn iter_txs_in_blocks(
+        &self,
+        heights: Range<u64>,
+    ) -> impl Stream<Item = Result<Tx, Error>> + '_ {
+        self.iter_blocks(heights)
+            .map_ok(|block| stream::iter(block.txs).map(Ok))
+            .try_flatten()
+            .map(move |id| async move {
+                let id = id?;
+                match self.gateway.get_tx(&id).await? {
+                    (_, Some(tx)) => Ok(tx),
+                    (status, None) => Err(Error::TransactionInfoError(format!(
+                        "mined transaction {} is {}",
+                        id, status
+                    ))),
+                }
             })
-            .buffer_unordered(buffer)
+            .buffered(SCAN_CONCURRENCY)
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use std::{fs::File, io::Read, path::PathBuf, str::FromStr};
+    /// Height of the block mined nearest to `timestamp`, in seconds since the epoch.
+    pub async fn height_at(&self, timestamp: u64) -> Result<u64, Error> {
+        let top = self.network_info().await?.height as u64;
+        blocks::height_at_timestamp(self.gateway.as_ref(), timestamp, 0..top + 1).await
+    }
 
-    use pretend::Url;
+    /// Heights of the blocks mined from `from` to before `to`, in seconds since the epoch,
+    /// e.g. to pass to [`Arweave::iter_txs_in_blocks`].
+    pub async fn heights_between(&self, from: u64, to: u64) -> Result<Range<u64>, Error> {
+        let heights = 0..self.network_info().await?.height as u64 + 1;
+        let start =
+            blocks::first_height_since(self.gateway.as_ref(), from, heights.clone()).await?;
+        let end = blocks::first_height_since(self.gateway.as_ref(), to, start..heights.end).await?;
+        Ok(start..end)
+    }
 
-    use crate::{error::Error, transaction::Tx, Arweave, ARWEAVE_BASE_URL};
+    /// Balance of wallet `address` in winstons.
+    pub async fn get_balance(&self, address: &Base64) -> Result<u128, Error> {
+        self.gateway.get_balance(address).await
+    }
 
-    #[test]
-    pub fn should_parse_and_verify_valid_tx() -> Result<(), Error> {
-        let mut file = File::open("res/sample_tx.json").unwrap();
-        let mut data = String::new();
-        file.read_to_string(&mut data).unwrap();
-        let tx = Tx::from_str(&data).unwrap();
+    /// Whether transaction `id` is known to the gateway, pending or mined. Uses a `HEAD`
+    /// request, so nothing is downloaded.
+    pub async fn tx_exists(&self, id: Base64) -> Result<bool, Error> {
+        self.gateway.tx_exists(&id).await
+    }
 
-        let path = PathBuf::from_str("res/test_wallet.json").unwrap();
-        let arweave =
-            Arweave::from_keypair_path(path, Url::from_str(ARWEAVE_BASE_URL).unwrap()).unwrap();
+    /// Size of the data of transaction `id` if the gateway can serve it, checked with a
+    /// `HEAD` request.
+    pub async fn data_available(&self, id: Base64) -> Result<Option<u64>, Error> {
+        self.gateway.data_available(&id).await
+    }
 
-        match arweave.verify_transaction(&tx) {
-            Ok(_) => Ok(()),
-            Err(_) => Err(Error::InvalidSignature),
+    /// Moves the largest tag values of `tags` into companion transactions, replacing their
+    /// tags with [`Tag::pointer`]s, until the tags take at most `max_size` bytes, e.g.
+    /// [`consts::MAX_TAGS_SIZE`] minus room for the tags added when creating transactions.
+    /// Each companion is signed and posted right away. See [`Arweave::resolve_tag_pointers`].
+    pub async fn offload_large_tags(
+        &self,
+        mut tags: Vec<Tag<Base64>>,
+        max_size: usize,
+    ) -> Result<Vec<Tag<Base64>>, Error> {
+        // Plan the offloads first, so nothing is paid for when the tags can't fit.
+        let pointer =
+            |tag: &Tag<Base64>| Tag::<Base64>::pointer(&tag.name, &Base64::from(vec![0; 32]));
+        let mut by_size: Vec<usize> = (0..tags.len()).collect();
+        by_size.sort_by_key(|&i| std::cmp::Reverse(tags[i].value.0.len()));
+        let mut size = tags.iter().map(Tag::size).sum::<usize>();
+        let mut offloaded = Vec::new();
+        for i in by_size {
+            if size <= max_size {
+                break;
+            }
+            size = size - tags[i].size() + pointer(&tags[i]).size();
+            offloaded.push(i);
+        }
+        if size > max_size {
+            return Err(Error::InvalidTagValue(format!(
+                "tags take {} bytes once offloaded, over {}",
+                size, max_size
+            )));
+        }
+
+        for i in offloaded {
+            let value = tags[i].value.0.to_vec();
+            let fee = self.get_fee_by_size(value.len() as u64, None).await?;
+            let companion = self
+                .create_transaction(
+                    Base64::default(),
+                    vec![Tag::<Base64>::content_type("application/octet-stream")?],
+                    value,
+                    0,
+                    fee,
+                    false,
+                )
+                .await?;
+            let companion = self.sign_transaction(companion)?;
+            self.post_transaction(&companion).await?;
+            tags[i] = Tag::<Base64>::pointer(&tags[i].name, &companion.id);
+        }
+        Ok(tags)
+    }
+
+    /// Replaces the [`Tag::pointer`]s of `tags` by the tags they stand in for, fetching
+    /// their values from the gateway.
+    pub async fn resolve_tag_pointers(
+        &self,
+        tags: Vec<Tag<Base64>>,
+    ) -> Result<Vec<Tag<Base64>>, Error> {
+        let mut resolved = Vec::with_capacity(tags.len());
+        for tag in tags {
+            resolved.push(match tag.pointer_target() {
+                Some((name, id)) => Tag {
+                    name,
+                    value: Base64::from(self.gateway.get_tx_data(&id).await?),
+                },
+                None => tag,
+            });
+        }
+        Ok(resolved)
+    }
+
+    /// Fetches a transaction, its decoded tags and its data. Data embedded in the transaction
+    /// is used as-is, otherwise it is downloaded from the gateway.
+    pub async fn get_tx_with_data(&self, id: Base64) -> Result<TxWithData, Error> {
+        let tx = match self.gateway.get_tx(&id).await? {
+            (_, Some(tx)) => tx,
+            (status, None) => return Err(Error::TransactionInfoError(status.to_string())),
+        };
+
+        let data = if !tx.data.is_empty() || tx.data_size == 0 {
+            tx.data.to_vec()
+        } else {
+            self.gateway.get_tx_data(&id).await?
+        };
+        let tags = tx
+            .tags
+            .iter()
+            .map(|tag| Tag {
+                name: String::from_utf8_lossy(&tag.name.0).into_owned(),
+                value: String::from_utf8_lossy(&tag.value.0).into_owned(),
+            })
+            .collect();
+
+        Ok(TxWithData { tx, tags, data })
+    }
+
+    /// Checks that transaction `tx_id` is a valid transfer of at least `min_quantity` winstons
+    /// to `expected_target` with `min_confirmations`. Fails when the transaction can't be
+    /// fetched or its signature is invalid.
+    pub async fn verify_payment(
+        &self,
+        tx_id: &Base64,
+        expected_target: &Base64,
+        min_quantity: u128,
+        min_confirmations: u64,
+    ) -> Result<PaymentStatus, Error> {
+        let tx = match self.gateway.get_tx(tx_id).await? {
+            (_, Some(tx)) => tx,
+            (StatusCode::ACCEPTED, None) => return Ok(PaymentStatus::Pending),
+            (status, None) => return Err(Error::TransactionInfoError(status.to_string())),
+        };
+        if !tx.id.ct_eq(tx_id) {
+            return Err(Error::TransactionInfoError(format!(
+                "gateway returned transaction {} for {}",
+                tx.id, tx_id
+            )));
+        }
+        self.verify_transaction(&tx)?;
+        let confirmations = match self.gateway.get_tx_status(tx_id).await? {
+            (_, Some(status)) => status.number_of_confirmations,
+            (_, None) => 0,
+        };
+        Ok(PaymentStatus::of(
+            &tx,
+            expected_target,
+            min_quantity,
+            confirmations,
+            min_confirmations,
+        ))
+    }
+
+    /// CID of the `IPFS-Add` tag of transaction `id`, if it has one.
+    pub async fn get_ipfs_cid(&self, id: Base64) -> Result<Option<Cid>, Error> {
+        match self.gateway.get_tx(&id).await? {
+            (_, Some(tx)) => Cid::from_tags(&tx.tags),
+            (status, None) => Err(Error::TransactionInfoError(status.to_string())),
+        }
+    }
+
+    /// Id of a transaction of the wallet whose `File-Hash` tag is `digest`, if any. Only the
+    /// wallet's own transactions are trusted, since anyone can tag data with any digest.
+    pub async fn find_by_digest(&self, digest: &Digest) -> Result<Option<String>, Error> {
+        let query = TransactionQuery::new()
+            .owners(vec![self.current_signer()?.wallet_address().to_string()])
+            .tag(FILE_HASH, &[&digest.to_string()])
+            .first(1);
+        let page = self.query_transactions_page(&query).await?;
+        Ok(page.edges.into_iter().next().map(|edge| edge.node.id))
+    }
+
+    /// Posts `entry` as JSON to the wallet's log `app_name`, tagged with the time and the
+    /// next number of the log's counter. The first append of a process resumes the counter
+    /// after the highest number found through GraphQL, so entries not indexed yet may be
+    /// numbered again; set the counter through [`Arweave::log_counters`] to avoid it.
+    pub async fn append_log<T: Serialize>(
+        &self,
+        app_name: &str,
+        entry: &T,
+    ) -> Result<LogEntry, Error> {
+        let entry = serde_json::to_value(entry).map_err(|e| Error::LogEntryError(e.to_string()))?;
+        let unix_time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("System time before unix epoch")
+            .as_secs();
+        let sequence = match self.log_counters.try_next(app_name) {
+            Some(sequence) => sequence,
+            None => {
+                let first = self.next_log_sequence(app_name).await?;
+                self.log_counters.next_from(app_name, first)
+            }
+        };
+        let tags = log_tags(app_name, sequence, unix_time)?;
+        let (id, _) = self.post_data(entry.to_string().into_bytes(), tags).await?;
+        Ok(LogEntry {
+            id,
+            sequence,
+            unix_time,
+            entry,
+        })
+    }
+
+    /// One past the highest sequence number of the wallet's log `app_name`, as indexed.
+    async fn next_log_sequence(&self, app_name: &str) -> Result<u64, Error> {
+        let query = TransactionQuery::new()
+            .owners(vec![self.current_signer()?.wallet_address().to_string()])
+            .tag(APP_NAME, &[app_name]);
+        let nodes = self.query_all_transactions(&query).await?;
+        Ok(nodes
+            .iter()
+            .filter_map(|node| node.tag(SEQUENCE)?.parse::<u64>().ok())
+            .max()
+            .map_or(0, |last| last + 1))
+    }
+
+    /// Sequence counters of the logs appended to with [`Arweave::append_log`].
+    pub fn log_counters(&self) -> &LogCounters {
+        &self.log_counters
+    }
+
+    /// Entries of log `app_name` written by wallet `owner`, in sequence order. Found through
+    /// GraphQL, so recent entries may be missing until indexed.
+    pub async fn read_log(&self, app_name: &str, owner: &str) -> Result<Vec<LogEntry>, Error> {
+        let query = TransactionQuery::new()
+            .owners(vec![owner.to_string()])
+            .tag(APP_NAME, &[app_name]);
+        let nodes = self.query_all_transactions(&query).await?;
+        let mut entries: Vec<LogEntry> = stream::iter(nodes)
+            .filter(|node| futures::future::ready(node.tag(SEQUENCE).is_some()))
+            .map(|node| async move {
+                let data = self
+                    .gateway
+                    .get_tx_data(&Base64::from_str(&node.id)?)
+                    .await?;
+                LogEntry::from_node(&node, &data)
+            })
+            .buffered(SCAN_CONCURRENCY)
+            .try_collect()
+            .await?;
+        entries.sort_by_key(|entry| (entry.sequence, entry.unix_time));
+        Ok(entries)
+    }
+
+    /// Streams the mined transactions signed by or sent to `address` from `cursor` on,
+    /// polling GraphQL every [`ACTIVITY_POLL_INTERVAL`] seconds. Each item carries the
+    /// cursor to resume from after it. Failed polls are yielded as errors and retried at the
+    /// next poll; the stream never ends.
+    pub fn watch_address<'a>(
+        &'a self,
+        address: &'a str,
+        cursor: ActivityCursor,
+    ) -> impl Stream<Item = Result<AddressActivity, Error>> + 'a {
+        stream::unfold((cursor, true), move |(mut cursor, first)| async move {
+            if !first {
+                tokio::time::sleep(Duration::from_secs(ACTIVITY_POLL_INTERVAL)).await;
+            }
+            let outgoing = TransactionQuery::new()
+                .owners(vec![address.to_string()])
+                .block_min(cursor.height);
+            let incoming = TransactionQuery::new()
+                .recipients(vec![address.to_string()])
+                .block_min(cursor.height);
+            let polled = futures::future::try_join(
+                self.query_all_transactions(&outgoing),
+                self.query_all_transactions(&incoming),
+            )
+            .await;
+            let items = match polled {
+                Ok((mut nodes, incoming)) => {
+                    nodes.extend(incoming);
+                    cursor.advance(address, nodes).into_iter().map(Ok).collect()
+                }
+                Err(err) => vec![Err(err)],
+            };
+            Some((stream::iter(items), (cursor, false)))
+        })
+        .flatten()
+    }
+
+    /// Numbers missing from the `Sequence` tags of the transactions matching `query`, e.g.
+    /// those of the wallet stamped through [`Arweave::set_sequence`]. Transactions still
+    /// unindexed show up as gaps too.
+    pub async fn sequence_gaps(&self, query: &TransactionQuery) -> Result<Vec<Range<u64>>, Error> {
+        let nodes = self.query_all_transactions(query).await?;
+        Ok(find_gaps(
+            nodes
+                .iter()
+                .filter_map(|node| node.tag(SEQUENCE)?.parse().ok()),
+        ))
+    }
+
+    /// Streams the data of transaction `id` through a hasher and checks it against
+    /// `expected`, e.g. the digest of its `File-Hash` tag.
+    pub async fn verify_digest(&self, id: Base64, expected: &Digest) -> Result<(), Error> {
+        let mut hasher = expected.algorithm.hasher();
+        let mut data = self.stream_tx_data(id.clone());
+        while let Some(bytes) = data.try_next().await? {
+            hasher.update(&bytes);
+        }
+        let actual = hasher.finalize();
+        if actual != *expected {
+            return Err(Error::DigestMismatch(format!(
+                "{} hashes to {}, expected {}",
+                id, actual, expected
+            )));
+        }
+        Ok(())
+    }
+
+    /// Streams the data of transaction `id`, so it can be piped into files, hashers or
+    /// HTTP responses without being buffered in memory.
+    pub fn stream_tx_data(&self, id: Base64) -> impl Stream<Item = Result<Bytes, Error>> + '_ {
+        self.gateway.stream_tx_data(id)
+    }
+
+    pub async fn get_tx_status(&self, id: Base64) -> Result<(StatusCode, Option<TxStatus>), Error> {
+        self.gateway.get_tx_status(&id).await
+    }
+
+    /// Runs a `transactions` GraphQL query against the gateway and returns the raw `data` object.
+    pub async fn query_transactions(
+        &self,
+        query: &TransactionQuery,
+    ) -> Result<serde_json::Value, Error> {
+        let _permit = self.acquire_in_flight().await?;
+        self.graphql()?.transactions(query).await
+    }
+
+    /// Like [`Arweave::query_transactions`], parsed into typed models.
+    pub async fn query_transactions_page(
+        &self,
+        query: &TransactionQuery,
+    ) -> Result<TransactionsPage, Error> {
+        let _permit = self.acquire_in_flight().await?;
+        self.graphql()?.transactions_page(query).await
+    }
+
+    /// Fetches every page of `query`, splitting filters too large for a single query. See
+    /// [`GraphqlClient::transactions_all`].
+    pub async fn query_all_transactions(
+        &self,
+        query: &TransactionQuery,
+    ) -> Result<Vec<TxNode>, Error> {
+        let _permit = self.acquire_in_flight().await?;
+        self.graphql()?.transactions_all(query).await
+    }
+
+    fn graphql(&self) -> Result<GraphqlClient, Error> {
+        Ok(
+            GraphqlClient::with_client(self.base_url.clone(), self.http_client()?)
+                .with_middleware(self.middleware.clone()),
+        )
+    }
+
+    /// Verifies a bundler receipt and checks whether its data item landed on chain before the
+    /// receipt's deadline height.
+    pub async fn confirm_receipt(&self, receipt: &Receipt) -> Result<ReceiptStatus, Error> {
+        receipt.verify()?;
+
+        let query = TransactionQuery::new().ids(vec![receipt.id.clone()]);
+        let page = self.query_transactions_page(&query).await?;
+        let included_at = page
+            .edges
+            .first()
+            .and_then(|edge| edge.node.block.as_ref())
+            .map(|block| block.height);
+        let current_height = self.network_info().await?.height as u64;
+
+        Ok(receipt.status(included_at, current_height))
+    }
+
+    /// Sends `amount` winstons to the bundler's deposit address and has the bundler credit
+    /// them to the wallet's balance, e.g. to pay for uploads of users. Returns the id of the
+    /// transfer.
+    pub async fn fund_bundler(&self, irys: &IrysClient, amount: u128) -> Result<String, Error> {
+        let target = irys.deposit_address().await?;
+        let fee = self.get_fee_by_size(0, Some(&target)).await?;
+        let transaction = self
+            .create_transaction(target, vec![], vec![], amount, fee, false)
+            .await?;
+        let signed_transaction = self.sign_transaction_async(transaction).await?;
+        let (id, _) = self.post_signed_transaction(signed_transaction).await?;
+        if !self.dry_run {
+            irys.notify_funding(&id).await?;
+        }
+        Ok(id)
+    }
+
+    /// Balance of the wallet on the bundler, in winstons.
+    pub async fn bundler_balance(&self, irys: &IrysClient) -> Result<u128, Error> {
+        let address = self.current_signer()?.wallet_address().to_string();
+        irys.balance(&address).await
+    }
+
+    /// Withdraws `amount` winstons from the wallet's bundler balance back to the wallet.
+    pub async fn withdraw_from_bundler(
+        &self,
+        irys: &IrysClient,
+        amount: u128,
+    ) -> Result<Withdrawal, Error> {
+        irys.withdraw(&self.current_signer()?, amount).await
+    }
+
+    /// Signs `data` as a data item owned by `user` and uploads it to the bundler, charged to
+    /// the wallet's balance so users don't need AR. See [`Arweave::fund_bundler`]. Returns
+    /// `None` in dry-run mode, when nothing is uploaded.
+    pub async fn upload_sponsored(
+        &self,
+        irys: &IrysClient,
+        user: &ArweaveSigner,
+        data: Vec<u8>,
+        tags: Vec<Tag<Base64>>,
+    ) -> Result<Option<Receipt>, Error> {
+        let user = user.clone();
+        let item = tokio::task::spawn_blocking(move || DataItem::new(data, tags).sign(&user))
+            .await
+            .map_err(|e| Error::TaskError(e.to_string()))??;
+        let sponsor = self.current_signer()?.wallet_address().to_string();
+        self.post_data_item(
+            &item,
+            irys.url(),
+            irys.price(item.to_bytes()?.len() as u64),
+            irys.upload(&item, Some(&sponsor)),
+        )
+        .await
+    }
+
+    /// Signs `data` as a data item and uploads it through Turbo, paid with the wallet's
+    /// credits. Returns `None` in dry-run mode, when nothing is uploaded.
+    pub async fn upload_to_turbo(
+        &self,
+        turbo: &TurboClient,
+        data: Vec<u8>,
+        tags: Vec<Tag<Base64>>,
+    ) -> Result<Option<TurboReceipt>, Error> {
+        let signer = self.current_signer()?;
+        let item = tokio::task::spawn_blocking(move || DataItem::new(data, tags).sign(&signer))
+            .await
+            .map_err(|e| Error::TaskError(e.to_string()))??;
+        self.post_data_item(
+            &item,
+            turbo.upload_url(),
+            turbo.price(item.to_bytes()?.len() as u64),
+            turbo.upload(&item),
+        )
+        .await
+    }
+
+    pub fn get_pub_key(&self) -> Result<String, Error> {
+        Ok(self.current_signer()?.keypair_modulus().to_string())
+    }
+
+    pub fn get_wallet_address(&self) -> Result<String, Error> {
+        Ok(self.current_signer()?.wallet_address().to_string())
+    }
+
+    pub async fn upload_file_from_path(
+        &self,
+        file_path: PathBuf,
+        additional_tags: Vec<Tag<Base64>>,
+        fee: u64,
+    ) -> Result<(String, u64), Error> {
+        let res = self
+            .upload_file(file_path.clone(), additional_tags, fee)
+            .await;
+        if let Err(err) = &res {
+            self.emit(UploadEvent::Failed {
+                path: file_path,
+                error: err.to_string(),
+            });
+        }
+        res
+    }
+
+    /// Uploads every file of the tar, gzipped tar or zip archive at `path` as its own
+    /// transaction, or data item depending on the upload backend, tagged with
+    /// `additional_tags` and the content type of its name. Files are read one at a time and
+    /// never extracted to disk.
+    ///
+    /// Returns a manifest mapping the paths inside the archive to their ids, with
+    /// `index.html` as index when the archive has one; post it with
+    /// [`Arweave::post_manifest`]. Stops at the first file that fails.
+    #[cfg(feature = "archive")]
+    pub async fn upload_archive(
+        &self,
+        path: &Path,
+        additional_tags: Vec<Tag<Base64>>,
+    ) -> Result<Manifest, Error> {
+        let format = ArchiveFormat::from_path(path).ok_or_else(|| {
+            Error::ArchiveError(format!("unknown archive format {}", path.display()))
+        })?;
+        let mut entries = archive::read_entries(path.to_path_buf(), format);
+        let mut manifest = Manifest::new();
+        while let Some(entry) = entries.recv().await {
+            let entry = entry?;
+            let entry_path = path.join(&entry.path);
+            let (tags, auto_content_tag) =
+                Self::file_tags(Path::new(&entry.path), additional_tags.clone())?;
+            let fee = self.get_fee_by_size(entry.data.len() as u64, None).await?;
+            let res = self
+                .upload_bytes(entry_path.clone(), entry.data, tags, auto_content_tag, fee)
+                .await;
+            match res {
+                Ok((id, _)) => manifest.insert(&entry.path, &id),
+                Err(err) => {
+                    self.emit(UploadEvent::Failed {
+                        path: entry_path,
+                        error: err.to_string(),
+                    });
+                    return Err(err);
+                }
+            }
+        }
+        if manifest.get("index.html").is_some() {
+            manifest.set_index("index.html");
+        }
+        Ok(manifest)
+    }
+
+    /// Downloads `url` and uploads its body, tagged with `additional_tags` and the content type
+    /// the server declared, or else the one of the URL path. The body is buffered in memory,
+    /// sized up front when the server sends a `Content-Length`.
+    pub async fn upload_from_url(
+        &self,
+        url: url::Url,
+        additional_tags: Vec<Tag<Base64>>,
+    ) -> Result<(String, u64), Error> {
+        let path = PathBuf::from(url.as_str());
+        let res = self.upload_url(url, additional_tags).await;
+        if let Err(err) = &res {
+            self.emit(UploadEvent::Failed {
+                path,
+                error: err.to_string(),
+            });
+        }
+        res
+    }
+
+    async fn upload_url(
+        &self,
+        url: url::Url,
+        mut additional_tags: Vec<Tag<Base64>>,
+    ) -> Result<(String, u64), Error> {
+        let source_error = |e: reqwest::Error| Error::SourceError(e.to_string());
+        let mut res = self
+            .http_client()?
+            .get(url.clone())
+            .send()
+            .await
+            .map_err(source_error)?;
+        if !res.status().is_success() {
+            return Err(Error::SourceError(format!("{}: {}", url, res.status())));
+        }
+
+        let declared = res
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|mime| Tag::<Base64>::content_type(mime).ok());
+        let auto_content_tag = match declared {
+            Some(tag) => {
+                additional_tags.push(tag);
+                false
+            }
+            None => {
+                let (tags, auto_content_tag) =
+                    Self::file_tags(Path::new(url.path()), additional_tags)?;
+                additional_tags = tags;
+                auto_content_tag
+            }
+        };
+
+        let capacity = res.content_length().unwrap_or_default().min(MAX_TX_DATA);
+        let mut data = Vec::with_capacity(capacity as usize);
+        while let Some(chunk) = res.chunk().await.map_err(source_error)? {
+            data.extend_from_slice(&chunk);
+        }
+        let fee = self.get_fee_by_size(data.len() as u64, None).await?;
+        self.upload_bytes(
+            PathBuf::from(url.as_str()),
+            data,
+            additional_tags,
+            auto_content_tag,
+            fee,
+        )
+        .await
+    }
+
+    /// Uploads every object of `s3`'s bucket whose key starts with `prefix`, one at a time,
+    /// tagging each with `additional_tags` and the content type of its key. Objects are read
+    /// into memory and handed to the upload backend, never written to disk.
+    ///
+    /// Each object is attempted up to [`consts::S3_OBJECT_RETRIES`] times before it is
+    /// reported failed, without stopping the others. The returned manifest maps the keys,
+    /// stripped of `prefix`, to their transactions; post it with [`Arweave::post_manifest`].
+    #[cfg(feature = "s3")]
+    pub async fn upload_from_s3(
+        &self,
+        s3: &S3Client,
+        prefix: &str,
+        additional_tags: Vec<Tag<Base64>>,
+    ) -> Result<S3Import, Error> {
+        let mut import = S3Import::default();
+        for object in s3.list_objects(prefix).await? {
+            let path = PathBuf::from(&object.key);
+            let mut attempt = 1;
+            let res = loop {
+                let res = self
+                    .upload_s3_object(s3, &object.key, additional_tags.clone())
+                    .await;
+                if res.is_ok() || attempt >= consts::S3_OBJECT_RETRIES {
+                    break res;
+                }
+                attempt += 1;
+                tokio::time::sleep(Duration::from_secs(consts::S3_RETRY_SLEEP)).await;
+            };
+            match res {
+                Ok((id, _)) => {
+                    let key = object.key.strip_prefix(prefix).unwrap_or(&object.key);
+                    import.manifest.insert(key.trim_start_matches('/'), &id);
+                }
+                Err(err) => {
+                    self.emit(UploadEvent::Failed {
+                        path,
+                        error: err.to_string(),
+                    });
+                    import.failed.push((object.key, err));
+                }
+            }
+        }
+        Ok(import)
+    }
+
+    #[cfg(feature = "s3")]
+    async fn upload_s3_object(
+        &self,
+        s3: &S3Client,
+        key: &str,
+        additional_tags: Vec<Tag<Base64>>,
+    ) -> Result<(String, u64), Error> {
+        let path = PathBuf::from(key);
+        let (tags, auto_content_tag) = Self::file_tags(&path, additional_tags)?;
+        let data = s3.get_object(key).await?;
+        let fee = self.get_fee_by_size(data.len() as u64, None).await?;
+        self.upload_bytes(path, data, tags, auto_content_tag, fee)
+            .await
+    }
+
+    async fn upload_file(
+        &self,
+        file_path: PathBuf,
+        additional_tags: Vec<Tag<Base64>>,
+        fee: u64,
+    ) -> Result<(String, u64), Error> {
+        let (additional_tags, auto_content_tag) = Self::file_tags(&file_path, additional_tags)?;
+        let data = fs::read(&file_path).map_err(|e| Error::IoError(e.to_string()))?;
+        self.upload_bytes(file_path, data, additional_tags, auto_content_tag, fee)
+            .await
+    }
+
+    /// Uploads `data` through the upload backend, reporting progress under `file_path`.
+    async fn upload_bytes(
+        &self,
+        file_path: PathBuf,
+        data: Vec<u8>,
+        mut additional_tags: Vec<Tag<Base64>>,
+        auto_content_tag: bool,
+        fee: u64,
+    ) -> Result<(String, u64), Error> {
+        if self.ipfs_tags && data.len() <= IPFS_CHUNK_SIZE {
+            additional_tags.push(Cid::raw(&data)?.to_tag()?);
+        }
+        if let Some(algorithm) = self.upload_digest_algorithm() {
+            let digest = Digest::compute(algorithm, &data);
+            if self.dedup {
+                if let Some(id) = self.find_by_digest(&digest).await? {
+                    self.emit(UploadEvent::Deduplicated {
+                        path: file_path,
+                        id: id.clone(),
+                    });
+                    return Ok((id, 0));
+                }
+            }
+            additional_tags.push(digest.to_tag()?);
+        }
+        self.emit(UploadEvent::FeeQuoted {
+            path: file_path.clone(),
+            fee,
+        });
+        let upload = Upload {
+            data,
+            tags: additional_tags,
+            fee,
+            auto_content_tag,
+        };
+        let signed = self.backend.sign(self, upload).await?;
+        self.emit(UploadEvent::Signed {
+            path: file_path,
+            id: signed.id(),
+        });
+        let (id, reward) = self.backend.post(self, signed).await?;
+        self.emit(UploadEvent::Completed {
+            id: id.clone(),
+            reward,
+        });
+
+        Ok((id, reward))
+    }
+
+    /// Downloads and parses the path manifest posted as transaction `id`.
+    pub async fn get_manifest(&self, id: Base64) -> Result<Manifest, Error> {
+        Manifest::from_slice(&self.get_tx_with_data(id).await?.data)
+    }
+
+    /// Posts `manifest` as a new transaction tagged with the manifest content type.
+    pub async fn post_manifest(&self, manifest: &Manifest) -> Result<(String, u64), Error> {
+        self.post_data(
+            manifest.to_vec()?,
+            vec![Tag::<Base64>::content_type(MANIFEST_CONTENT_TYPE)?],
+        )
+        .await
+    }
+
+    /// Posts the metadata transaction of an ArFS entity.
+    pub async fn post_arfs_entity(&self, entity: &Entity) -> Result<(String, u64), Error> {
+        self.post_data(entity.metadata()?, entity.tags()?).await
+    }
+
+    /// Downloads and parses the ArFS entity whose metadata transaction is `id`.
+    pub async fn get_arfs_entity(&self, id: Base64) -> Result<Entity, Error> {
+        let tx = self.get_tx_with_data(id).await?;
+        Entity::parse(&tx.tags, &tx.data)
+    }
+
+    /// Key of private drive `drive_id`, unlocked by this wallet and `password`.
+    pub fn arfs_drive_key(&self, drive_id: &str, password: &str) -> Result<ArfsKey, Error> {
+        ArfsKey::drive(&self.current_signer()?, drive_id, password)
+    }
+
+    /// Encrypts the metadata of an entity of a private drive and posts it.
+    pub async fn post_private_arfs_entity(
+        &self,
+        entity: &Entity,
+        drive_key: &ArfsKey,
+    ) -> Result<(String, u64), Error> {
+        let (tags, data) = entity.seal(drive_key)?;
+        self.post_data(data, tags).await
+    }
+
+    /// Downloads and decrypts the entity of a private drive whose metadata transaction is `id`.
+    pub async fn get_private_arfs_entity(
+        &self,
+        id: Base64,
+        drive_key: &ArfsKey,
+    ) -> Result<Entity, Error> {
+        let tx = self.get_tx_with_data(id).await?;
+        Entity::parse_private(&tx.tags, &tx.data, drive_key)
+    }
+
+    /// Encrypts `data` with the key of file `file_id` and posts it. The returned id is the
+    /// `data_tx_id` of the file entity.
+    pub async fn post_private_file_data(
+        &self,
+        data: &[u8],
+        file_id: &str,
+        drive_key: &ArfsKey,
+    ) -> Result<(String, u64), Error> {
+        let (mut tags, ciphertext) = drive_key.file(file_id)?.seal(data)?;
+        tags.push(Tag::<Base64>::content_type(PRIVATE_CONTENT_TYPE)?);
+        self.post_data(ciphertext, tags).await
+    }
+
+    /// Downloads and decrypts the contents of file `file_id` posted as transaction `id`.
+    pub async fn get_private_file_data(
+        &self,
+        id: Base64,
+        file_id: &str,
+        drive_key: &ArfsKey,
+    ) -> Result<Vec<u8>, Error> {
+        let tx = self.get_tx_with_data(id).await?;
+        drive_key.file(file_id)?.open(&tx.tags, &tx.data)
+    }
+
+    /// Creates, signs and posts a transaction holding `data`, paying the quoted fee.
+    async fn post_data(
+        &self,
+        data: Vec<u8>,
+        tags: Vec<Tag<Base64>>,
+    ) -> Result<(String, u64), Error> {
+        let fee = self.get_fee_by_size(data.len() as u64, None).await?;
+        let transaction = self
+            .create_transaction(Base64::empty(), tags, data, 0, fee, false)
+            .await?;
+        let signed_transaction = self.sign_transaction_async(transaction).await?;
+        self.post_signed_transaction(signed_transaction).await
+    }
+
+    /// Downloads manifest `id`, adds the `path -> transaction id` pairs of `entries`, replacing
+    /// paths already present, and posts the result as a new manifest whose id is returned.
+    pub async fn append_to_manifest(
+        &self,
+        id: Base64,
+        entries: BTreeMap<String, String>,
+    ) -> Result<(String, u64), Error> {
+        let mut manifest = self.get_manifest(id).await?;
+        manifest.extend(entries);
+        self.post_manifest(&manifest).await
+    }
+
+    /// Uploads `file_paths`, then appends them to manifest `id` under their path relative to
+    /// `base_dir`. Returns the id of the new manifest.
+    pub async fn append_files_to_manifest(
+        &self,
+        id: Base64,
+        base_dir: &Path,
+        file_paths: Vec<PathBuf>,
+        additional_tags: Vec<Tag<Base64>>,
+    ) -> Result<(String, u64), Error> {
+        let mut entries = BTreeMap::new();
+        for file_path in file_paths {
+            let key = Manifest::path_key(base_dir, &file_path)?;
+            let size = fs::metadata(&file_path)
+                .map_err(|e| Error::IoError(e.to_string()))?
+                .len();
+            let fee = self.get_fee_by_size(size, None).await?;
+            let (file_id, _) = self
+                .upload_file_from_path(file_path, additional_tags.clone(), fee)
+                .await?;
+            entries.insert(key, file_id);
+        }
+        self.append_to_manifest(id, entries).await
+    }
+
+    /// Uploads the files below `dir` that are new or changed since manifest
+    /// `previous_manifest_id`, compared through the `File-Hash` tag of the transactions it
+    /// lists, and posts the updated manifest. Unchanged files keep their transaction and
+    /// files no longer in `dir` are dropped; without a previous manifest every file is
+    /// uploaded.
+    ///
+    /// Uploads are tagged with their digest, SHA-256 unless [`Arweave::set_digest_tags`]
+    /// says otherwise, so the next sync can compare them. When nothing changed the previous
+    /// manifest is kept and nothing is posted.
+    pub async fn sync_directory(
+        &self,
+        dir: &Path,
+        previous_manifest_id: Option<Base64>,
+    ) -> Result<SyncReport, Error> {
+        let mut manifest = match &previous_manifest_id {
+            Some(id) => self.get_manifest(id.clone()).await?,
+            None => Manifest::new(),
+        };
+        let files = schedule::list_files(dir)?;
+        let keys = files
+            .iter()
+            .map(|file| Manifest::path_key(dir, file))
+            .collect::<Result<Vec<_>, _>>()?;
+        let previous_ids: Vec<Option<String>> = keys
+            .iter()
+            .map(|key| manifest.get(key).map(str::to_string))
+            .collect();
+        let previous_digests: Vec<Option<Digest>> = stream::iter(previous_ids)
+            .map(|id| async move {
+                match id {
+                    Some(id) => self.uploaded_digest(&id).await,
+                    None => Ok(None),
+                }
+            })
+            .buffered(SCAN_CONCURRENCY)
+            .try_collect()
+            .await?;
+
+        let mut report = SyncReport::default();
+        let listed: BTreeSet<&String> = keys.iter().collect();
+        let stale: Vec<String> = manifest
+            .paths
+            .keys()
+            .filter(|path| !listed.contains(path))
+            .cloned()
+            .collect();
+        for path in stale {
+            manifest.remove(&path);
+            report.removed.push(path);
+        }
+        let algorithm = self.digest_tags.unwrap_or(DigestAlgorithm::Sha256);
+        for ((file, key), previous) in files.into_iter().zip(keys).zip(previous_digests) {
+            if let Some(previous) = previous {
+                if Digest::of_file_async(previous.algorithm, &file).await? == previous {
+                    report.unchanged.push(key);
+                    continue;
+                }
+            }
+            let mut tags = Vec::new();
+            if self.upload_digest_algorithm().is_none() {
+                tags.push(Digest::of_file_async(algorithm, &file).await?.to_tag()?);
+            }
+            let size = fs::metadata(&file)
+                .map_err(|e| Error::IoError(e.to_string()))?
+                .len();
+            let fee = self.get_fee_by_size(size, None).await?;
+            let (id, _) = self.upload_file_from_path(file, tags, fee).await?;
+            manifest.insert(&key, &id);
+            report.uploaded.push(key);
+        }
+
+        report.manifest_id = match previous_manifest_id {
+            Some(id) if report.uploaded.is_empty() && report.removed.is_empty() => id.to_string(),
+            _ => {
+                if manifest.index.is_none() && manifest.get("index.html").is_some() {
+                    manifest.set_index("index.html");
+                }
+                self.post_manifest(&manifest).await?.0
+            }
+        };
+        Ok(report)
+    }
+
+    /// Digest of the `File-Hash` tag of transaction `id`, `None` when it has no valid one.
+    async fn uploaded_digest(&self, id: &str) -> Result<Option<Digest>, Error> {
+        let (_, tx) = self.get_tx(Base64::from_str(id)?).await?;
+        Ok(tx.and_then(|tx| Digest::from_tags(&tx.tags).ok().flatten()))
+    }
+
+    /// Algorithm of the `File-Hash` tag added to uploads, if any.
+    fn upload_digest_algorithm(&self) -> Option<DigestAlgorithm> {
+        self.digest_tags
+            .or(self.dedup.then_some(DigestAlgorithm::Sha256))
+    }
+
+    /// Adds a `Content-Type` tag guessed from the file extension. The returned flag tells
+    /// whether the content type still has to be inferred from the data.
+    fn file_tags(
+        file_path: &Path,
+        mut additional_tags: Vec<Tag<Base64>>,
+    ) -> Result<(Vec<Tag<Base64>>, bool), Error> {
+        match mime_guess::from_path(file_path).first() {
+            Some(content_type) => {
+                additional_tags.push(Tag::<Base64>::content_type(content_type.as_ref())?);
+                Ok((additional_tags, false))
+            }
+            None => Ok((additional_tags, true)),
+        }
+    }
+
+    /// Posts the transaction directly, or its header followed by its chunks when the data is
+    /// too large for the `tx/` endpoint.
+    async fn post_signed_transaction(
+        &self,
+        signed_transaction: Tx,
+    ) -> Result<(String, u64), Error> {
+        if signed_transaction.data.0.len() > self.network.max_tx_data as usize {
+            self.post_transaction_chunks(signed_transaction, 100).await
+        } else {
+            let (id, reward) = self.post_transaction(&signed_transaction).await?;
+            self.emit(UploadEvent::HeaderPosted { id: id.clone() });
+            Ok((id, reward))
+        }
+    }
+
+    async fn post_transaction_chunks(
+        &self,
+        signed_transaction: Tx,
+        chunks_buffer: usize,
+    ) -> Result<(String, u64), Error> {
+        if signed_transaction.id.0.is_empty() {
+            return Err(error::Error::UnsignedTransaction);
+        }
+        signed_transaction.validate_chunk_layout()?;
+        if self.already_posted(&signed_transaction).await?
+            && self.gateway.data_available(&signed_transaction.id).await?
+                == Some(signed_transaction.data_size)
+        {
+            return Ok((signed_transaction.id.to_string(), signed_transaction.reward));
+        }
+
+        // Audited as a whole, so chunk retries are counted.
+        let transaction_with_no_data = signed_transaction.clone_with_no_data()?;
+        let post = async {
+            let (id, reward) = self.submit_transaction(&transaction_with_no_data).await?;
+            self.emit(UploadEvent::HeaderPosted { id: id.clone() });
+            if self.dry_run {
+                return Ok((id, reward));
+            }
+
+            self.uploader.expect_bytes(signed_transaction.data_size);
+            let acked = BTreeSet::new();
+            let results: Vec<Result<usize, Error>> = Self::upload_transaction_chunks_stream(
+                self,
+                &signed_transaction,
+                &acked,
+                chunks_buffer,
+            )
+            .inspect(|res| {
+                if let Ok(offset) = res {
+                    self.emit(UploadEvent::ChunkPosted {
+                        id: id.clone(),
+                        offset: *offset,
+                    });
+                }
+            })
+            .collect()
+            .await;
+
+            results.into_iter().collect::<Result<Vec<usize>, Error>>()?;
+
+            Ok((id, reward))
+        };
+        self.audited(&transaction_with_no_data, post).await
+    }
+
+    /// Posts the chunks of `signed_transaction` whose offset is not in `acked`, borrowing its
+    /// data so that only the chunks in flight are copied.
+    fn upload_transaction_chunks_stream<'a>(
+        arweave: &'a Arweave,
+        signed_transaction: &'a Tx,
+        acked: &'a BTreeSet<usize>,
+        buffer: usize,
+    ) -> impl Stream<Item = Result<usize, Error>> + 'a {
+        stream::iter(0..signed_transaction.chunks.len())
+            .map(move |i| signed_transaction.get_chunk(i).unwrap())
+            .filter(move |chunk| futures::future::ready(!acked.contains(&chunk.offset)))
+            .map(move |chunk| {
+                arweave
+                    .uploader
+                    .post_chunk_with_retries(chunk, arweave.gateway.as_ref())
+            })
+            .buffer_unordered(buffer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs::{self, File},
+        io::Read,
+        path::PathBuf,
+        str::FromStr,
+        sync::Arc,
+        time::Duration,
+    };
+
+    use futures::{StreamExt, TryStreamExt};
+    use httpmock::{
+        Method::{GET, POST},
+        MockServer,
+    };
+    use pretend::Url;
+
+    use crate::{
+        activity::{ActivityCursor, AddressActivity, Direction},
+        arfs::{DriveEntity, Entity, FileEntity},
+        audit::AuditLog,
+        backend::MockBackend,
+        budget::Budget,
+        consts::{IPFS_CHUNK_SIZE, MAX_TAGS_SIZE},
+        crypto::{
+            base64::Base64,
+            merkle::{ChunkingConfig, MAX_CHUNK_SIZE},
+        },
+        digest::{Digest, DigestAlgorithm},
+        error::Error,
+        events::UploadEvent,
+        finality::FinalityPolicy,
+        gateway::{DnsOverrides, Gateway, MockGateway, TrustPolicy},
+        graphql::TransactionQuery,
+        irys::{IrysClient, PAID_BY_HEADER},
+        manifest::Manifest,
+        network::NetworkConfig,
+        payment::PaymentStatus,
+        sequence::SequenceManager,
+        transaction::{
+            prepared::PreparedTx,
+            tags::{FromUtf8Strs, Tag, SEQUENCE},
+            template::{QuantityRule, TxTemplate},
+            Tx,
+        },
+        turbo::TurboClient,
+        Arweave, ArweaveSigner, SignerContext, ARWEAVE_BASE_URL,
+    };
+
+    #[test]
+    pub fn should_parse_and_verify_valid_tx() -> Result<(), Error> {
+        let mut file = File::open("res/sample_tx.json").unwrap();
+        let mut data = String::new();
+        file.read_to_string(&mut data).unwrap();
+        let tx = Tx::from_str(&data).unwrap();
+
+        let path = PathBuf::from_str("res/test_wallet.json").unwrap();
+        let arweave =
+            Arweave::from_keypair_path(path, Url::from_str(ARWEAVE_BASE_URL).unwrap()).unwrap();
+
+        match arweave.verify_transaction(&tx) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(Error::InvalidSignature),
+        }
+    }
+
+    #[test]
+    pub fn should_reject_signing_over_budget() {
+        let path = PathBuf::from_str("res/test_wallet.json").unwrap();
+        let mut arweave =
+            Arweave::from_keypair_path(path, Url::from_str(ARWEAVE_BASE_URL).unwrap()).unwrap();
+        arweave.set_budget(Budget::new(Some(1_000), None));
+
+        let tx = Tx::new(
+            arweave.signer.get_provider(),
+            Base64::empty(),
+            b"data".to_vec(),
+            0,
+            1_001,
+            Base64::empty(),
+            vec![],
+            false,
+        )
+        .unwrap();
+
+        assert!(matches!(
+            arweave.sign_transaction(tx),
+            Err(Error::BudgetExceeded(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn should_not_submit_in_dry_run() {
+        let path = PathBuf::from_str("res/test_wallet.json").unwrap();
+        // Unroutable gateway: any network submission would fail.
+        let mut arweave =
+            Arweave::from_keypair_path(path, Url::from_str("http://127.0.0.1:1").unwrap()).unwrap();
+        arweave.set_dry_run(true);
+
+        let tx = Tx::new(
+            arweave.signer.get_provider(),
+            Base64::empty(),
+            vec![1; 1024],
+            0,
+            1_000,
+            Base64::empty(),
+            vec![],
+            false,
+        )
+        .unwrap();
+        let signed_tx = arweave.sign_transaction(tx).unwrap();
+        let expected_id = signed_tx.id.to_string();
+
+        let (id, reward) = arweave.post_transaction(&signed_tx).await.unwrap();
+        assert_eq!(id, expected_id);
+        assert_eq!(reward, 1_000);
+    }
+
+    #[tokio::test]
+    async fn should_re_anchor_stale_transaction() {
+        let server = MockServer::start_async().await;
+        let stale_anchor = Base64::from_utf8_str("stale-anchor").unwrap();
+        let fresh_anchor = Base64::from_utf8_str("fresh-anchor").unwrap();
+
+        let anchor_mock = server.mock(|when, then| {
+            when.method(GET).path("/tx_anchor");
+            then.status(200).body(fresh_anchor.to_string());
+        });
+        let rejected_mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/tx")
+                .body_contains(stale_anchor.to_string());
+            then.status(400).body("Invalid anchor (last_tx).");
+        });
+        let accepted_mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/tx")
+                .body_contains(fresh_anchor.to_string());
+            then.status(200);
+        });
+
+        let path = PathBuf::from_str("res/test_wallet.json").unwrap();
+        let mut arweave =
+            Arweave::from_keypair_path(path, Url::parse(&server.url("")).unwrap()).unwrap();
+        arweave.set_re_anchor(true);
+        arweave.set_audit_log(AuditLog::new());
+
+        let tx = Tx::new(
+            arweave.signer.get_provider(),
+            Base64::empty(),
+            vec![],
+            0,
+            1_000,
+            stale_anchor,
+            vec![],
+            false,
+        )
+        .unwrap();
+        let signed_tx = arweave.sign_transaction(tx).unwrap();
+
+        let (id, _) = arweave.post_transaction(&signed_tx).await.unwrap();
+        assert_ne!(id, signed_tx.id.to_string());
+        let audit = arweave.last_audit().unwrap();
+        assert_eq!(audit.id, signed_tx.id.to_string());
+        assert_eq!(audit.posted_id, Some(id));
+        assert_eq!(audit.anchor, fresh_anchor.to_string());
+        assert_eq!(audit.retries, 1);
+        assert_eq!(audit.gateway, Some(arweave.base_url.to_string()));
+        assert_eq!(audit.error, None);
+        rejected_mock.assert();
+        anchor_mock.assert();
+        accepted_mock.assert();
+    }
+
+    #[tokio::test]
+    async fn should_audit_chunk_retries() {
+        let server = MockServer::start_async().await;
+        server.mock(|when, then| {
+            when.method(POST).path("/tx");
+            then.status(200);
+        });
+        let chunks = server.mock(|when, then| {
+            when.method(POST).path("/chunk");
+            then.status(429).header("retry-after", "0");
+        });
+
+        let signer =
+            ArweaveSigner::from_keypair_path(PathBuf::from("res/test_wallet.json")).unwrap();
+        let network = NetworkConfig {
+            max_tx_data: 1_000,
+            chunk_retries: 3,
+            ..NetworkConfig::arlocal().with_gateway(Url::parse(&server.base_url()).unwrap())
+        };
+        let mut arweave = Arweave::for_network(signer, network).unwrap();
+        arweave.set_audit_log(AuditLog::new());
+
+        let tx = Tx::new(
+            arweave.signer.get_provider(),
+            Base64::empty(),
+            vec![7; 5_000],
+            0,
+            1_000,
+            Base64::from(vec![1; 32]),
+            vec![],
+            false,
+        )
+        .unwrap();
+        let signed_tx = arweave.sign_transaction(tx).unwrap();
+        assert!(matches!(
+            arweave.post_signed_transaction(signed_tx).await,
+            Err(Error::RateLimited(0))
+        ));
+
+        chunks.assert_hits(4);
+        let audit = arweave.last_audit().unwrap();
+        assert_eq!(audit.retries, 3);
+        assert_eq!(audit.gateway, Some(arweave.base_url.to_string()));
+        assert!(audit.error.is_some());
+    }
+
+    #[tokio::test]
+    async fn should_not_wait_out_long_rate_limits() {
+        let server = MockServer::start_async().await;
+        server.mock(|when, then| {
+            when.method(POST).path("/tx");
+            then.status(200);
+        });
+        let chunks = server.mock(|when, then| {
+            when.method(POST).path("/chunk");
+            then.status(429).header("retry-after", "3600");
+        });
+
+        let signer =
+            ArweaveSigner::from_keypair_path(PathBuf::from("res/test_wallet.json")).unwrap();
+        let network = NetworkConfig {
+            max_tx_data: 1_000,
+            chunk_retries: 3,
+            max_retry_after: Duration::from_secs(60),
+            ..NetworkConfig::arlocal().with_gateway(Url::parse(&server.base_url()).unwrap())
+        };
+        let arweave = Arweave::for_network(signer, network).unwrap();
+
+        let tx = Tx::new(
+            arweave.signer.get_provider(),
+            Base64::empty(),
+            vec![7; 5_000],
+            0,
+            1_000,
+            Base64::from(vec![1; 32]),
+            vec![],
+            false,
+        )
+        .unwrap();
+        let signed_tx = arweave.sign_transaction(tx).unwrap();
+        let res = tokio::time::timeout(
+            Duration::from_secs(5),
+            arweave.post_signed_transaction(signed_tx),
+        )
+        .await
+        .unwrap();
+        assert!(matches!(res, Err(Error::RateLimited(3600))));
+        chunks.assert_hits(1);
+    }
+
+    #[tokio::test]
+    async fn should_get_tx_with_data() {
+        let mut tx_json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string("res/sample_tx.json").unwrap()).unwrap();
+        tx_json["data_size"] = "4".into();
+        let id = tx_json["id"].as_str().unwrap().to_string();
+
+        let server = MockServer::start_async().await;
+        let tx_mock = server.mock(|when, then| {
+            when.method(GET).path(format!("/tx/{}", id));
+            then.status(200).body(tx_json.to_string());
+        });
+        let data_mock = server.mock(|when, then| {
+            when.method(GET).path(format!("/{}", id));
+            then.status(200).body("data");
+        });
+
+        let arweave = Arweave::from_keypair_path(
+            PathBuf::from_str("res/test_wallet.json").unwrap(),
+            Url::parse(&server.url("")).unwrap(),
+        )
+        .unwrap();
+        let tx_with_data = arweave
+            .get_tx_with_data(Base64::from_str(&id).unwrap())
+            .await
+            .unwrap();
+
+        tx_mock.assert();
+        data_mock.assert();
+        assert_eq!(tx_with_data.data, b"data".to_vec());
+        assert_eq!(tx_with_data.tags[0].name, "test");
+        assert_eq!(tx_with_data.tags[0].value, "test");
+    }
+
+    #[tokio::test]
+    async fn should_get_tx_with_binary_tags() {
+        let path = PathBuf::from_str("res/test_wallet.json").unwrap();
+        let mut arweave =
+            Arweave::from_keypair_path(path, Url::from_str("http://127.0.0.1:1").unwrap()).unwrap();
+        arweave.set_gateway(Arc::new(MockGateway::new()));
+
+        let value = [0xff, 0x00, b'a'];
+        let (id, _) = arweave
+            .post_data(b"binary".to_vec(), vec![Tag::from_bytes("Key-Id", &value)])
+            .await
+            .unwrap();
+        let tx_with_data = arweave
+            .get_tx_with_data(Base64::from_str(&id).unwrap())
+            .await
+            .unwrap();
+
+        assert!(tx_with_data.tx.has_tag("Key-Id", value));
+        let tag = tx_with_data
+            .tags
+            .iter()
+            .find(|tag| tag.name == "Key-Id")
+            .unwrap();
+        assert_eq!(tag.value, "\u{fffd}\0a");
+    }
+
+    #[tokio::test]
+    async fn should_quote_fee_with_target() {
+        let server = MockServer::start_async().await;
+        let target = "ggHWyKn0I_CTtsyyt2OR85sPYz9OvKLd9DYIvRQ2ET4";
+        server.mock(|when, then| {
+            when.method(GET).path("/price/1024");
+            then.status(200).body("50");
+        });
+        server.mock(|when, then| {
+            when.method(GET).path(format!("/price/1024/{}", target));
+            then.status(200).body("75");
+        });
+        server.mock(|when, then| {
+            when.method(GET).path(format!("/price/0/{}", target));
+            then.status(200).body("25");
+        });
+
+        let path = PathBuf::from_str("res/test_wallet.json").unwrap();
+        let arweave =
+            Arweave::from_keypair_path(path, Url::from_str(&server.url("")).unwrap()).unwrap();
+        let target = Base64::from_str(target).unwrap();
+
+        assert_eq!(arweave.get_fee_by_size(1024, None).await.unwrap(), 50);
+        assert_eq!(
+            arweave.get_fee_by_size(1024, Some(&target)).await.unwrap(),
+            75
+        );
+        assert_eq!(
+            arweave
+                .get_fee(target.clone(), vec![0; 1024])
+                .await
+                .unwrap(),
+            75
+        );
+        assert_eq!(arweave.get_fee_for_transfer(&target).await.unwrap(), 25);
+    }
+
+    #[tokio::test]
+    async fn should_emit_upload_events() {
+        let path = PathBuf::from_str("res/test_wallet.json").unwrap();
+        let mut arweave =
+            Arweave::from_keypair_path(path, Url::from_str("http://127.0.0.1:1").unwrap()).unwrap();
+        arweave.set_gateway(Arc::new(MockGateway::new()));
+        let mut events = arweave.subscribe_upload_events();
+
+        let file_path = PathBuf::from_str("res/test_image.jpg").unwrap();
+        let (id, reward) = arweave
+            .upload_file_from_path(file_path.clone(), vec![], 42)
+            .await
+            .unwrap();
+        let missing = PathBuf::from_str("res/missing.bin").unwrap();
+        let error = arweave
+            .upload_file_from_path(missing.clone(), vec![], 42)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, Error::IoError(_)));
+
+        let mut received = vec![];
+        while let Ok(event) = events.try_recv() {
+            received.push(event);
+        }
+        assert_eq!(
+            received,
+            vec![
+                UploadEvent::FeeQuoted {
+                    path: file_path.clone(),
+                    fee: 42
+                },
+                UploadEvent::Signed {
+                    path: file_path,
+                    id: id.clone()
+                },
+                UploadEvent::HeaderPosted { id: id.clone() },
+                UploadEvent::Completed { id, reward },
+                UploadEvent::Failed {
+                    path: missing,
+                    error: error.to_string()
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn should_append_files_to_manifest() {
+        let path = PathBuf::from_str("res/test_wallet.json").unwrap();
+        let mut arweave =
+            Arweave::from_keypair_path(path, Url::from_str("http://127.0.0.1:1").unwrap()).unwrap();
+        arweave.set_gateway(Arc::new(MockGateway::new()));
+
+        let mut manifest = Manifest::new();
+        manifest.insert("index.html", "index-id");
+        manifest.set_index("index.html");
+        let (manifest_id, _) = arweave.post_manifest(&manifest).await.unwrap();
+
+        let (new_id, _) = arweave
+            .append_files_to_manifest(
+                Base64::from_str(&manifest_id).unwrap(),
+                &PathBuf::from("res"),
+                vec![PathBuf::from("res/test_image.jpg")],
+                vec![],
+            )
+            .await
+            .unwrap();
+        assert_ne!(new_id, manifest_id);
+
+        let updated = arweave
+            .get_manifest(Base64::from_str(&new_id).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(updated.index, manifest.index);
+        assert_eq!(updated.get("index.html"), Some("index-id"));
+        let image_id = updated.get("test_image.jpg").unwrap();
+        let image = arweave
+            .get_tx_with_data(Base64::from_str(image_id).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(image.data, std::fs::read("res/test_image.jpg").unwrap());
+    }
+
+    #[tokio::test]
+    async fn should_map_offsets_to_the_weave() {
+        let path = PathBuf::from_str("res/test_wallet.json").unwrap();
+        let mut arweave =
+            Arweave::from_keypair_path(path, Url::from_str("http://127.0.0.1:1").unwrap()).unwrap();
+        arweave.set_gateway(Arc::new(MockGateway::new()));
+
+        let (first, _) = arweave.post_data(vec![1; 10], vec![]).await.unwrap();
+        let (second, _) = arweave.post_data(vec![2; 5], vec![]).await.unwrap();
+        let second = Base64::from_str(&second).unwrap();
+
+        let offset = arweave.get_tx_offset(second.clone()).await.unwrap();
+        assert_eq!((offset.start().unwrap(), offset.offset), (10, 14));
+        assert_eq!(
+            arweave.absolute_offset(second.clone(), 2).await.unwrap(),
+            12
+        );
+        assert_eq!(
+            arweave.relative_offset(second.clone(), 12).await.unwrap(),
+            2
+        );
+        assert!(arweave.relative_offset(second, 9).await.is_err());
+        let first = Base64::from_str(&first).unwrap();
+        assert_eq!(arweave.relative_offset(first, 9).await.unwrap(), 9);
+    }
+
+    #[tokio::test]
+    async fn should_append_and_read_log() {
+        let server = MockServer::start_async().await;
+        let path = PathBuf::from_str("res/test_wallet.json").unwrap();
+        let mut arweave =
+            Arweave::from_keypair_path(path, Url::parse(&server.url("/")).unwrap()).unwrap();
+        let gateway = Arc::new(MockGateway::new());
+        arweave.set_gateway(gateway.clone());
+        let mut empty = server.mock(|when, then| {
+            when.method(POST).path("/graphql");
+            then.status(200)
+                .json_body(serde_json::json!({ "data": { "transactions": {
+                "pageInfo": { "hasNextPage": false }, "edges": []
+            } } }));
+        });
+
+        let first = arweave
+            .append_log("events", &serde_json::json!({ "event": "start" }))
+            .await
+            .unwrap();
+        let second = arweave
+            .append_log("events", &serde_json::json!({ "event": "stop" }))
+            .await
+            .unwrap();
+        assert_eq!((first.sequence, second.sequence), (0, 1));
+        empty.assert_hits(1);
+        empty.delete();
+
+        let owner = arweave.signer.wallet_address().to_string();
+        let mut edges = Vec::new();
+        for entry in [&second, &first] {
+            let id = Base64::from_str(&entry.id).unwrap();
+            let (_, tx) = gateway.get_tx(&id).await.unwrap();
+            let tags: Vec<serde_json::Value> = tx
+                .unwrap()
+                .tags
+                .iter()
+                .map(|tag| {
+                    serde_json::json!({
+                        "name": tag.name.to_utf8_string().unwrap(),
+                        "value": tag.value.to_utf8_string().unwrap(),
+                    })
+                })
+                .collect();
+            edges.push(serde_json::json!({ "cursor": entry.id, "node": {
+                "id": entry.id, "anchor": "", "signature": "", "recipient": "",
+                "owner": { "address": owner, "key": "" },
+                "fee": { "winston": "0", "ar": "0" },
+                "quantity": { "winston": "0", "ar": "0" },
+                "data": { "size": "0", "type": "application/json" },
+                "tags": tags, "block": null, "bundledIn": null
+            } }));
         }
+        let graphql = server.mock(|when, then| {
+            when.method(POST)
+                .path("/graphql")
+                .body_contains("App-Name")
+                .body_contains(owner.as_str());
+            then.status(200)
+                .json_body(serde_json::json!({ "data": { "transactions": {
+                "pageInfo": { "hasNextPage": false }, "edges": edges
+            } } }));
+        });
+
+        let entries = arweave.read_log("events", &owner).await.unwrap();
+        graphql.assert();
+        assert_eq!(entries, vec![first, second]);
+        assert_eq!(entries[1].entry["event"], "stop");
+
+        // A new process resumes after the last entry.
+        let path = PathBuf::from_str("res/test_wallet.json").unwrap();
+        let mut restarted =
+            Arweave::from_keypair_path(path, Url::parse(&server.url("/")).unwrap()).unwrap();
+        restarted.set_gateway(gateway);
+        let third = restarted
+            .append_log("events", &serde_json::json!({ "event": "restart" }))
+            .await
+            .unwrap();
+        assert_eq!(third.sequence, 2);
+    }
+
+    #[tokio::test]
+    async fn should_verify_payments() {
+        let path = PathBuf::from_str("res/test_wallet.json").unwrap();
+        let mut arweave =
+            Arweave::from_keypair_path(path, Url::from_str("http://127.0.0.1:1").unwrap()).unwrap();
+        let gateway = Arc::new(MockGateway::new());
+        arweave.set_gateway(gateway.clone());
+        let merchant = Base64::from(vec![1; 32]);
+
+        let tx = arweave
+            .create_transaction(merchant.clone(), vec![], vec![], 500, 1, false)
+            .await
+            .unwrap();
+        let tx = arweave.sign_transaction(tx).unwrap();
+        arweave.post_transaction(&tx).await.unwrap();
+
+        gateway.set_confirmations(&tx.id, 2);
+        let status = arweave
+            .verify_payment(&tx.id, &merchant, 500, 3)
+            .await
+            .unwrap();
+        assert_eq!(
+            status,
+            PaymentStatus::Unconfirmed {
+                quantity: 500,
+                confirmations: 2
+            }
+        );
+
+        gateway.set_confirmations(&tx.id, 3);
+        let status = arweave
+            .verify_payment(&tx.id, &merchant, 500, 3)
+            .await
+            .unwrap();
+        assert!(status.is_confirmed());
+        assert_eq!(
+            arweave
+                .verify_payment(&tx.id, &merchant, 501, 3)
+                .await
+                .unwrap(),
+            PaymentStatus::Underpaid { quantity: 500 }
+        );
+    }
+
+    #[tokio::test]
+    async fn should_use_network_limits() {
+        let signer =
+            ArweaveSigner::from_keypair_path(PathBuf::from("res/test_wallet.json")).unwrap();
+        let network = NetworkConfig {
+            max_tx_data: 1_000,
+            ..NetworkConfig::arlocal()
+        };
+        let mut arweave = Arweave::for_network(signer, network.clone()).unwrap();
+        assert_eq!(arweave.base_url, network.gateway_url);
+        assert_eq!(arweave.network(), &network);
+        let gateway = Arc::new(MockGateway::new());
+        arweave.set_gateway(gateway.clone());
+
+        let data = vec![7; 5_000];
+        let tx = arweave
+            .create_bundle_transaction(data.clone(), vec![], 1)
+            .await
+            .unwrap();
+        let tx = arweave.sign_transaction(tx).unwrap();
+        arweave.post_bundle_transaction(tx.clone()).await.unwrap();
+
+        // Over the network's limit, so the data went through the chunk endpoint.
+        assert_eq!(gateway.chunk_count(&tx.data_root), 1);
+        assert_eq!(gateway.get_tx_data(&tx.id).await.unwrap(), data);
+    }
+
+    #[tokio::test]
+    async fn should_request_faucet() {
+        let server = MockServer::start_async().await;
+        let signer =
+            ArweaveSigner::from_keypair_path(PathBuf::from("res/test_wallet.json")).unwrap();
+        let address = signer.wallet_address();
+        let mint = server.mock(|when, then| {
+            when.method(GET)
+                .path(format!("/mint/{}/1000000000000", address));
+            then.status(200).body("1000000000000");
+        });
+
+        let network =
+            NetworkConfig::arlocal().with_gateway(Url::parse(&server.base_url()).unwrap());
+        let arweave = Arweave::for_network(signer.clone(), network).unwrap();
+        arweave.request_faucet(1_000_000_000_000).await.unwrap();
+        mint.assert();
+
+        let mainnet = Arweave::for_network(signer, NetworkConfig::mainnet()).unwrap();
+        assert!(matches!(
+            mainnet.request_faucet(1).await,
+            Err(Error::FaucetError(_))
+        ));
+        assert!(NetworkConfig::testnet().faucet_url.is_some());
+    }
+
+    #[tokio::test]
+    async fn should_offload_large_tags() {
+        let path = PathBuf::from_str("res/test_wallet.json").unwrap();
+        let mut arweave =
+            Arweave::from_keypair_path(path, Url::from_str("http://127.0.0.1:1").unwrap()).unwrap();
+        let gateway = Arc::new(MockGateway::new());
+        arweave.set_gateway(gateway.clone());
+
+        let description = "x".repeat(3000);
+        let tags = vec![
+            Tag::<Base64>::from_utf8_strs("Title", "small").unwrap(),
+            Tag::<Base64>::from_utf8_strs("Description", &description).unwrap(),
+        ];
+        let offloaded = arweave
+            .offload_large_tags(tags.clone(), MAX_TAGS_SIZE - 256)
+            .await
+            .unwrap();
+        assert_eq!(offloaded[0], tags[0]);
+        assert_eq!(
+            offloaded[1].name.to_utf8_string().unwrap(),
+            "Tag-Pointer-Description"
+        );
+        assert_eq!(gateway.posted_ids().len(), 1);
+
+        let tx = arweave
+            .create_transaction(Base64::default(), offloaded, vec![], 0, 1, false)
+            .await
+            .unwrap();
+        let tx = arweave.sign_transaction(tx).unwrap();
+        arweave.post_transaction(&tx).await.unwrap();
+        let read = arweave.get_tx_with_data(tx.id).await.unwrap().tx.tags;
+        let resolved = arweave.resolve_tag_pointers(read).await.unwrap();
+        assert!(resolved.contains(&tags[1]));
+
+        assert!(arweave.offload_large_tags(tags, 10).await.is_err());
+        assert_eq!(gateway.posted_ids().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn should_post_idempotently() {
+        let path = PathBuf::from_str("res/test_wallet.json").unwrap();
+        let mut arweave =
+            Arweave::from_keypair_path(path, Url::from_str("http://127.0.0.1:1").unwrap()).unwrap();
+        arweave.set_gateway(Arc::new(MockGateway::new()));
+        arweave.set_budget(Budget::new(None, Some(1_000)));
+
+        let tx = arweave
+            .create_transaction(
+                Base64::default(),
+                vec![],
+                b"retried".to_vec(),
+                0,
+                600,
+                false,
+            )
+            .await
+            .unwrap();
+        let tx = arweave.sign_transaction(tx).unwrap();
+        let (id, reward) = arweave.post_transaction(&tx).await.unwrap();
+        assert_eq!(id, tx.id.to_string());
+
+        // A retried job posting the same transaction doesn't pay twice.
+        assert!(matches!(
+            arweave.post_transaction(&tx).await,
+            Err(Error::BudgetExceeded(_))
+        ));
+        arweave.set_idempotent(true);
+        assert_eq!(arweave.post_transaction(&tx).await.unwrap(), (id, reward));
+        assert_eq!(arweave.budget().unwrap().spent(), 600);
+    }
+
+    #[tokio::test]
+    async fn should_wait_for_finality() {
+        let path = PathBuf::from_str("res/test_wallet.json").unwrap();
+        let mut arweave =
+            Arweave::from_keypair_path(path, Url::from_str("http://127.0.0.1:1").unwrap()).unwrap();
+        let gateway = Arc::new(MockGateway::new());
+        arweave.set_gateway(gateway.clone());
+        arweave.set_finality(FinalityPolicy::BlocksAfterInclusion(2));
+
+        let tx = arweave
+            .create_transaction(Base64::default(), vec![], b"final".to_vec(), 0, 1, false)
+            .await
+            .unwrap();
+        let tx = arweave.sign_transaction(tx).unwrap();
+        arweave.post_transaction(&tx).await.unwrap();
+        gateway.set_confirmations(&tx.id, 3);
+
+        let timeout = Duration::from_secs(10);
+        let status = arweave
+            .wait_for_confirmation(&tx.id, timeout)
+            .await
+            .unwrap();
+        assert_eq!(status.number_of_confirmations, 3);
+        assert!(arweave
+            .wait_for_confirmation(&Base64::from(vec![9; 32]), timeout)
+            .await
+            .is_err());
+
+        gateway.set_confirmations(&tx.id, 1);
+        assert!(matches!(
+            arweave
+                .wait_for_confirmation(&tx.id, Duration::from_millis(50))
+                .await,
+            Err(Error::Timeout(_))
+        ));
+        assert_eq!(arweave.finality(), FinalityPolicy::BlocksAfterInclusion(2));
+    }
+
+    #[tokio::test]
+    async fn should_keep_polling_confirmations_through_gateway_failures() {
+        let server = MockServer::start_async().await;
+        let id = Base64::from(vec![5; 32]);
+        let status_path = format!("/tx/{}/status", id);
+        let mut unavailable = server.mock(|when, then| {
+            when.method(GET).path(status_path.clone());
+            then.status(503);
+        });
+        let signer =
+            ArweaveSigner::from_keypair_path(PathBuf::from("res/test_wallet.json")).unwrap();
+        let network = NetworkConfig {
+            block_time: Duration::from_millis(20),
+            ..NetworkConfig::arlocal().with_gateway(Url::parse(&server.base_url()).unwrap())
+        };
+        let arweave = Arweave::for_network(signer, network).unwrap();
+
+        let recover = async {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            assert!(unavailable.hits() > 1);
+            unavailable.delete();
+            server.mock(|when, then| {
+                when.method(GET).path(status_path.clone());
+                then.status(200).json_body(serde_json::json!({
+                    "block_height": 1000,
+                    "block_indep_hash": "",
+                    "number_of_confirmations": 100
+                }));
+            });
+        };
+        let (status, _) = tokio::join!(
+            arweave.wait_for_confirmation(&id, Duration::from_secs(10)),
+            recover
+        );
+        assert_eq!(status.unwrap().number_of_confirmations, 100);
+    }
+
+    #[tokio::test]
+    async fn should_watch_address_activity() {
+        let server = MockServer::start_async().await;
+        let node = |id: &str, owner: &str, height: u64| {
+            serde_json::json!({ "cursor": id, "node": {
+                "id": id, "anchor": "", "signature": "", "recipient": "",
+                "owner": { "address": owner, "key": "" },
+                "fee": { "winston": "0", "ar": "0" },
+                "quantity": { "winston": "0", "ar": "0" },
+                "data": { "size": "0", "type": null }, "tags": [],
+                "block": { "id": "", "timestamp": 0, "height": height, "previous": "" },
+                "bundledIn": null
+            } })
+        };
+        for (filter, edges) in [
+            ("owners", vec![node("out", "me", 6)]),
+            (
+                "recipients",
+                vec![node("in", "payer", 5), node("old", "payer", 3)],
+            ),
+        ] {
+            server.mock(|when, then| {
+                when.method(POST).path("/graphql").body_contains(filter);
+                then.status(200)
+                    .json_body(serde_json::json!({ "data": { "transactions": {
+                    "pageInfo": { "hasNextPage": false }, "edges": edges
+                } } }));
+            });
+        }
+        let path = PathBuf::from_str("res/test_wallet.json").unwrap();
+        let arweave =
+            Arweave::from_keypair_path(path, Url::parse(&server.url("/")).unwrap()).unwrap();
+
+        let activity: Vec<AddressActivity> = arweave
+            .watch_address("me", ActivityCursor::at(4))
+            .take(2)
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(activity[0].node.id, "in");
+        assert_eq!(activity[0].direction, Direction::Incoming);
+        assert_eq!(activity[1].node.id, "out");
+        assert_eq!(activity[1].direction, Direction::Outgoing);
+        assert_eq!(activity[1].cursor.height, 6);
+    }
+
+    #[tokio::test]
+    async fn should_stamp_sequence_numbers() {
+        let server = MockServer::start_async().await;
+        let path = PathBuf::from_str("res/test_wallet.json").unwrap();
+        let mut arweave =
+            Arweave::from_keypair_path(path, Url::parse(&server.url("/")).unwrap()).unwrap();
+        arweave.set_gateway(Arc::new(MockGateway::new()));
+        let dir = tempfile::tempdir().unwrap();
+        let sequence_path = dir.path().join("sequence");
+        arweave.set_sequence(Some(Arc::new(
+            SequenceManager::open(&sequence_path).unwrap(),
+        )));
+
+        let sequence_of = |tx: &Tx| {
+            tx.tags
+                .iter()
+                .filter(|t| t.name.0 == SEQUENCE.as_bytes())
+                .map(|t| t.value.to_utf8_string().unwrap())
+                .collect::<Vec<_>>()
+        };
+        for expected in ["0", "1"] {
+            let tx = arweave
+                .create_transaction(Base64::empty(), vec![], vec![1], 0, 1, false)
+                .await
+                .unwrap();
+            assert_eq!(sequence_of(&tx), vec![expected]);
+        }
+        let tx = arweave
+            .create_transaction(
+                Base64::empty(),
+                vec![Tag::<Base64>::sequence(7)],
+                vec![1],
+                0,
+                1,
+                false,
+            )
+            .await
+            .unwrap();
+        assert_eq!(sequence_of(&tx), vec!["7"]);
+
+        let edges: Vec<serde_json::Value> = [0, 3, 1]
+            .iter()
+            .map(|n| {
+                serde_json::json!({ "cursor": n.to_string(), "node": {
+                    "id": n.to_string(), "anchor": "", "signature": "", "recipient": "",
+                    "owner": { "address": "", "key": "" },
+                    "fee": { "winston": "0", "ar": "0" },
+                    "quantity": { "winston": "0", "ar": "0" },
+                    "data": { "size": "0", "type": null },
+                    "tags": [{ "name": "Sequence", "value": n.to_string() }],
+                    "block": null, "bundledIn": null
+                } })
+            })
+            .collect();
+        server.mock(|when, then| {
+            when.method(POST).path("/graphql");
+            then.status(200)
+                .json_body(serde_json::json!({ "data": { "transactions": {
+                "pageInfo": { "hasNextPage": false }, "edges": edges
+            } } }));
+        });
+        assert_eq!(
+            arweave
+                .sequence_gaps(&TransactionQuery::new())
+                .await
+                .unwrap(),
+            vec![2..3]
+        );
+    }
+
+    #[tokio::test]
+    async fn should_create_from_template() {
+        let path = PathBuf::from_str("res/test_wallet.json").unwrap();
+        let mut arweave =
+            Arweave::from_keypair_path(path, Url::from_str("http://127.0.0.1:1").unwrap()).unwrap();
+        arweave.set_gateway(Arc::new(MockGateway::new()));
+        let template = TxTemplate::new("event")
+            .with_target(Base64::from(vec![1; 32]))
+            .with_tag("App-Name", "events")
+            .unwrap()
+            .with_quantity(QuantityRule::PerByte(10));
+        let extra = Tag::<Base64>::from_utf8_strs("Seq", "7").unwrap();
+
+        let tx = arweave
+            .create_from_template(&template, b"entry".to_vec(), vec![extra.clone()], 3)
+            .await
+            .unwrap();
+        assert_eq!(tx.target, *template.target());
+        assert_eq!(tx.quantity.winstons(), 50);
+        assert_eq!(tx.reward, 3);
+        assert_eq!(tx.tags[1..], [template.tags()[0].clone(), extra]);
+        assert_eq!(tx.data.0, b"entry".as_slice());
+    }
+
+    #[tokio::test]
+    async fn should_chunk_as_configured() {
+        let path = PathBuf::from_str("res/test_wallet.json").unwrap();
+        let mut arweave =
+            Arweave::from_keypair_path(path, Url::from_str("http://127.0.0.1:1").unwrap()).unwrap();
+        arweave.set_gateway(Arc::new(MockGateway::new()));
+        let data = vec![0; MAX_CHUNK_SIZE + 1];
+
+        let tx = arweave
+            .create_transaction(Base64::empty(), vec![], data.clone(), 0, 1, false)
+            .await
+            .unwrap();
+        assert_eq!(tx.chunks[0].max_byte_range, MAX_CHUNK_SIZE / 2 + 1);
+
+        arweave.set_chunking(ChunkingConfig {
+            rebalance: false,
+            ..Default::default()
+        });
+        let unbalanced = arweave
+            .create_transaction(Base64::empty(), vec![], data, 0, 1, false)
+            .await
+            .unwrap();
+        assert_eq!(unbalanced.chunks[0].max_byte_range, MAX_CHUNK_SIZE);
+        assert_eq!(unbalanced.chunks.len(), 2);
+        assert_ne!(unbalanced.data_root, tx.data_root);
+    }
+
+    #[tokio::test]
+    async fn should_validate_chunks_by_weave_offset() {
+        let path = PathBuf::from_str("res/test_wallet.json").unwrap();
+        let mut arweave =
+            Arweave::from_keypair_path(path, Url::from_str("http://127.0.0.1:1").unwrap()).unwrap();
+        let gateway = Arc::new(MockGateway::new());
+        arweave.set_gateway(gateway.clone());
+
+        arweave.post_data(vec![1; 10], vec![]).await.unwrap();
+        let data: Vec<u8> = (0..600_000).map(|i| i as u8).collect();
+        let (id, _) = arweave.post_data(data.clone(), vec![]).await.unwrap();
+        let id = Base64::from_str(&id).unwrap();
+        let offset = arweave.get_tx_offset(id.clone()).await.unwrap();
+        let block_range = offset.start().unwrap()..offset.offset + 1;
+        let tx_root = gateway.tx_root(&id).unwrap();
+
+        let absolute = offset.to_absolute(300_000).unwrap();
+        let chunk = arweave
+            .get_chunk_validated(absolute, &tx_root, block_range.clone())
+            .await
+            .unwrap();
+        assert_eq!(chunk.tx_range, block_range);
+        assert!(chunk.chunk_range.contains(&absolute));
+        let start = offset.to_relative(chunk.chunk_range.start).unwrap() as usize;
+        assert_eq!(chunk.chunk, &data[start..start + chunk.chunk.len()]);
+
+        let wrong_root = Base64::from(vec![0; 32]);
+        assert!(matches!(
+            arweave
+                .get_chunk_validated(absolute, &wrong_root, block_range.clone())
+                .await,
+            Err(Error::InvalidProof)
+        ));
+        assert!(arweave
+            .get_chunk_validated(block_range.end, &tx_root, block_range)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn should_sync_directory() {
+        let path = PathBuf::from_str("res/test_wallet.json").unwrap();
+        let mut arweave =
+            Arweave::from_keypair_path(path, Url::from_str("http://127.0.0.1:1").unwrap()).unwrap();
+        let gateway = Arc::new(MockGateway::new());
+        arweave.set_gateway(gateway.clone());
+
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().to_path_buf();
+        fs::create_dir_all(dir.join("css")).unwrap();
+        fs::write(dir.join("index.html"), "<html>").unwrap();
+        fs::write(dir.join("css/site.css"), "body {}").unwrap();
+        fs::write(dir.join("old.txt"), "old").unwrap();
+
+        let first = arweave.sync_directory(&dir, None).await.unwrap();
+        assert_eq!(
+            first.uploaded,
+            vec!["css/site.css", "index.html", "old.txt"]
+        );
+        let first_id = Base64::from_str(&first.manifest_id).unwrap();
+
+        let again = arweave
+            .sync_directory(&dir, Some(first_id.clone()))
+            .await
+            .unwrap();
+        assert_eq!(again.manifest_id, first.manifest_id);
+        assert!(again.uploaded.is_empty());
+        let posted = gateway.posted_ids().len();
+
+        fs::write(dir.join("css/site.css"), "body { margin: 0 }").unwrap();
+        fs::remove_file(dir.join("old.txt")).unwrap();
+        let second = arweave.sync_directory(&dir, Some(first_id)).await.unwrap();
+        assert_eq!(second.uploaded, vec!["css/site.css"]);
+        assert_eq!(second.unchanged, vec!["index.html"]);
+        assert_eq!(second.removed, vec!["old.txt"]);
+        // The changed file and the new manifest.
+        assert_eq!(gateway.posted_ids().len(), posted + 2);
+
+        let old = arweave
+            .get_manifest(Base64::from_str(&first.manifest_id).unwrap())
+            .await
+            .unwrap();
+        let new = arweave
+            .get_manifest(Base64::from_str(&second.manifest_id).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(new.get("index.html"), old.get("index.html"));
+        assert_ne!(new.get("css/site.css"), old.get("css/site.css"));
+        assert_eq!(new.get("old.txt"), None);
+        assert_eq!(new.resolve("/"), new.get("index.html"));
+
+        fs::remove_file(dir.join("index.html")).unwrap();
+        let third = arweave
+            .sync_directory(&dir, Some(Base64::from_str(&second.manifest_id).unwrap()))
+            .await
+            .unwrap();
+        assert_eq!(third.removed, vec!["index.html"]);
+        let last = arweave
+            .get_manifest(Base64::from_str(&third.manifest_id).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(last.index, None);
+        assert_eq!(last.resolve("/"), None);
+    }
+
+    #[tokio::test]
+    async fn should_post_and_fetch_arfs_entities() {
+        let path = PathBuf::from_str("res/test_wallet.json").unwrap();
+        let mut arweave =
+            Arweave::from_keypair_path(path, Url::from_str("http://127.0.0.1:1").unwrap()).unwrap();
+        arweave.set_gateway(Arc::new(MockGateway::new()));
+
+        let (drive, root) = DriveEntity::new("my drive");
+        for entity in [Entity::Drive(drive), Entity::Folder(root)] {
+            let (id, _) = arweave.post_arfs_entity(&entity).await.unwrap();
+            let fetched = arweave
+                .get_arfs_entity(Base64::from_str(&id).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(fetched, entity);
+        }
+    }
+
+    #[tokio::test]
+    async fn should_post_and_fetch_private_arfs_entities() {
+        let path = PathBuf::from_str("res/test_wallet.json").unwrap();
+        let mut arweave =
+            Arweave::from_keypair_path(path, Url::from_str("http://127.0.0.1:1").unwrap()).unwrap();
+        arweave.set_gateway(Arc::new(MockGateway::new()));
+
+        let (drive, root) = DriveEntity::new_private("my drive");
+        let drive_key = arweave.arfs_drive_key(&drive.drive_id, "secret").unwrap();
+        let file = FileEntity::new(
+            &drive.drive_id,
+            &root.folder_id,
+            "notes.txt",
+            5,
+            0,
+            "",
+            "text/plain",
+        );
+
+        let (data_id, _) = arweave
+            .post_private_file_data(b"hello", &file.file_id, &drive_key)
+            .await
+            .unwrap();
+        let data = arweave
+            .get_private_file_data(
+                Base64::from_str(&data_id).unwrap(),
+                &file.file_id,
+                &drive_key,
+            )
+            .await
+            .unwrap();
+        assert_eq!(data, b"hello");
+
+        let (id, _) = arweave
+            .post_private_arfs_entity(&Entity::File(file.clone()), &drive_key)
+            .await
+            .unwrap();
+        let wrong_key = arweave.arfs_drive_key(&drive.drive_id, "wrong").unwrap();
+        assert!(arweave
+            .get_private_arfs_entity(Base64::from_str(&id).unwrap(), &wrong_key)
+            .await
+            .is_err());
+        let fetched = arweave
+            .get_private_arfs_entity(Base64::from_str(&id).unwrap(), &drive_key)
+            .await
+            .unwrap();
+        assert_eq!(fetched, Entity::File(file));
+    }
+
+    #[tokio::test]
+    async fn should_post_prepared_transaction() {
+        let path = PathBuf::from_str("res/test_wallet.json").unwrap();
+        let mut arweave =
+            Arweave::from_keypair_path(path, Url::from_str("http://127.0.0.1:1").unwrap()).unwrap();
+        arweave.set_gateway(Arc::new(MockGateway::new()));
+
+        let prepared = arweave
+            .prepare_transaction(Base64::empty(), vec![], b"handoff".to_vec(), 0, false)
+            .await
+            .unwrap();
+        assert_eq!(
+            prepared.fee,
+            arweave.get_fee_by_size(7, None).await.unwrap()
+        );
+
+        let json = prepared
+            .with_metadata("origin", "builder")
+            .to_json()
+            .unwrap();
+        let tx = PreparedTx::from_json(&json).unwrap().into_tx();
+        let signed = arweave.sign_transaction(tx).unwrap();
+        let (id, _) = arweave.post_transaction(&signed).await.unwrap();
+        assert_eq!(id, signed.id.to_string());
+    }
+
+    #[tokio::test]
+    async fn should_check_existence_without_downloading() {
+        let path = PathBuf::from_str("res/test_wallet.json").unwrap();
+        let mut arweave =
+            Arweave::from_keypair_path(path, Url::from_str("http://127.0.0.1:1").unwrap()).unwrap();
+        arweave.set_gateway(Arc::new(MockGateway::new()));
+
+        let (id, _) = arweave.post_data(b"exists".to_vec(), vec![]).await.unwrap();
+        let id = Base64::from_str(&id).unwrap();
+        let missing = Base64::from(vec![9; 32]);
+
+        assert!(arweave.tx_exists(id.clone()).await.unwrap());
+        assert_eq!(arweave.data_available(id).await.unwrap(), Some(6));
+        assert!(!arweave.tx_exists(missing.clone()).await.unwrap());
+        assert_eq!(arweave.data_available(missing).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn should_only_talk_to_trusted_hosts() {
+        let server = MockServer::start_async().await;
+        server.mock(|when, then| {
+            when.method(POST).path("/graphql");
+            then.status(200)
+                .json_body(serde_json::json!({ "data": { "transactions": { "edges": [] } } }));
+        });
+        let path = PathBuf::from_str("res/test_wallet.json").unwrap();
+        let mut arweave =
+            Arweave::from_keypair_path(path, Url::parse(&server.url("/")).unwrap()).unwrap();
+
+        assert!(matches!(
+            arweave.set_trust_policy(TrustPolicy::new().allow_host("arweave.net")),
+            Err(Error::UntrustedHost(_))
+        ));
+        assert!(arweave.trust_policy().is_none());
+
+        arweave
+            .set_trust_policy(TrustPolicy::new().allow_host("127.0.0.1"))
+            .unwrap();
+        let data = arweave
+            .query_transactions(&TransactionQuery::new())
+            .await
+            .unwrap();
+        assert!(data["transactions"]["edges"].as_array().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn should_resolve_pinned_hosts() {
+        let server = MockServer::start_async().await;
+        let price = server.mock(|when, then| {
+            when.method(GET).path("/price/7");
+            then.status(200).body("10");
+        });
+        let path = PathBuf::from_str("res/test_wallet.json").unwrap();
+        let base_url = format!("http://gateway.internal:{}/", server.address().port());
+        let mut arweave = Arweave::from_keypair_path(path, Url::parse(&base_url).unwrap()).unwrap();
+
+        arweave
+            .set_dns_overrides(DnsOverrides::new().pin("gateway.internal", server.address().ip()))
+            .unwrap();
+        assert_eq!(arweave.get_fee_by_size(7, None).await.unwrap(), 10);
+        price.assert();
+    }
+
+    #[tokio::test]
+    async fn should_run_requests_through_middleware() {
+        let server = MockServer::start_async().await;
+        let price = server.mock(|when, then| {
+            when.method(GET)
+                .path("/price/7")
+                .header("x-api-key", "secret");
+            then.status(200).body("10");
+        });
+        let path = PathBuf::from_str("res/test_wallet.json").unwrap();
+        let mut arweave =
+            Arweave::from_keypair_path(path, Url::parse(&server.url("/")).unwrap()).unwrap();
+
+        arweave
+            .add_middleware(Arc::new(|request: &mut reqwest::Request| {
+                request
+                    .headers_mut()
+                    .insert("x-api-key", "secret".parse().unwrap());
+                Ok(())
+            }))
+            .unwrap();
+        assert_eq!(arweave.get_fee_by_size(7, None).await.unwrap(), 10);
+        price.assert();
+
+        let info = server.mock(|when, then| {
+            when.method(GET).path("/info").header("x-api-key", "secret");
+            then.status(200).json_body(serde_json::json!({
+                "network": "arweave.N.1",
+                "version": 5,
+                "release": 53,
+                "height": 1000,
+                "current": Base64::from(vec![1; 48]).to_string(),
+                "blocks": 1001,
+                "peers": 10,
+                "queue_length": 0,
+                "node_state_latency": 1
+            }));
+        });
+        assert_eq!(arweave.network_info().await.unwrap().height, 1000);
+        info.assert();
+    }
+
+    #[tokio::test]
+    async fn should_audit_posted_transactions() {
+        let path = PathBuf::from_str("res/test_wallet.json").unwrap();
+        let mut arweave =
+            Arweave::from_keypair_path(path, Url::from_str("http://127.0.0.1:1").unwrap()).unwrap();
+        arweave.set_gateway(Arc::new(MockGateway::new()));
+        let dir = tempfile::tempdir().unwrap();
+        let export = dir.path().join("audit.jsonl");
+        arweave.set_audit_log(AuditLog::open(&export).unwrap());
+
+        let (id, reward) = arweave
+            .post_data(b"audited".to_vec(), vec![])
+            .await
+            .unwrap();
+        let audit = arweave.last_audit().unwrap();
+        assert_eq!(audit.posted_id, Some(id));
+        assert_eq!(audit.fee_quoted, Some(reward));
+        assert_eq!(audit.fee_paid, reward);
+        assert_eq!(audit.data_size, 7);
+        // The mock gateway doesn't report itself.
+        assert_eq!(audit.gateway, None);
+        assert_eq!(audit.retries, 0);
+
+        let unsigned = arweave
+            .create_transaction(Base64::empty(), vec![], vec![], 0, 1, false)
+            .await
+            .unwrap();
+        assert!(arweave.post_transaction(&unsigned).await.is_err());
+        let failed = arweave.last_audit().unwrap();
+        assert_eq!(failed.posted_id, None);
+        assert!(failed.error.is_some());
+
+        assert_eq!(AuditLog::read(&export).unwrap(), vec![audit, failed]);
+    }
+
+    #[tokio::test]
+    async fn should_sample_price_history() {
+        let path = PathBuf::from_str("res/test_wallet.json").unwrap();
+        let mut arweave =
+            Arweave::from_keypair_path(path, Url::from_str("http://127.0.0.1:1").unwrap()).unwrap();
+        arweave.set_gateway(Arc::new(MockGateway::new()));
+
+        let history = arweave
+            .get_price_history(&[10, 1000], 3, Duration::from_millis(1))
+            .await
+            .unwrap();
+        assert_eq!(history.sizes().collect::<Vec<_>>(), vec![10, 1000]);
+        assert_eq!(history.samples(1000).len(), 3);
+        assert_eq!(history.latest(10), Some(10));
+    }
+
+    #[tokio::test]
+    async fn should_iterate_txs_in_blocks() {
+        let path = PathBuf::from_str("res/test_wallet.json").unwrap();
+        let mut arweave =
+            Arweave::from_keypair_path(path, Url::from_str("http://127.0.0.1:1").unwrap()).unwrap();
+        let gateway = Arc::new(MockGateway::new());
+        arweave.set_gateway(gateway.clone());
+
+        let mut ids = vec![];
+        for data in [b"a", b"b", b"c"] {
+            let (id, _) = arweave.post_data(data.to_vec(), vec![]).await.unwrap();
+            ids.push(Base64::from_str(&id).unwrap());
+        }
+        gateway.add_block(1, 100, vec![ids[0].clone()]);
+        gateway.add_block(2, 200, vec![]);
+        gateway.add_block(3, 300, vec![ids[1].clone(), ids[2].clone()]);
+
+        let txs: Vec<Tx> = arweave
+            .iter_txs_in_blocks(1..4)
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(txs.into_iter().map(|tx| tx.id).collect::<Vec<_>>(), ids);
+        let missing: Result<Vec<Tx>, Error> = arweave.iter_txs_in_blocks(3..5).try_collect().await;
+        assert!(missing.is_err());
+    }
+
+    #[tokio::test]
+    async fn should_sign_with_tenant_keys() {
+        let path = PathBuf::from_str("res/test_wallet.json").unwrap();
+        let mut arweave =
+            Arweave::from_keypair_path(path, Url::from_str("http://127.0.0.1:1").unwrap()).unwrap();
+        arweave.set_gateway(Arc::new(MockGateway::new()));
+        let bob =
+            ArweaveSigner::from_pem_str(&fs::read_to_string("res/test_wallet_2.pem").unwrap())
+                .unwrap();
+        let tenant_signer = bob.clone();
+        arweave.set_signer_resolver(Arc::new(move |context: &SignerContext| {
+            (context.tenant == "bob").then(|| tenant_signer.clone())
+        }));
+
+        let create =
+            || arweave.create_transaction(Base64::empty(), vec![], vec![1; 64], 0, 1, false);
+        let tx = arweave
+            .with_tenant(SignerContext::new("bob"), async {
+                arweave.sign_transaction(create().await?)
+            })
+            .await
+            .unwrap();
+        assert_eq!(tx.owner, bob.keypair_modulus());
+        arweave.verify_transaction(&tx).unwrap();
+        let (address, signature) = arweave
+            .with_tenant(SignerContext::new("bob"), async {
+                Ok::<_, Error>((arweave.get_wallet_address()?, arweave.sign(b"hello")?))
+            })
+            .await
+            .unwrap();
+        assert_eq!(address, bob.wallet_address().to_string());
+        Arweave::verify(&bob.keypair_modulus(), b"hello", &signature).unwrap();
+
+        let tx = arweave.sign_transaction(create().await.unwrap()).unwrap();
+        assert_eq!(tx.owner, arweave.signer.keypair_modulus());
+
+        let unknown = arweave
+            .with_tenant(SignerContext::new("mallory"), create())
+            .await;
+        assert!(matches!(unknown, Err(Error::SigningError(_))));
+    }
+
+    #[tokio::test]
+    async fn should_sponsor_uploads() {
+        let server = MockServer::start_async().await;
+        let deposit = Base64::from(vec![9; 32]).to_string();
+        let info = server.mock(|when, then| {
+            when.method(GET).path("/info");
+            then.status(200).json_body(serde_json::json!({
+                "version": "0.2.0",
+                "addresses": { "arweave": deposit },
+                "gateway": "arweave.net"
+            }));
+        });
+        let path = PathBuf::from_str("res/test_wallet.json").unwrap();
+        let mut arweave =
+            Arweave::from_keypair_path(path, Url::from_str("http://127.0.0.1:1").unwrap()).unwrap();
+        arweave.set_gateway(Arc::new(MockGateway::new()));
+        let sponsor = arweave.get_wallet_address().unwrap();
+        let funding = server.mock(|when, then| {
+            when.method(POST).path("/account/balance/arweave");
+            then.status(200);
+        });
+        let upload = server.mock(|when, then| {
+            when.method(POST)
+                .path("/tx/arweave")
+                .header(PAID_BY_HEADER, &sponsor);
+            then.status(200).json_body(serde_json::json!({
+                "id": "item",
+                "timestamp": 1,
+                "version": "1.0.0",
+                "public": "",
+                "signature": "",
+                "deadlineHeight": 10
+            }));
+        });
+
+        let irys = IrysClient::new(Url::parse(&server.url("/")).unwrap());
+        arweave.fund_bundler(&irys, 1_000_000).await.unwrap();
+        info.assert();
+        funding.assert();
+
+        let user =
+            ArweaveSigner::from_pem_str(&fs::read_to_string("res/test_wallet_4096.pem").unwrap())
+                .unwrap();
+        let receipt = arweave
+            .upload_sponsored(&irys, &user, b"hello".to_vec(), vec![])
+            .await
+            .unwrap();
+        upload.assert();
+        assert_eq!(receipt.unwrap().deadline_height, 10);
+    }
+
+    #[tokio::test]
+    async fn should_budget_and_audit_backend_uploads() {
+        let server = MockServer::start_async().await;
+        server.mock(|when, then| {
+            when.method(GET).path_contains("/payment/v1/price/bytes/");
+            then.status(200)
+                .json_body(serde_json::json!({ "winc": "600" }));
+        });
+        let upload = server.mock(|when, then| {
+            when.method(POST).path("/upload/v1/tx");
+            then.status(200).json_body(serde_json::json!({
+                "id": "item",
+                "owner": "owner",
+                "winc": "600"
+            }));
+        });
+        let turbo = Arc::new(TurboClient::new(
+            Url::parse(&server.url("/upload/")).unwrap(),
+            Url::parse(&server.url("/payment/")).unwrap(),
+        ));
+
+        let signer =
+            ArweaveSigner::from_pem_str(&fs::read_to_string("res/test_wallet_4096.pem").unwrap())
+                .unwrap();
+        let mut arweave =
+            Arweave::from_signer(signer, Url::from_str("http://127.0.0.1:1").unwrap()).unwrap();
+        arweave.set_gateway(Arc::new(MockGateway::new()));
+        arweave.set_upload_backend(turbo.clone());
+        arweave.set_budget(Budget::new(None, Some(1_000)));
+        arweave.set_audit_log(AuditLog::new());
+        let file_path = PathBuf::from_str("res/test_image.jpg").unwrap();
+
+        arweave.set_dry_run(true);
+        arweave
+            .upload_file_from_path(file_path.clone(), vec![], 0)
+            .await
+            .unwrap();
+        assert!(arweave
+            .upload_to_turbo(&turbo, b"hello".to_vec(), vec![])
+            .await
+            .unwrap()
+            .is_none());
+        upload.assert_hits(0);
+        assert_eq!(arweave.budget().unwrap().spent(), 0);
+        assert!(arweave.last_audit().unwrap().dry_run);
+
+        arweave.set_dry_run(false);
+        let (id, reward) = arweave
+            .upload_file_from_path(file_path.clone(), vec![], 0)
+            .await
+            .unwrap();
+        assert_eq!((id.as_str(), reward), ("item", 0));
+        upload.assert_hits(1);
+        assert_eq!(arweave.budget().unwrap().spent(), 600);
+        let audit = arweave.last_audit().unwrap();
+        assert_eq!(audit.fee_paid, 600);
+        assert_eq!(audit.gateway, Some(turbo.upload_url().to_string()));
+
+        assert!(matches!(
+            arweave
+                .upload_to_turbo(&turbo, b"hello".to_vec(), vec![])
+                .await,
+            Err(Error::BudgetExceeded(_))
+        ));
+        upload.assert_hits(1);
+        assert!(arweave.last_audit().unwrap().error.is_some());
+    }
+
+    #[tokio::test]
+    async fn should_upload_through_backend() {
+        let path = PathBuf::from_str("res/test_wallet.json").unwrap();
+        let mut arweave =
+            Arweave::from_keypair_path(path, Url::from_str("http://127.0.0.1:1").unwrap()).unwrap();
+        let backend = Arc::new(MockBackend::new());
+        arweave.set_upload_backend(backend.clone());
+
+        let file_path = PathBuf::from_str("res/test_image.jpg").unwrap();
+        let (id, reward) = arweave
+            .upload_file_from_path(file_path, vec![], 42)
+            .await
+            .unwrap();
+
+        let uploads = backend.uploads();
+        assert_eq!(uploads.len(), 1);
+        assert_eq!(uploads[0].id(), id);
+        assert_eq!(reward, 42);
+    }
+
+    #[tokio::test]
+    async fn should_upload_from_url() {
+        let server = MockServer::start_async().await;
+        server.mock(|when, then| {
+            when.method(GET).path("/page");
+            then.status(200)
+                .header("content-type", "text/html; charset=utf-8")
+                .body("<html></html>");
+        });
+        server.mock(|when, then| {
+            when.method(GET).path("/style.css");
+            then.status(200).body("body {}");
+        });
+        server.mock(|when, then| {
+            when.method(GET).path("/missing");
+            then.status(404);
+        });
+
+        let path = PathBuf::from_str("res/test_wallet.json").unwrap();
+        let mut arweave =
+            Arweave::from_keypair_path(path, Url::from_str("http://127.0.0.1:1").unwrap()).unwrap();
+        arweave.set_gateway(Arc::new(MockGateway::new()));
+
+        for (path, content_type, body) in [
+            ("/page", "text/html; charset=utf-8", "<html></html>"),
+            ("/style.css", "text/css", "body {}"),
+        ] {
+            let url = Url::parse(&server.url(path)).unwrap();
+            let (id, _) = arweave.upload_from_url(url, vec![]).await.unwrap();
+            let tx = arweave
+                .get_tx_with_data(Base64::from_str(&id).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(tx.data, body.as_bytes());
+            assert!(tx
+                .tags
+                .iter()
+                .any(|tag| tag.name == "Content-Type" && tag.value == content_type));
+        }
+
+        let url = Url::parse(&server.url("/missing")).unwrap();
+        assert!(matches!(
+            arweave.upload_from_url(url, vec![]).await,
+            Err(Error::SourceError(_))
+        ));
+    }
+
+    #[cfg(feature = "archive")]
+    #[tokio::test]
+    async fn should_upload_archive() {
+        use std::io::Write;
+
+        let dir = tempfile::tempdir().unwrap();
+        let archive_path = dir.path().join("site.zip");
+        let mut zip = zip::ZipWriter::new(File::create(&archive_path).unwrap());
+        for (name, data) in [("index.html", "<html>"), ("img/logo.svg", "<svg>")] {
+            zip.start_file(name, zip::write::FileOptions::default())
+                .unwrap();
+            zip.write_all(data.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+
+        let path = PathBuf::from_str("res/test_wallet.json").unwrap();
+        let mut arweave =
+            Arweave::from_keypair_path(path, Url::from_str("http://127.0.0.1:1").unwrap()).unwrap();
+        arweave.set_gateway(Arc::new(MockGateway::new()));
+        let backend = Arc::new(MockBackend::new());
+        arweave.set_upload_backend(backend.clone());
+
+        let manifest = arweave.upload_archive(&archive_path, vec![]).await.unwrap();
+
+        let ids: Vec<String> = backend.uploads().iter().map(|u| u.id()).collect();
+        assert_eq!(manifest.get("index.html"), Some(ids[0].as_str()));
+        assert_eq!(manifest.get("img/logo.svg"), Some(ids[1].as_str()));
+        assert_eq!(manifest.resolve("/"), Some(ids[0].as_str()));
+        assert!(arweave
+            .upload_archive(std::path::Path::new("site.rar"), vec![])
+            .await
+            .is_err());
+    }
+
+    #[cfg(feature = "s3")]
+    #[tokio::test]
+    async fn should_upload_from_s3() {
+        use crate::s3::{S3Client, S3Config};
+
+        let server = MockServer::start_async().await;
+        server.mock(|when, then| {
+            when.method(GET)
+                .path("/bucket")
+                .query_param("list-type", "2");
+            then.status(200).body(
+                "<ListBucketResult><IsTruncated>false</IsTruncated>\
+                 <Contents><Key>site/index.html</Key><Size>5</Size></Contents>\
+                 <Contents><Key>site/broken.txt</Key><Size>5</Size></Contents>\
+                 </ListBucketResult>",
+            );
+        });
+        server.mock(|when, then| {
+            when.method(GET).path("/bucket/site/index.html");
+            then.status(200).body("hello");
+        });
+        let broken = server.mock(|when, then| {
+            when.method(GET).path("/bucket/site/broken.txt");
+            then.status(500);
+        });
+        let s3 = S3Client::new(S3Config {
+            endpoint: Url::parse(&server.url("/")).unwrap(),
+            region: "us-east-1".to_string(),
+            bucket: "bucket".to_string(),
+            access_key_id: "key".to_string(),
+            secret_access_key: "secret".to_string(),
+        });
+
+        let path = PathBuf::from_str("res/test_wallet.json").unwrap();
+        let mut arweave =
+            Arweave::from_keypair_path(path, Url::from_str("http://127.0.0.1:1").unwrap()).unwrap();
+        arweave.set_gateway(Arc::new(MockGateway::new()));
+        let backend = Arc::new(MockBackend::new());
+        arweave.set_upload_backend(backend.clone());
+
+        let import = arweave.upload_from_s3(&s3, "site/", vec![]).await.unwrap();
+        let uploads = backend.uploads();
+        assert_eq!(uploads.len(), 1);
+        assert_eq!(
+            import.manifest.get("index.html"),
+            Some(uploads[0].id().as_str())
+        );
+        assert_eq!(import.failed.len(), 1);
+        assert_eq!(import.failed[0].0, "site/broken.txt");
+        broken.assert_hits(crate::consts::S3_OBJECT_RETRIES as usize);
+    }
+
+    #[tokio::test]
+    async fn should_tag_uploads_with_ipfs_cid() {
+        let path = PathBuf::from_str("res/test_wallet.json").unwrap();
+        let mut arweave =
+            Arweave::from_keypair_path(path, Url::from_str("http://127.0.0.1:1").unwrap()).unwrap();
+        arweave.set_gateway(Arc::new(MockGateway::new()));
+        arweave.set_ipfs_tags(true);
+
+        let file_path = PathBuf::from_str("res/test_image.jpg").unwrap();
+        let (id, _) = arweave
+            .upload_file_from_path(file_path.clone(), vec![], 42)
+            .await
+            .unwrap();
+
+        let cid = arweave
+            .get_ipfs_cid(Base64::from_str(&id).unwrap())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(cid.matches(&fs::read(file_path).unwrap()));
+
+        // Spanning several IPFS blocks: uploaded without the tag.
+        let (id, _) = arweave
+            .upload_bytes(
+                PathBuf::from("large.bin"),
+                vec![7; IPFS_CHUNK_SIZE + 1],
+                vec![],
+                false,
+                42,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            arweave
+                .get_ipfs_cid(Base64::from_str(&id).unwrap())
+                .await
+                .unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn should_verify_digest_tags() {
+        let path = PathBuf::from_str("res/test_wallet.json").unwrap();
+        let mut arweave =
+            Arweave::from_keypair_path(path, Url::from_str("http://127.0.0.1:1").unwrap()).unwrap();
+        arweave.set_gateway(Arc::new(MockGateway::new()));
+        arweave.set_digest_tags(Some(DigestAlgorithm::Blake3));
+
+        let (id, _) = arweave
+            .upload_file_from_path(PathBuf::from_str("res/test_image.jpg").unwrap(), vec![], 42)
+            .await
+            .unwrap();
+        let id = Base64::from_str(&id).unwrap();
+
+        let (_, tx) = arweave.get_tx(id.clone()).await.unwrap();
+        let digest = Digest::from_tags(&tx.unwrap().tags).unwrap().unwrap();
+        assert_eq!(digest.algorithm, DigestAlgorithm::Blake3);
+        arweave.verify_digest(id.clone(), &digest).await.unwrap();
+
+        let wrong = Digest::compute(DigestAlgorithm::Blake3, b"something else");
+        assert!(matches!(
+            arweave.verify_digest(id, &wrong).await,
+            Err(Error::DigestMismatch(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn should_skip_duplicate_uploads() {
+        let server = MockServer::start_async().await;
+        let file_path = PathBuf::from_str("res/test_image.jpg").unwrap();
+        let digest = Digest::compute(DigestAlgorithm::Sha256, &fs::read(&file_path).unwrap());
+        let graphql = server.mock(|when, then| {
+            when.method(POST)
+                .path("/graphql")
+                .body_contains(digest.to_string());
+            then.status(200)
+                .json_body(serde_json::json!({ "data": { "transactions": {
+                "pageInfo": { "hasNextPage": false },
+                "edges": [{ "cursor": "c", "node": {
+                    "id": "existing",
+                    "anchor": "",
+                    "signature": "",
+                    "recipient": "",
+                    "owner": { "address": "", "key": "" },
+                    "fee": { "winston": "0", "ar": "0" },
+                    "quantity": { "winston": "0", "ar": "0" },
+                    "data": { "size": "0", "type": null },
+                    "tags": [],
+                    "block": null,
+                    "bundledIn": null
+                } }]
+            } } }));
+        });
+        let path = PathBuf::from_str("res/test_wallet.json").unwrap();
+        let mut arweave =
+            Arweave::from_keypair_path(path, Url::parse(&server.url("/")).unwrap()).unwrap();
+        let gateway = Arc::new(MockGateway::new());
+        arweave.set_gateway(gateway.clone());
+        arweave.set_dedup(true);
+        let mut events = arweave.subscribe_upload_events();
+
+        let (id, reward) = arweave
+            .upload_file_from_path(file_path.clone(), vec![], 42)
+            .await
+            .unwrap();
+        graphql.assert();
+        assert_eq!((id.as_str(), reward), ("existing", 0));
+        assert!(gateway.posted_ids().is_empty());
+        assert_eq!(
+            events.try_recv().unwrap(),
+            UploadEvent::Deduplicated {
+                path: file_path,
+                id
+            }
+        );
     }
 }
@@ -0,0 +1,114 @@
+//! On-chain event logs: small JSON entries posted by a wallet under an `App-Name`, numbered
+//! by a `Sequence` tag. See [`crate::Arweave::append_log`] and [`crate::Arweave::read_log`].
+
+use std::{collections::HashMap, sync::Mutex};
+
+use serde_json::Value;
+
+use crate::{
+    crypto::base64::Base64,
+    error::Error,
+    graphql::TxNode,
+    transaction::tags::{Tag, SEQUENCE, UNIX_TIME},
+};
+
+/// Content type of log entries.
+pub const LOG_CONTENT_TYPE: &str = "application/json";
+
+/// An entry of a log, as read back.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogEntry {
+    pub id: String,
+    pub sequence: u64,
+    pub unix_time: u64,
+    pub entry: Value,
+}
+
+impl LogEntry {
+    /// Entry of transaction `node` holding `data`. Fails when its tags or data aren't
+    /// those of a log entry.
+    pub fn from_node(node: &TxNode, data: &[u8]) -> Result<Self, Error> {
+        let number = |name: &str| {
+            node.tag(name)
+                .and_then(|value| value.parse().ok())
+                .ok_or_else(|| {
+                    Error::InvalidTagValue(format!("{}: missing on log entry {}", name, node.id))
+                })
+        };
+        Ok(Self {
+            id: node.id.clone(),
+            sequence: number(SEQUENCE)?,
+            unix_time: number(UNIX_TIME)?,
+            entry: serde_json::from_slice(data)
+                .map_err(|e| Error::LogEntryError(format!("{}: {}", node.id, e)))?,
+        })
+    }
+}
+
+/// Next sequence number of every log appended to. A log unknown to the counters is resumed
+/// from its last entry by [`crate::Arweave::append_log`].
+#[derive(Debug, Default)]
+pub struct LogCounters {
+    next: Mutex<HashMap<String, u64>>,
+}
+
+impl LogCounters {
+    /// Takes the next sequence number of log `app_name`, counting from zero if it is unknown.
+    pub fn next(&self, app_name: &str) -> u64 {
+        self.next_from(app_name, 0)
+    }
+
+    /// Takes the next sequence number of log `app_name` if it is known.
+    pub fn try_next(&self, app_name: &str) -> Option<u64> {
+        let mut next = self.next.lock().unwrap();
+        let counter = next.get_mut(app_name)?;
+        *counter += 1;
+        Some(*counter - 1)
+    }
+
+    /// Takes the next sequence number of log `app_name`, counting from `first` if it is
+    /// unknown.
+    pub fn next_from(&self, app_name: &str, first: u64) -> u64 {
+        let mut next = self.next.lock().unwrap();
+        let counter = next.entry(app_name.to_string()).or_insert(first);
+        *counter += 1;
+        *counter - 1
+    }
+
+    /// Resumes log `app_name` at `sequence`, e.g. one past the last entry read back.
+    pub fn set(&self, app_name: &str, sequence: u64) {
+        self.next
+            .lock()
+            .unwrap()
+            .insert(app_name.to_string(), sequence);
+    }
+}
+
+/// Tags of entry `sequence` of log `app_name`, written at `unix_time`.
+pub fn log_tags(app_name: &str, sequence: u64, unix_time: u64) -> Result<Vec<Tag<Base64>>, Error> {
+    Ok(vec![
+        Tag::<Base64>::content_type(LOG_CONTENT_TYPE)?,
+        Tag::<Base64>::app_name(app_name)?,
+        Tag::<Base64>::unix_time(unix_time),
+        Tag::<Base64>::sequence(sequence),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LogCounters;
+
+    #[test]
+    fn test_counters() {
+        let counters = LogCounters::default();
+        assert_eq!(counters.next("a"), 0);
+        assert_eq!(counters.next("a"), 1);
+        assert_eq!(counters.next("b"), 0);
+        counters.set("a", 10);
+        assert_eq!(counters.next("a"), 10);
+        assert_eq!(counters.try_next("c"), None);
+        assert_eq!(counters.next_from("c", 5), 5);
+        assert_eq!(counters.next_from("c", 0), 6);
+        assert_eq!(counters.try_next("c"), Some(7));
+    }
+}
@@ -0,0 +1,230 @@
+//! Arweave path manifests, mapping paths to transaction ids so a set of transactions can be
+//! served as a static site.
+
+use std::{
+    collections::BTreeMap,
+    path::{Component, Path},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+
+pub const MANIFEST_CONTENT_TYPE: &str = "application/x.arweave-manifest+json";
+pub const MANIFEST_TYPE: &str = "arweave/paths";
+pub const MANIFEST_VERSION: &str = "0.1.0";
+/// Version of manifests with a fallback, which gateways only honour from 0.2.0.
+pub const MANIFEST_FALLBACK_VERSION: &str = "0.2.0";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ManifestIndex {
+    pub path: String,
+}
+
+/// Transaction served for paths missing from the manifest, typically a 404 page.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ManifestFallback {
+    pub id: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ManifestPath {
+    pub id: String,
+}
+
+/// Outcome of [`crate::Arweave::sync_directory`], listing manifest paths.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyncReport {
+    /// Id of the manifest mirroring the directory.
+    pub manifest_id: String,
+    pub uploaded: Vec<String>,
+    pub unchanged: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Manifest {
+    pub manifest: String,
+    pub version: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub index: Option<ManifestIndex>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub fallback: Option<ManifestFallback>,
+    pub paths: BTreeMap<String, ManifestPath>,
+}
+
+impl Default for Manifest {
+    fn default() -> Self {
+        Self {
+            manifest: MANIFEST_TYPE.to_string(),
+            version: MANIFEST_VERSION.to_string(),
+            index: None,
+            fallback: None,
+            paths: BTreeMap::new(),
+        }
+    }
+}
+
+impl Manifest {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses a manifest, rejecting documents that aren't `arweave/paths` manifests.
+    pub fn from_slice(data: &[u8]) -> Result<Self, Error> {
+        let manifest: Manifest =
+            serde_json::from_slice(data).map_err(|e| Error::ManifestError(e.to_string()))?;
+        if manifest.manifest != MANIFEST_TYPE {
+            return Err(Error::ManifestError(format!(
+                "unsupported manifest type {}",
+                manifest.manifest
+            )));
+        }
+        Ok(manifest)
+    }
+
+    pub fn to_vec(&self) -> Result<Vec<u8>, Error> {
+        serde_json::to_vec(self).map_err(|e| Error::ManifestError(e.to_string()))
+    }
+
+    /// Maps `path` to transaction `id`, replacing any previous entry.
+    pub fn insert(&mut self, path: &str, id: &str) {
+        self.paths
+            .insert(path.to_string(), ManifestPath { id: id.to_string() });
+    }
+
+    /// Removes `path`, returning the id it mapped to. The index is cleared if it pointed
+    /// to `path`.
+    pub fn remove(&mut self, path: &str) -> Option<String> {
+        if self.index.as_ref().is_some_and(|index| index.path == path) {
+            self.index = None;
+        }
+        self.paths.remove(path).map(|p| p.id)
+    }
+
+    /// Sets the path served at the root of the manifest.
+    pub fn set_index(&mut self, path: &str) {
+        self.index = Some(ManifestIndex {
+            path: path.to_string(),
+        });
+    }
+
+    /// Sets the transaction served for paths the manifest doesn't contain, bumping the
+    /// manifest to [`MANIFEST_FALLBACK_VERSION`].
+    pub fn set_fallback(&mut self, id: &str) {
+        self.fallback = Some(ManifestFallback { id: id.to_string() });
+        self.version = MANIFEST_FALLBACK_VERSION.to_string();
+    }
+
+    /// Id of the transaction served at `path`, falling back to the index for the root and
+    /// to the fallback for unknown paths.
+    pub fn resolve(&self, path: &str) -> Option<&str> {
+        let path = path.trim_start_matches('/');
+        let path = match (&self.index, path) {
+            (Some(index), "") => index.path.as_str(),
+            _ => path,
+        };
+        self.get(path)
+            .or_else(|| self.fallback.as_ref().map(|f| f.id.as_str()))
+    }
+
+    /// Id of the transaction served at `path`.
+    pub fn get(&self, path: &str) -> Option<&str> {
+        self.paths.get(path).map(|p| p.id.as_str())
+    }
+
+    /// Adds every `(path, id)` of `entries`, new entries winning over existing ones.
+    pub fn extend<I: IntoIterator<Item = (String, String)>>(&mut self, entries: I) {
+        for (path, id) in entries {
+            self.paths.insert(path, ManifestPath { id });
+        }
+    }
+
+    /// Manifest path of `file_path`: its path relative to `base_dir`, `/` separated.
+    pub fn path_key(base_dir: &Path, file_path: &Path) -> Result<String, Error> {
+        let relative = file_path.strip_prefix(base_dir).map_err(|_| {
+            Error::ManifestError(format!(
+                "{} is not inside {}",
+                file_path.display(),
+                base_dir.display()
+            ))
+        })?;
+        let parts = relative
+            .components()
+            .map(|c| match c {
+                Component::Normal(part) => part.to_str().map(str::to_string).ok_or_else(|| {
+                    Error::ManifestError(format!("{} is not valid UTF-8", file_path.display()))
+                }),
+                _ => Err(Error::ManifestError(format!(
+                    "unsupported path {}",
+                    file_path.display()
+                ))),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(parts.join("/"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::Manifest;
+
+    #[test]
+    fn test_parse_and_extend() {
+        let mut manifest = Manifest::from_slice(
+            br#"{"manifest":"arweave/paths","version":"0.1.0","index":{"path":"index.html"},
+                "paths":{"index.html":{"id":"a"},"app.js":{"id":"b"}}}"#,
+        )
+        .unwrap();
+        manifest.extend([
+            ("app.js".to_string(), "c".to_string()),
+            ("img/logo.png".to_string(), "d".to_string()),
+        ]);
+
+        assert_eq!(manifest.get("index.html"), Some("a"));
+        assert_eq!(manifest.get("app.js"), Some("c"));
+        assert_eq!(manifest.get("img/logo.png"), Some("d"));
+        assert_eq!(
+            Manifest::from_slice(&manifest.to_vec().unwrap()).unwrap(),
+            manifest
+        );
+        assert!(Manifest::from_slice(br#"{"manifest":"other","version":"1","paths":{}}"#).is_err());
+    }
+
+    #[test]
+    fn test_index_and_fallback() {
+        let mut manifest = Manifest::new();
+        manifest.insert("index.html", "index-id");
+        assert_eq!(manifest.resolve("/"), None);
+
+        manifest.set_index("index.html");
+        manifest.set_fallback("not-found-id");
+        assert_eq!(manifest.resolve("/"), Some("index-id"));
+        assert_eq!(manifest.resolve("/index.html"), Some("index-id"));
+        assert_eq!(manifest.resolve("missing"), Some("not-found-id"));
+
+        let json: serde_json::Value = serde_json::from_slice(&manifest.to_vec().unwrap()).unwrap();
+        assert_eq!(json["index"]["path"], "index.html");
+        assert_eq!(json["fallback"]["id"], "not-found-id");
+        assert_eq!(json["version"], "0.2.0");
+        assert!(!String::from_utf8(Manifest::new().to_vec().unwrap())
+            .unwrap()
+            .contains("fallback"));
+
+        assert_eq!(manifest.remove("index.html"), Some("index-id".to_string()));
+        assert_eq!(manifest.index, None);
+        assert_eq!(manifest.resolve("/"), Some("not-found-id"));
+    }
+
+    #[test]
+    fn test_path_key() {
+        let base = Path::new("site");
+        assert_eq!(
+            Manifest::path_key(base, &base.join("img").join("logo.png")).unwrap(),
+            "img/logo.png"
+        );
+        assert!(Manifest::path_key(base, Path::new("other/file")).is_err());
+    }
+}
@@ -1,81 +1,160 @@
-use pretend::{pretend, resolver::UrlResolver, JsonResult, Pretend, Url};
-use pretend_reqwest::Client as HttpClient;
-use serde::{Deserialize, Serialize};
+use std::{str::FromStr, time::Duration};
+
+use serde::de::DeserializeOwned;
+use url::Url;
 
 use crate::{
+    consts::{
+        ANCHOR_RETRIES, ARLOCAL_BASE_URL, ARWEAVE_BASE_URL, BLOCK_TIME, CHUNKS_RETRIES,
+        MAX_RETRY_AFTER, MAX_TX_DATA, TESTNET_BASE_URL,
+    },
     error::Error,
-    types::{BlockInfo, NetworkInfo},
+    gateway::MiddlewareStack,
+    types::{parse_response, BlockInfo, NetworkInfo},
 };
-#[derive(Serialize, Deserialize, Debug)]
-struct HeightInfo {
-    height: u64,
+
+pub mod testing;
+
+/// Parameters that differ between networks, picked when building an
+/// [`crate::Arweave`] with [`crate::Arweave::for_network`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NetworkConfig {
+    pub name: String,
+    pub gateway_url: Url,
+    /// Data larger than this is posted in chunks rather than with the transaction.
+    pub max_tx_data: u64,
+    /// Times a transaction rejected for a stale anchor is re-anchored and re-posted.
+    pub anchor_retries: u16,
+    /// Times a failed chunk is posted again.
+    pub chunk_retries: u16,
+    /// Longest `Retry-After` a rate limited chunk waits out. Chunks asked to wait longer fail
+    /// at once.
+    pub max_retry_after: Duration,
+    /// Average time between blocks. Confirmations are polled at least once per block.
+    pub block_time: Duration,
+    /// Node minting free tokens with ArLocal's `/mint/{address}/{winstons}` endpoint, on
+    /// networks where tokens are worthless. See [`crate::Arweave::request_faucet`].
+    pub faucet_url: Option<Url>,
 }
-#[pretend]
-trait NetworkInfoFetch {
-    #[request(method = "GET", path = "/info")]
-    async fn network_info(&self) -> pretend::Result<JsonResult<NetworkInfo, Error>>;
 
-    #[request(method = "GET", path = "/peers")]
-    async fn peer_info(&self) -> pretend::Result<JsonResult<Vec<String>, Error>>;
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self::mainnet()
+    }
+}
+
+impl NetworkConfig {
+    pub fn mainnet() -> Self {
+        Self {
+            name: "mainnet".to_string(),
+            gateway_url: Url::from_str(ARWEAVE_BASE_URL).unwrap(),
+            max_tx_data: MAX_TX_DATA,
+            anchor_retries: ANCHOR_RETRIES,
+            chunk_retries: CHUNKS_RETRIES,
+            max_retry_after: Duration::from_secs(MAX_RETRY_AFTER),
+            block_time: Duration::from_secs(BLOCK_TIME),
+            faucet_url: None,
+        }
+    }
+
+    /// Public testnet, to exercise the whole upload path, chunks included, without real AR.
+    /// Its faucet funds wallets on request.
+    pub fn testnet() -> Self {
+        let gateway_url = Url::from_str(TESTNET_BASE_URL).unwrap();
+        Self {
+            name: "testnet".to_string(),
+            faucet_url: Some(gateway_url.clone()),
+            gateway_url,
+            ..Self::mainnet()
+        }
+    }
 
-    #[request(method = "GET", path = "/block/hash/{id}")]
-    async fn block_by_hash(&self, id: &str) -> pretend::Result<JsonResult<BlockInfo, Error>>;
+    /// Local ArLocal node, mining blocks on demand. Failures are not transient there, so
+    /// nothing is retried.
+    pub fn arlocal() -> Self {
+        let gateway_url = Url::from_str(ARLOCAL_BASE_URL).unwrap();
+        Self {
+            name: "arlocal".to_string(),
+            faucet_url: Some(gateway_url.clone()),
+            gateway_url,
+            max_tx_data: MAX_TX_DATA,
+            anchor_retries: 0,
+            chunk_retries: 0,
+            max_retry_after: Duration::from_secs(MAX_RETRY_AFTER),
+            block_time: Duration::from_secs(1),
+        }
+    }
 
-    #[request(method = "GET", path = "/block/height/{height}")]
-    async fn block_by_height(&self, height: u64) -> pretend::Result<JsonResult<BlockInfo, Error>>;
+    /// Same network through another gateway, e.g. a self-hosted one. A faucet served by the
+    /// previous gateway moves along.
+    pub fn with_gateway(mut self, gateway_url: Url) -> Self {
+        if self.faucet_url.as_ref() == Some(&self.gateway_url) {
+            self.faucet_url = Some(gateway_url.clone());
+        }
+        self.gateway_url = gateway_url;
+        self
+    }
 }
 
-pub struct NetworkInfoClient(Pretend<HttpClient, UrlResolver>);
+/// Client of the network and block endpoints of a gateway.
+pub struct NetworkInfoClient {
+    client: reqwest::Client,
+    url: Url,
+    middleware: MiddlewareStack,
+}
 
 impl NetworkInfoClient {
     pub fn new(url: Url) -> Self {
-        let client = HttpClient::default();
-        let pretend = Pretend::for_client(client).with_url(url);
-        Self(pretend)
+        Self::with_client(url, reqwest::Client::new())
     }
 
-    pub async fn network_info(&self) -> Result<NetworkInfo, Error> {
-        let response = self
-            .0
-            .network_info()
-            .await
-            .expect("Error getting network info");
-        match response {
-            JsonResult::Ok(n) => Ok(n),
-            JsonResult::Err(err) => Err(err),
+    pub fn with_client(url: Url, client: reqwest::Client) -> Self {
+        Self {
+            client,
+            url,
+            middleware: MiddlewareStack::default(),
         }
     }
 
-    pub async fn peer_info(&self) -> Result<Vec<String>, Error> {
-        let response = self.0.peer_info().await.expect("Error getting peer info");
-        match response {
-            JsonResult::Ok(n) => Ok(n),
-            JsonResult::Err(err) => Err(err),
+    /// Runs every request of this client through `middleware`.
+    pub fn with_middleware(mut self, middleware: MiddlewareStack) -> Self {
+        self.middleware = middleware;
+        self
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, Error> {
+        let url = self
+            .url
+            .join(path)
+            .map_err(|e| Error::NetworkInfoError(e.to_string()))?;
+        let res = self
+            .middleware
+            .send(&self.client, self.client.get(url), Error::NetworkInfoError)
+            .await?;
+        if !res.status().is_success() {
+            return Err(Error::NetworkInfoError(res.status().to_string()));
         }
+        let body = res
+            .bytes()
+            .await
+            .map_err(|e| Error::NetworkInfoError(e.to_string()))?;
+        parse_response(&body).map_err(Error::NetworkInfoError)
+    }
+
+    pub async fn network_info(&self) -> Result<NetworkInfo, Error> {
+        self.get("info").await
+    }
+
+    pub async fn peer_info(&self) -> Result<Vec<String>, Error> {
+        self.get("peers").await
     }
 
     pub async fn block_by_hash(&self, id: &str) -> Result<BlockInfo, Error> {
-        let response = self
-            .0
-            .block_by_hash(id)
-            .await
-            .expect("Error getting block info");
-        match response {
-            JsonResult::Ok(n) => Ok(n),
-            JsonResult::Err(err) => Err(err),
-        }
+        self.get(&format!("block/hash/{}", id)).await
     }
 
-    pub async fn block_by_height(&self, id: &str) -> Result<BlockInfo, Error> {
-        let response = self
-            .0
-            .block_by_hash(id)
-            .await
-            .expect("Error getting block info");
-        match response {
-            JsonResult::Ok(n) => Ok(n),
-            JsonResult::Err(err) => Err(err),
-        }
+    pub async fn block_by_height(&self, height: u64) -> Result<BlockInfo, Error> {
+        self.get(&format!("block/height/{}", height)).await
     }
 }
 
@@ -83,9 +162,10 @@ impl NetworkInfoClient {
 mod tests {
     use std::str::FromStr;
 
-    use crate::{crypto::base64::Base64, network::NetworkInfoClient, ARWEAVE_BASE_URL};
-    use pretend::Url;
     use tokio_test::block_on;
+    use url::Url;
+
+    use crate::{crypto::base64::Base64, network::NetworkInfoClient, ARWEAVE_BASE_URL};
 
     #[test]
     fn test_network_info() {
@@ -0,0 +1,62 @@
+//! Control of an ArLocal node through its admin endpoints, so integration tests can advance
+//! the chain deterministically after posting. ArLocal only mines when asked to.
+
+use url::Url;
+
+use crate::error::Error;
+
+/// Mines `blocks` blocks on the ArLocal node at `url`, confirming the pending transactions.
+pub async fn mine(url: &Url, blocks: u64) -> Result<(), Error> {
+    admin_get(url, &format!("mine/{}", blocks)).await
+}
+
+/// Resets the ArLocal node at `url` to an empty chain, dropping transactions and balances.
+pub async fn reset(url: &Url) -> Result<(), Error> {
+    admin_get(url, "reset").await
+}
+
+async fn admin_get(url: &Url, path: &str) -> Result<(), Error> {
+    let url = url
+        .join(path)
+        .map_err(|e| Error::NetworkInfoError(e.to_string()))?;
+    let res = reqwest::get(url.clone())
+        .await
+        .map_err(|e| Error::NetworkInfoError(format!("{}: {}", url, e)))?;
+    if !res.status().is_success() {
+        return Err(Error::NetworkInfoError(format!(
+            "{}: {}",
+            url,
+            res.status()
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use httpmock::{Method::GET, MockServer};
+    use url::Url;
+
+    use super::{mine, reset};
+
+    #[tokio::test]
+    async fn test_mine_and_reset() {
+        let server = MockServer::start_async().await;
+        let url = Url::parse(&server.base_url()).unwrap();
+        let mined = server.mock(|when, then| {
+            when.method(GET).path("/mine/3");
+            then.status(200)
+                .json_body(serde_json::json!({ "height": 3 }));
+        });
+        let reset_mock = server.mock(|when, then| {
+            when.method(GET).path("/reset");
+            then.status(200).body("reset");
+        });
+
+        mine(&url, 3).await.unwrap();
+        reset(&url).await.unwrap();
+        mined.assert();
+        reset_mock.assert();
+        assert!(mine(&url, 4).await.is_err());
+    }
+}
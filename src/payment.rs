@@ -0,0 +1,92 @@
+//! Checks of AR payments received by a service. See [`crate::Arweave::verify_payment`].
+
+use crate::{crypto::base64::Base64, transaction::Tx};
+
+/// Outcome of checking a payment transaction against what was expected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PaymentStatus {
+    /// Paid as expected, with enough confirmations.
+    Confirmed { quantity: u128, confirmations: u64 },
+    /// Paid as expected, but with fewer confirmations than required, zero while pending.
+    Unconfirmed { quantity: u128, confirmations: u64 },
+    /// Known to the gateway but not mined, so its content can't be checked yet.
+    Pending,
+    /// Sent to another wallet.
+    WrongTarget { target: Base64 },
+    /// Transfers less than expected.
+    Underpaid { quantity: u128 },
+}
+
+impl PaymentStatus {
+    pub fn is_confirmed(&self) -> bool {
+        matches!(self, PaymentStatus::Confirmed { .. })
+    }
+
+    /// Status of `tx`, having `confirmations`, as a payment of at least `min_quantity`
+    /// winstons to `expected_target`.
+    pub fn of(
+        tx: &Tx,
+        expected_target: &Base64,
+        min_quantity: u128,
+        confirmations: u64,
+        min_confirmations: u64,
+    ) -> Self {
+        let quantity = tx.quantity.winstons();
+        if !tx.target.ct_eq(expected_target) {
+            PaymentStatus::WrongTarget {
+                target: tx.target.clone(),
+            }
+        } else if quantity < min_quantity {
+            PaymentStatus::Underpaid { quantity }
+        } else if confirmations < min_confirmations {
+            PaymentStatus::Unconfirmed {
+                quantity,
+                confirmations,
+            }
+        } else {
+            PaymentStatus::Confirmed {
+                quantity,
+                confirmations,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PaymentStatus;
+    use crate::{crypto::base64::Base64, currency::Currency, transaction::Tx};
+
+    #[test]
+    fn test_payment_status() {
+        let merchant = Base64::from(vec![1; 32]);
+        let tx = Tx {
+            target: merchant.clone(),
+            quantity: Currency::from(100_u128),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            PaymentStatus::of(&tx, &merchant, 100, 3, 3),
+            PaymentStatus::Confirmed {
+                quantity: 100,
+                confirmations: 3
+            }
+        );
+        assert_eq!(
+            PaymentStatus::of(&tx, &merchant, 100, 1, 3),
+            PaymentStatus::Unconfirmed {
+                quantity: 100,
+                confirmations: 1
+            }
+        );
+        assert_eq!(
+            PaymentStatus::of(&tx, &merchant, 101, 3, 3),
+            PaymentStatus::Underpaid { quantity: 100 }
+        );
+        assert!(matches!(
+            PaymentStatus::of(&tx, &Base64::from(vec![2; 32]), 100, 3, 3),
+            PaymentStatus::WrongTarget { .. }
+        ));
+    }
+}
@@ -0,0 +1,143 @@
+//! Fee quotes sampled over time, so schedulers can post large uploads during cheaper
+//! periods.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::consts::PRICE_TREND_THRESHOLD;
+
+/// A fee quoted for `data_size` bytes at `timestamp`, in seconds since the epoch.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PriceSample {
+    pub timestamp: u64,
+    pub data_size: u64,
+    pub fee: u64,
+}
+
+/// Direction of the fees sampled for a data size.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trend {
+    Rising,
+    Falling,
+    Flat,
+}
+
+/// Samples returned by [`crate::Arweave::get_price_history`], grouped by data size and
+/// ordered by time.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct PriceHistory {
+    samples: BTreeMap<u64, Vec<PriceSample>>,
+}
+
+impl PriceHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, sample: PriceSample) {
+        let samples = self.samples.entry(sample.data_size).or_default();
+        let at = samples.partition_point(|s| s.timestamp <= sample.timestamp);
+        samples.insert(at, sample);
+    }
+
+    pub fn samples(&self, data_size: u64) -> &[PriceSample] {
+        self.samples.get(&data_size).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn sizes(&self) -> impl Iterator<Item = u64> + '_ {
+        self.samples.keys().copied()
+    }
+
+    pub fn latest(&self, data_size: u64) -> Option<u64> {
+        self.samples(data_size).last().map(|s| s.fee)
+    }
+
+    pub fn min(&self, data_size: u64) -> Option<u64> {
+        self.samples(data_size).iter().map(|s| s.fee).min()
+    }
+
+    pub fn average(&self, data_size: u64) -> Option<f64> {
+        let samples = self.samples(data_size);
+        if samples.is_empty() {
+            return None;
+        }
+        Some(samples.iter().map(|s| s.fee as f64).sum::<f64>() / samples.len() as f64)
+    }
+
+    /// Change of the fee over the sampled period, relative to the average fee, estimated by
+    /// a least-squares fit so a single outlier doesn't flip it. `None` with fewer than two
+    /// samples or when they were all taken at the same time.
+    pub fn change(&self, data_size: u64) -> Option<f64> {
+        let samples = self.samples(data_size);
+        let (first, last) = (samples.first()?, samples.last()?);
+        let span = (last.timestamp - first.timestamp) as f64;
+        let mean_fee = self.average(data_size)?;
+        if span == 0.0 || mean_fee == 0.0 {
+            return None;
+        }
+        let n = samples.len() as f64;
+        let mean_t = samples
+            .iter()
+            .map(|s| (s.timestamp - first.timestamp) as f64)
+            .sum::<f64>()
+            / n;
+        let (cov, var) = samples.iter().fold((0.0, 0.0), |(cov, var), s| {
+            let dt = (s.timestamp - first.timestamp) as f64 - mean_t;
+            (cov + dt * (s.fee as f64 - mean_fee), var + dt * dt)
+        });
+        Some(cov / var * span / mean_fee)
+    }
+
+    /// Trend of the fee, flat when it changed by less than [`PRICE_TREND_THRESHOLD`].
+    pub fn trend(&self, data_size: u64) -> Option<Trend> {
+        let change = self.change(data_size)?;
+        Some(if change > PRICE_TREND_THRESHOLD {
+            Trend::Rising
+        } else if change < -PRICE_TREND_THRESHOLD {
+            Trend::Falling
+        } else {
+            Trend::Flat
+        })
+    }
+
+    /// Whether the latest fee is below the average of the samples, i.e. now is a cheaper
+    /// period than usual.
+    pub fn is_cheap(&self, data_size: u64) -> Option<bool> {
+        Some((self.latest(data_size)? as f64) < self.average(data_size)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PriceHistory, PriceSample, Trend};
+
+    fn history(fees: &[u64]) -> PriceHistory {
+        let mut history = PriceHistory::new();
+        for (i, fee) in fees.iter().enumerate().rev() {
+            history.record(PriceSample {
+                timestamp: 60 * i as u64,
+                data_size: 1024,
+                fee: *fee,
+            });
+        }
+        history
+    }
+
+    #[test]
+    fn test_trend() {
+        let rising = history(&[100, 104, 103, 110]);
+        assert_eq!(rising.samples(1024)[0].fee, 100);
+        assert_eq!(rising.trend(1024), Some(Trend::Rising));
+        assert_eq!(rising.is_cheap(1024), Some(false));
+
+        let falling = history(&[110, 90, 100, 80]);
+        assert_eq!(falling.trend(1024), Some(Trend::Falling));
+        assert_eq!(falling.min(1024), Some(80));
+        assert_eq!(falling.is_cheap(1024), Some(true));
+
+        assert_eq!(history(&[100, 100, 100]).trend(1024), Some(Trend::Flat));
+        assert_eq!(history(&[100]).trend(1024), None);
+        assert_eq!(history(&[100]).trend(2048), None);
+    }
+}
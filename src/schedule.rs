@@ -0,0 +1,186 @@
+//! Recurring snapshots of a directory, uploading the files whose content changed since the
+//! previous run. See [`crate::batch::UploadManager::run_scheduled`].
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    crypto::base64::Base64,
+    digest::{Digest, DigestAlgorithm},
+    error::Error,
+    transaction::tags::Tag,
+};
+
+const DAY: u64 = 24 * 60 * 60;
+
+/// When a recurring job runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Schedule {
+    /// Every period, the first run one period after the job starts.
+    Every(Duration),
+    /// Every day, this long after midnight UTC.
+    Daily(Duration),
+}
+
+impl Schedule {
+    /// Every day at `hour:minute` UTC.
+    pub fn daily_at(hour: u64, minute: u64) -> Self {
+        Schedule::Daily(Duration::from_secs((hour * 60 + minute) * 60 % DAY))
+    }
+
+    /// First run strictly after `time`.
+    pub fn next_after(&self, time: SystemTime) -> SystemTime {
+        match self {
+            Schedule::Every(period) => time + *period,
+            Schedule::Daily(offset) => {
+                let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+                let midnight = since_epoch.as_secs() / DAY * DAY;
+                let mut next = Duration::from_secs(midnight) + *offset;
+                if next <= since_epoch {
+                    next += Duration::from_secs(DAY);
+                }
+                UNIX_EPOCH + next
+            }
+        }
+    }
+}
+
+/// A directory snapshotted again and again, remembering the digest of every file uploaded
+/// so that unchanged files are skipped.
+#[derive(Debug, Clone)]
+pub struct SnapshotJob {
+    dir: PathBuf,
+    algorithm: DigestAlgorithm,
+    tags: Vec<Tag<Base64>>,
+    digests: HashMap<PathBuf, Digest>,
+}
+
+impl SnapshotJob {
+    pub fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            algorithm: DigestAlgorithm::Sha256,
+            tags: Vec::new(),
+            digests: HashMap::new(),
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn set_algorithm(&mut self, algorithm: DigestAlgorithm) {
+        self.algorithm = algorithm;
+        self.digests.clear();
+    }
+
+    /// Tags added to every uploaded file, besides its content type.
+    pub fn set_tags(&mut self, tags: Vec<Tag<Base64>>) {
+        self.tags = tags;
+    }
+
+    pub fn tags(&self) -> &[Tag<Base64>] {
+        &self.tags
+    }
+
+    /// Digest of the last uploaded content of every file.
+    pub fn digests(&self) -> &HashMap<PathBuf, Digest> {
+        &self.digests
+    }
+
+    /// Marks `path` as uploaded with content `digest`, e.g. to resume from a previous run.
+    pub fn record(&mut self, path: PathBuf, digest: Digest) {
+        self.digests.insert(path, digest);
+    }
+
+    /// Files of the directory that are new or whose content changed since recorded, with
+    /// their current digest.
+    pub fn changed_files(&self) -> Result<Vec<(PathBuf, Digest)>, Error> {
+        let mut changed = Vec::new();
+        for path in list_files(&self.dir)? {
+            let digest = Digest::of_file(self.algorithm, &path)?;
+            if self.digests.get(&path) != Some(&digest) {
+                changed.push((path, digest));
+            }
+        }
+        Ok(changed)
+    }
+}
+
+/// Regular files below `dir`, recursively, sorted by path.
+pub fn list_files(dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    let io_error = |e: std::io::Error| Error::IoError(format!("{}: {}", dir.display(), e));
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir).map_err(io_error)? {
+        let entry = entry.map_err(io_error)?;
+        let file_type = entry.file_type().map_err(io_error)?;
+        if file_type.is_dir() {
+            files.extend(list_files(&entry.path())?);
+        } else if file_type.is_file() {
+            files.push(entry.path());
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        time::{Duration, UNIX_EPOCH},
+    };
+
+    use super::{list_files, Schedule, SnapshotJob};
+
+    #[test]
+    fn test_next_run() {
+        let noon = UNIX_EPOCH + Duration::from_secs(10 * 86400 + 12 * 3600);
+        assert_eq!(
+            Schedule::Every(Duration::from_secs(60)).next_after(noon),
+            noon + Duration::from_secs(60)
+        );
+        assert_eq!(
+            Schedule::daily_at(13, 30).next_after(noon),
+            noon + Duration::from_secs(5400)
+        );
+        assert_eq!(
+            Schedule::daily_at(12, 0).next_after(noon),
+            noon + Duration::from_secs(86400)
+        );
+        assert_eq!(
+            Schedule::daily_at(2, 0).next_after(noon),
+            noon + Duration::from_secs(14 * 3600)
+        );
+    }
+
+    #[test]
+    fn test_changed_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().to_path_buf();
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("a.txt"), "a").unwrap();
+        fs::write(dir.join("sub/b.txt"), "b").unwrap();
+        assert_eq!(
+            list_files(&dir).unwrap(),
+            vec![dir.join("a.txt"), dir.join("sub/b.txt")]
+        );
+
+        let mut job = SnapshotJob::new(dir.clone());
+        let changed = job.changed_files().unwrap();
+        assert_eq!(changed.len(), 2);
+        for (path, digest) in changed {
+            job.record(path, digest);
+        }
+        assert!(job.changed_files().unwrap().is_empty());
+
+        fs::write(dir.join("sub/b.txt"), "c").unwrap();
+        let changed = job.changed_files().unwrap();
+        assert_eq!(changed.len(), 1);
+        assert_eq!(changed[0].0, dir.join("sub/b.txt"));
+    }
+}
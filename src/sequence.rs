@@ -0,0 +1,111 @@
+//! Sequence numbers stamped on outgoing transactions as `Sequence` tags, since Arweave has
+//! no nonces to order the transactions of a wallet. See [`crate::Arweave::set_sequence`].
+
+use std::{
+    fs,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use tokio::{io::AsyncWriteExt, sync::Mutex};
+
+use crate::error::Error;
+
+/// Monotonic counter persisted in a file holding the next number.
+#[derive(Debug)]
+pub struct SequenceManager {
+    path: PathBuf,
+    next: Mutex<u64>,
+}
+
+impl SequenceManager {
+    /// Opens the counter at `path`, starting from zero when the file doesn't exist.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref().to_path_buf();
+        let next = match fs::read_to_string(&path) {
+            Ok(contents) => contents.trim().parse().map_err(|_| {
+                Error::IoError(format!(
+                    "{}: {:?} is not a sequence number",
+                    path.display(),
+                    contents
+                ))
+            })?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => 0,
+            Err(e) => return Err(io_error(&path, e)),
+        };
+        Ok(Self {
+            path,
+            next: Mutex::new(next),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Number the next transaction gets.
+    pub async fn peek(&self) -> u64 {
+        *self.next.lock().await
+    }
+
+    /// Takes the next number. It is persisted before being returned, so no number is handed
+    /// out twice, even across crashes; numbers of transactions never posted leave gaps.
+    pub async fn next(&self) -> Result<u64, Error> {
+        let mut next = self.next.lock().await;
+        let tmp = self.path.with_extension("tmp");
+        let mut file = tokio::fs::File::create(&tmp)
+            .await
+            .map_err(|e| io_error(&tmp, e))?;
+        file.write_all((*next + 1).to_string().as_bytes())
+            .await
+            .map_err(|e| io_error(&tmp, e))?;
+        file.sync_all().await.map_err(|e| io_error(&tmp, e))?;
+        tokio::fs::rename(&tmp, &self.path)
+            .await
+            .map_err(|e| io_error(&self.path, e))?;
+        *next += 1;
+        Ok(*next - 1)
+    }
+}
+
+/// Ranges missing from `sequences` between their lowest and highest number.
+pub fn find_gaps(sequences: impl IntoIterator<Item = u64>) -> Vec<Range<u64>> {
+    let mut sequences: Vec<u64> = sequences.into_iter().collect();
+    sequences.sort_unstable();
+    sequences.dedup();
+    sequences
+        .windows(2)
+        .filter(|pair| pair[1] > pair[0] + 1)
+        .map(|pair| pair[0] + 1..pair[1])
+        .collect()
+}
+
+fn io_error(path: &Path, e: std::io::Error) -> Error {
+    Error::IoError(format!("{}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_gaps, SequenceManager};
+
+    #[tokio::test]
+    async fn test_sequence_manager() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sequence");
+
+        let sequence = SequenceManager::open(&path).unwrap();
+        assert_eq!(sequence.next().await.unwrap(), 0);
+        assert_eq!(sequence.next().await.unwrap(), 1);
+        assert_eq!(sequence.peek().await, 2);
+
+        let reopened = SequenceManager::open(&path).unwrap();
+        assert_eq!(reopened.next().await.unwrap(), 2);
+    }
+
+    #[test]
+    fn test_find_gaps() {
+        assert_eq!(find_gaps([3, 0, 1, 6, 1]), vec![2..3, 4..6]);
+        assert!(find_gaps([]).is_empty());
+        assert!(find_gaps([5, 6, 7]).is_empty());
+    }
+}
@@ -1,55 +1,126 @@
-use std::path::PathBuf;
+use std::{future::Future, path::PathBuf, sync::Arc};
 
-use data_encoding::BASE64URL;
-use jsonwebkey::JsonWebKey;
-use rand::thread_rng;
-use rsa::{pkcs8::DecodePublicKey, PaddingScheme, PublicKey, RsaPublicKey};
-use sha2::Digest;
+use rand::{thread_rng, RngCore};
+use serde::Serialize;
 
 use crate::{
-    crypto::{base64::Base64, hash::ToItems, Provider},
+    crypto::{
+        base64::Base64,
+        canonical::canonicalize,
+        hash::{deep_hash, DeepHashItem},
+        sign::{verify_pss, RsaPublicKey, Signer},
+        Provider,
+    },
     error::Error,
     transaction::Tx,
 };
 
+/// First item of the deep hash signed by [`ArweaveSigner::sign_message`].
+const MESSAGE_DOMAIN: &[u8] = b"arweave-rs message";
+
+/// Who an operation is performed for, when one client signs with the keys of many users.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SignerContext {
+    pub tenant: String,
+}
+
+impl SignerContext {
+    pub fn new(tenant: &str) -> Self {
+        Self {
+            tenant: tenant.to_string(),
+        }
+    }
+
+    /// Context of the enclosing [`SignerContext::scope`], if any.
+    pub fn current() -> Option<SignerContext> {
+        CURRENT_CONTEXT.try_with(Clone::clone).ok()
+    }
+
+    /// Runs `f` with `self` as the current context.
+    pub async fn scope<F: Future>(self, f: F) -> F::Output {
+        CURRENT_CONTEXT.scope(self, f).await
+    }
+}
+
+tokio::task_local! {
+    static CURRENT_CONTEXT: SignerContext;
+}
+
+/// Picks the signer of a tenant, `None` if it has none.
+pub type SignerResolver = Arc<dyn Fn(&SignerContext) -> Option<ArweaveSigner> + Send + Sync>;
+
+/// Cloning is cheap: clones share the same key.
+#[derive(Clone)]
 pub struct ArweaveSigner {
-    crypto: Box<Provider>,
+    crypto: Arc<Provider>,
 }
 
 impl Default for ArweaveSigner {
     fn default() -> Self {
         Self {
-            crypto: Box::new(Provider::default()),
+            crypto: Arc::new(Provider::default()),
         }
     }
 }
 
 impl ArweaveSigner {
     pub fn verify(pub_key: &[u8], message: &[u8], signature: &[u8]) -> Result<(), Error> {
-        let crypto = Provider::default();
-        match crypto.verify(pub_key, message, signature) {
-            true => Ok(()),
-            false => Err(Error::InvalidSignature),
-        }
+        verify_pss(pub_key, message, signature)
     }
 
     pub fn from_keypair_path(keypair_path: PathBuf) -> Result<ArweaveSigner, Error> {
         let crypto = Provider::from_keypair_path(keypair_path);
         let signer = ArweaveSigner {
-            crypto: Box::new(crypto),
+            crypto: Arc::new(crypto),
         };
         Ok(signer)
     }
 
-    pub fn sign_transaction(&self, mut transaction: Tx) -> Result<Tx, Error> {
-        let deep_hash_item = transaction
-            .to_deep_hash_item()
-            .expect("Could not convert transaction into deep hash item");
-        let signature_data = self.crypto.deep_hash(deep_hash_item);
-        let signature = self.crypto.sign(&signature_data);
-        let id = self.crypto.hash_sha256(&signature.0);
-        transaction.signature = signature;
-        transaction.id = Base64(id.to_vec());
+    pub fn from_signer(signer: Signer) -> ArweaveSigner {
+        ArweaveSigner {
+            crypto: Arc::new(Provider::new(Box::new(signer))),
+        }
+    }
+
+    /// Signer from a PEM encoded PKCS#8 or PKCS#1 RSA key.
+    pub fn from_pem_str(pem: &str) -> Result<ArweaveSigner, Error> {
+        Signer::from_pem_str(pem).map(Self::from_signer)
+    }
+
+    /// Signer from a DER encoded PKCS#8 or PKCS#1 RSA key.
+    pub fn from_der_bytes(der: &[u8]) -> Result<ArweaveSigner, Error> {
+        Signer::from_der_bytes(der).map(Self::from_signer)
+    }
+
+    /// Signer from a base64 encoded JWK keyfile in environment variable `var`, so keys can
+    /// come from a secret manager without touching the filesystem.
+    pub fn from_base64_env(var: &str) -> Result<ArweaveSigner, Error> {
+        Signer::from_base64_env(var).map(Self::from_signer)
+    }
+
+    pub fn sign_transaction(&self, transaction: Tx) -> Result<Tx, Error> {
+        self.sign_transaction_with_rng(transaction, thread_rng())
+    }
+
+    /// Signs `transaction` on tokio's blocking thread pool, so the RSA work doesn't stall
+    /// the async executor.
+    pub async fn sign_transaction_async(&self, transaction: Tx) -> Result<Tx, Error> {
+        let signer = self.clone();
+        tokio::task::spawn_blocking(move || signer.sign_transaction(transaction))
+            .await
+            .map_err(|e| Error::TaskError(e.to_string()))?
+    }
+
+    /// Signs `transaction` drawing the PSS salt from `rng`, so a seeded rng reproduces the
+    /// same signature and id.
+    pub fn sign_transaction_with_rng(
+        &self,
+        mut transaction: Tx,
+        rng: impl RngCore + 'static,
+    ) -> Result<Tx, Error> {
+        let signature_data = transaction.signature_data()?;
+        transaction.signature = self.crypto.sign_with_rng(&signature_data, rng);
+        transaction.id = transaction.compute_id()?;
         Ok(transaction)
     }
 
@@ -57,39 +128,29 @@ impl ArweaveSigner {
         self.crypto.sign(message)
     }
 
+    pub fn sign_with_rng(&self, message: &[u8], rng: impl RngCore + 'static) -> Base64 {
+        self.crypto.sign_with_rng(message, rng)
+    }
+
+    /// Deterministic signature of `message`, for deriving keys from the wallet.
+    pub fn sign_unsalted(&self, message: &[u8]) -> Base64 {
+        self.crypto.sign_unsalted(message)
+    }
+
     pub fn verify_transaction(transaction: &Tx) -> Result<(), Error> {
         if transaction.signature.is_empty() {
             return Err(Error::UnsignedTransaction);
         }
 
-        let crypto = Provider::default();
-        let deep_hash_item = transaction
-            .to_deep_hash_item()
-            .expect("Could not convert transaction into deep hash item");
-        let message = crypto.deep_hash(deep_hash_item);
+        let message = transaction.signature_data()?;
         let signature = &transaction.signature;
 
-        let jwt_str = format!(
-            "{{\"kty\":\"RSA\",\"e\":\"AQAB\",\"n\":\"{}\"}}",
-            BASE64URL.encode(&transaction.owner.0)
-        );
-        let jwk: JsonWebKey = jwt_str.parse().unwrap();
-
-        let pub_key = RsaPublicKey::from_public_key_der(jwk.key.to_der().as_slice()).unwrap();
-        let mut hasher = sha2::Sha256::new();
-        hasher.update(&message);
-        let hashed = &hasher.finalize();
-
-        let rng = thread_rng();
-        let padding = PaddingScheme::PSS {
-            salt_rng: Box::new(rng),
-            digest: Box::new(sha2::Sha256::new()),
-            salt_len: None,
-        };
-        pub_key
-            .verify(padding, hashed, &signature.0)
-            .map(|_| ())
-            .map_err(|_| Error::InvalidSignature)
+        // The id commits to the signature, check it before the costlier RSA verification.
+        if !transaction.compute_id()?.ct_eq(&transaction.id) {
+            return Err(Error::InvalidSignature);
+        }
+
+        verify_pss(&transaction.owner.0, &message, &signature.0)
     }
 
     pub fn wallet_address(&self) -> Base64 {
@@ -107,17 +168,79 @@ impl ArweaveSigner {
     pub fn get_public_key(&self) -> Base64 {
         self.crypto.public_key()
     }
+
+    /// Signs `message` for the application protocol `domain`, e.g. `"myapp/login"`. The
+    /// signature is over a deep hash tying the message to its domain, so it can't be passed
+    /// off as a transaction, a data item, or a message of another protocol.
+    pub fn sign_message(&self, domain: &str, message: &[u8]) -> Base64 {
+        self.sign(&message_signature_data(domain, message))
+    }
+
+    /// Verifies a [`ArweaveSigner::sign_message`] signature of `message` in `domain` by the
+    /// wallet with modulus `pub_key`.
+    pub fn verify_message(
+        pub_key: &[u8],
+        domain: &str,
+        message: &[u8],
+        signature: &[u8],
+    ) -> Result<(), Error> {
+        verify_pss(pub_key, &message_signature_data(domain, message), signature)
+    }
+
+    /// Signs the JCS canonical form of `value`, which other languages reproduce whatever
+    /// their JSON serializer, e.g. arweave-js `crypto.sign` over the canonical UTF-8 bytes.
+    pub fn sign_json(&self, value: &impl Serialize) -> Result<Base64, Error> {
+        Ok(self.sign(canonicalize(value)?.as_bytes()))
+    }
+
+    /// Verifies a [`ArweaveSigner::sign_json`] signature of `value` by the wallet with
+    /// modulus `pub_key`.
+    pub fn verify_json(
+        pub_key: &[u8],
+        value: &impl Serialize,
+        signature: &[u8],
+    ) -> Result<(), Error> {
+        verify_pss(pub_key, canonicalize(value)?.as_bytes(), signature)
+    }
+
+    /// Public key as an [`RsaPublicKey`], e.g. to encrypt data to this wallet.
+    pub fn public_rsa_key(&self) -> RsaPublicKey {
+        self.crypto.public_rsa_key()
+    }
+}
+
+fn message_signature_data(domain: &str, message: &[u8]) -> [u8; 48] {
+    deep_hash(DeepHashItem::from_children(vec![
+        DeepHashItem::from_item(MESSAGE_DOMAIN),
+        DeepHashItem::from_item(domain.as_bytes()),
+        DeepHashItem::from_item(message),
+    ]))
 }
 
 #[cfg(test)]
-mod tests {
-    use crate::error::Error;
+pub(crate) mod tests {
+    use crate::{error::Error, transaction::Tx};
 
     use super::{ArweaveSigner, Base64};
 
+    /// An unsigned transaction carrying `data`, owned by `signer`.
+    pub(crate) fn unsigned_tx(signer: &ArweaveSigner, data: &[u8]) -> Tx {
+        Tx::new(
+            signer.get_provider(),
+            Base64::empty(),
+            data.to_vec(),
+            0,
+            0,
+            Base64::from(vec![1; 32]),
+            vec![],
+            false,
+        )
+        .unwrap()
+    }
+
     #[test]
     fn test_sign_verify() -> Result<(), Error> {
-        let message = Base64(
+        let message = Base64::from(
             [
                 74, 15, 74, 255, 248, 205, 47, 229, 107, 195, 69, 76, 215, 249, 34, 186, 197, 31,
                 178, 163, 72, 54, 78, 179, 19, 178, 1, 132, 183, 231, 131, 213, 146, 203, 6, 99,
@@ -130,4 +253,132 @@ mod tests {
         let pubk = signer.get_public_key();
         ArweaveSigner::verify(&pubk.0, &message.0, &signature.0)
     }
+
+    #[test]
+    fn test_sign_message() -> Result<(), Error> {
+        let signer = ArweaveSigner::default();
+        let pub_key = signer.get_public_key();
+        let signature = signer.sign_message("myapp/login", b"nonce 42");
+
+        ArweaveSigner::verify_message(&pub_key.0, "myapp/login", b"nonce 42", &signature.0)?;
+        for (domain, message) in [
+            ("otherapp/login", &b"nonce 42"[..]),
+            ("myapp/login", b"nonce 43"),
+        ] {
+            assert!(
+                ArweaveSigner::verify_message(&pub_key.0, domain, message, &signature.0).is_err()
+            );
+        }
+        assert!(ArweaveSigner::verify(&pub_key.0, b"nonce 42", &signature.0).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_sign_json() -> Result<(), Error> {
+        use serde_json::json;
+
+        let signer = ArweaveSigner::default();
+        let pub_key = signer.get_public_key();
+        let signature = signer.sign_json(&json!({ "b": [1, 2.50], "a": "x" }))?;
+
+        let reordered: serde_json::Value =
+            serde_json::from_str(r#"{"a":"x","b":[1,2.5]}"#).unwrap();
+        ArweaveSigner::verify_json(&pub_key.0, &reordered, &signature.0)?;
+        assert!(matches!(
+            ArweaveSigner::verify_json(
+                &pub_key.0,
+                &json!({ "a": "y", "b": [1, 2.5] }),
+                &signature.0
+            ),
+            Err(Error::InvalidSignature)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_public_rsa_key() -> Result<(), Error> {
+        use rsa::PublicKeyParts;
+
+        let signer = ArweaveSigner::default();
+        let key = signer.public_rsa_key();
+        assert_eq!(key.n().to_bytes_be(), signer.keypair_modulus().0.to_vec());
+
+        let tx = unsigned_tx(&signer, &[]);
+        assert_eq!(tx.owner_public_key()?, key);
+        assert!(matches!(
+            Tx::default().owner_public_key(),
+            Err(Error::InvalidKey(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_sign_transaction_with_seeded_rng() -> Result<(), Error> {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let signer = ArweaveSigner::default();
+        let tx = unsigned_tx(&signer, b"test vector");
+        let first = signer.sign_transaction_with_rng(tx.clone(), StdRng::seed_from_u64(1))?;
+        let second = signer.sign_transaction_with_rng(tx, StdRng::seed_from_u64(1))?;
+
+        assert_eq!(first.id, second.id);
+        assert_eq!(first.signature, second.signature);
+        ArweaveSigner::verify_transaction(&first)
+    }
+
+    #[tokio::test]
+    async fn test_sign_transaction_async() -> Result<(), Error> {
+        let signer = ArweaveSigner::default();
+        let tx = unsigned_tx(&signer, &[5; 1024]);
+        let signed = signer.sign_transaction_async(tx).await?;
+
+        assert!(!signed.id.is_empty());
+        ArweaveSigner::verify_transaction(&signed)
+    }
+
+    #[test]
+    fn test_verify_rejects_mismatched_id() -> Result<(), Error> {
+        let signer = ArweaveSigner::default();
+        let tx = unsigned_tx(&signer, b"test vector");
+        let mut signed = signer.sign_transaction(tx)?;
+        signed.id = Base64::from(vec![0; 32]);
+
+        assert!(matches!(
+            ArweaveSigner::verify_transaction(&signed),
+            Err(Error::InvalidSignature)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_compute_id() -> Result<(), Error> {
+        let signer = ArweaveSigner::default();
+        let tx = unsigned_tx(&signer, b"persist me first");
+        assert!(matches!(tx.compute_id(), Err(Error::UnsignedTransaction)));
+
+        let signed = signer.sign_transaction(tx)?;
+        assert!(!signed.id.is_empty());
+        assert_eq!(signed.compute_id()?, signed.id);
+        Ok(())
+    }
+
+    #[test]
+    fn test_attach_external_signature() -> Result<(), Error> {
+        let hsm = ArweaveSigner::default();
+        let mut tx = unsigned_tx(&hsm, b"air gapped");
+        let owner = hsm.get_public_key();
+        tx.owner = owner.clone();
+        let message = tx.signature_data()?;
+
+        let signature = hsm.sign(&message);
+        assert!(matches!(
+            tx.attach_signature(owner.clone(), hsm.sign(b"something else")),
+            Err(Error::InvalidSignature)
+        ));
+        assert!(tx.signature.is_empty());
+
+        tx.attach_signature(owner, signature)?;
+        assert!(!tx.id.is_empty());
+        ArweaveSigner::verify_transaction(&tx)
+    }
 }
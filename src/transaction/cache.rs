@@ -0,0 +1,255 @@
+//! Caches of gateway responses keyed by URL: immutable data revalidated with
+//! `If-None-Match`, and short-lived network metadata reused for a fixed time.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use bytes::Bytes;
+
+use crate::consts::{ANCHOR_CACHE_TTL, ETAG_CACHE_SIZE, INFO_CACHE_TTL, PRICE_CACHE_TTL};
+
+struct Cached {
+    etag: String,
+    body: Bytes,
+}
+
+/// Bodies of responses that came with an `ETag`, so re-fetches of the same immutable data can
+/// be answered from memory on `304 Not Modified`.
+///
+/// The cache holds at most `capacity` bytes of bodies and evicts the oldest entries first.
+pub struct EtagCache {
+    capacity: usize,
+    inner: Mutex<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    entries: HashMap<String, Cached>,
+    order: VecDeque<String>,
+    size: usize,
+}
+
+impl Default for EtagCache {
+    fn default() -> Self {
+        Self::new(ETAG_CACHE_SIZE)
+    }
+}
+
+impl EtagCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            inner: Mutex::new(Inner::default()),
+        }
+    }
+
+    /// `ETag` to send as `If-None-Match` when fetching `url`.
+    pub fn etag(&self, url: &str) -> Option<String> {
+        let inner = self.inner.lock().unwrap();
+        inner.entries.get(url).map(|cached| cached.etag.clone())
+    }
+
+    /// Body cached for `url`, to serve on `304 Not Modified`.
+    pub fn body(&self, url: &str) -> Option<Bytes> {
+        let inner = self.inner.lock().unwrap();
+        inner.entries.get(url).map(|cached| cached.body.clone())
+    }
+
+    pub fn insert(&self, url: &str, etag: String, body: Bytes) {
+        if body.len() > self.capacity {
+            return;
+        }
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(old) = inner.entries.remove(url) {
+            inner.size -= old.body.len();
+            inner.order.retain(|key| key != url);
+        }
+        while inner.size + body.len() > self.capacity {
+            let Some(oldest) = inner.order.pop_front() else {
+                break;
+            };
+            if let Some(evicted) = inner.entries.remove(&oldest) {
+                inner.size -= evicted.body.len();
+            }
+        }
+        inner.size += body.len();
+        inner.order.push_back(url.to_string());
+        inner.entries.insert(url.to_string(), Cached { etag, body });
+    }
+
+    /// Total size of the cached bodies, in bytes.
+    pub fn size(&self) -> usize {
+        self.inner.lock().unwrap().size
+    }
+}
+
+/// Kinds of metadata responses, each reused for its own TTL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Metadata {
+    Price,
+    Anchor,
+    Info,
+}
+
+/// How long metadata responses are reused. A zero TTL disables caching of that kind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheTtls {
+    pub price: Duration,
+    pub anchor: Duration,
+    pub info: Duration,
+}
+
+impl Default for CacheTtls {
+    fn default() -> Self {
+        Self {
+            price: Duration::from_secs(PRICE_CACHE_TTL),
+            anchor: Duration::from_secs(ANCHOR_CACHE_TTL),
+            info: Duration::from_secs(INFO_CACHE_TTL),
+        }
+    }
+}
+
+impl CacheTtls {
+    /// TTLs caching nothing.
+    pub fn disabled() -> Self {
+        Self {
+            price: Duration::ZERO,
+            anchor: Duration::ZERO,
+            info: Duration::ZERO,
+        }
+    }
+
+    pub fn ttl(&self, kind: Metadata) -> Duration {
+        match kind {
+            Metadata::Price => self.price,
+            Metadata::Anchor => self.anchor,
+            Metadata::Info => self.info,
+        }
+    }
+}
+
+/// `/price`, `/tx_anchor` and `/info` responses, reused until their TTL elapses to cut
+/// redundant requests in hot loops.
+///
+/// Clones share their entries and TTLs, so every client built by one
+/// [`crate::Arweave`] benefits from the others' requests.
+#[derive(Clone, Default)]
+pub struct MetadataCache {
+    inner: Arc<Mutex<MetadataInner>>,
+}
+
+#[derive(Default)]
+struct MetadataInner {
+    ttls: CacheTtls,
+    entries: HashMap<String, (Metadata, Instant, Bytes)>,
+}
+
+impl MetadataCache {
+    pub fn new(ttls: CacheTtls) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(MetadataInner {
+                ttls,
+                entries: HashMap::new(),
+            })),
+        }
+    }
+
+    pub fn ttls(&self) -> CacheTtls {
+        self.inner.lock().unwrap().ttls
+    }
+
+    /// Changes the TTLs of every clone of this cache, dropping the entries cached so far.
+    pub fn set_ttls(&self, ttls: CacheTtls) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.ttls = ttls;
+        inner.entries.clear();
+    }
+
+    /// Response of `kind` cached for `url`, if still fresh.
+    pub fn get(&self, kind: Metadata, url: &str) -> Option<Bytes> {
+        let inner = self.inner.lock().unwrap();
+        let ttl = inner.ttls.ttl(kind);
+        inner
+            .entries
+            .get(url)
+            .filter(|(cached, at, _)| *cached == kind && at.elapsed() < ttl)
+            .map(|(_, _, body)| body.clone())
+    }
+
+    /// Caches `body` for `url`, pruning expired entries.
+    pub fn insert(&self, kind: Metadata, url: &str, body: Bytes) {
+        let mut inner = self.inner.lock().unwrap();
+        let ttls = inner.ttls;
+        inner
+            .entries
+            .retain(|_, (kind, at, _)| at.elapsed() < ttls.ttl(*kind));
+        if !ttls.ttl(kind).is_zero() {
+            inner
+                .entries
+                .insert(url.to_string(), (kind, Instant::now(), body));
+        }
+    }
+
+    /// Drops the cached responses of `kind`, e.g. anchors after one was rejected as stale.
+    pub fn invalidate(&self, kind: Metadata) {
+        self.inner
+            .lock()
+            .unwrap()
+            .entries
+            .retain(|_, (cached, _, _)| *cached != kind);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bytes::Bytes;
+
+    use super::{CacheTtls, EtagCache, Metadata, MetadataCache};
+
+    #[test]
+    fn test_evicts_oldest() {
+        let cache = EtagCache::new(10);
+        cache.insert("a", "1".to_string(), Bytes::from_static(b"aaaa"));
+        cache.insert("b", "2".to_string(), Bytes::from_static(b"bbbb"));
+        cache.insert("c", "3".to_string(), Bytes::from_static(b"cccc"));
+
+        assert_eq!(cache.etag("a"), None);
+        assert_eq!(cache.etag("b"), Some("2".to_string()));
+        assert_eq!(cache.body("c"), Some(Bytes::from_static(b"cccc")));
+        assert_eq!(cache.size(), 8);
+
+        cache.insert("big", "4".to_string(), Bytes::from(vec![0; 11]));
+        assert_eq!(cache.etag("big"), None);
+        assert_eq!(cache.size(), 8);
+    }
+
+    #[test]
+    fn test_metadata_ttls() {
+        let cache = MetadataCache::new(CacheTtls {
+            anchor: Duration::ZERO,
+            ..CacheTtls::default()
+        });
+        let shared = cache.clone();
+        cache.insert(Metadata::Price, "price/1", Bytes::from_static(b"42"));
+        cache.insert(Metadata::Anchor, "tx_anchor", Bytes::from_static(b"anchor"));
+
+        assert_eq!(
+            shared.get(Metadata::Price, "price/1"),
+            Some(Bytes::from_static(b"42"))
+        );
+        assert_eq!(shared.get(Metadata::Info, "price/1"), None);
+        assert_eq!(shared.get(Metadata::Anchor, "tx_anchor"), None);
+
+        shared.invalidate(Metadata::Price);
+        assert_eq!(cache.get(Metadata::Price, "price/1"), None);
+
+        cache.insert(Metadata::Price, "price/1", Bytes::from_static(b"42"));
+        shared.set_ttls(CacheTtls::disabled());
+        assert_eq!(cache.get(Metadata::Price, "price/1"), None);
+    }
+}
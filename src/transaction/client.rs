@@ -1,22 +1,36 @@
+use async_stream::try_stream;
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::{stream::BoxStream, Stream, StreamExt};
 use reqwest::{
-    header::{ACCEPT, CONTENT_TYPE},
+    header::{ACCEPT, CONTENT_LENGTH, CONTENT_TYPE, ETAG, IF_NONE_MATCH},
     StatusCode,
 };
 use serde_json::json;
-use std::{str::FromStr, thread::sleep, time::Duration};
+use std::{str::FromStr, time::Duration};
+use tokio::time::sleep;
 
 use crate::{
+    audit,
     consts::{ARWEAVE_BASE_URL, CHUNKS_RETRIES, CHUNKS_RETRY_SLEEP},
     crypto::base64::Base64,
     error::Error,
-    types::TxStatus,
+    gateway::{Gateway, MiddlewareStack},
+    types::{parse_response, BlockInfo, ChunkData, ChunkRef, TxOffset, TxStatus, Winstons},
+    upload::{retry_after, Uploader},
 };
 
-use super::Tx;
+use super::{
+    cache::{EtagCache, Metadata, MetadataCache},
+    Tx,
+};
 
 pub struct TxClient {
     client: reqwest::Client,
     base_url: url::Url,
+    cache: EtagCache,
+    metadata: MetadataCache,
+    middleware: MiddlewareStack,
 }
 
 impl Default for TxClient {
@@ -24,13 +38,167 @@ impl Default for TxClient {
         Self {
             client: reqwest::Client::new(),
             base_url: url::Url::from_str(ARWEAVE_BASE_URL).unwrap(),
+            cache: EtagCache::default(),
+            metadata: MetadataCache::default(),
+            middleware: MiddlewareStack::default(),
         }
     }
 }
 
 impl TxClient {
     pub fn new(client: reqwest::Client, base_url: url::Url) -> Result<Self, Error> {
-        Ok(Self { client, base_url })
+        Ok(Self {
+            client,
+            base_url,
+            cache: EtagCache::default(),
+            metadata: MetadataCache::default(),
+            middleware: MiddlewareStack::default(),
+        })
+    }
+
+    /// Shares `metadata` with other clients, so price, anchor and info responses fetched by
+    /// any of them are reused by all.
+    pub fn with_metadata_cache(mut self, metadata: MetadataCache) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    /// Runs every request of this client through `middleware`.
+    pub fn with_middleware(mut self, middleware: MiddlewareStack) -> Self {
+        self.middleware = middleware;
+        self
+    }
+
+    /// Sends `request` through the middleware stack. Transport errors are reported with
+    /// `error`.
+    async fn send(
+        &self,
+        request: reqwest::RequestBuilder,
+        error: fn(String) -> Error,
+    ) -> Result<reqwest::Response, Error> {
+        self.middleware.send(&self.client, request, error).await
+    }
+
+    /// GETs `path` unless a fresh response of `kind` is cached for it. Failures are reported
+    /// with `error`.
+    async fn metadata_get(
+        &self,
+        kind: Metadata,
+        path: &str,
+        error: fn(String) -> Error,
+    ) -> Result<Bytes, Error> {
+        let url = self
+            .base_url
+            .join(path)
+            .expect("Could not join base_url with path");
+        if let Some(body) = self.metadata.get(kind, url.as_str()) {
+            return Ok(body);
+        }
+        let res = self.send(self.client.get(url.clone()), error).await?;
+        if res.status() != StatusCode::OK {
+            return Err(error(res.status().to_string()));
+        }
+        let body = res.bytes().await.map_err(|e| error(e.to_string()))?;
+        self.metadata.insert(kind, url.as_str(), body.clone());
+        Ok(body)
+    }
+
+    /// GETs `url`, revalidating a previously cached body with `If-None-Match`. A
+    /// `304 Not Modified` is answered from the cache and reported as `200 OK`.
+    async fn conditional_get(&self, url: url::Url) -> Result<(StatusCode, Bytes), Error> {
+        let key = url.to_string();
+        let mut req = self.client.get(url);
+        if let Some(etag) = self.cache.etag(&key) {
+            req = req.header(IF_NONE_MATCH, etag);
+        }
+        let res = self.send(req, Error::TransactionInfoError).await?;
+
+        let status = res.status();
+        if status == StatusCode::NOT_MODIFIED {
+            if let Some(body) = self.cache.body(&key) {
+                return Ok((StatusCode::OK, body));
+            }
+        }
+        let etag = res
+            .headers()
+            .get(ETAG)
+            .and_then(|etag| etag.to_str().ok())
+            .map(str::to_string);
+        let body = res
+            .bytes()
+            .await
+            .map_err(|e| Error::TransactionInfoError(e.to_string()))?;
+        if let (StatusCode::OK, Some(etag)) = (status, etag) {
+            self.cache.insert(&key, etag, body.clone());
+        }
+        Ok((status, body))
+    }
+
+    /// Sends a `HEAD` request to `path`, returning the status and the `Content-Length`.
+    async fn head(&self, path: &str) -> Result<(StatusCode, Option<u64>), Error> {
+        let res = self
+            .send(
+                self.client.head(
+                    self.base_url
+                        .join(path)
+                        .expect("Could not join base_url with path"),
+                ),
+                Error::TransactionInfoError,
+            )
+            .await?;
+        let length = res
+            .headers()
+            .get(CONTENT_LENGTH)
+            .and_then(|length| length.to_str().ok())
+            .and_then(|length| length.parse().ok());
+        Ok((res.status(), length))
+    }
+
+    /// Balance of wallet `address` in winstons.
+    pub async fn get_balance(&self, address: &Base64) -> Result<u128, Error> {
+        let res = self
+            .send(
+                self.client.get(
+                    self.base_url
+                        .join(&format!("wallet/{}/balance", address))
+                        .expect("Could not join base_url with /wallet/{}/balance"),
+                ),
+                Error::WalletError,
+            )
+            .await?;
+        if res.status() != StatusCode::OK {
+            return Err(Error::WalletError(res.status().to_string()));
+        }
+        let body = res
+            .text()
+            .await
+            .map_err(|e| Error::WalletError(e.to_string()))?;
+        body.trim()
+            .parse()
+            .map_err(|_| Error::WalletError(format!("balance of {}: {:?}", address, body)))
+    }
+
+    /// Whether transaction `id` is known, pending or mined, without downloading its header.
+    pub async fn tx_exists(&self, id: &Base64) -> Result<bool, Error> {
+        match self.head(&format!("tx/{}", id)).await? {
+            (StatusCode::OK | StatusCode::ACCEPTED, _) => Ok(true),
+            (StatusCode::NOT_FOUND | StatusCode::GONE, _) => Ok(false),
+            (status, _) => Err(Error::TransactionInfoError(status.to_string())),
+        }
+    }
+
+    /// Size of the data of transaction `id` if the gateway serves it, without downloading it.
+    /// Falls back to the header's `data_size` when the gateway omits `Content-Length`.
+    pub async fn data_available(&self, id: &Base64) -> Result<Option<u64>, Error> {
+        match self.head(&id.to_string()).await? {
+            (StatusCode::OK, Some(length)) => Ok(Some(length)),
+            (StatusCode::OK, None) => match TxClient::get_tx(self, id.clone()).await? {
+                (_, Some(tx)) => Ok(Some(tx.data_size)),
+                (_, None) => Ok(None),
+            },
+            (StatusCode::NOT_FOUND | StatusCode::GONE, _) => Ok(None),
+            (status, _) => Err(Error::TransactionInfoError(status.to_string())),
+        }
     }
 
     pub async fn post_transaction(&self, signed_transaction: &Tx) -> Result<(Base64, u64), Error> {
@@ -47,102 +215,217 @@ impl TxClient {
 
         dbg!(json!(signed_transaction));
         while (retries < CHUNKS_RETRIES) & (status != reqwest::StatusCode::OK) {
+            if retries > 0 {
+                audit::note_retry();
+            }
             let res = self
-                .client
-                .post(url.clone())
-                .json(&signed_transaction)
-                .header(&ACCEPT, "application/json")
-                .header(&CONTENT_TYPE, "application/json")
-                .send()
-                .await
-                .expect("Could not post transaction");
+                .send(
+                    self.client
+                        .post(url.clone())
+                        .json(&signed_transaction)
+                        .header(&ACCEPT, "application/json")
+                        .header(&CONTENT_TYPE, "application/json"),
+                    Error::TransactionInfoError,
+                )
+                .await?;
             status = res.status();
             dbg!(status);
             if status == reqwest::StatusCode::OK {
+                audit::note_gateway(&self.base_url);
                 return Ok((signed_transaction.id.clone(), signed_transaction.reward));
             }
-            sleep(Duration::from_secs(CHUNKS_RETRY_SLEEP));
+            // Rate limited gateways tell us how long to back off for.
+            let delay = match status {
+                reqwest::StatusCode::TOO_MANY_REQUESTS => retry_after(res.headers()),
+                _ => None,
+            };
+            if status == reqwest::StatusCode::BAD_REQUEST {
+                let body = res.text().await.unwrap_or_default();
+                // Anchors expire after ~50 blocks; retrying the same payload can't succeed.
+                if body.contains("Invalid anchor") {
+                    return Err(Error::InvalidAnchor);
+                }
+            }
+            sleep(delay.unwrap_or(Duration::from_secs(CHUNKS_RETRY_SLEEP))).await;
             retries += 1;
         }
 
         Err(Error::StatusCodeNotOk)
     }
 
-    pub async fn get_last_tx(&self) -> Base64 {
-        let resp = self
-            .client
-            .get(
-                self.base_url
-                    .join("tx_anchor")
-                    .expect("Could not join base_url with /tx_anchor"),
-            )
-            .send()
-            .await
-            .expect("Could not get last tx");
-        let last_tx_str = resp.text().await.unwrap();
-        Base64::from_str(&last_tx_str).unwrap()
+    pub async fn get_last_tx(&self) -> Result<Base64, Error> {
+        self.get_anchor().await
     }
 
     pub async fn get_fee(&self, target: Base64, data: Vec<u8>) -> Result<u64, Error> {
-        let url = self
-            .base_url
-            .join(&format!("price/{}/{}", data.len(), target))
-            .expect("Could not join base_url with /price/{}/{}");
-        let winstons_per_bytes = reqwest::get(url)
-            .await
-            .map_err(|e| Error::GetPriceError(e.to_string()))?
-            .json::<u64>()
-            .await
-            .expect("Could not get base fee");
-
-        Ok(winstons_per_bytes)
+        self.get_price(data.len() as u64, Some(&target)).await
     }
 
     pub async fn get_tx(&self, id: Base64) -> Result<(StatusCode, Option<Tx>), Error> {
-        let res = self
-            .client
-            .get(
+        let (status, body) = self
+            .conditional_get(
                 self.base_url
                     .join(&format!("tx/{}", id))
                     .expect("Could not join base_url with /tx"),
             )
-            .send()
-            .await
-            .expect("Could not get tx");
+            .await?;
 
-        if res.status() == StatusCode::OK {
-            let text = res
-                .text()
-                .await
-                .expect("Could not parse response to string");
-            let tx = Tx::from_str(&text).expect("Could not create Tx from string");
+        if status == StatusCode::OK {
+            let text = String::from_utf8(body.to_vec())
+                .map_err(|e| Error::TransactionInfoError(e.to_string()))?;
+            let tx = Tx::from_str(&text)?;
             return Ok((StatusCode::OK, Some(tx)));
-        } else if res.status() == StatusCode::ACCEPTED {
+        } else if status == StatusCode::ACCEPTED {
             //Tx is pending
             return Ok((StatusCode::ACCEPTED, None));
         }
 
-        Err(Error::TransactionInfoError(res.status().to_string()))
+        Err(Error::TransactionInfoError(status.to_string()))
     }
 
-    pub async fn get_tx_status(&self, id: Base64) -> Result<(StatusCode, Option<TxStatus>), Error> {
-        let res = self
-            .client
-            .get(
+    /// Fetches the data of transaction `id`, preferring the gateway's raw `/{id}` endpoint and
+    /// falling back to the node's base64url encoded `/tx/{id}/data`.
+    pub async fn get_tx_data(&self, id: &Base64) -> Result<Vec<u8>, Error> {
+        let raw_url = self
+            .base_url
+            .join(&id.to_string())
+            .expect("Could not join base_url with /{id}");
+        let (status, body) = self.conditional_get(raw_url).await?;
+        if status == StatusCode::OK {
+            return Ok(body.to_vec());
+        }
+
+        let (status, body) = self
+            .conditional_get(
                 self.base_url
-                    .join(&format!("tx/{}/status", id))
-                    .expect("Could not join base_url with /tx/{}/status"),
+                    .join(&format!("tx/{}/data", id))
+                    .expect("Could not join base_url with /tx/{}/data"),
+            )
+            .await?;
+        if status != StatusCode::OK {
+            return Err(Error::TransactionInfoError(status.to_string()));
+        }
+        let text = String::from_utf8(body.to_vec())
+            .map_err(|e| Error::TransactionInfoError(e.to_string()))?;
+        Base64::from_str(&text)
+            .map(|data| data.0.to_vec())
+            .map_err(|e| Error::TransactionInfoError(e.to_string()))
+    }
+
+    /// Streams the data of transaction `id` from the gateway body as it arrives. If the gateway
+    /// can't serve the data, it is assembled from `/chunk` fetches instead.
+    pub fn stream_tx_data(&self, id: Base64) -> impl Stream<Item = Result<Bytes, Error>> + '_ {
+        try_stream! {
+            let raw_url = self
+                .base_url
+                .join(&id.to_string())
+                .expect("Could not join base_url with /{id}");
+            let mut res = self.send(self.client.get(raw_url), Error::TransactionInfoError).await?;
+
+            if res.status() == StatusCode::OK {
+                while let Some(bytes) = res
+                    .chunk()
+                    .await
+                    .map_err(|e| Error::TransactionInfoError(e.to_string()))?
+                {
+                    yield bytes;
+                }
+            } else {
+                let tx_offset = self.get_tx_offset(&id).await?;
+                let end = tx_offset.offset + 1;
+                let mut offset = tx_offset.start()?;
+                while offset < end {
+                    let chunk = self.get_chunk(offset).await?.chunk.0;
+                    if chunk.is_empty() {
+                        Err(Error::TransactionInfoError(format!("Empty chunk at offset {}", offset)))?;
+                    }
+                    offset += chunk.len() as u64;
+                    yield Bytes::from(chunk);
+                }
+            }
+        }
+    }
+
+    pub async fn get_tx_offset(&self, id: &Base64) -> Result<TxOffset, Error> {
+        let res = self
+            .send(
+                self.client.get(
+                    self.base_url
+                        .join(&format!("tx/{}/offset", id))
+                        .expect("Could not join base_url with /tx/{}/offset"),
+                ),
+                Error::TransactionInfoError,
             )
-            .send()
+            .await?;
+        if res.status() != StatusCode::OK {
+            return Err(Error::TransactionInfoError(res.status().to_string()));
+        }
+        res.json::<TxOffset>()
             .await
-            .expect("Could not get tx status");
+            .map_err(|e| Error::TransactionInfoError(e.to_string()))
+    }
+
+    pub async fn get_block(&self, height: u64) -> Result<BlockInfo, Error> {
+        let res = self
+            .send(
+                self.client.get(
+                    self.base_url
+                        .join(&format!("block/height/{}", height))
+                        .expect("Could not join base_url with /block/height/{}"),
+                ),
+                Error::NetworkInfoError,
+            )
+            .await?;
+        if res.status() != StatusCode::OK {
+            return Err(Error::NetworkInfoError(res.status().to_string()));
+        }
+        let body = res
+            .bytes()
+            .await
+            .map_err(|e| Error::NetworkInfoError(e.to_string()))?;
+        parse_response::<BlockInfo>(&body)
+            .map_err(|e| Error::NetworkInfoError(format!("block {}: {}", height, e)))
+    }
+
+    /// Fetches the chunk containing absolute weave `offset`.
+    pub async fn get_chunk(&self, offset: u64) -> Result<ChunkData, Error> {
+        let res = self
+            .send(
+                self.client.get(
+                    self.base_url
+                        .join(&format!("chunk/{}", offset))
+                        .expect("Could not join base_url with /chunk/{}"),
+                ),
+                Error::TransactionInfoError,
+            )
+            .await?;
+        if res.status() != StatusCode::OK {
+            return Err(Error::TransactionInfoError(res.status().to_string()));
+        }
+        res.json::<ChunkData>()
+            .await
+            .map_err(|e| Error::TransactionInfoError(e.to_string()))
+    }
+
+    pub async fn get_tx_status(&self, id: Base64) -> Result<(StatusCode, Option<TxStatus>), Error> {
+        let res = self
+            .send(
+                self.client.get(
+                    self.base_url
+                        .join(&format!("tx/{}/status", id))
+                        .expect("Could not join base_url with /tx/{}/status"),
+                ),
+                Error::TransactionInfoError,
+            )
+            .await?;
 
         if res.status() == StatusCode::OK {
-            let status = res
-                .json::<TxStatus>()
+            let body = res
+                .bytes()
                 .await
-                .map_err(|err| Error::TransactionInfoError(err.to_string()))
-                .expect("Could not parse tx status");
+                .map_err(|e| Error::TransactionInfoError(e.to_string()))?;
+            let status = parse_response::<TxStatus>(&body)
+                .map_err(|e| Error::TransactionInfoError(format!("status of {}: {}", id, e)))?;
 
             Ok((StatusCode::OK, Some(status)))
         } else if res.status() == StatusCode::ACCEPTED {
@@ -152,3 +435,280 @@ impl TxClient {
         }
     }
 }
+
+#[async_trait]
+impl Gateway for TxClient {
+    async fn post_tx(&self, signed_transaction: &Tx) -> Result<(Base64, u64), Error> {
+        self.post_transaction(signed_transaction).await
+    }
+
+    async fn get_tx(&self, id: &Base64) -> Result<(StatusCode, Option<Tx>), Error> {
+        TxClient::get_tx(self, id.clone()).await
+    }
+
+    async fn get_tx_status(&self, id: &Base64) -> Result<(StatusCode, Option<TxStatus>), Error> {
+        TxClient::get_tx_status(self, id.clone()).await
+    }
+
+    async fn get_tx_data(&self, id: &Base64) -> Result<Vec<u8>, Error> {
+        TxClient::get_tx_data(self, id).await
+    }
+
+    async fn get_block(&self, height: u64) -> Result<BlockInfo, Error> {
+        TxClient::get_block(self, height).await
+    }
+
+    async fn get_tx_offset(&self, id: &Base64) -> Result<TxOffset, Error> {
+        TxClient::get_tx_offset(self, id).await
+    }
+
+    async fn get_chunk(&self, offset: u64) -> Result<ChunkData, Error> {
+        TxClient::get_chunk(self, offset).await
+    }
+
+    async fn get_balance(&self, address: &Base64) -> Result<u128, Error> {
+        TxClient::get_balance(self, address).await
+    }
+
+    async fn tx_exists(&self, id: &Base64) -> Result<bool, Error> {
+        TxClient::tx_exists(self, id).await
+    }
+
+    async fn data_available(&self, id: &Base64) -> Result<Option<u64>, Error> {
+        TxClient::data_available(self, id).await
+    }
+
+    fn stream_tx_data(&self, id: Base64) -> BoxStream<'_, Result<Bytes, Error>> {
+        TxClient::stream_tx_data(self, id).boxed()
+    }
+
+    async fn get_price(&self, data_size: u64, target: Option<&Base64>) -> Result<u64, Error> {
+        let path = match target {
+            Some(target) if !target.is_empty() => format!("price/{}/{}", data_size, target),
+            _ => format!("price/{}", data_size),
+        };
+        let body = self
+            .metadata_get(Metadata::Price, &path, Error::GetPriceError)
+            .await?;
+        parse_response::<Winstons>(&body)
+            .map(|Winstons(fee)| fee)
+            .map_err(|e| Error::GetPriceError(format!("{}: {}", path, e)))
+    }
+
+    async fn get_anchor(&self) -> Result<Base64, Error> {
+        let body = self
+            .metadata_get(Metadata::Anchor, "tx_anchor", Error::TransactionInfoError)
+            .await?;
+        let text =
+            std::str::from_utf8(&body).map_err(|e| Error::TransactionInfoError(e.to_string()))?;
+        Base64::from_str(text).map_err(|e| Error::TransactionInfoError(e.to_string()))
+    }
+
+    async fn post_chunk(&self, chunk: ChunkRef<'_>) -> Result<usize, Error> {
+        Uploader::new(self.base_url.clone())
+            .post_chunk_with_middleware(chunk, &self.client, &self.middleware)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use futures::TryStreamExt;
+    use httpmock::{
+        Method::{GET, HEAD, POST},
+        MockServer,
+    };
+    use serde_json::json;
+
+    use super::{Tx, TxClient};
+    use crate::{
+        consts::{CHUNKS_RETRIES, CHUNKS_RETRY_SLEEP},
+        crypto::base64::Base64,
+        gateway::Gateway,
+        transaction::cache::{CacheTtls, MetadataCache},
+    };
+
+    const ID: &str = "t3K1b8IhvtGWxAGsipZE5NafmEGrtj3OAcYikJ0edeU";
+
+    #[tokio::test]
+    async fn test_stream_tx_data_from_gateway() {
+        let server = MockServer::start_async().await;
+        let mock = server.mock(|when, then| {
+            when.method(GET).path(format!("/{}", ID));
+            then.status(200).body("abcdef");
+        });
+
+        let client = TxClient::new(
+            reqwest::Client::new(),
+            url::Url::parse(&server.url("")).unwrap(),
+        )
+        .unwrap();
+        let chunks: Vec<_> = client
+            .stream_tx_data(Base64::from_str(ID).unwrap())
+            .try_collect()
+            .await
+            .unwrap();
+
+        mock.assert();
+        assert_eq!(chunks.concat(), b"abcdef".to_vec());
+    }
+
+    #[tokio::test]
+    async fn test_stream_tx_data_from_chunks() {
+        let server = MockServer::start_async().await;
+        server.mock(|when, then| {
+            when.method(GET).path(format!("/{}", ID));
+            then.status(404);
+        });
+        server.mock(|when, then| {
+            when.method(GET).path(format!("/tx/{}/offset", ID));
+            then.status(200)
+                .json_body(json!({ "size": "6", "offset": "105" }));
+        });
+        for (offset, chunk) in [(100, "abc"), (103, "def")] {
+            server.mock(|when, then| {
+                when.method(GET).path(format!("/chunk/{}", offset));
+                then.status(200).json_body(json!({
+                    "chunk": Base64::from_utf8_str(chunk).unwrap().to_string(),
+                    "data_path": "",
+                    "tx_path": "",
+                }));
+            });
+        }
+
+        let client = TxClient::new(
+            reqwest::Client::new(),
+            url::Url::parse(&server.url("")).unwrap(),
+        )
+        .unwrap();
+        let chunks: Vec<_> = client
+            .stream_tx_data(Base64::from_str(ID).unwrap())
+            .try_collect()
+            .await
+            .unwrap();
+
+        assert_eq!(chunks.concat(), b"abcdef".to_vec());
+    }
+
+    #[tokio::test]
+    async fn test_post_transaction_backs_off_on_429() {
+        let server = MockServer::start_async().await;
+        let mock = server.mock(|when, then| {
+            when.method(POST).path("/tx");
+            then.status(429).header("Retry-After", "0");
+        });
+
+        let client = TxClient::new(
+            reqwest::Client::new(),
+            url::Url::parse(&server.url("")).unwrap(),
+        )
+        .unwrap();
+        let tx = Tx {
+            id: Base64::from(vec![1; 32]),
+            ..Tx::default()
+        };
+        let started = std::time::Instant::now();
+        assert!(client.post_transaction(&tx).await.is_err());
+
+        // Retry-After: 0 replaces the fixed one second sleep between attempts.
+        mock.assert_hits(CHUNKS_RETRIES as usize);
+        assert!(started.elapsed() < std::time::Duration::from_secs(CHUNKS_RETRY_SLEEP));
+    }
+
+    #[tokio::test]
+    async fn test_get_tx_data_revalidates_with_etag() {
+        let server = MockServer::start_async().await;
+        let not_modified = server.mock(|when, then| {
+            when.method(GET)
+                .path(format!("/{}", ID))
+                .header("If-None-Match", "\"v1\"");
+            then.status(304);
+        });
+        let fresh = server.mock(|when, then| {
+            when.method(GET).path(format!("/{}", ID));
+            then.status(200).header("ETag", "\"v1\"").body("abcdef");
+        });
+
+        let client = TxClient::new(
+            reqwest::Client::new(),
+            url::Url::parse(&server.url("")).unwrap(),
+        )
+        .unwrap();
+        let id = Base64::from_str(ID).unwrap();
+        assert_eq!(client.get_tx_data(&id).await.unwrap(), b"abcdef".to_vec());
+        assert_eq!(client.get_tx_data(&id).await.unwrap(), b"abcdef".to_vec());
+
+        fresh.assert_hits(1);
+        not_modified.assert_hits(1);
+    }
+
+    #[tokio::test]
+    async fn test_head_existence_checks() {
+        let server = MockServer::start_async().await;
+        let tx = server.mock(|when, then| {
+            when.method(HEAD).path(format!("/tx/{}", ID));
+            then.status(202);
+        });
+        let data = server.mock(|when, then| {
+            when.method(HEAD).path(format!("/{}", ID));
+            then.status(200).header("Content-Length", "1024");
+        });
+        server.mock(|when, then| {
+            when.method(HEAD).path_contains("bWlzc2luZw");
+            then.status(404);
+        });
+
+        let client = TxClient::new(
+            reqwest::Client::new(),
+            url::Url::parse(&server.url("")).unwrap(),
+        )
+        .unwrap();
+        let id = Base64::from_str(ID).unwrap();
+        let missing = Base64::from_str("bWlzc2luZw").unwrap();
+
+        assert!(client.tx_exists(&id).await.unwrap());
+        assert_eq!(client.data_available(&id).await.unwrap(), Some(1024));
+        assert!(!client.tx_exists(&missing).await.unwrap());
+        assert_eq!(client.data_available(&missing).await.unwrap(), None);
+        tx.assert();
+        data.assert();
+    }
+
+    #[tokio::test]
+    async fn test_shares_cached_metadata() {
+        let server = MockServer::start_async().await;
+        let price = server.mock(|when, then| {
+            when.method(GET).path("/price/100");
+            then.status(200).body("1234");
+        });
+        let anchor = server.mock(|when, then| {
+            when.method(GET).path("/tx_anchor");
+            then.status(200).body(ID);
+        });
+
+        let metadata = MetadataCache::default();
+        let clients: Vec<TxClient> = (0..2)
+            .map(|_| {
+                TxClient::new(
+                    reqwest::Client::new(),
+                    url::Url::parse(&server.url("")).unwrap(),
+                )
+                .unwrap()
+                .with_metadata_cache(metadata.clone())
+            })
+            .collect();
+        for client in &clients {
+            assert_eq!(client.get_price(100, None).await.unwrap(), 1234);
+            assert_eq!(client.get_anchor().await.unwrap().to_string(), ID);
+        }
+        price.assert_hits(1);
+        anchor.assert_hits(1);
+
+        metadata.set_ttls(CacheTtls::disabled());
+        clients[0].get_price(100, None).await.unwrap();
+        clients[1].get_price(100, None).await.unwrap();
+        price.assert_hits(3);
+    }
+}
@@ -4,22 +4,36 @@ use crate::{
     consts::VERSION,
     crypto::{base64::Base64, Provider},
     crypto::{
-        hash::{DeepHashItem, ToItems},
-        merkle::{generate_data_root, generate_leaves, resolve_proofs, Node, Proof},
+        hash::{deep_hash, sha256, DeepHashItem, ToItems},
+        merkle::{
+            generate_data_root, generate_leaves_with, resolve_proofs, ChunkingConfig, Node, Proof,
+            MAX_CHUNK_SIZE,
+        },
+        sign::{rsa_public_key, verify_pss, RsaPublicKey},
     },
     currency::Currency,
     error::Error,
     transaction::tags::Tag,
-    types::Chunk,
+    types::ChunkRef,
 };
 
-use self::tags::FromUtf8Strs;
+use self::{
+    summary::TxSummary,
+    tags::{
+        FromUtf8Strs, ANS104_BUNDLE_FORMAT, ANS104_BUNDLE_VERSION, BUNDLE_FORMAT, BUNDLE_VERSION,
+        USER_AGENT,
+    },
+};
 
+pub mod cache;
 pub mod client;
 pub mod parser;
+pub mod prepared;
+pub mod summary;
 pub mod tags;
+pub mod template;
 
-#[derive(Deserialize, Debug, Default, PartialEq)]
+#[derive(Deserialize, Debug, Default, PartialEq, Clone)]
 pub struct Tx {
     /* Fields required for signing */
     pub format: u8,
@@ -40,6 +54,16 @@ pub struct Tx {
     pub proofs: Vec<Proof>,
 }
 
+/// A transaction together with its decoded tags and data.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TxWithData {
+    pub tx: Tx,
+    /// Tags of `tx` as strings, invalid UTF-8 being replaced with U+FFFD. The exact bytes
+    /// are in `tx.tags`.
+    pub tags: Vec<Tag<String>>,
+    pub data: Vec<u8>,
+}
+
 impl<'a> ToItems<'a, Tx> for Tx {
     fn to_deep_hash_item(&'a self) -> Result<DeepHashItem, Error> {
         match &self.format {
@@ -88,12 +112,16 @@ impl<'a> ToItems<'a, Tx> for Tx {
 
 impl Tx {
     fn base_tag() -> Tag<Base64> {
-        Tag::<Base64>::from_utf8_strs("User-Agent", &format!("arweave-rs/{}", VERSION)).unwrap()
+        Tag::<Base64>::from_utf8_strs(USER_AGENT, &format!("arweave-rs/{}", VERSION)).unwrap()
     }
 
     fn generate_merkle(data: Vec<u8>) -> Result<Tx, Error> {
+        Tx::generate_merkle_with(data, &ChunkingConfig::default())
+    }
+
+    fn generate_merkle_with(data: Vec<u8>, config: &ChunkingConfig) -> Result<Tx, Error> {
         if data.is_empty() {
-            let empty = Base64(vec![]);
+            let empty = Base64::from(vec![]);
             Ok(Tx {
                 format: 2,
                 data_size: 0,
@@ -104,9 +132,9 @@ impl Tx {
                 ..Default::default()
             })
         } else {
-            let mut chunks = generate_leaves(data.clone()).unwrap();
+            let mut chunks = generate_leaves_with(&data, config).unwrap();
             let root = generate_data_root(chunks.clone()).unwrap();
-            let data_root = Base64(root.id.into_iter().collect());
+            let data_root = Base64::from(&root.id[..]);
             let mut proofs = resolve_proofs(root, None).unwrap();
 
             // Discard the last chunk & proof if it's zero length.
@@ -119,7 +147,7 @@ impl Tx {
             Ok(Tx {
                 format: 2,
                 data_size: data.len() as u64,
-                data: Base64(data),
+                data: Base64::from(data),
                 data_root,
                 chunks,
                 proofs,
@@ -130,6 +158,7 @@ impl Tx {
 }
 
 impl Tx {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         crypto: &Provider,
         target: Base64,
@@ -158,7 +187,7 @@ impl Tx {
                 "application/octet-stream"
             };
 
-            tags.push(Tag::<Base64>::from_utf8_strs("Content-Type", content_type)?)
+            tags.push(Tag::<Base64>::content_type(content_type)?)
         }
 
         // Add other tags if provided.
@@ -169,12 +198,119 @@ impl Tx {
         transaction.last_tx = last_tx;
 
         transaction.reward = fee;
-        transaction.quantity = Currency::from(quantity);
+        transaction.quantity = Currency::checked_from_winstons(quantity)?;
         transaction.target = target;
 
         Ok(transaction)
     }
 
+    /// Tags every ANS-104 bundle transaction must carry to be picked up by indexers.
+    pub fn bundle_tags() -> Vec<Tag<Base64>> {
+        vec![
+            Tag::<Base64>::bundle_format(ANS104_BUNDLE_FORMAT).unwrap(),
+            Tag::<Base64>::from_utf8_strs(BUNDLE_VERSION, ANS104_BUNDLE_VERSION).unwrap(),
+        ]
+    }
+
+    /// Checks the transaction carries exactly one `Bundle-Format: binary` and one
+    /// `Bundle-Version: 2.0.0` tag.
+    pub fn validate_bundle_tags(&self) -> Result<(), Error> {
+        for (name, expected) in [
+            (BUNDLE_FORMAT, ANS104_BUNDLE_FORMAT),
+            (BUNDLE_VERSION, ANS104_BUNDLE_VERSION),
+        ] {
+            let values: Vec<&Base64> = self
+                .tags
+                .iter()
+                .filter(|t| t.name.0 == name.as_bytes())
+                .map(|t| &t.value)
+                .collect();
+            match values.as_slice() {
+                [value] if value.0 == expected.as_bytes() => {}
+                [] => {
+                    return Err(Error::InvalidBundleTags(format!("missing {} tag", name)));
+                }
+                _ => {
+                    return Err(Error::InvalidBundleTags(format!(
+                        "expected a single {}: {} tag",
+                        name, expected
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the transaction has a tag `name` holding `value`, compared decoded.
+    pub fn has_tag(&self, name: &str, value: impl AsRef<[u8]>) -> bool {
+        self.tags
+            .iter()
+            .any(|t| t.name.0 == name.as_bytes() && t.value.0 == value.as_ref())
+    }
+
+    /// Tags whose decoded name starts with `prefix`, in order, e.g. `Tag-Pointer-`.
+    pub fn tags_matching(&self, prefix: &str) -> Vec<&Tag<Base64>> {
+        self.tags
+            .iter()
+            .filter(|t| t.name.0.starts_with(prefix.as_bytes()))
+            .collect()
+    }
+
+    /// Message the owner signs: the deep hash of the signed fields. It covers `owner`, so
+    /// when signing on another machine or an HSM, set `owner` to its key's modulus first.
+    pub fn signature_data(&self) -> Result<[u8; 48], Error> {
+        Ok(deep_hash(self.to_deep_hash_item()?))
+    }
+
+    /// Attaches a signature of [`Tx::signature_data`] made elsewhere by `owner` and sets the
+    /// id. Fails with [`Error::InvalidSignature`], leaving the transaction untouched, when the
+    /// signature does not match.
+    pub fn attach_signature(&mut self, owner: Base64, signature: Base64) -> Result<(), Error> {
+        let previous_owner = std::mem::replace(&mut self.owner, owner);
+        let verified = self
+            .signature_data()
+            .and_then(|message| verify_pss(&self.owner, &message, &signature));
+        if let Err(err) = verified {
+            self.owner = previous_owner;
+            return Err(err);
+        }
+        self.signature = signature;
+        self.id = self.compute_id()?;
+        Ok(())
+    }
+
+    /// Id the transaction is posted under: the SHA-256 of its signature. Computed locally, so
+    /// it can be persisted before the transaction is submitted. Fails with
+    /// [`Error::UnsignedTransaction`] when there is no signature yet.
+    pub fn compute_id(&self) -> Result<Base64, Error> {
+        if self.signature.is_empty() {
+            return Err(Error::UnsignedTransaction);
+        }
+        Ok(Base64::from(sha256(&self.signature.0).to_vec()))
+    }
+
+    /// Address of the wallet owning the transaction: the SHA-256 of its owner's modulus.
+    pub fn owner_address(&self) -> Base64 {
+        Base64::from(sha256(&self.owner.0).to_vec())
+    }
+
+    /// Public key of the owner, e.g. to encrypt data to them. Fails with
+    /// [`Error::InvalidKey`] if the owner is missing or not a valid modulus.
+    pub fn owner_public_key(&self) -> Result<RsaPublicKey, Error> {
+        if self.owner.is_empty() {
+            return Err(Error::InvalidKey(format!("{} has no owner", self.id)));
+        }
+        rsa_public_key(&self.owner.0)
+    }
+
+    /// Total winstons spent by posting this transaction: reward plus quantity.
+    pub fn cost(&self) -> Result<u128, Error> {
+        self.quantity
+            .winstons()
+            .checked_add(self.reward as u128)
+            .ok_or_else(|| Error::AmountOverflow(format!("{} + {}", self.quantity, self.reward)))
+    }
+
     pub fn clone_with_no_data(&self) -> Result<Self, Error> {
         Ok(Self {
             format: self.format,
@@ -194,16 +330,229 @@ impl Tx {
         })
     }
 
-    pub fn get_chunk(&self, idx: usize) -> Result<Chunk, Error> {
-        Ok(Chunk {
-            data_root: self.data_root.clone(),
+    /// Restores the data, chunks and proofs of a header returned by [`Tx::clone_with_no_data`].
+    /// Fails with [`Error::InvalidProof`] when `data` does not match the header's data root.
+    pub fn with_data(self, data: Vec<u8>) -> Result<Self, Error> {
+        let merkle = Tx::generate_merkle(data)?;
+        if !merkle.data_root.ct_eq(&self.data_root) || merkle.data_size != self.data_size {
+            return Err(Error::InvalidProof);
+        }
+        Ok(Self {
+            data: merkle.data,
+            chunks: merkle.chunks,
+            proofs: merkle.proofs,
+            ..self
+        })
+    }
+
+    /// Re-chunks the data as `config` says, updating the data root, chunks and proofs; call
+    /// it before signing. Data chunked otherwise than by default can't be checked with
+    /// [`Tx::with_data`].
+    pub fn with_chunking(self, config: &ChunkingConfig) -> Result<Self, Error> {
+        let merkle = Tx::generate_merkle_with(self.data.0.to_vec(), config)?;
+        Ok(Self {
+            data_root: merkle.data_root,
+            chunks: merkle.chunks,
+            proofs: merkle.proofs,
+            ..self
+        })
+    }
+
+    /// Checks the chunks cover the data contiguously within protocol limits: none empty or
+    /// over [`MAX_CHUNK_SIZE`], only the last two smaller than it, the last no larger than the
+    /// one before. Gateways answer a bare 400 to chunks breaking these rules.
+    pub fn validate_chunk_layout(&self) -> Result<(), Error> {
+        let invalid = |reason: String| Err(Error::InvalidChunkLayout(reason));
+        if self.data.0.len() as u64 != self.data_size {
+            return invalid(format!(
+                "{} bytes of data for a data size of {}",
+                self.data.0.len(),
+                self.data_size
+            ));
+        }
+        if self.chunks.len() != self.proofs.len() {
+            return invalid(format!(
+                "{} chunks but {} proofs",
+                self.chunks.len(),
+                self.proofs.len()
+            ));
+        }
+        let sizes: Vec<usize> = self
+            .chunks
+            .iter()
+            .map(|c| c.max_byte_range.saturating_sub(c.min_byte_range))
+            .collect();
+        let mut end = 0;
+        for (i, (chunk, size)) in self.chunks.iter().zip(&sizes).enumerate() {
+            if chunk.min_byte_range != end {
+                return invalid(format!(
+                    "chunk {} starts at {}, expected {}",
+                    i, chunk.min_byte_range, end
+                ));
+            }
+            if *size == 0 {
+                return invalid(format!("chunk {} is empty", i));
+            }
+            if *size > MAX_CHUNK_SIZE {
+                return invalid(format!(
+                    "chunk {} is {} bytes, over the {} bytes limit",
+                    i, size, MAX_CHUNK_SIZE
+                ));
+            }
+            if *size < MAX_CHUNK_SIZE && i + 2 < sizes.len() {
+                return invalid(format!(
+                    "chunk {} is {} bytes, only the last two may be under {}",
+                    i, size, MAX_CHUNK_SIZE
+                ));
+            }
+            end = chunk.max_byte_range;
+        }
+        if end as u64 != self.data_size {
+            return invalid(format!("chunks cover {} of {} bytes", end, self.data_size));
+        }
+        if let [.., second_to_last, last] = sizes[..] {
+            if last > second_to_last {
+                return invalid(format!(
+                    "last chunk is {} bytes, larger than the {} bytes one before",
+                    last, second_to_last
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Summary of what signing the transaction commits to, to show before signing.
+    pub fn summary(&self) -> TxSummary {
+        let chunk_count = if self.chunks.is_empty() {
+            // Headers carry no chunks; default chunking yields this many.
+            (self.data_size as usize).div_ceil(MAX_CHUNK_SIZE)
+        } else {
+            self.chunks.len()
+        };
+        let tags = self
+            .tags
+            .iter()
+            .map(|tag| {
+                Tag::<String>::try_from(tag).unwrap_or_else(|_| Tag {
+                    name: String::from_utf8_lossy(&tag.name.0).into_owned(),
+                    value: tag.value.to_string(),
+                })
+            })
+            .collect();
+        TxSummary {
+            id: (!self.id.is_empty()).then(|| self.id.to_string()),
+            format: self.format,
+            target: (!self.target.is_empty()).then(|| self.target.to_string()),
+            quantity: self.quantity,
+            fee: self.reward,
+            data_size: self.data_size,
+            chunk_count,
+            tags,
+        }
+    }
+
+    /// Borrowed view of chunk `idx`, with its proof, over the transaction data.
+    pub fn get_chunk(&self, idx: usize) -> Result<ChunkRef<'_>, Error> {
+        let (chunk, proof) = match (self.chunks.get(idx), self.proofs.get(idx)) {
+            (Some(chunk), Some(proof)) => (chunk, proof),
+            _ => return Err(Error::SliceError),
+        };
+        Ok(ChunkRef {
+            data_root: &self.data_root,
             data_size: self.data_size,
-            data_path: Base64(self.proofs[idx].proof.clone()),
-            offset: self.proofs[idx].offset,
-            chunk: Base64(
-                self.data.0[self.chunks[idx].min_byte_range..self.chunks[idx].max_byte_range]
-                    .to_vec(),
-            ),
+            data_path: &proof.proof,
+            offset: proof.offset,
+            chunk: self
+                .data
+                .0
+                .get(chunk.min_byte_range..chunk.max_byte_range)
+                .ok_or(Error::SliceError)?,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{tags::Tag, Tx};
+    use crate::{
+        crypto::{base64::Base64, merkle::MAX_CHUNK_SIZE},
+        error::Error,
+        transaction::tags::FromUtf8Strs,
+    };
+
+    #[test]
+    fn test_tag_search() {
+        let tx = Tx {
+            tags: vec![
+                Tag::<Base64>::from_utf8_strs("App-Name", "Notes").unwrap(),
+                Tag::<Base64>::from_utf8_strs("App-Version", "1.0").unwrap(),
+                Tag::<Base64>::from_utf8_strs("Content-Type", "text/plain").unwrap(),
+                Tag::from_bytes("Key-Id", &[0xff, 0]),
+            ],
+            ..Default::default()
+        };
+        assert!(tx.has_tag("App-Name", "Notes"));
+        assert!(tx.has_tag("Key-Id", [0xff, 0]));
+        assert!(!tx.has_tag("App-Name", "notes"));
+        assert!(!tx.has_tag("App-Version", "Notes"));
+
+        let app: Vec<&Tag<Base64>> = tx.tags_matching("App-");
+        assert_eq!(app, vec![&tx.tags[0], &tx.tags[1]]);
+        assert!(tx.tags_matching("Unix-").is_empty());
+    }
+
+    #[test]
+    fn test_validate_bundle_tags() {
+        let mut tx = Tx {
+            tags: Tx::bundle_tags(),
+            ..Default::default()
+        };
+        assert!(tx.validate_bundle_tags().is_ok());
+
+        tx.tags.pop();
+        assert!(matches!(
+            tx.validate_bundle_tags(),
+            Err(Error::InvalidBundleTags(_))
+        ));
+
+        tx.tags
+            .push(Tag::<Base64>::from_utf8_strs("Bundle-Version", "1.0.0").unwrap());
+        assert!(matches!(
+            tx.validate_bundle_tags(),
+            Err(Error::InvalidBundleTags(_))
+        ));
+    }
+
+    #[test]
+    fn test_validate_chunk_layout() {
+        let tx = Tx::generate_merkle(vec![0; 2 * MAX_CHUNK_SIZE + 1]).unwrap();
+        assert!(tx.validate_chunk_layout().is_ok());
+        assert!(Tx::generate_merkle(vec![])
+            .unwrap()
+            .validate_chunk_layout()
+            .is_ok());
+
+        let mut short_first = tx.clone();
+        short_first.chunks[0].max_byte_range -= 1;
+        short_first.chunks[1].min_byte_range -= 1;
+        assert!(matches!(
+            short_first.validate_chunk_layout(),
+            Err(Error::InvalidChunkLayout(_))
+        ));
+
+        let mut gap = tx.clone();
+        gap.chunks[1].min_byte_range += 1;
+        assert!(gap.validate_chunk_layout().is_err());
+
+        let mut truncated = tx.clone();
+        truncated.chunks.pop();
+        truncated.proofs.pop();
+        assert!(truncated.validate_chunk_layout().is_err());
+
+        let mut larger_last = tx;
+        let last = larger_last.chunks.len() - 1;
+        larger_last.chunks[last - 1].max_byte_range -= 2;
+        larger_last.chunks[last].min_byte_range -= 2;
+        assert!(larger_last.validate_chunk_layout().is_err());
+    }
+}
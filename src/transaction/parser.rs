@@ -1,17 +1,30 @@
 use std::str::FromStr;
 
-use serde::{ser::SerializeStruct, Serialize, Serializer};
+use serde::{de, ser::SerializeStruct, Deserialize, Deserializer, Serialize, Serializer};
 
-use crate::{currency::Currency, error::Error};
+use crate::{crypto::base64::Base64, currency::Currency, error::Error, types::Tag as JsonTag};
 
 use super::{tags::Tag, Tx};
 use crate::types::Tx as JsonTx;
 
-impl From<JsonTx> for Tx {
-    fn from(json_tx: JsonTx) -> Self {
+impl TryFrom<JsonTx> for Tx {
+    type Error = Error;
+
+    fn try_from(json_tx: JsonTx) -> Result<Self, Error> {
+        if !matches!(json_tx.format, 1 | 2) {
+            return Err(Error::TransactionInfoError(format!(
+                "unsupported format {}",
+                json_tx.format
+            )));
+        }
         let tags = json_tx.tags.iter().map(Tag::from).collect();
-        Tx {
-            quantity: Currency::from_str(&json_tx.quantity).unwrap(),
+        let number = |name: &str, value: &str| {
+            u64::from_str(value).map_err(|e| {
+                Error::TransactionInfoError(format!("invalid {} {:?}: {}", name, value, e))
+            })
+        };
+        Ok(Tx {
+            quantity: Currency::from_str(&json_tx.quantity)?,
             format: json_tx.format,
             id: json_tx.id,
             last_tx: json_tx.last_tx,
@@ -20,12 +33,12 @@ impl From<JsonTx> for Tx {
             target: json_tx.target,
             data_root: json_tx.data_root,
             data: json_tx.data,
-            data_size: u64::from_str(&json_tx.data_size).unwrap(),
-            reward: u64::from_str(&json_tx.reward).unwrap(),
+            data_size: number("data_size", &json_tx.data_size)?,
+            reward: number("reward", &json_tx.reward)?,
             signature: json_tx.signature,
             chunks: vec![],
             proofs: vec![],
-        }
+        })
     }
 }
 
@@ -33,11 +46,17 @@ impl FromStr for Tx {
     type Err = Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let json_tx: JsonTx = serde_json::from_str(s).expect("Could not parse json");
-        Ok(Tx::from(json_tx))
+        let json_tx: JsonTx =
+            serde_json::from_str(s).map_err(|e| Error::TransactionInfoError(e.to_string()))?;
+        Tx::try_from(json_tx)
     }
 }
 
+/// Deserializes a [`Tx`] from the node's JSON format, for `#[serde(deserialize_with)]`.
+pub(crate) fn deserialize_tx<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Tx, D::Error> {
+    Tx::try_from(JsonTx::deserialize(deserializer)?).map_err(de::Error::custom)
+}
+
 impl Serialize for Tx {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -61,6 +80,108 @@ impl Serialize for Tx {
     }
 }
 
+/// A transaction as `JSON.stringify` serializes an arweave-js `Transaction`: fields in
+/// that order, numbers other than `format` as strings, and a `data_tree` array. Missing
+/// fields take the defaults of arweave-js.
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+struct ArweaveJsTx {
+    format: u8,
+    id: Base64,
+    last_tx: Base64,
+    owner: Base64,
+    tags: Vec<JsonTag>,
+    target: Base64,
+    quantity: String,
+    data: Base64,
+    data_size: String,
+    data_root: Base64,
+    data_tree: Vec<String>,
+    reward: String,
+    signature: Base64,
+}
+
+impl Default for ArweaveJsTx {
+    fn default() -> Self {
+        Self {
+            format: 2,
+            id: Base64::default(),
+            last_tx: Base64::default(),
+            owner: Base64::default(),
+            tags: vec![],
+            target: Base64::default(),
+            quantity: "0".to_string(),
+            data: Base64::default(),
+            data_size: "0".to_string(),
+            data_root: Base64::default(),
+            data_tree: vec![],
+            reward: "0".to_string(),
+            signature: Base64::default(),
+        }
+    }
+}
+
+impl Tx {
+    /// Parses the JSON of an arweave-js `Transaction`. Empty amounts are rejected: arweave-js
+    /// signs them as empty strings, which a [`Tx`] can't hold, so the signature data would
+    /// differ.
+    pub fn from_arweave_js_json(json: &str) -> Result<Self, Error> {
+        let js: ArweaveJsTx =
+            serde_json::from_str(json).map_err(|e| Error::TransactionInfoError(e.to_string()))?;
+        for (name, value) in [
+            ("quantity", &js.quantity),
+            ("data_size", &js.data_size),
+            ("reward", &js.reward),
+        ] {
+            if value.is_empty() {
+                return Err(Error::TransactionInfoError(format!("empty {}", name)));
+            }
+        }
+        Tx::try_from(JsonTx {
+            format: js.format,
+            id: js.id,
+            last_tx: js.last_tx,
+            owner: js.owner,
+            tags: js.tags,
+            target: js.target,
+            quantity: js.quantity,
+            data_root: js.data_root,
+            data: js.data,
+            data_size: js.data_size,
+            reward: js.reward,
+            signature: js.signature,
+        })
+    }
+
+    /// Serializes the transaction byte for byte as `JSON.stringify` does an arweave-js
+    /// `Transaction`.
+    pub fn to_arweave_js_json(&self) -> Result<String, Error> {
+        let js = ArweaveJsTx {
+            format: self.format,
+            id: self.id.clone(),
+            last_tx: self.last_tx.clone(),
+            owner: self.owner.clone(),
+            tags: self
+                .tags
+                .iter()
+                .map(|tag| JsonTag {
+                    name: tag.name.clone(),
+                    value: tag.value.clone(),
+                })
+                .collect(),
+            target: self.target.clone(),
+            quantity: self.quantity.to_string(),
+            data: self.data.clone(),
+            data_size: self.data_size.to_string(),
+            data_root: self.data_root.clone(),
+            data_tree: vec![],
+            reward: self.reward.to_string(),
+            signature: self.signature.clone(),
+        };
+        serde_json::to_string(&js).map_err(|e| Error::TransactionInfoError(e.to_string()))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::{fs::File, io::Read, str::FromStr};
@@ -68,6 +189,8 @@ mod tests {
     use crate::{
         crypto::base64::Base64,
         currency::Currency,
+        error::Error,
+        signer::ArweaveSigner,
         transaction::{tags::Tag, Tx},
     };
 
@@ -84,12 +207,12 @@ mod tests {
             last_tx: Base64::from_str("ddvXNxatQmS3LeKi_x1RJn6g9G0esUaTEgT40a6f_WYyawZaSK3w8WC2czAuLgmT").unwrap(),
             owner: Base64::from_str("pjdss8ZaDfEH6K6U7GeW2nxDqR4IP049fk1fK0lndimbMMVBdPv_hSpm8T8EtBDxrUdi1OHZfMhUixGaut-3nQ4GG9nM249oxhCtxqqNvEXrmQRGqczyLxuh-fKn9Fg--hS9UpazHpfVAFnB5aCfXoNhPuI8oByyFKMKaOVgHNqP5NBEqabiLftZD3W_lsFCPGuzr4Vp0YS7zS2hDYScC2oOMu4rGU1LcMZf39p3153Cq7bS2Xh6Y-vw5pwzFYZdjQxDn8x8BG3fJ6j8TGLXQsbKH1218_HcUJRvMwdpbUQG5nvA2GXVqLqdwp054Lzk9_B_f1lVrmOKuHjTNHq48w").unwrap(),
             tags: vec![
-                Tag { name: Base64(b"test".to_vec()), value: Base64(b"test".to_vec()) }
+                Tag { name: Base64::from(b"test".to_vec()), value: Base64::from(b"test".to_vec()) }
             ],
             target: Base64::from_str("PAgdonEn9f5xd-UbYdCX40Sj28eltQVnxz6bbUijeVY").unwrap(),
             quantity: Currency::from(100000),
-            data_root: Base64(vec![]),
-            data: Base64(vec![]),
+            data_root: Base64::from(vec![]),
+            data: Base64::from(vec![]),
             data_size: 0,
             reward: 600912,
             signature: Base64::from_str("EJQN0DpfPBm1aUo1qk6dCkrY_zKHMJBQx3v36UOzmodF39RvBI2rqx_gTgLzszNkHIWnf-zwzXCz6xF5wzlrHWkosgfSwfZOhm3aVE5KLGvqVqSlMTlIzkIcR6KKFRe9m7HyOxJHvXykAD8X1X_6RExnXAZX4B9mwR10lqCG2wkRMJxchVisOZph-O5OfgteC1lb5YFx0BNAtmVgtUlY7dQdV1vVYq2_sDJPkYpHK5YIMIjoRsqdGP31gOFXTmzuIHYhRyii-clx2uxrv0pjfnv9tl9WPViHu3FGLlW9tH5z3mXdt7PQx-o8MGK_MXz10LLlqsPdos2rI3D3MgPUqQ").unwrap(),
@@ -99,4 +222,65 @@ mod tests {
 
         assert_eq!(actual_tx, expected_tx);
     }
+
+    #[test]
+    fn should_round_trip_arweave_js_json() {
+        let mut file = File::open("res/sample_tx.json").unwrap();
+        let mut data = String::new();
+        file.read_to_string(&mut data).unwrap();
+        let tx = Tx::from_str(&data).unwrap();
+
+        let json = format!(
+            concat!(
+                r#"{{"format":2,"id":"t3K1b8IhvtGWxAGsipZE5NafmEGrtj3OAcYikJ0edeU","#,
+                r#""last_tx":"ddvXNxatQmS3LeKi_x1RJn6g9G0esUaTEgT40a6f_WYyawZaSK3w8WC2czAuLgmT","#,
+                r#""owner":"{}","tags":[{{"name":"dGVzdA","value":"dGVzdA"}}],"#,
+                r#""target":"PAgdonEn9f5xd-UbYdCX40Sj28eltQVnxz6bbUijeVY","quantity":"100000","#,
+                r#""data":"","data_size":"0","data_root":"","data_tree":[],"reward":"600912","#,
+                r#""signature":"{}"}}"#
+            ),
+            tx.owner, tx.signature
+        );
+        assert_eq!(tx.to_arweave_js_json().unwrap(), json);
+
+        let parsed = Tx::from_arweave_js_json(&json).unwrap();
+        assert_eq!(parsed, tx);
+        ArweaveSigner::verify_transaction(&parsed).unwrap();
+    }
+
+    #[test]
+    fn should_apply_arweave_js_defaults() {
+        let tx = Tx::from_arweave_js_json(
+            r#"{"last_tx":"","tags":[{"name":"dGVzdA","value":"dGVzdA"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(tx.format, 2);
+        assert_eq!(tx.quantity, Currency::from(0));
+        assert_eq!(tx.reward, 0);
+        assert_eq!(tx.tags.len(), 1);
+        assert_eq!(
+            tx.to_arweave_js_json().unwrap(),
+            concat!(
+                r#"{"format":2,"id":"","last_tx":"","owner":"","tags":[{"name":"dGVzdA","value":"dGVzdA"}],"#,
+                r#""target":"","quantity":"0","data":"","data_size":"0","data_root":"","data_tree":[],"#,
+                r#""reward":"0","signature":""}"#
+            )
+        );
+    }
+
+    #[test]
+    fn should_reject_empty_amounts_and_unknown_formats() {
+        for json in [
+            r#"{"quantity":""}"#,
+            r#"{"data_size":""}"#,
+            r#"{"reward":""}"#,
+            r#"{"format":3}"#,
+            r#"{"format":0}"#,
+        ] {
+            assert!(matches!(
+                Tx::from_arweave_js_json(json),
+                Err(Error::TransactionInfoError(_))
+            ));
+        }
+    }
 }
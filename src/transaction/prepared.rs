@@ -0,0 +1,161 @@
+//! Unsigned transactions handed off between services, e.g. from a service building
+//! transactions to one holding the signing key.
+
+use std::{
+    collections::BTreeMap,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+
+use super::{parser::deserialize_tx, Tx};
+
+/// Envelope version written by [`PreparedTx::to_json`].
+pub const PREPARED_TX_VERSION: u8 = 1;
+
+/// An unsigned transaction, the fee it was quoted and free-form metadata, serialized to
+/// JSON. Rehydrating it with [`PreparedTx::from_json`] validates the transaction.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PreparedTx {
+    pub version: u8,
+    #[serde(deserialize_with = "deserialize_tx")]
+    pub tx: Tx,
+    /// Reward quoted by the gateway for the transaction.
+    pub fee: u64,
+    /// When the fee was quoted, in seconds since the epoch.
+    pub quoted_at: u64,
+    #[serde(default)]
+    pub metadata: BTreeMap<String, String>,
+}
+
+impl PreparedTx {
+    /// Wraps unsigned `tx`, whose reward is the fee just quoted.
+    pub fn new(tx: Tx) -> Result<Self, Error> {
+        let prepared = Self {
+            version: PREPARED_TX_VERSION,
+            fee: tx.reward,
+            quoted_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .expect("System time before unix epoch")
+                .as_secs(),
+            tx,
+            metadata: BTreeMap::new(),
+        };
+        prepared.validate()?;
+        Ok(prepared)
+    }
+
+    pub fn with_metadata(mut self, key: &str, value: &str) -> Self {
+        self.metadata.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    pub fn to_json(&self) -> Result<String, Error> {
+        serde_json::to_string(self).map_err(|e| Error::PreparedTxError(e.to_string()))
+    }
+
+    /// Parses and validates an envelope. Chunks and proofs of the transaction are rebuilt
+    /// from its data, which must match the data root.
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        let mut prepared: Self =
+            serde_json::from_str(json).map_err(|e| Error::PreparedTxError(e.to_string()))?;
+        prepared.validate()?;
+        if !prepared.tx.data.is_empty() {
+            let data = prepared.tx.data.to_vec();
+            prepared.tx = prepared
+                .tx
+                .clone_with_no_data()?
+                .with_data(data)
+                .map_err(|_| Error::PreparedTxError("data does not match data root".to_string()))?;
+        }
+        Ok(prepared)
+    }
+
+    /// Seconds elapsed since the fee was quoted, to decide whether to quote again.
+    pub fn age(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|now| now.as_secs().saturating_sub(self.quoted_at))
+            .unwrap_or_default()
+    }
+
+    pub fn into_tx(self) -> Tx {
+        self.tx
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        let invalid = |reason: String| Err(Error::PreparedTxError(reason));
+        if self.version != PREPARED_TX_VERSION {
+            return invalid(format!("unsupported version {}", self.version));
+        }
+        if self.tx.format != 2 {
+            return invalid(format!("unsupported transaction format {}", self.tx.format));
+        }
+        if !self.tx.signature.is_empty() || !self.tx.id.is_empty() {
+            return invalid("transaction is already signed".to_string());
+        }
+        if self.tx.reward != self.fee {
+            return invalid(format!(
+                "reward {} differs from quoted fee {}",
+                self.tx.reward, self.fee
+            ));
+        }
+        if !self.tx.data.is_empty() && self.tx.data.len() as u64 != self.tx.data_size {
+            return invalid(format!(
+                "data is {} bytes, data_size is {}",
+                self.tx.data.len(),
+                self.tx.data_size
+            ));
+        }
+        self.tx.cost()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PreparedTx;
+    use crate::{
+        crypto::base64::Base64,
+        signer::{tests::unsigned_tx, ArweaveSigner},
+    };
+
+    fn prepared() -> PreparedTx {
+        let mut tx = unsigned_tx(&ArweaveSigner::default(), &[7; 300_000]);
+        tx.reward = 1234;
+        PreparedTx::new(tx).unwrap().with_metadata("job", "42")
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let prepared = prepared();
+        let json = prepared.to_json().unwrap();
+        let rehydrated = PreparedTx::from_json(&json).unwrap();
+
+        assert_eq!(rehydrated.metadata["job"], "42");
+        assert_eq!(rehydrated.tx.chunks, prepared.tx.chunks);
+        assert_eq!(rehydrated, prepared);
+
+        let signed = ArweaveSigner::default()
+            .sign_transaction(rehydrated.into_tx())
+            .unwrap();
+        assert!(ArweaveSigner::verify_transaction(&signed).is_ok());
+    }
+
+    #[test]
+    fn test_rejects_invalid_envelopes() {
+        let mut value = serde_json::to_value(prepared()).unwrap();
+        value["fee"] = 1.into();
+        assert!(PreparedTx::from_json(&value.to_string()).is_err());
+
+        let mut value = serde_json::to_value(prepared()).unwrap();
+        value["tx"]["data_root"] = Base64::from(vec![0; 32]).to_string().into();
+        assert!(PreparedTx::from_json(&value.to_string()).is_err());
+
+        let mut value = serde_json::to_value(prepared()).unwrap();
+        value["tx"]["signature"] = Base64::from(vec![1; 512]).to_string().into();
+        assert!(PreparedTx::from_json(&value.to_string()).is_err());
+    }
+}
@@ -0,0 +1,107 @@
+//! What a transaction does, laid out for review before it is signed. See
+//! [`super::Tx::summary`].
+
+use std::fmt;
+
+use crate::currency::Currency;
+
+use super::tags::Tag;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TxSummary {
+    /// Id of the transaction, once signed.
+    pub id: Option<String>,
+    pub format: u8,
+    /// Wallet receiving `quantity`, if any.
+    pub target: Option<String>,
+    pub quantity: Currency,
+    /// Reward paid to miners, in winstons.
+    pub fee: u64,
+    pub data_size: u64,
+    pub chunk_count: usize,
+    /// Tags, with values that aren't UTF-8 shown in Base64.
+    pub tags: Vec<Tag<String>>,
+}
+
+impl TxSummary {
+    /// Winstons leaving the wallet: fee plus quantity.
+    pub fn total_cost(&self) -> u128 {
+        self.quantity.winstons() + self.fee as u128
+    }
+}
+
+impl fmt::Display for TxSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.id {
+            Some(id) => writeln!(f, "Transaction {} (format {})", id, self.format)?,
+            None => writeln!(f, "Unsigned transaction (format {})", self.format)?,
+        }
+        writeln!(
+            f,
+            "  Target:   {}",
+            self.target.as_deref().unwrap_or("none")
+        )?;
+        writeln!(f, "  Quantity: {} winston", self.quantity)?;
+        writeln!(f, "  Fee:      {} winston", self.fee)?;
+        writeln!(f, "  Total:    {} winston", self.total_cost())?;
+        write!(
+            f,
+            "  Data:     {} bytes in {} chunk(s)",
+            self.data_size, self.chunk_count
+        )?;
+        for tag in &self.tags {
+            write!(f, "\n  Tag:      {}: {}", tag.name, tag.value)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        crypto::base64::Base64,
+        currency::Currency,
+        transaction::{
+            tags::{FromUtf8Strs, Tag},
+            Tx,
+        },
+    };
+
+    #[test]
+    fn test_summary() {
+        let tx = Tx {
+            format: 2,
+            target: Base64::from(vec![1; 32]),
+            quantity: Currency::from(5_u128),
+            reward: 10,
+            data_size: 600 * 1024,
+            tags: vec![
+                Tag::<Base64>::from_utf8_strs("Content-Type", "text/plain").unwrap(),
+                Tag {
+                    name: Base64::from_utf8_str("Raw").unwrap(),
+                    value: Base64::from(vec![0xff]),
+                },
+            ],
+            ..Default::default()
+        };
+        let summary = tx.summary();
+        assert_eq!(summary.id, None);
+        assert_eq!(summary.chunk_count, 3);
+        assert_eq!(summary.total_cost(), 15);
+        assert_eq!(summary.tags[1].value, "_w");
+        assert_eq!(
+            summary.to_string(),
+            format!(
+                "Unsigned transaction (format 2)
+  Target:   {}
+  Quantity: 5 winston
+  Fee:      10 winston
+  Total:    15 winston
+  Data:     614400 bytes in 3 chunk(s)
+  Tag:      Content-Type: text/plain
+  Tag:      Raw: _w",
+                Base64::from(vec![1; 32])
+            )
+        );
+    }
+}
@@ -1,3 +1,5 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
 use serde::{ser::SerializeStruct, Deserialize, Serialize, Serializer};
 
 use crate::{
@@ -8,6 +10,26 @@ use crate::{
 
 use super::ToItems;
 
+pub const CONTENT_TYPE: &str = "Content-Type";
+pub const USER_AGENT: &str = "User-Agent";
+pub const APP_NAME: &str = "App-Name";
+pub const APP_VERSION: &str = "App-Version";
+pub const UNIX_TIME: &str = "Unix-Time";
+pub const BUNDLE_FORMAT: &str = "Bundle-Format";
+pub const BUNDLE_VERSION: &str = "Bundle-Version";
+pub const IPFS_ADD: &str = "IPFS-Add";
+pub const FILE_HASH: &str = "File-Hash";
+pub const SEQUENCE: &str = "Sequence";
+
+/// Prefix of the name of a tag pointing to the transaction holding the value of the tag
+/// named by the rest, see [`Tag::pointer`].
+pub const TAG_POINTER_PREFIX: &str = "Tag-Pointer-";
+
+/// `Bundle-Format` value of ANS-104 bundles.
+pub const ANS104_BUNDLE_FORMAT: &str = "binary";
+/// `Bundle-Version` value of ANS-104 bundles.
+pub const ANS104_BUNDLE_VERSION: &str = "2.0.0";
+
 /// Transaction tag.
 #[derive(Deserialize, Debug, Clone, Eq, PartialEq)]
 pub struct Tag<T> {
@@ -15,6 +37,140 @@ pub struct Tag<T> {
     pub value: T,
 }
 
+impl Tag<Base64> {
+    /// Tag holding raw bytes, e.g. a binary fingerprint, without requiring UTF-8. Such tags
+    /// can't be read back as [`Tag<String>`].
+    pub fn from_bytes(name: &str, value: &[u8]) -> Self {
+        Self {
+            name: Base64::from(name.as_bytes().to_vec()),
+            value: Base64::from(value.to_vec()),
+        }
+    }
+}
+
+/// Builders for common protocol tags, validating their values.
+impl Tag<Base64> {
+    /// `Content-Type` tag. `mime` must look like `type/subtype`, optionally followed by parameters.
+    pub fn content_type(mime: &str) -> Result<Self, Error> {
+        let essence = mime.split(';').next().unwrap_or_default().trim();
+        let valid = match essence.split_once('/') {
+            Some((type_, subtype)) => is_token(type_) && is_token(subtype),
+            None => false,
+        };
+        if !valid {
+            return Err(Error::InvalidTagValue(format!(
+                "{}: {:?} is not a MIME type",
+                CONTENT_TYPE, mime
+            )));
+        }
+        Self::from_utf8_strs(CONTENT_TYPE, mime)
+    }
+
+    pub fn app_name(name: &str) -> Result<Self, Error> {
+        if name.trim().is_empty() || name.chars().any(char::is_control) {
+            return Err(Error::InvalidTagValue(format!(
+                "{}: {:?} is empty or contains control characters",
+                APP_NAME, name
+            )));
+        }
+        Self::from_utf8_strs(APP_NAME, name)
+    }
+
+    pub fn app_version(version: &str) -> Result<Self, Error> {
+        if version.is_empty() || version.chars().any(|c| c.is_whitespace() || c.is_control()) {
+            return Err(Error::InvalidTagValue(format!(
+                "{}: {:?} is empty or contains whitespace",
+                APP_VERSION, version
+            )));
+        }
+        Self::from_utf8_strs(APP_VERSION, version)
+    }
+
+    /// `Unix-Time` tag holding `secs` since the epoch.
+    pub fn unix_time(secs: u64) -> Self {
+        Self::from_utf8_strs(UNIX_TIME, &secs.to_string()).unwrap()
+    }
+
+    /// `Unix-Time` tag holding the current time.
+    pub fn unix_time_now() -> Self {
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("System time before unix epoch")
+            .as_secs();
+        Self::unix_time(secs)
+    }
+
+    /// `Sequence` tag holding the position of the transaction in a series of the wallet's.
+    pub fn sequence(n: u64) -> Self {
+        Self::from_utf8_strs(SEQUENCE, &n.to_string()).unwrap()
+    }
+
+    /// `IPFS-Add` tag holding the CID of the data, e.g. from [`crate::ipfs::Cid`].
+    pub fn ipfs_add(cid: &str) -> Result<Self, Error> {
+        if cid.is_empty() || !cid.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(Error::InvalidTagValue(format!(
+                "{}: {:?} is not a CID",
+                IPFS_ADD, cid
+            )));
+        }
+        Self::from_utf8_strs(IPFS_ADD, cid)
+    }
+
+    /// `File-Hash` tag holding a digest of the data, written `<algorithm>:<hex>` as by
+    /// [`crate::digest::Digest`].
+    pub fn file_hash(digest: &str) -> Result<Self, Error> {
+        if digest.is_empty() || digest.chars().any(|c| c.is_whitespace() || c.is_control()) {
+            return Err(Error::InvalidTagValue(format!(
+                "{}: {:?} is empty or contains whitespace",
+                FILE_HASH, digest
+            )));
+        }
+        Self::from_utf8_strs(FILE_HASH, digest)
+    }
+
+    /// `Bundle-Format` tag; ANS-104 bundles use `binary`, ANS-102 bundles `json`.
+    pub fn bundle_format(format: &str) -> Result<Self, Error> {
+        if format != "binary" && format != "json" {
+            return Err(Error::InvalidTagValue(format!(
+                "{}: {:?} is not one of binary, json",
+                BUNDLE_FORMAT, format
+            )));
+        }
+        Self::from_utf8_strs(BUNDLE_FORMAT, format)
+    }
+
+    /// Tag standing in for tag `name`, whose value is the data of transaction `id`, for
+    /// values too large to be carried by a tag.
+    pub fn pointer(name: &Base64, id: &Base64) -> Self {
+        let mut pointer_name = TAG_POINTER_PREFIX.as_bytes().to_vec();
+        pointer_name.extend_from_slice(&name.0);
+        Self {
+            name: Base64::from(pointer_name),
+            value: Base64::from_utf8_str(&id.to_string()).unwrap(),
+        }
+    }
+
+    /// Name of the tag this tag stands in for and id of the transaction holding its value,
+    /// when it is a [`Tag::pointer`].
+    pub fn pointer_target(&self) -> Option<(Base64, Base64)> {
+        let name = self.name.0.strip_prefix(TAG_POINTER_PREFIX.as_bytes())?;
+        let id = self.value.to_utf8_string().ok()?.parse().ok()?;
+        Some((Base64::from(name.to_vec()), id))
+    }
+
+    /// Bytes the tag counts toward [`crate::consts::MAX_TAGS_SIZE`].
+    pub fn size(&self) -> usize {
+        self.name.0.len() + self.value.0.len()
+    }
+}
+
+/// RFC 2045 token: non-empty and free of whitespace, control characters and tspecials.
+fn is_token(s: &str) -> bool {
+    !s.is_empty()
+        && s.chars()
+            .all(|c| c.is_ascii_graphic() && !"()<>@,;:\\\"/[]?=".contains(c))
+}
+
 /// Implemented to create [`Tag`]s from utf-8 strings.
 pub trait FromUtf8Strs<T> {
     fn from_utf8_strs(name: &str, value: &str) -> Result<T, Error>;
@@ -41,6 +197,19 @@ impl FromUtf8Strs<Tag<String>> for Tag<String> {
     }
 }
 
+impl TryFrom<&Tag<Base64>> for Tag<String> {
+    type Error = Error;
+
+    fn try_from(tag: &Tag<Base64>) -> Result<Self, Error> {
+        let name = tag.name.to_utf8_string()?;
+        let value = tag
+            .value
+            .to_utf8_string()
+            .map_err(|e| Error::InvalidBase64(format!("tag {}: {}", name, e)))?;
+        Ok(Tag { name, value })
+    }
+}
+
 impl<'a> ToItems<'a, Vec<Tag<Base64>>> for Vec<Tag<Base64>> {
     fn to_deep_hash_item(&'a self) -> Result<DeepHashItem, Error> {
         Ok(DeepHashItem::List(
@@ -81,3 +250,68 @@ impl From<&BaseTag> for Tag<Base64> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{FromUtf8Strs, Tag};
+    use crate::{crypto::base64::Base64, error::Error};
+
+    #[test]
+    fn test_content_type() {
+        let tag = Tag::<Base64>::content_type("text/html; charset=utf-8").unwrap();
+        assert_eq!(
+            tag,
+            Tag::<Base64>::from_utf8_strs("Content-Type", "text/html; charset=utf-8").unwrap()
+        );
+
+        for invalid in ["", "text", "text/", "/html", "text/ html", "te xt/html"] {
+            assert!(matches!(
+                Tag::<Base64>::content_type(invalid),
+                Err(Error::InvalidTagValue(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_binary_tags() {
+        let fingerprint = [0xff, 0x00, 0xfe, 0x80];
+        let tag = Tag::<Base64>::from_bytes("Fingerprint", &fingerprint);
+        assert_eq!(&tag.value.0[..], &fingerprint);
+        assert_eq!(tag.value.to_string(), "_wD-gA");
+        assert_eq!(
+            Tag::<Base64>::from_bytes("Name", b"text"),
+            Tag::<Base64>::from_utf8_strs("Name", "text").unwrap()
+        );
+        assert!(Tag::<String>::try_from(&tag).is_err());
+    }
+
+    #[test]
+    fn test_pointer_tags() {
+        let name = Base64::from_utf8_str("Description").unwrap();
+        let id = Base64::from(vec![7; 32]);
+        let pointer = Tag::<Base64>::pointer(&name, &id);
+        assert_eq!(
+            pointer.name.to_utf8_string().unwrap(),
+            "Tag-Pointer-Description"
+        );
+        assert_eq!(pointer.pointer_target(), Some((name.clone(), id)));
+        assert_eq!(pointer.size(), 23 + 43);
+
+        let plain = Tag::<Base64>::from_utf8_strs("Description", "short").unwrap();
+        assert_eq!(plain.pointer_target(), None);
+    }
+
+    #[test]
+    fn test_protocol_tags() {
+        assert!(Tag::<Base64>::app_name("MyApp").is_ok());
+        assert!(Tag::<Base64>::app_name(" ").is_err());
+        assert!(Tag::<Base64>::app_version("1.0.0").is_ok());
+        assert!(Tag::<Base64>::app_version("1 .0").is_err());
+        assert!(Tag::<Base64>::bundle_format("binary").is_ok());
+        assert!(Tag::<Base64>::bundle_format("zip").is_err());
+
+        let tag = Tag::<Base64>::unix_time(1_600_000_000);
+        assert_eq!(tag.name.to_utf8_string().unwrap(), "Unix-Time");
+        assert_eq!(tag.value.to_utf8_string().unwrap(), "1600000000");
+    }
+}
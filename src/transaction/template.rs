@@ -0,0 +1,199 @@
+//! Named presets for transactions posted over and over with the same shape, e.g. event
+//! log entries, instantiated with their data by [`crate::Arweave::create_from_template`].
+
+use std::{collections::BTreeMap, fmt, sync::Arc};
+
+use crate::{crypto::base64::Base64, error::Error};
+
+use super::tags::{FromUtf8Strs, Tag};
+
+/// Derives the quantity of a transaction from its data.
+pub type QuantityFn = Arc<dyn Fn(&[u8]) -> u128 + Send + Sync>;
+
+/// Winstons transferred to the target by a transaction of the template.
+#[derive(Clone)]
+pub enum QuantityRule {
+    Fixed(u128),
+    /// Winstons per byte of data.
+    PerByte(u128),
+    /// Computed from the data.
+    Derived(QuantityFn),
+}
+
+impl QuantityRule {
+    pub fn quantity(&self, data: &[u8]) -> Result<u128, Error> {
+        match self {
+            QuantityRule::Fixed(quantity) => Ok(*quantity),
+            QuantityRule::PerByte(rate) => rate.checked_mul(data.len() as u128).ok_or_else(|| {
+                Error::AmountOverflow(format!("{} winstons x {} bytes", rate, data.len()))
+            }),
+            QuantityRule::Derived(derive) => Ok(derive(data)),
+        }
+    }
+}
+
+impl Default for QuantityRule {
+    fn default() -> Self {
+        QuantityRule::Fixed(0)
+    }
+}
+
+impl fmt::Debug for QuantityRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QuantityRule::Fixed(quantity) => f.debug_tuple("Fixed").field(quantity).finish(),
+            QuantityRule::PerByte(rate) => f.debug_tuple("PerByte").field(rate).finish(),
+            QuantityRule::Derived(_) => f.write_str("Derived(..)"),
+        }
+    }
+}
+
+/// Target, tags and quantity shared by the transactions created from it.
+#[derive(Debug, Clone)]
+pub struct TxTemplate {
+    name: String,
+    target: Base64,
+    tags: Vec<Tag<Base64>>,
+    quantity: QuantityRule,
+    auto_content_tag: bool,
+}
+
+impl TxTemplate {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            target: Base64::empty(),
+            tags: Vec::new(),
+            quantity: QuantityRule::default(),
+            auto_content_tag: false,
+        }
+    }
+
+    pub fn with_target(mut self, target: Base64) -> Self {
+        self.target = target;
+        self
+    }
+
+    pub fn with_tag(mut self, name: &str, value: &str) -> Result<Self, Error> {
+        self.tags.push(Tag::<Base64>::from_utf8_strs(name, value)?);
+        Ok(self)
+    }
+
+    pub fn with_tags(mut self, tags: Vec<Tag<Base64>>) -> Self {
+        self.tags.extend(tags);
+        self
+    }
+
+    pub fn with_quantity(mut self, quantity: QuantityRule) -> Self {
+        self.quantity = quantity;
+        self
+    }
+
+    /// Whether instances get a `Content-Type` tag guessed from their data.
+    pub fn with_auto_content_tag(mut self, auto_content_tag: bool) -> Self {
+        self.auto_content_tag = auto_content_tag;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn target(&self) -> &Base64 {
+        &self.target
+    }
+
+    pub fn tags(&self) -> &[Tag<Base64>] {
+        &self.tags
+    }
+
+    pub fn auto_content_tag(&self) -> bool {
+        self.auto_content_tag
+    }
+
+    pub fn quantity(&self, data: &[u8]) -> Result<u128, Error> {
+        self.quantity.quantity(data)
+    }
+
+    /// Preset tags followed by `extra_tags`.
+    pub fn tags_with(&self, extra_tags: Vec<Tag<Base64>>) -> Vec<Tag<Base64>> {
+        let mut tags = self.tags.clone();
+        tags.extend(extra_tags);
+        tags
+    }
+}
+
+/// Templates by name.
+#[derive(Debug, Clone, Default)]
+pub struct TemplateRegistry {
+    templates: BTreeMap<String, TxTemplate>,
+}
+
+impl TemplateRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `template`, returning the one it replaces under the same name.
+    pub fn register(&mut self, template: TxTemplate) -> Option<TxTemplate> {
+        self.templates.insert(template.name.clone(), template)
+    }
+
+    pub fn get(&self, name: &str) -> Result<&TxTemplate, Error> {
+        self.templates
+            .get(name)
+            .ok_or_else(|| Error::TemplateNotFound(name.to_string()))
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<TxTemplate> {
+        self.templates.remove(name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.templates.keys().map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::{QuantityRule, TemplateRegistry, TxTemplate};
+    use crate::{
+        crypto::base64::Base64,
+        error::Error,
+        transaction::tags::{FromUtf8Strs, Tag},
+    };
+
+    #[test]
+    fn test_templates() {
+        let mut registry = TemplateRegistry::new();
+        registry.register(
+            TxTemplate::new("event")
+                .with_tag("App-Name", "events")
+                .unwrap()
+                .with_quantity(QuantityRule::PerByte(2)),
+        );
+        registry.register(
+            TxTemplate::new("tip")
+                .with_target(Base64::from(vec![1; 32]))
+                .with_quantity(QuantityRule::Derived(Arc::new(|data| data[0] as u128))),
+        );
+        assert_eq!(registry.names().collect::<Vec<_>>(), vec!["event", "tip"]);
+
+        let event = registry.get("event").unwrap();
+        assert_eq!(event.quantity(b"abc").unwrap(), 6);
+        let extra = Tag::<Base64>::from_utf8_strs("Seq", "1").unwrap();
+        let tags = event.tags_with(vec![extra.clone()]);
+        assert_eq!(tags.len(), 2);
+        assert_eq!(tags[1], extra);
+
+        assert_eq!(registry.get("tip").unwrap().quantity(&[7]).unwrap(), 7);
+        assert!(QuantityRule::PerByte(u128::MAX).quantity(b"ab").is_err());
+        assert!(registry.remove("tip").is_some());
+        assert!(matches!(
+            registry.get("tip"),
+            Err(Error::TemplateNotFound(_))
+        ));
+    }
+}
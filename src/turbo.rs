@@ -0,0 +1,236 @@
+//! Support for ArDrive Turbo, an upload service paid with Turbo credits instead of AR.
+
+use std::str::FromStr;
+
+use async_trait::async_trait;
+use rand::{thread_rng, RngCore};
+use reqwest::{header::CONTENT_TYPE, RequestBuilder, Response};
+use serde::Deserialize;
+
+use crate::{
+    backend::{sign_data_item, SignedUpload, Upload, UploadBackend},
+    consts::{TURBO_PAYMENT_URL, TURBO_UPLOAD_URL},
+    crypto::base64::Base64,
+    currency::deserialize_winstons,
+    data_item::DataItem,
+    error::Error,
+    signer::ArweaveSigner,
+    Arweave,
+};
+
+/// Headers of requests signed by a wallet, for account endpoints.
+pub const PUBLIC_KEY_HEADER: &str = "x-public-key";
+pub const NONCE_HEADER: &str = "x-nonce";
+pub const SIGNATURE_HEADER: &str = "x-signature";
+
+/// What Turbo returns for an accepted data item.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct TurboReceipt {
+    pub id: String,
+    pub owner: String,
+    /// Credits charged for the upload, in winc.
+    #[serde(deserialize_with = "deserialize_winstons")]
+    pub winc: u128,
+    #[serde(default)]
+    pub data_caches: Vec<String>,
+    #[serde(default)]
+    pub fast_finality_indexes: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct Winc {
+    #[serde(deserialize_with = "deserialize_winstons")]
+    winc: u128,
+}
+
+/// Client of Turbo's upload and payment services.
+pub struct TurboClient {
+    client: reqwest::Client,
+    upload_url: url::Url,
+    payment_url: url::Url,
+}
+
+impl Default for TurboClient {
+    fn default() -> Self {
+        Self::new(
+            url::Url::from_str(TURBO_UPLOAD_URL).unwrap(),
+            url::Url::from_str(TURBO_PAYMENT_URL).unwrap(),
+        )
+    }
+}
+
+impl TurboClient {
+    pub fn new(upload_url: url::Url, payment_url: url::Url) -> Self {
+        Self::with_client(upload_url, payment_url, reqwest::Client::new())
+    }
+
+    pub fn with_client(
+        upload_url: url::Url,
+        payment_url: url::Url,
+        client: reqwest::Client,
+    ) -> Self {
+        Self {
+            client,
+            upload_url,
+            payment_url,
+        }
+    }
+
+    pub fn upload_url(&self) -> &url::Url {
+        &self.upload_url
+    }
+
+    /// Credits charged to upload `bytes` bytes, in winc.
+    pub async fn price(&self, bytes: u64) -> Result<u128, Error> {
+        let url = join(&self.payment_url, &format!("v1/price/bytes/{}", bytes))?;
+        let price: Winc = json(self.send(self.client.get(url)).await?).await?;
+        Ok(price.winc)
+    }
+
+    /// Credits of `signer`'s account, in winc.
+    pub async fn balance(&self, signer: &ArweaveSigner) -> Result<u128, Error> {
+        let url = join(&self.payment_url, "v1/balance")?;
+        let request = signed(self.client.get(url), signer);
+        let balance: Winc = json(self.send(request).await?).await?;
+        Ok(balance.winc)
+    }
+
+    /// Posts signed `item`, paid with the credits of its owner.
+    pub async fn upload(&self, item: &DataItem) -> Result<TurboReceipt, Error> {
+        let url = join(&self.upload_url, "v1/tx")?;
+        let request = self
+            .client
+            .post(url)
+            .header(CONTENT_TYPE, "application/octet-stream")
+            .body(item.to_bytes()?);
+        json(self.send(request).await?).await
+    }
+
+    async fn send(&self, request: RequestBuilder) -> Result<Response, Error> {
+        let res = request
+            .send()
+            .await
+            .map_err(|e| Error::BundlerError(e.to_string()))?;
+        if !res.status().is_success() {
+            let status = res.status();
+            let body = res.text().await.unwrap_or_default();
+            return Err(Error::BundlerError(format!("{}: {}", status, body)));
+        }
+        Ok(res)
+    }
+}
+
+/// Adds the headers proving `request` comes from `signer`: its public key, a random nonce
+/// and the signature of the nonce.
+fn signed(request: RequestBuilder, signer: &ArweaveSigner) -> RequestBuilder {
+    let mut nonce = [0; 16];
+    thread_rng().fill_bytes(&mut nonce);
+    let nonce = Base64::from(nonce.to_vec()).to_string();
+    request
+        .header(PUBLIC_KEY_HEADER, signer.keypair_modulus().to_string())
+        .header(NONCE_HEADER, &nonce)
+        .header(SIGNATURE_HEADER, signer.sign(nonce.as_bytes()).to_string())
+}
+
+fn join(base: &url::Url, path: &str) -> Result<url::Url, Error> {
+    base.join(path)
+        .map_err(|e| Error::BundlerError(e.to_string()))
+}
+
+async fn json<T: serde::de::DeserializeOwned>(res: Response) -> Result<T, Error> {
+    res.json()
+        .await
+        .map_err(|e| Error::BundlerError(e.to_string()))
+}
+
+#[async_trait]
+impl UploadBackend for TurboClient {
+    async fn sign(&self, arweave: &Arweave, upload: Upload) -> Result<SignedUpload, Error> {
+        sign_data_item(arweave, upload)
+            .await
+            .map(SignedUpload::DataItem)
+    }
+
+    async fn post(&self, arweave: &Arweave, signed: SignedUpload) -> Result<(String, u64), Error> {
+        match signed {
+            SignedUpload::DataItem(item) => {
+                let receipt = arweave
+                    .post_data_item(
+                        &item,
+                        &self.upload_url,
+                        self.price(item.to_bytes()?.len() as u64),
+                        self.upload(&item),
+                    )
+                    .await?;
+                Ok((receipt.map_or_else(|| item.id().to_string(), |r| r.id), 0))
+            }
+            SignedUpload::Transaction(tx) => Err(Error::DataItemError(format!(
+                "transaction {} can't be posted to Turbo",
+                tx.id
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use httpmock::{
+        Method::{GET, POST},
+        MockServer,
+    };
+    use serde_json::json;
+    use url::Url;
+
+    use super::{TurboClient, PUBLIC_KEY_HEADER};
+    use crate::{data_item::DataItem, signer::ArweaveSigner};
+
+    #[tokio::test]
+    async fn test_price_balance_and_upload() {
+        let server = MockServer::start_async().await;
+        let signer = ArweaveSigner::from_pem_str(
+            &std::fs::read_to_string("res/test_wallet_4096.pem").unwrap(),
+        )
+        .unwrap();
+        let price = server.mock(|when, then| {
+            when.method(GET).path("/payment/v1/price/bytes/1024");
+            then.status(200)
+                .json_body(json!({ "winc": "332000000", "adjustments": [] }));
+        });
+        let balance = server.mock(|when, then| {
+            when.method(GET)
+                .path("/payment/v1/balance")
+                .header(PUBLIC_KEY_HEADER, signer.keypair_modulus().to_string())
+                .header_exists("x-signature");
+            then.status(200).json_body(json!({ "winc": "1000000000" }));
+        });
+        let upload = server.mock(|when, then| {
+            when.method(POST)
+                .path("/upload/v1/tx")
+                .header("content-type", "application/octet-stream");
+            then.status(200).json_body(json!({
+                "id": "item",
+                "owner": "owner",
+                "winc": "0",
+                "dataCaches": ["arweave.net"],
+                "fastFinalityIndexes": ["arweave.net"]
+            }));
+        });
+
+        let turbo = TurboClient::new(
+            Url::parse(&server.url("/upload/")).unwrap(),
+            Url::parse(&server.url("/payment/")).unwrap(),
+        );
+        assert_eq!(turbo.price(1024).await.unwrap(), 332_000_000);
+        assert_eq!(turbo.balance(&signer).await.unwrap(), 1_000_000_000);
+        let item = DataItem::new(b"hello".to_vec(), vec![])
+            .sign(&signer)
+            .unwrap();
+        let receipt = turbo.upload(&item).await.unwrap();
+        assert_eq!(receipt.data_caches, vec!["arweave.net".to_string()]);
+
+        price.assert();
+        balance.assert();
+        upload.assert();
+    }
+}
@@ -1,7 +1,13 @@
-use serde::{Deserialize, Serialize};
+use std::{fmt::Display, ops::Range, str::FromStr};
+
+use serde::{
+    de::{DeserializeOwned, Error as _},
+    Deserialize, Deserializer, Serialize,
+};
 use serde_aux::prelude::*;
+use serde_json::Value;
 
-use crate::crypto::base64::Base64;
+use crate::{crypto::base64::Base64, error::Error};
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct NetworkInfo {
@@ -16,7 +22,7 @@ pub struct NetworkInfo {
     pub node_state_latency: usize,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct ProofOfAccess {
     pub option: String,
     pub tx_path: Base64,
@@ -25,14 +31,17 @@ pub struct ProofOfAccess {
 }
 
 //Defined in https://docs.arweave.org/developers/server/http-api#block-format
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct BlockInfo {
     pub nonce: Base64,
     pub previous_block: Base64,
+    #[serde(deserialize_with = "number_or_string")]
     pub timestamp: u64,
+    #[serde(deserialize_with = "number_or_string")]
     pub last_retarget: u64,
     #[serde(deserialize_with = "deserialize_string_from_number")]
     pub diff: String,
+    #[serde(deserialize_with = "number_or_string")]
     pub height: u64,
     pub hash: Base64,
     pub indep_hash: Base64,
@@ -40,9 +49,15 @@ pub struct BlockInfo {
     pub wallet_list: Base64,
     pub reward_addr: Base64,
     pub tags: Vec<Tag>,
-    pub reward_pool: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub reward_pool: u128,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
     pub weave_size: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
     pub block_size: u64,
+    /// Reward paid to the miner, only reported by 2.6+ blocks.
+    #[serde(default, deserialize_with = "deserialize_option_number_from_string")]
+    pub reward: Option<u128>,
 
     //V2 Stuff
     pub cumulative_diff: Option<String>,
@@ -53,6 +68,33 @@ pub struct BlockInfo {
     pub tx_tree: Vec<Base64>,
     pub poa: ProofOfAccess,
 }
+
+impl BlockInfo {
+    /// Address of the miner that produced the block.
+    pub fn miner_address(&self) -> &Base64 {
+        &self.reward_addr
+    }
+
+    /// Reward paid to the miner, in winstons.
+    pub fn reward(&self) -> Option<u128> {
+        self.reward
+    }
+
+    /// Endowment pool after this block, in winstons.
+    pub fn reward_pool(&self) -> u128 {
+        self.reward_pool
+    }
+
+    /// Total size of the weave up to and including this block, in bytes.
+    pub fn weave_size(&self) -> u64 {
+        self.weave_size
+    }
+
+    /// Absolute weave range of the data of the block's transactions.
+    pub fn weave_range(&self) -> Range<u64> {
+        self.weave_size.saturating_sub(self.block_size)..self.weave_size
+    }
+}
 #[derive(Deserialize, Debug, Default, Eq, PartialEq)]
 pub struct Tx {
     pub format: u8,
@@ -75,14 +117,100 @@ pub struct Tag {
     pub value: Base64,
 }
 
-#[derive(Serialize, Deserialize)]
+/// Status of a mined transaction, as returned by `/tx/{id}/status`.
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
 pub struct TxStatus {
+    #[serde(deserialize_with = "number_or_string")]
     pub block_height: u128,
     pub block_indep_hash: Base64,
+    #[serde(deserialize_with = "number_or_string")]
     pub number_of_confirmations: u64,
 }
 
+impl TxStatus {
+    /// Whether the transaction has at least `min` confirmations.
+    pub fn is_confirmed(&self, min: u64) -> bool {
+        self.number_of_confirmations >= min
+    }
+
+    /// Confirmations once the chain reaches `height`. The block holding the transaction counts
+    /// as the first one.
+    pub fn confirmations_at(&self, height: u128) -> u64 {
+        if height < self.block_height {
+            return 0;
+        }
+        (height - self.block_height + 1) as u64
+    }
+}
+
+/// Position of a transaction's data in the weave, as returned by `/tx/{id}/offset`.
+/// `offset` is the absolute offset of the last byte of the data.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct TxOffset {
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub size: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub offset: u64,
+}
+
+impl TxOffset {
+    /// Absolute weave offset of the first byte of the data. Fails if the data would start
+    /// before the weave, as a malformed gateway response may claim.
+    pub fn start(&self) -> Result<u64, Error> {
+        (self.offset + 1).checked_sub(self.size).ok_or_else(|| {
+            Error::OffsetOutOfRange(format!(
+                "{} byte data cannot end at weave offset {}",
+                self.size, self.offset
+            ))
+        })
+    }
+
+    /// Absolute weave offset of byte `relative` of the data.
+    pub fn to_absolute(&self, relative: u64) -> Result<u64, Error> {
+        if relative >= self.size {
+            return Err(Error::OffsetOutOfRange(format!(
+                "byte {} of {} byte data",
+                relative, self.size
+            )));
+        }
+        Ok(self.start()? + relative)
+    }
+
+    /// Offset within the data of absolute weave offset `absolute`.
+    pub fn to_relative(&self, absolute: u64) -> Result<u64, Error> {
+        let start = self.start()?;
+        if absolute < start || absolute > self.offset {
+            return Err(Error::OffsetOutOfRange(format!(
+                "weave offset {} is outside of {}..={}",
+                absolute, start, self.offset
+            )));
+        }
+        Ok(absolute - start)
+    }
+}
+
+/// Chunk fetched by [`crate::Arweave::get_chunk_validated`], with the ranges its proofs
+/// establish.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct ValidatedChunk {
+    pub chunk: Vec<u8>,
+    /// Data root of the transaction holding the chunk, proven by the `tx_path`.
+    pub data_root: Base64,
+    /// Absolute weave range of the transaction's data.
+    pub tx_range: Range<u64>,
+    /// Absolute weave range of the chunk.
+    pub chunk_range: Range<u64>,
+}
+
+/// Chunk returned by `/chunk/{offset}`.
 #[derive(Serialize, Deserialize, Debug, Default, Eq, PartialEq)]
+pub struct ChunkData {
+    pub chunk: Base64,
+    pub data_path: Base64,
+    pub tx_path: Base64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, Eq, PartialEq)]
 pub struct Chunk {
     pub data_root: Base64,
     pub data_size: u64,
@@ -90,3 +218,206 @@ pub struct Chunk {
     pub offset: usize,
     pub chunk: Base64,
 }
+
+impl Chunk {
+    pub fn as_chunk_ref(&self) -> ChunkRef<'_> {
+        ChunkRef {
+            data_root: &self.data_root,
+            data_size: self.data_size,
+            data_path: &self.data_path.0,
+            offset: self.offset,
+            chunk: &self.chunk.0,
+        }
+    }
+}
+
+/// Borrowed view of a [`Chunk`] over the data and proofs of a transaction. Serializes to the
+/// same JSON as [`Chunk`], so it can be posted without copying the chunk data.
+#[derive(Serialize, Debug, Clone, Copy, Eq, PartialEq)]
+pub struct ChunkRef<'a> {
+    pub data_root: &'a Base64,
+    pub data_size: u64,
+    #[serde(serialize_with = "serialize_base64")]
+    pub data_path: &'a [u8],
+    pub offset: usize,
+    #[serde(serialize_with = "serialize_base64")]
+    pub chunk: &'a [u8],
+}
+
+impl ChunkRef<'_> {
+    pub fn to_chunk(&self) -> Chunk {
+        Chunk {
+            data_root: self.data_root.clone(),
+            data_size: self.data_size,
+            data_path: Base64::from(self.data_path.to_vec()),
+            offset: self.offset,
+            chunk: Base64::from(self.chunk.to_vec()),
+        }
+    }
+}
+
+fn serialize_base64<S: serde::Serializer>(bytes: &&[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(&base64::display::Base64Display::with_config(
+        bytes,
+        base64::URL_SAFE_NO_PAD,
+    ))
+}
+
+/// Fee returned by `/price/{size}`, as a JSON number or string.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Winstons(#[serde(deserialize_with = "number_or_string")] pub u64);
+
+/// Number sent as a JSON number or string, depending on the gateway version.
+fn number_or_string<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    match Value::deserialize(deserializer)? {
+        Value::Number(number) => number.to_string().parse(),
+        Value::String(string) => string.parse(),
+        other => {
+            return Err(D::Error::custom(format!(
+                "expected a number, got {}",
+                other
+            )))
+        }
+    }
+    .map_err(D::Error::custom)
+}
+
+/// Parses a gateway response into `T`. Unknown fields are ignored, since gateway versions
+/// differ, and the path of a field failing to parse is named in the error.
+pub fn parse_response<T: DeserializeOwned>(body: &[u8]) -> Result<T, String> {
+    let mut deserializer = serde_json::Deserializer::from_slice(body);
+    let value = serde_path_to_error::deserialize(&mut deserializer).map_err(|e| {
+        let path = e.path().to_string();
+        let e = e.into_inner();
+        if e.is_syntax() || e.is_eof() {
+            format!("invalid JSON: {}", e)
+        } else if path == "." {
+            e.to_string()
+        } else {
+            format!("field `{}`: {}", path, e)
+        }
+    })?;
+    deserializer
+        .end()
+        .map_err(|e| format!("invalid JSON: {}", e))?;
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_response, BlockInfo, Chunk, TxOffset, TxStatus, Winstons};
+    use crate::crypto::base64::Base64;
+
+    #[test]
+    fn test_tx_offset_math() {
+        // 6 bytes ending at weave offset 105: bytes 100 to 105.
+        let offset = TxOffset {
+            size: 6,
+            offset: 105,
+        };
+        assert_eq!(offset.start().unwrap(), 100);
+        assert_eq!(offset.to_absolute(0).unwrap(), 100);
+        assert_eq!(offset.to_absolute(5).unwrap(), 105);
+        assert!(offset.to_absolute(6).is_err());
+        assert_eq!(offset.to_relative(103).unwrap(), 3);
+        assert!(offset.to_relative(99).is_err());
+        assert!(offset.to_relative(106).is_err());
+
+        let malformed = TxOffset {
+            size: 10,
+            offset: 3,
+        };
+        assert!(malformed.start().is_err());
+        assert!(malformed.to_absolute(0).is_err());
+        assert!(malformed.to_relative(0).is_err());
+    }
+
+    #[test]
+    fn test_chunk_ref_serializes_like_chunk() {
+        let chunk = Chunk {
+            data_root: Base64::from(vec![1; 32]),
+            data_size: 3,
+            data_path: Base64::from(vec![2; 64]),
+            offset: 2,
+            chunk: Base64::from(vec![3; 3]),
+        };
+        let chunk_ref = chunk.as_chunk_ref();
+
+        assert_eq!(
+            serde_json::to_string(&chunk_ref).unwrap(),
+            serde_json::to_string(&chunk).unwrap()
+        );
+        assert_eq!(chunk_ref.to_chunk(), chunk);
+    }
+
+    #[test]
+    fn test_block_economics() {
+        let block = serde_json::json!({
+            "nonce": "", "previous_block": "", "timestamp": 1, "last_retarget": 1,
+            "diff": "1", "height": 1_000_000, "hash": "", "indep_hash": "", "txs": [],
+            "wallet_list": "", "reward_addr": "ggHWyKn0I_CTtsyyt2OR85sPYz9OvKLd9DYIvRQ2ET4",
+            "tags": [], "reward_pool": "31286541292587437843", "weave_size": "407672420044",
+            "block_size": "937455", "reward": "822154410426", "tx_root": "", "tx_tree": [],
+            "poa": { "option": "1", "tx_path": "", "data_path": "", "chunk": "" }
+        });
+        let block: BlockInfo = serde_json::from_str(&block.to_string()).unwrap();
+
+        assert_eq!(
+            block.miner_address().to_string(),
+            "ggHWyKn0I_CTtsyyt2OR85sPYz9OvKLd9DYIvRQ2ET4"
+        );
+        assert_eq!(block.reward(), Some(822154410426));
+        assert_eq!(block.reward_pool(), 31286541292587437843);
+        assert_eq!(block.weave_size(), 407672420044);
+        assert_eq!(block.block_size, 937455);
+    }
+
+    #[test]
+    fn test_tx_status_confirmations() {
+        let status: TxStatus = serde_json::from_str(
+            r#"{"block_height":1000,"block_indep_hash":"3Q1Omx4xGqJd0X8JfD7fh7XMUz8jCZ4sjOtnEGX_RW0-pxVU-9T4S1EjkKPxfpPt","number_of_confirmations":5}"#,
+        )
+        .unwrap();
+
+        assert!(status.is_confirmed(5));
+        assert!(!status.is_confirmed(6));
+        assert_eq!(status.confirmations_at(999), 0);
+        assert_eq!(status.confirmations_at(1000), 1);
+        assert_eq!(status.confirmations_at(1009), 10);
+    }
+
+    #[test]
+    fn test_parse_response_tolerance() {
+        let status: TxStatus = parse_response(
+            br#"{"block_height":"1000","block_indep_hash":"","number_of_confirmations":"5","extra":true}"#,
+        )
+        .unwrap();
+        assert_eq!(status.block_height, 1000);
+        assert_eq!(status.number_of_confirmations, 5);
+
+        assert_eq!(
+            parse_response::<TxStatus>(
+                br#"{"block_height":1000,"block_indep_hash":"","number_of_confirmations":[5]}"#,
+            )
+            .unwrap_err(),
+            "field `number_of_confirmations`: expected a number, got [5] at line 1 column 73"
+        );
+        assert_eq!(
+            parse_response::<TxStatus>(br#"{"block_height":1000}"#).unwrap_err(),
+            "missing field `block_indep_hash` at line 1 column 21"
+        );
+        assert!(parse_response::<TxStatus>(b"<html>").is_err());
+
+        assert_eq!(parse_response::<Winstons>(b"123").unwrap(), Winstons(123));
+        assert_eq!(
+            parse_response::<Winstons>(br#""123""#).unwrap(),
+            Winstons(123)
+        );
+        assert!(parse_response::<Winstons>(br#""12a""#).is_err());
+    }
+}
@@ -1,70 +1,310 @@
-use std::{str::FromStr, thread::sleep, time::Duration};
+use std::{
+    collections::VecDeque,
+    str::FromStr,
+    sync::Mutex,
+    time::{Duration, Instant, SystemTime},
+};
 
 use reqwest::{
-    header::{ACCEPT, CONTENT_TYPE},
+    header::{HeaderMap, ACCEPT, CONTENT_TYPE, RETRY_AFTER},
     Client,
 };
+use tokio::time::sleep;
 
 use crate::{
-    consts::{ARWEAVE_BASE_URL, CHUNKS_RETRIES, CHUNKS_RETRY_SLEEP},
+    audit,
+    consts::{
+        ARWEAVE_BASE_URL, CHUNKS_RETRIES, CHUNKS_RETRY_SLEEP, MAX_RETRY_AFTER, UPLOAD_RATE_WINDOW,
+    },
     error::Error,
-    types::Chunk,
+    gateway::{Gateway, MiddlewareStack},
+    types::ChunkRef,
 };
 
 pub struct Uploader {
     url: url::Url,
+    retries: u16,
+    max_retry_after: Duration,
+    throughput: Mutex<Throughput>,
+}
+
+/// Chunk uploads of the last [`UPLOAD_RATE_WINDOW`] seconds, and totals for the ETA.
+#[derive(Default)]
+struct Throughput {
+    samples: VecDeque<(Instant, u64)>,
+    started: Option<Instant>,
+    expected: u64,
+    uploaded: u64,
+}
+
+impl Throughput {
+    fn record(&mut self, bytes: u64, now: Instant) {
+        self.started.get_or_insert(now);
+        self.uploaded += bytes;
+        self.samples.push_back((now, bytes));
+        self.prune(now);
+    }
+
+    fn prune(&mut self, now: Instant) {
+        let window = Duration::from_secs(UPLOAD_RATE_WINDOW);
+        while let Some((at, _)) = self.samples.front() {
+            if now.duration_since(*at) <= window {
+                break;
+            }
+            self.samples.pop_front();
+        }
+    }
+
+    fn rate(&mut self, now: Instant) -> f64 {
+        self.prune(now);
+        let started = match self.started {
+            Some(started) => started,
+            None => return 0.0,
+        };
+        let span = now
+            .duration_since(started)
+            .min(Duration::from_secs(UPLOAD_RATE_WINDOW))
+            .as_secs_f64();
+        let bytes: u64 = self.samples.iter().map(|(_, bytes)| bytes).sum();
+        if span > 0.0 {
+            bytes as f64 / span
+        } else {
+            0.0
+        }
+    }
+
+    fn remaining(&self) -> u64 {
+        self.expected.saturating_sub(self.uploaded)
+    }
+
+    fn eta(&mut self, now: Instant) -> Option<Duration> {
+        let remaining = self.remaining();
+        if remaining == 0 {
+            return Some(Duration::ZERO);
+        }
+        let rate = self.rate(now);
+        if rate > 0.0 {
+            Some(Duration::from_secs_f64(remaining as f64 / rate))
+        } else {
+            None
+        }
+    }
 }
 
 impl Default for Uploader {
     fn default() -> Self {
         let url = url::Url::from_str(ARWEAVE_BASE_URL).unwrap();
-        Self { url }
+        Self::new(url)
     }
 }
 
 impl Uploader {
     pub fn new(url: url::Url) -> Self {
-        Uploader { url }
+        Uploader {
+            url,
+            retries: CHUNKS_RETRIES,
+            max_retry_after: Duration::from_secs(MAX_RETRY_AFTER),
+            throughput: Mutex::new(Throughput::default()),
+        }
+    }
+
+    /// Retries failed chunks `retries` times instead of [`CHUNKS_RETRIES`].
+    pub fn with_retries(mut self, retries: u16) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Waits out rate limits of up to `max_retry_after` instead of [`MAX_RETRY_AFTER`]
+    /// seconds. Chunks asked to wait longer fail with [`Error::RateLimited`] at once.
+    pub fn with_max_retry_after(mut self, max_retry_after: Duration) -> Self {
+        self.max_retry_after = max_retry_after;
+        self
+    }
+
+    /// Registers `bytes` of chunk data about to be uploaded, counted by [`Uploader::eta`].
+    pub fn expect_bytes(&self, bytes: u64) {
+        self.throughput.lock().unwrap().expected += bytes;
+    }
+
+    /// Chunk upload rate over the last [`UPLOAD_RATE_WINDOW`] seconds, in bytes per second.
+    pub fn current_rate(&self) -> f64 {
+        self.throughput.lock().unwrap().rate(Instant::now())
+    }
+
+    /// Bytes registered with [`Uploader::expect_bytes`] that haven't been uploaded yet.
+    pub fn remaining_bytes(&self) -> u64 {
+        self.throughput.lock().unwrap().remaining()
     }
 
+    /// Estimated time to upload the remaining bytes at the current rate. `None` until a
+    /// rate is known.
+    pub fn eta(&self) -> Option<Duration> {
+        self.throughput.lock().unwrap().eta(Instant::now())
+    }
+
+    /// Posts `chunk` through `gateway`, retrying up to [`CHUNKS_RETRIES`] times unless
+    /// configured otherwise. Gives up at once when rate limited for longer than the
+    /// configured maximum.
     pub async fn post_chunk_with_retries(
         &self,
-        chunk: Chunk,
-        client: Client,
+        chunk: ChunkRef<'_>,
+        gateway: &dyn Gateway,
     ) -> Result<usize, Error> {
         let mut retries = 0;
-        let mut resp = self.post_chunk(&chunk, &client).await;
+        let mut resp = gateway.post_chunk(chunk).await;
 
-        while retries < CHUNKS_RETRIES {
+        while retries < self.retries {
             match resp {
-                Ok(offset) => return Ok(offset),
+                Ok(offset) => {
+                    self.throughput
+                        .lock()
+                        .unwrap()
+                        .record(chunk.chunk.len() as u64, Instant::now());
+                    return Ok(offset);
+                }
                 Err(e) => {
-                    dbg!("post_chunk_with_retries: {:?}", e);
-                    sleep(Duration::from_secs(CHUNKS_RETRY_SLEEP));
+                    let delay = match e {
+                        Error::RateLimited(secs)
+                            if Duration::from_secs(secs) > self.max_retry_after =>
+                        {
+                            return Err(e)
+                        }
+                        Error::RateLimited(secs) => secs,
+                        _ => CHUNKS_RETRY_SLEEP,
+                    };
+                    sleep(Duration::from_secs(delay)).await;
                     retries += 1;
-                    resp = self.post_chunk(&chunk, &client).await;
+                    audit::note_retry();
+                    resp = gateway.post_chunk(chunk).await;
                 }
             }
         }
         resp
     }
 
-    pub async fn post_chunk(&self, chunk: &Chunk, client: &Client) -> Result<usize, Error> {
+    pub async fn post_chunk(&self, chunk: ChunkRef<'_>, client: &Client) -> Result<usize, Error> {
+        self.post_chunk_with_middleware(chunk, client, &MiddlewareStack::default())
+            .await
+    }
+
+    /// Posts `chunk` with `client`, running the request through `middleware`.
+    pub async fn post_chunk_with_middleware(
+        &self,
+        chunk: ChunkRef<'_>,
+        client: &Client,
+        middleware: &MiddlewareStack,
+    ) -> Result<usize, Error> {
         let url = self.url.join("chunk").expect("Could not join url");
-        // let client = reqwest::Client::new();
 
-        let resp = client
+        let request = client
             .post(url)
             .json(&chunk)
             .header(&ACCEPT, "application/json")
-            .header(&CONTENT_TYPE, "application/json")
-            .send()
-            .await
-            .map_err(|e| Error::PostChunkError(e.to_string()))?;
+            .header(&CONTENT_TYPE, "application/json");
+        let resp = middleware
+            .send(client, request, Error::PostChunkError)
+            .await?;
 
         match resp.status() {
             reqwest::StatusCode::OK => Ok(chunk.offset),
+            reqwest::StatusCode::TOO_MANY_REQUESTS => Err(Error::RateLimited(
+                retry_after(resp.headers()).map_or(CHUNKS_RETRY_SLEEP, |delay| delay.as_secs()),
+            )),
             _ => Err(Error::StatusCodeNotOk),
         }
     }
 }
+
+/// Delay requested by a response's `Retry-After` header, given either in seconds or as an
+/// HTTP date. Dates in the past yield a zero delay.
+pub(crate) fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let date = httpdate::parse_http_date(value).ok()?;
+    let delay = date
+        .duration_since(SystemTime::now())
+        .unwrap_or(Duration::ZERO);
+    // Round up so we never retry before the requested time.
+    Some(Duration::from_secs(
+        delay.as_secs() + u64::from(delay.subsec_nanos() > 0),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant, SystemTime};
+
+    use httpmock::{Method::POST, MockServer};
+    use reqwest::header::{HeaderMap, HeaderValue, RETRY_AFTER};
+
+    use super::{retry_after, Throughput, Uploader};
+    use crate::{error::Error, types::Chunk};
+
+    #[test]
+    fn test_retry_after() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(retry_after(&headers), None);
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("120"));
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(120)));
+
+        let date = httpdate::fmt_http_date(SystemTime::now() + Duration::from_secs(30));
+        headers.insert(RETRY_AFTER, HeaderValue::from_str(&date).unwrap());
+        let delay = retry_after(&headers).unwrap();
+        assert!(delay <= Duration::from_secs(30) && delay >= Duration::from_secs(28));
+
+        headers.insert(
+            RETRY_AFTER,
+            HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"),
+        );
+        assert_eq!(retry_after(&headers), Some(Duration::ZERO));
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("soon"));
+        assert_eq!(retry_after(&headers), None);
+    }
+
+    #[test]
+    fn test_throughput() {
+        let start = Instant::now();
+        let mut throughput = Throughput {
+            expected: 1000,
+            ..Default::default()
+        };
+        assert_eq!(throughput.rate(start), 0.0);
+        assert_eq!(throughput.eta(start), None);
+
+        throughput.record(100, start);
+        throughput.record(100, start + Duration::from_secs(1));
+        throughput.record(200, start + Duration::from_secs(2));
+        let now = start + Duration::from_secs(4);
+        assert_eq!(throughput.rate(now), 100.0);
+        assert_eq!(throughput.remaining(), 600);
+        assert_eq!(throughput.eta(now), Some(Duration::from_secs(6)));
+
+        // Samples older than the window no longer count.
+        let later = start + Duration::from_secs(12);
+        throughput.record(500, later);
+        assert_eq!(throughput.rate(later), 70.0);
+        assert_eq!(
+            throughput.eta(later),
+            Some(Duration::from_secs_f64(100.0 / 70.0))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_post_chunk_rate_limited() {
+        let server = MockServer::start_async().await;
+        server.mock(|when, then| {
+            when.method(POST).path("/chunk");
+            then.status(429).header("Retry-After", "7");
+        });
+
+        let uploader = Uploader::new(url::Url::parse(&server.url("")).unwrap());
+        let res = uploader
+            .post_chunk(Chunk::default().as_chunk_ref(), &reqwest::Client::new())
+            .await;
+        assert!(matches!(res, Err(Error::RateLimited(7))));
+    }
+}
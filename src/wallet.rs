@@ -1,7 +1,29 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+};
+
 use pretend::{pretend, resolver::UrlResolver, Pretend, Url};
 use pretend_reqwest::Client as HttpClient;
+use rand::thread_rng;
+use rsa::{BigUint, PublicKeyParts, RsaPrivateKey};
+use serde_json::json;
+use zeroize::Zeroizing;
+
+use crate::{
+    crypto::{base64::Base64, hash::sha256, sign::Signer},
+    error::Error,
+    signer::ArweaveSigner,
+};
 
-use crate::error::Error;
+/// Size of the keys of Arweave wallets.
+const KEY_BITS: usize = 4096;
+/// Length of an address: the base64url encoded SHA-256 of the modulus.
+const ADDRESS_LENGTH: usize = 43;
+const ADDRESS_ALPHABET: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
 
 #[pretend]
 trait TransactionInfoFetch {
@@ -36,6 +58,189 @@ impl WalletInfoClient {
     }
 }
 
+/// A freshly generated wallet whose address matched a [`VanitySearch`].
+pub struct VanityWallet {
+    pub address: Base64,
+    keyfile: Zeroizing<String>,
+}
+
+impl std::fmt::Debug for VanityWallet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VanityWallet")
+            .field("address", &self.address)
+            .finish_non_exhaustive()
+    }
+}
+
+impl VanityWallet {
+    fn from_key(address: Base64, key: &RsaPrivateKey) -> Self {
+        let encode = |n: &BigUint| base64::encode_config(n.to_bytes_be(), base64::URL_SAFE_NO_PAD);
+        let (p, q) = (&key.primes()[0], &key.primes()[1]);
+        let one = BigUint::from(1u32);
+        // p is prime, so q^(p - 2) is the inverse of q modulo p.
+        let qi = q.modpow(&(p - 2u32), p);
+        let keyfile = json!({
+            "kty": "RSA",
+            "e": encode(key.e()),
+            "n": encode(key.n()),
+            "d": encode(key.d()),
+            "p": encode(p),
+            "q": encode(q),
+            "dp": encode(&(key.d() % (p - &one))),
+            "dq": encode(&(key.d() % (q - &one))),
+            "qi": encode(&qi),
+        });
+        Self {
+            address,
+            keyfile: Zeroizing::new(keyfile.to_string()),
+        }
+    }
+
+    /// JWK keyfile of the wallet, as arweave-js and wallet extensions import.
+    pub fn keyfile(&self) -> &str {
+        &self.keyfile
+    }
+
+    pub fn signer(&self) -> Result<ArweaveSigner, Error> {
+        Signer::from_jwk_str(&self.keyfile).map(ArweaveSigner::from_signer)
+    }
+}
+
+/// Generates wallets on several threads until one has an address starting with a prefix
+/// and ending with a suffix. Each character matched makes the search 64 times longer.
+/// Clones share their progress and cancellation, so a clone can watch or cancel a running
+/// search from another thread.
+#[derive(Debug, Clone)]
+pub struct VanitySearch {
+    prefix: String,
+    suffix: String,
+    threads: usize,
+    bits: usize,
+    attempts: Arc<AtomicU64>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl VanitySearch {
+    pub fn new(prefix: &str, threads: usize) -> Result<Self, Error> {
+        Self {
+            prefix: String::new(),
+            suffix: String::new(),
+            threads: threads.max(1),
+            bits: KEY_BITS,
+            attempts: Arc::new(AtomicU64::new(0)),
+            cancelled: Arc::new(AtomicBool::new(false)),
+        }
+        .with_pattern(prefix, "")
+    }
+
+    pub fn with_suffix(self, suffix: &str) -> Result<Self, Error> {
+        let prefix = self.prefix.clone();
+        self.with_pattern(&prefix, suffix)
+    }
+
+    fn with_pattern(mut self, prefix: &str, suffix: &str) -> Result<Self, Error> {
+        if let Some(c) = prefix
+            .chars()
+            .chain(suffix.chars())
+            .find(|c| !ADDRESS_ALPHABET.contains(*c))
+        {
+            return Err(Error::VanityError(format!(
+                "{:?} never appears in an address",
+                c
+            )));
+        }
+        if prefix.len() + suffix.len() > ADDRESS_LENGTH {
+            return Err(Error::VanityError(format!(
+                "addresses are {} characters long",
+                ADDRESS_LENGTH
+            )));
+        }
+        // The last character of an address only encodes 4 bits.
+        if suffix.ends_with(|c| ADDRESS_ALPHABET.find(c).unwrap() % 4 != 0) {
+            return Err(Error::VanityError(format!(
+                "no address ends with {:?}",
+                suffix
+            )));
+        }
+        self.prefix = prefix.to_string();
+        self.suffix = suffix.to_string();
+        Ok(self)
+    }
+
+    /// Average number of wallets to generate before one matches.
+    pub fn expected_attempts(&self) -> u128 {
+        let suffix_bits = if self.suffix.is_empty() {
+            0
+        } else {
+            6 * self.suffix.len() as u32 - 2
+        };
+        2u128.saturating_pow(6 * self.prefix.len() as u32 + suffix_bits)
+    }
+
+    /// Wallets generated so far.
+    pub fn attempts(&self) -> u64 {
+        self.attempts.load(Ordering::Relaxed)
+    }
+
+    /// Stops the search, which then fails with [`Error::VanityError`].
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Generates a wallet, returned if its address matches.
+    fn attempt(&self) -> Result<Option<VanityWallet>, Error> {
+        let key = RsaPrivateKey::new(&mut thread_rng(), self.bits)
+            .map_err(|e| Error::VanityError(e.to_string()))?;
+        let address = Base64::from(sha256(&key.n().to_bytes_be()).to_vec());
+        let encoded = address.to_string();
+        if encoded.starts_with(&self.prefix) && encoded.ends_with(&self.suffix) {
+            Ok(Some(VanityWallet::from_key(address, &key)))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Runs the search, blocking until a wallet matches or the search is cancelled.
+    /// `on_progress` is called from the worker threads with the number of wallets generated
+    /// after each of them.
+    pub fn run(&self, on_progress: impl Fn(u64) + Sync) -> Result<VanityWallet, Error> {
+        let result: Mutex<Option<Result<VanityWallet, Error>>> = Mutex::new(None);
+        let done = AtomicBool::new(false);
+        thread::scope(|scope| {
+            for _ in 0..self.threads {
+                scope.spawn(|| {
+                    while !done.load(Ordering::Relaxed) && !self.is_cancelled() {
+                        let found = self.attempt();
+                        on_progress(self.attempts.fetch_add(1, Ordering::Relaxed) + 1);
+                        if matches!(found, Ok(None)) {
+                            continue;
+                        }
+                        if !done.swap(true, Ordering::Relaxed) {
+                            *result.lock().unwrap() = found.transpose();
+                        }
+                    }
+                });
+            }
+        });
+        result.into_inner().unwrap().unwrap_or_else(|| {
+            Err(Error::VanityError(format!(
+                "cancelled after {} attempts",
+                self.attempts()
+            )))
+        })
+    }
+}
+
+/// Generates a wallet whose address starts with `prefix`, on `threads` threads. See
+/// [`VanitySearch`] for suffixes, progress and cancellation.
+pub fn generate_vanity(prefix: &str, threads: usize) -> Result<VanityWallet, Error> {
+    VanitySearch::new(prefix, threads)?.run(|_| ())
+}
+
 #[cfg(test)]
 mod tests {
     use httpmock::{Method::GET, MockServer};
@@ -85,4 +290,39 @@ mod tests {
         mock.assert();
         assert_eq!(tx_info, "last_tx".to_string());
     }
+
+    #[test]
+    fn test_vanity_search() {
+        use std::sync::atomic::{AtomicU64, Ordering};
+
+        use crate::wallet::VanitySearch;
+
+        assert!(VanitySearch::new("not base64!", 1).is_err());
+        assert!(VanitySearch::new("", 1).unwrap().with_suffix("B").is_err());
+        assert_eq!(
+            VanitySearch::new("ab", 1)
+                .unwrap()
+                .with_suffix("A")
+                .unwrap()
+                .expected_attempts(),
+            1 << 16
+        );
+
+        // Small keys keep the test fast; wallets use 4096 bit ones.
+        let mut search = VanitySearch::new("x", 4).unwrap();
+        search.bits = 512;
+        let calls = AtomicU64::new(0);
+        let wallet = search
+            .run(|_| {
+                calls.fetch_add(1, Ordering::Relaxed);
+            })
+            .unwrap();
+        assert!(wallet.address.to_string().starts_with('x'));
+        assert_eq!(wallet.signer().unwrap().wallet_address(), wallet.address);
+        assert!(calls.load(Ordering::Relaxed) >= 1);
+        assert!(search.attempts() >= 1);
+
+        search.cancel();
+        assert!(search.run(|_| ()).is_err());
+    }
 }
@@ -0,0 +1,299 @@
+//! Background watcher notifying callbacks and channels as transactions get confirmed.
+
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use tokio::{sync::mpsc, task::JoinHandle};
+
+use crate::{crypto::base64::Base64, finality::FinalityPolicy, gateway::Gateway, types::TxStatus};
+
+/// Stage of a watched transaction. Stages only ever move forward, unless the transaction
+/// is [`ConfirmationState::Reorged`].
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ConfirmationState {
+    /// Not a stage: the block of a confirmed transaction changed, or its confirmations
+    /// decreased, so it was orphaned by a fork. The event carries the new status, and the
+    /// transaction goes through its stages again.
+    Reorged,
+    /// Accepted by the gateway but not mined yet.
+    Pending,
+    /// Mined, but not final yet.
+    Confirmed,
+    /// Final according to the [`FinalityPolicy`] of the watcher. The transaction stops
+    /// being watched once it gets there.
+    Final,
+}
+
+#[derive(Debug, Clone)]
+pub struct ConfirmationEvent {
+    pub id: Base64,
+    pub state: ConfirmationState,
+    /// Status reported by the gateway, `None` while pending.
+    pub status: Option<TxStatus>,
+}
+
+type Callback = Arc<dyn Fn(ConfirmationEvent) -> BoxFuture<'static, ()> + Send + Sync>;
+
+#[derive(Clone)]
+enum Sink {
+    Callback(Callback),
+    Channel(mpsc::Sender<ConfirmationEvent>),
+}
+
+impl Sink {
+    async fn deliver(&self, event: ConfirmationEvent) {
+        match self {
+            Sink::Callback(callback) => callback(event).await,
+            // A dropped receiver only means nobody listens anymore.
+            Sink::Channel(sender) => {
+                let _ = sender.send(event).await;
+            }
+        }
+    }
+}
+
+struct Watch {
+    id: Base64,
+    state: Option<ConfirmationState>,
+    /// Last status of the transaction while mined.
+    mined: Option<TxStatus>,
+    sinks: Vec<Sink>,
+}
+
+type Watches = Arc<Mutex<HashMap<String, Watch>>>;
+
+/// Polls the status of watched transactions on a background task and notifies their
+/// registered callbacks and channels each time a transaction reaches a new
+/// [`ConfirmationState`]. The task stops when the watcher is dropped.
+pub struct ConfirmationWatcher {
+    watches: Watches,
+    task: JoinHandle<()>,
+}
+
+impl ConfirmationWatcher {
+    /// Spawns the watcher task, checking every `interval` and treating transactions as final
+    /// according to `finality`. Must be called within a tokio runtime.
+    pub fn spawn(gateway: Arc<dyn Gateway>, interval: Duration, finality: FinalityPolicy) -> Self {
+        let watches = Watches::default();
+        let task = tokio::spawn(run(gateway, watches.clone(), interval, finality));
+        Self { watches, task }
+    }
+
+    /// Calls `callback` every time `id` reaches a new state. Callbacks run on the watcher
+    /// task, one after the other.
+    pub fn watch<F, Fut>(&self, id: Base64, callback: F)
+    where
+        F: Fn(ConfirmationEvent) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let callback: Callback = Arc::new(move |event| Box::pin(callback(event)));
+        self.add_sink(id, Sink::Callback(callback));
+    }
+
+    /// Sends an event to `sender` every time `id` reaches a new state.
+    pub fn watch_channel(&self, id: Base64, sender: mpsc::Sender<ConfirmationEvent>) {
+        self.add_sink(id, Sink::Channel(sender));
+    }
+
+    /// Stops watching `id`, dropping its callbacks and channels.
+    pub fn unwatch(&self, id: &Base64) {
+        self.watches.lock().unwrap().remove(&id.to_string());
+    }
+
+    /// Number of transactions still being watched.
+    pub fn watched(&self) -> usize {
+        self.watches.lock().unwrap().len()
+    }
+
+    fn add_sink(&self, id: Base64, sink: Sink) {
+        self.watches
+            .lock()
+            .unwrap()
+            .entry(id.to_string())
+            .or_insert_with(|| Watch {
+                id,
+                state: None,
+                mined: None,
+                sinks: Vec::new(),
+            })
+            .sinks
+            .push(sink);
+    }
+}
+
+impl Drop for ConfirmationWatcher {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn run(
+    gateway: Arc<dyn Gateway>,
+    watches: Watches,
+    interval: Duration,
+    finality: FinalityPolicy,
+) {
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        ticker.tick().await;
+
+        let ids: Vec<Base64> = watches
+            .lock()
+            .unwrap()
+            .values()
+            .map(|watch| watch.id.clone())
+            .collect();
+        for id in ids {
+            // Unknown or failing ids are retried on the next tick.
+            let status = match gateway.get_tx_status(&id).await {
+                Ok((_, status)) => status,
+                Err(_) => continue,
+            };
+            let state = match &status {
+                Some(status) if finality.is_status_final(status) => ConfirmationState::Final,
+                Some(status) if status.is_confirmed(1) => ConfirmationState::Confirmed,
+                _ => ConfirmationState::Pending,
+            };
+
+            let (reorged, sinks) = {
+                let mut watches = watches.lock().unwrap();
+                let watch = match watches.get_mut(&id.to_string()) {
+                    Some(watch) => watch,
+                    None => continue,
+                };
+                let reorged = match (&watch.mined, &status) {
+                    (Some(previous), Some(status)) => {
+                        !status.block_indep_hash.ct_eq(&previous.block_indep_hash)
+                            || status.number_of_confirmations < previous.number_of_confirmations
+                    }
+                    (Some(_), None) => true,
+                    (None, _) => false,
+                };
+                watch.mined = status.clone().filter(|status| status.is_confirmed(1));
+                if reorged {
+                    watch.state = None;
+                } else if watch.state >= Some(state) {
+                    continue;
+                }
+                watch.state = Some(state);
+                let sinks = watch.sinks.clone();
+                if state == ConfirmationState::Final {
+                    watches.remove(&id.to_string());
+                }
+                (reorged, sinks)
+            };
+
+            let mut events = Vec::with_capacity(2);
+            if reorged {
+                events.push(ConfirmationEvent {
+                    id: id.clone(),
+                    state: ConfirmationState::Reorged,
+                    status: status.clone(),
+                });
+            }
+            events.push(ConfirmationEvent { id, state, status });
+            for event in events {
+                for sink in &sinks {
+                    sink.deliver(event.clone()).await;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use tokio::{sync::mpsc, time::timeout};
+
+    use super::{ConfirmationEvent, ConfirmationState, ConfirmationWatcher};
+    use crate::{
+        crypto::base64::Base64,
+        finality::FinalityPolicy,
+        gateway::{Gateway, MockGateway},
+        transaction::Tx,
+    };
+
+    async fn next(receiver: &mut mpsc::Receiver<ConfirmationEvent>) -> ConfirmationEvent {
+        timeout(Duration::from_secs(5), receiver.recv())
+            .await
+            .unwrap()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_watch_until_final() {
+        let gateway = Arc::new(MockGateway::new());
+        let tx = Tx {
+            id: Base64::from(vec![1; 32]),
+            ..Tx::default()
+        };
+        gateway.post_tx(&tx).await.unwrap();
+        gateway.set_confirmations(&tx.id, 0);
+
+        let watcher = ConfirmationWatcher::spawn(
+            gateway.clone(),
+            Duration::from_millis(10),
+            FinalityPolicy::BlocksAfterInclusion(2),
+        );
+        let (sender, mut receiver) = mpsc::channel(8);
+        watcher.watch_channel(tx.id.clone(), sender);
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let callback_seen = seen.clone();
+        watcher.watch(tx.id.clone(), move |event| {
+            let seen = callback_seen.clone();
+            async move { seen.lock().unwrap().push(event.state) }
+        });
+        // Never posted, so it stays watched without events.
+        watcher.watch_channel(Base64::from(vec![2; 32]), mpsc::channel(1).0);
+
+        let event = next(&mut receiver).await;
+        assert_eq!(event.state, ConfirmationState::Pending);
+        assert!(event.status.is_none());
+
+        gateway.set_confirmations(&tx.id, 1);
+        let event = next(&mut receiver).await;
+        assert_eq!(event.state, ConfirmationState::Confirmed);
+        assert_eq!(event.status.unwrap().number_of_confirmations, 1);
+
+        // Back to pending means the block was orphaned, and the stages start over.
+        gateway.set_confirmations(&tx.id, 0);
+        let event = next(&mut receiver).await;
+        assert_eq!(event.state, ConfirmationState::Reorged);
+        assert!(event.status.is_none());
+        assert_eq!(next(&mut receiver).await.state, ConfirmationState::Pending);
+
+        gateway.set_confirmations(&tx.id, 1);
+        assert_eq!(
+            next(&mut receiver).await.state,
+            ConfirmationState::Confirmed
+        );
+        gateway.set_confirmations(&tx.id, 3);
+        assert_eq!(next(&mut receiver).await.state, ConfirmationState::Final);
+
+        // The final event closes the channel, as the watch and its sender are dropped.
+        assert!(receiver.recv().await.is_none());
+        assert_eq!(watcher.watched(), 1);
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![
+                ConfirmationState::Pending,
+                ConfirmationState::Confirmed,
+                ConfirmationState::Reorged,
+                ConfirmationState::Pending,
+                ConfirmationState::Confirmed,
+                ConfirmationState::Final
+            ]
+        );
+    }
+}